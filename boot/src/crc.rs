@@ -0,0 +1,36 @@
+/// The CRC-32 polynomial (as used by zlib and Ethernet), bit-reversed.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Returns the CRC-32 of `data`.
+///
+/// This goes a bit at a time rather than through a table: a kernel image is
+/// checked once per boot, and the bootloader has no room to spare.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (POLYNOMIAL & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::crc32;
+
+    #[test]
+    fn crc32_matches_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414F_A339);
+
+        // A single flipped bit changes the checksum
+        let mut image = [0u8; 4096];
+        let clean = crc32(&image);
+        image[1234] ^= 0x10;
+        assert_ne!(crc32(&image), clean);
+    }
+}
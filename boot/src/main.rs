@@ -8,6 +8,9 @@
 #[cfg(not(test))]
 mod init;
 mod allocator;
+mod crc;
+mod menu;
+mod progress;
 
 use xmodem::Xmodem;
 use core::time::Duration;
@@ -25,6 +28,7 @@ use crate::allocator::mutex::Mutex;
 extern crate alloc;
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::fmt;
 
 // I copied this from kern/src/fs.rs
@@ -65,6 +69,11 @@ const BINARY_START: *mut u8 = BINARY_START_ADDR as *mut u8;
 /// Free space between the bootloader and the loaded binary's start address.
 const MAX_BINARY_SIZE: usize = BOOTLOADER_START_ADDR - BINARY_START_ADDR;
 
+/// How long the first prompt waits for a key before booting from disk.
+const AUTO_BOOT_SECS: u64 = 5;
+
+/// Where downloaded kernels are saved, and the image the boot menu picks by
+/// default.
 const KERNEL_IMG_NAME: &'static str = "real_kernel.bin";
 
 #[cfg_attr(not(test), global_allocator)]
@@ -86,38 +95,82 @@ fn kmain() -> ! {
         uart.write_str("Welcome to the Bootloader!!\n");
     }*/
 
-    // load or boot
-    let mut choice = '0';
-    while choice != '1' && choice != '2' {
-        uart.write_str("\rDownload new kernel(1) or boot from disk(2)? ");
-        choice = uart.read_byte() as char;
-        uart.write_byte(choice as u8);
-    }
+    // load or boot, booting once the countdown runs out
+    uart.set_read_timeout(Duration::from_secs(1));
+    let choice = menu::countdown(AUTO_BOOT_SECS, |left| {
+        uart.write_str("\r\x1b[KDownload new kernel(1) or boot from disk(2)? ");
+        if let Some(secs) = left {
+            uart.write_str(&format!("(booting in {}s) ", secs));
+        }
+        while uart.wait_for_byte().is_err() {
+            if left.is_some() {
+                return None;
+            }
+        }
+        let key = uart.read_byte();
+        uart.write_byte(key);
+        Some(key)
+    });
 
     match choice {
-        '1' => download_kern(uart),
-        '2' => load_kern_from_disk(uart),
-        _ => unimplemented!()
+        menu::Choice::Download => download_kern(uart),
+        menu::Choice::Boot => if !load_kern_from_disk(&mut uart) {
+            uart.write_str("Falling back to downloading a kernel\n");
+            download_kern(uart)
+        },
     }
 
     // start main kernel
     unsafe { jump_to(BINARY_START) }
 }
 
-fn load_kern_from_disk(mut uart: pi::uart::MiniUart) {
+/// Lets the user pick a kernel image on disk, loads it and checks it against
+/// its stored CRC-32, if it has one. Returns `false` if there is no image or
+/// it doesn't match, in which case it mustn't be jumped to.
+fn load_kern_from_disk(uart: &mut pi::uart::MiniUart) -> bool {
     use shim::io::Read;
     use fat32::traits::File;
 
-    uart.write_str(&format!("\nLoading kernel from disk at path: /{}...", KERNEL_IMG_NAME));
+    let fs = match init_fs(uart) {
+        Some(fs) => fs,
+        None => return false
+    };
+    let name = match choose_image(uart, &fs) {
+        Some(name) => name,
+        None => {
+            uart.write_str("\nno image to load :(\n");
+            return false;
+        }
+    };
 
-    let mut fs = init_fs(&mut uart).unwrap();
-    if !kern_file_exists(&fs) {
-        uart.write_str("no image to load :(");
+    uart.write_str(&format!("Loading kernel from disk at path: /{}...", name));
+    let mut kern_fd = open_file(&fs, &name);
+    let size = kern_fd.size() as usize;
+    if size > MAX_BINARY_SIZE {
+        uart.write_str("image is too big :(\n");
+        return false;
+    }
+    let image = unsafe { core::slice::from_raw_parts_mut(BINARY_START, size) };
+    if kern_fd.read_exact(image).is_err() {
+        uart.write_str("couldn't read the image :(\n");
+        return false;
     }
 
-    let mut kern_fd = open_kern_file(&fs);
-    unsafe { kern_fd.read_exact(core::slice::from_raw_parts_mut(BINARY_START, kern_fd.size() as usize)); } 
+    match read_checksum(&fs, &name) {
+        Ok(Some(expected)) if expected == crc::crc32(image) => (),
+        Ok(Some(_)) => {
+            uart.write_str("checksum mismatch, image is corrupt :(\n");
+            return false;
+        },
+        // Images copied onto the card by hand don't have one
+        Ok(None) => uart.write_str("no checksum, booting it unverified..."),
+        Err(_) => {
+            uart.write_str("couldn't read the checksum :(\n");
+            return false;
+        }
+    }
     uart.write_str("done.\n");
+    true
 }
 
 // gonna save it to a hardcoded location on boot partition for now
@@ -129,11 +182,18 @@ fn download_kern(mut uart: pi::uart::MiniUart) {
         };
         let mut uart = pi::uart::MiniUart::new();
         uart.set_read_timeout(Duration::from_millis(750));
-        match Xmodem::receive(&mut uart, target) {
+        match Xmodem::receive_with_progress(&mut uart, target, progress::record) {
             Ok(size) => {
+                uart.write_str(&format!("\nreceived {}\n", progress::Size(size)));
                 save_kern(&mut uart, size);
                 break;
             },
+            // Attempts time out while the sender hasn't started yet, so only
+            // one that got somewhere is worth reporting
+            Err(_) if progress::received() > 0 => {
+                let received = progress::Size(progress::received());
+                uart.write_str(&format!("\ntransfer failed after {}, retrying", received));
+            },
             Err(_) => continue
         }
     }
@@ -141,29 +201,102 @@ fn download_kern(mut uart: pi::uart::MiniUart) {
 
 fn save_kern(mut uart: &mut pi::uart::MiniUart, size: usize) {
     let mut fs = init_fs(&mut uart).unwrap();
-       
-    let mut root_dir = match (&fs).open("/") {
-        Ok(entry) => entry.into_dir().unwrap(),
-        _ => return
-    };
+    let image = unsafe { core::slice::from_raw_parts(BINARY_START, size) };
 
-    if !kern_file_exists(&fs) {
+    uart.write_str(&format!("writing kernel to disk ({} bytes)...", size));
+    let checksum = crc::crc32(image).to_le_bytes();
+    let written = write_file(&fs, KERNEL_IMG_NAME, image)
+        .and_then(|_| write_file(&fs, &checksum_name(KERNEL_IMG_NAME), &checksum));
+    fs.flush();
+    match written {
+        Ok(()) => uart.write_str("done.\n"),
+        Err(e) => uart.write_str(&format!("error: {:?}\n", e)),
+    }
+}
+
+/// Lists the kernel images on disk and asks the user which one to boot.
+/// Returns its name, or `None` if there are no images.
+fn choose_image(uart: &mut pi::uart::MiniUart, fs: &PiVFatHandle) -> Option<String> {
+    let mut images = list_images(fs);
+    let default = menu::default_image(&images, KERNEL_IMG_NAME)?;
+
+    uart.write_str("\nKernel images on disk:\n");
+    for (i, image) in images.iter().enumerate() {
+        let marker = if i == default { " (default)" } else { "" };
+        uart.write_str(&format!("  {}) {}{}\n", i + 1, image.name, marker));
+    }
+
+    loop {
+        uart.write_str(&format!("Boot which image? [{}] ", default + 1));
+        let answer = read_line(uart);
+        if let Some(choice) = menu::parse_choice(&answer, images.len(), default) {
+            return Some(images.swap_remove(choice).name);
+        }
+    }
+}
+
+/// Reads a line typed over `uart`, echoing it back, up to Enter.
+fn read_line(uart: &mut pi::uart::MiniUart) -> String {
+    let mut line = String::new();
+    loop {
+        match uart.read_byte() {
+            b'\r' | b'\n' => {
+                uart.write_str("\n");
+                return line;
+            },
+            // Backspace and delete
+            8 | 127 => if line.pop().is_some() {
+                uart.write_str("\x08 \x08");
+            },
+            byte @ 0x20..=0x7e => {
+                line.push(byte as char);
+                uart.write_byte(byte);
+            },
+            _ => ()
+        }
+    }
+}
+
+/// Returns the name of the file holding the CRC-32 of the image `name`, as 4
+/// little-endian bytes: `name` with `.bin` swapped for `.crc`.
+fn checksum_name(name: &str) -> String {
+    let mut checksum = String::from(&name[..name.len() - 4]);
+    checksum.push_str(".crc");
+    checksum
+}
+
+/// Returns the checksum stored for the image `name`, or `None` if there
+/// isn't one.
+fn read_checksum(fs: &PiVFatHandle, name: &str) -> shim::io::Result<Option<u32>> {
+    use shim::io::Read;
+
+    let checksum_name = checksum_name(name);
+    if !file_exists(fs, &checksum_name) {
+        return Ok(None);
+    }
+    let mut checksum = [0u8; 4];
+    open_file(fs, &checksum_name).read_exact(&mut checksum)?;
+    Ok(Some(u32::from_le_bytes(checksum)))
+}
+
+/// Replaces the contents of the file `name` in the root directory with
+/// `data`, creating the file if it doesn't exist.
+fn write_file(fs: &PiVFatHandle, name: &str, data: &[u8]) -> shim::io::Result<()> {
+    use shim::io::Write;
+    use fat32::traits::File;
+
+    if !file_exists(fs, name) {
+        let mut root_dir = fs.open_dir("/")?;
         root_dir.create(Metadata {
-            name: String::from(KERNEL_IMG_NAME),
+            name: String::from(name),
             ..Default::default()
-        });
+        })?;
     }
 
-    let mut fd = open_kern_file(&fs);
-    // need to implement file shrinking to do this properly
-    // this might cause problems if we save a smaller kernel
-    uart.write_str(&format!("writing kernel to disk ({} bytes)...", size));
-    unsafe {
-        use shim::io::Write;
-        fd.write(core::slice::from_raw_parts(BINARY_START, size)); 
-    }
-    fs.flush();
-    uart.write_str("done.\n");
+    // Cut off what's left of a bigger file, so the size matches `data`
+    let mut fd = open_file(fs, name);
+    fd.write_all(data)?;
+    fd.set_len(data.len() as u64)
 }
 
 fn init_fs(mut uart: &mut pi::uart::MiniUart) -> Option<PiVFatHandle> {
@@ -179,29 +312,47 @@ fn init_fs(mut uart: &mut pi::uart::MiniUart) -> Option<PiVFatHandle> {
     Some(fs)
 }
 
-fn kern_file_exists(fs: &PiVFatHandle) -> bool {
+fn file_exists(fs: &PiVFatHandle, name: &str) -> bool {
     // we should add something to do this automatically in the FS
-    let mut root_dir = match (&fs).open("/") {
+    let root_dir = match fs.open("/") {
         Ok(entry) => entry.into_dir().unwrap(),
         _ => return false
     };
 
-    let mut kern_file_exists = false;
+    let mut file_exists = false;
     for entry in root_dir.entries().unwrap() {
-        if entry.name().eq(KERNEL_IMG_NAME) {
-            kern_file_exists = true; 
+        if entry.name().eq(name) {
+            file_exists = true; 
             break;
         }
     }
 
-    kern_file_exists
+    file_exists
 }
 
+/// Returns the kernel images in the root directory.
+fn list_images(fs: &PiVFatHandle) -> Vec<menu::Image> {
+    use fat32::traits::Metadata;
+
+    let root_dir = match fs.open("/") {
+        Ok(entry) => entry.into_dir().unwrap(),
+        _ => return Vec::new()
+    };
+
+    let mut images = Vec::new();
+    for entry in root_dir.entries().unwrap() {
+        if entry.is_file() && menu::is_image(entry.name()) {
+            images.push(menu::Image::new(String::from(entry.name()), &entry.metadata().modified()));
+        }
+    }
+
+    images
+}
 
-fn open_kern_file(fs: &PiVFatHandle) -> File<PiVFatHandle> {
+fn open_file(fs: &PiVFatHandle, name: &str) -> File<PiVFatHandle> {
     let mut img_path = String::from("/");
-    img_path.push_str(KERNEL_IMG_NAME);
-    let mut fd = fs.open_file(img_path).expect("Couldn't open file for writing");
+    img_path.push_str(name);
+    let fd = fs.open_file(img_path).expect("Couldn't open file for writing");
     fd
 }
 
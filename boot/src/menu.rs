@@ -0,0 +1,175 @@
+use alloc::string::String;
+use fat32::traits::Timestamp;
+
+/// What to do, as picked at the first prompt.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Choice {
+    /// Receive a kernel over XMODEM
+    Download,
+    /// Boot an image on disk
+    Boot,
+}
+
+/// Returns what the key `key` picks at the first prompt, if anything.
+fn choice(key: u8) -> Option<Choice> {
+    match key {
+        b'1' => Some(Choice::Download),
+        b'2' => Some(Choice::Boot),
+        _ => None,
+    }
+}
+
+/// Runs the first prompt, which picks `Choice::Boot` once `secs` seconds
+/// pass without a key being pressed.
+///
+/// `prompt` is called with the number of seconds left, shows the prompt and
+/// waits up to a second for a key. It returns the key, or `None` if none was
+/// pressed. Any other key stops the countdown, after which `prompt` is
+/// called with `None` and should wait for as long as it takes.
+pub fn countdown<P: FnMut(Option<u64>) -> Option<u8>>(secs: u64, mut prompt: P) -> Choice {
+    let mut left = Some(secs);
+    loop {
+        if left == Some(0) {
+            return Choice::Boot;
+        }
+        match prompt(left) {
+            Some(key) => match choice(key) {
+                Some(choice) => return choice,
+                None => left = None,
+            },
+            None => left = left.map(|secs| secs - 1),
+        }
+    }
+}
+
+/// A kernel image in the root directory of the boot partition.
+pub struct Image {
+    pub name: String,
+    /// When the image was last modified as (year, month, day, hour, minute,
+    /// second), which orders the same way the times do.
+    pub modified: (usize, u8, u8, u8, u8, u8),
+}
+
+impl Image {
+    pub fn new<T: Timestamp>(name: String, modified: &T) -> Image {
+        let modified = (modified.year(), modified.month(), modified.day(),
+                        modified.hour(), modified.minute(), modified.second());
+        Image { name, modified }
+    }
+}
+
+/// Returns `true` if `name` looks like a kernel image, i.e. ends in `.bin`
+/// in any case.
+pub fn is_image(name: &str) -> bool {
+    let name = name.as_bytes();
+    name.len() > 4 && name[name.len() - 4..].eq_ignore_ascii_case(b".bin")
+}
+
+/// Returns the index of the image to boot if the user doesn't pick one: the
+/// one named `preferred` if it's there, or else the newest. Returns `None` if
+/// there are no images.
+pub fn default_image(images: &[Image], preferred: &str) -> Option<usize> {
+    if let Some(index) = images.iter().position(|image| image.name == preferred) {
+        return Some(index);
+    }
+
+    let mut newest: Option<usize> = None;
+    for (index, image) in images.iter().enumerate() {
+        match newest {
+            Some(best) if images[best].modified >= image.modified => (),
+            _ => newest = Some(index),
+        }
+    }
+    newest
+}
+
+/// Parses the user's answer to the boot menu, which lists `count` images
+/// numbered from 1. An empty answer picks `default`. Returns the index of
+/// the chosen image, or `None` if the answer isn't one of the numbers.
+pub fn parse_choice(input: &str, count: usize, default: usize) -> Option<usize> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(default);
+    }
+
+    match input.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= count => Some(number - 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use super::{countdown, default_image, is_image, parse_choice, Choice, Image};
+
+    fn image(name: &str, day: u8) -> Image {
+        Image { name: String::from(name), modified: (2019, 11, day, 12, 0, 0) }
+    }
+
+    /// Runs `countdown()` with `keys` as what each call to the prompt gets,
+    /// and returns the choice along with the seconds left at each call.
+    fn run_countdown(secs: u64, keys: &[Option<u8>]) -> (Choice, Vec<Option<u64>>) {
+        let mut keys = keys.iter();
+        let mut shown = Vec::new();
+        let choice = countdown(secs, |left| {
+            shown.push(left);
+            *keys.next().expect("prompted too often")
+        });
+        (choice, shown)
+    }
+
+    #[test]
+    fn countdown_boots_unless_interrupted() {
+        // Nobody is there
+        let (choice, shown) = run_countdown(3, &[None, None, None]);
+        assert_eq!(choice, Choice::Boot);
+        assert_eq!(shown, [Some(3), Some(2), Some(1)]);
+
+        // Picked before the time is up
+        let (choice, shown) = run_countdown(3, &[None, Some(b'1')]);
+        assert_eq!(choice, Choice::Download);
+        assert_eq!(shown, [Some(3), Some(2)]);
+
+        // Any other key stops the countdown for good
+        let (choice, shown) = run_countdown(3, &[Some(b'x'), None, None, None, Some(b'1')]);
+        assert_eq!(choice, Choice::Download);
+        assert_eq!(shown, [Some(3), None, None, None, None]);
+
+        assert_eq!(run_countdown(0, &[]).0, Choice::Boot);
+    }
+
+    #[test]
+    fn images_are_bin_files() {
+        assert!(is_image("real_kernel.bin"));
+        assert!(is_image("GOOD.BIN"));
+        assert!(!is_image(".bin"));
+        assert!(!is_image("real_kernel.crc"));
+        assert!(!is_image("config.txt"));
+        assert!(!is_image("bin"));
+    }
+
+    #[test]
+    fn default_is_preferred_then_newest() {
+        let images = [image("old.bin", 1), image("new.bin", 20), image("real_kernel.bin", 5)];
+        assert_eq!(default_image(&images, "real_kernel.bin"), Some(2));
+        assert_eq!(default_image(&images, "missing.bin"), Some(1));
+        // The first of equally new images wins
+        let images = [image("a.bin", 1), image("b.bin", 1)];
+        assert_eq!(default_image(&images, "missing.bin"), Some(0));
+        assert_eq!(default_image(&[], "real_kernel.bin"), None);
+    }
+
+    #[test]
+    fn choice_parsing() {
+        assert_eq!(parse_choice("", 3, 1), Some(1));
+        assert_eq!(parse_choice("  ", 3, 2), Some(2));
+        assert_eq!(parse_choice("1", 3, 2), Some(0));
+        assert_eq!(parse_choice(" 3 ", 3, 0), Some(2));
+        assert_eq!(parse_choice("0", 3, 0), None);
+        assert_eq!(parse_choice("4", 3, 0), None);
+        assert_eq!(parse_choice("-1", 3, 0), None);
+        assert_eq!(parse_choice("two", 3, 0), None);
+    }
+}
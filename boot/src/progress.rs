@@ -0,0 +1,75 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use xmodem::Progress;
+
+/// Bytes received so far in the current XMODEM attempt. `Xmodem` takes a
+/// plain `fn` as its progress callback, so the count can't live in a closure.
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+/// Progress callback for `Xmodem::receive_with_progress()` that keeps count
+/// of the bytes received.
+///
+/// Nothing is printed from here: the sender reads every byte it gets back on
+/// the UART as its reply to a packet, so any text would abort the transfer.
+pub fn record(progress: Progress) {
+    // A load and a store, since atomics can't do more before the MMU is on
+    match progress {
+        // Sent as each attempt starts, so a retry counts from zero again
+        Progress::Started => RECEIVED.store(0, Ordering::Relaxed),
+        Progress::Packet(_) => RECEIVED.store(RECEIVED.load(Ordering::Relaxed) + 128, Ordering::Relaxed),
+        _ => (),
+    }
+}
+
+/// Returns the number of bytes received in the current or last attempt.
+pub fn received() -> usize {
+    RECEIVED.load(Ordering::Relaxed)
+}
+
+/// A number of bytes, shown in B, KiB or MiB with one decimal.
+pub struct Size(pub usize);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Size(bytes) = *self;
+        let (unit, name) = match bytes {
+            0..=1023 => return write!(f, "{} B", bytes),
+            1024..=0xF_FFFF => (1 << 10, "KiB"),
+            _ => (1 << 20, "MiB"),
+        };
+        // Round to the nearest tenth without floating point
+        let tenths = (bytes * 10 + unit / 2) / unit;
+        write!(f, "{}.{} {}", tenths / 10, tenths % 10, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use xmodem::Progress;
+    use super::{received, record, Size};
+
+    #[test]
+    fn sizes_are_readable() {
+        assert_eq!(Size(0).to_string(), "0 B");
+        assert_eq!(Size(1023).to_string(), "1023 B");
+        assert_eq!(Size(1024).to_string(), "1.0 KiB");
+        assert_eq!(Size(128 * 100).to_string(), "12.5 KiB");
+        assert_eq!(Size(3 << 20 | 1 << 19).to_string(), "3.5 MiB");
+    }
+
+    #[test]
+    fn retries_count_from_zero() {
+        record(Progress::Started);
+        record(Progress::Packet(1));
+        record(Progress::Packet(2));
+        record(Progress::NAK);
+        assert_eq!(received(), 256);
+
+        record(Progress::Started);
+        assert_eq!(received(), 0);
+        record(Progress::Packet(1));
+        assert_eq!(received(), 128);
+    }
+}
@@ -34,6 +34,11 @@ impl Allocator {
         let (start, end) = memory_map().expect("failed to find memory map");
         *self.0.lock() = Some(AllocatorImpl::new(start, end));
     }
+
+    /// Returns `true` if an allocation or deallocation is in progress.
+    pub fn is_locked(&self) -> bool {
+        self.0.is_locked()
+    }
 }
 
 unsafe impl GlobalAlloc for Allocator {
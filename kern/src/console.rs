@@ -1,18 +1,130 @@
+use alloc::collections::vec_deque::VecDeque;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
 use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use pi::framebuffer::Framebuffer;
 use pi::uart::MiniUart;
 use shim::io;
 
+use crate::fbcon::FbConsole;
 use crate::mutex::Mutex;
+use crate::shell::line_editor::LineEditor;
+
+/// Size in pixels of the framebuffer asked for by `Console::attach_screen()`.
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 768;
+
+/// The byte sent by Ctrl-C (ETX).
+pub const CTRL_C: u8 = 0x03;
+
+/// Number of bytes that can be received ahead of being read. Must be a power
+/// of two so that the ring indices can wrap around `usize`.
+const RX_MAX: usize = 256;
+
+/// Ring buffer of bytes received from the UART but not yet read. Only the
+/// UART interrupt pushes and only readers of the console pop, so each index
+/// has a single writer and the two sides never wait on each other.
+struct RxRing {
+    buf: UnsafeCell<[u8; RX_MAX]>,
+    /// Total number of bytes popped
+    head: AtomicUsize,
+    /// Total number of bytes pushed
+    tail: AtomicUsize,
+}
+
+unsafe impl Sync for RxRing {}
+
+impl RxRing {
+    const fn new() -> RxRing {
+        RxRing { buf: UnsafeCell::new([0; RX_MAX]), head: AtomicUsize::new(0), tail: AtomicUsize::new(0) }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+
+    /// Adds `byte` to the end of the ring. Returns `false` and drops the byte
+    /// if the ring is full.
+    fn push(&self, byte: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == RX_MAX {
+            return false;
+        }
+        unsafe { (*self.buf.get())[tail % RX_MAX] = byte };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let byte = unsafe { (*self.buf.get())[head % RX_MAX] };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(byte)
+    }
+}
+
+static RX: RxRing = RxRing::new();
+
+/// Set when a Ctrl-C has been received but not yet handled by `interrupted()`.
+static CTRL_C_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// The byte sent by Ctrl-D (EOT), which ends input when the line is empty.
+pub const CTRL_D: u8 = 0x04;
+
+/// The cooked-mode line discipline: input is edited a line at a time, with
+/// the same keys as the kernel shell, and only handed on once the line has
+/// been entered.
+pub struct LineReader {
+    editor: LineEditor,
+    /// The entered line and its newline, not yet handed on
+    ready: VecDeque<u8>,
+}
+
+impl LineReader {
+    /// Returns a reader with nothing typed yet.
+    pub fn new() -> LineReader {
+        LineReader { editor: LineEditor::new(), ready: VecDeque::new() }
+    }
+
+    /// Handles one input byte, echoing its effect to `out`. Returns `true`
+    /// if a line has been entered and `pop()` will return its bytes. Ctrl-D
+    /// on an empty line is passed on by itself to mark the end of input.
+    pub fn feed<W: fmt::Write>(&mut self, byte: u8, out: &mut W) -> bool {
+        if byte == CTRL_D && self.editor.line().is_empty() {
+            self.ready.push_back(CTRL_D);
+        } else if self.editor.feed(byte, out, |_| Vec::new()) {
+            let _ = out.write_str("\n");
+            self.ready.extend(self.editor.take_line().bytes());
+            self.ready.push_back(b'\n');
+        }
+        !self.ready.is_empty()
+    }
+
+    /// Returns the next byte of the entered line, if there is one.
+    pub fn pop(&mut self) -> Option<u8> {
+        self.ready.pop_front()
+    }
+}
 
 /// A global singleton allowing read/write access to the console.
 pub struct Console {
     inner: Option<MiniUart>,
+    /// In raw mode, input to processes is neither echoed nor edited
+    raw: bool,
+    /// Line discipline for cooked mode, created on first use
+    cooked: Option<LineReader>,
+    /// Text console on the screen that output is mirrored to, if any
+    screen: Option<FbConsole<'static>>,
 }
 
 impl Console {
     /// Creates a new instance of `Console`.
     const fn new() -> Console {
-        Console { inner: None }
+        Console { inner: None, raw: false, cooked: None, screen: None }
     }
 
     /// Initializes the console if it's not already initialized.
@@ -31,26 +143,115 @@ impl Console {
         self.inner.as_mut().unwrap()
     }
 
+    /// Starts mirroring output to a text console on the screen. Returns an
+    /// error if the firmware doesn't hand out a framebuffer, in which case
+    /// output keeps going to the UART alone.
+    pub fn attach_screen(&mut self) -> io::Result<()> {
+        let fb = Framebuffer::new(SCREEN_WIDTH, SCREEN_HEIGHT)?;
+        self.screen = Some(FbConsole::new(fb));
+        Ok(())
+    }
+
+    /// Makes the UART raise `Interrupt::Aux` when input arrives, so that
+    /// `receive()` can be called from its handler instead of polling.
+    pub fn enable_rx_interrupt(&mut self) {
+        self.inner().enable_rx_interrupt()
+    }
+
+    /// Returns `true` if a byte is available to read without blocking.
+    pub fn has_byte(&mut self) -> bool {
+        !RX.is_empty() || self.inner().has_byte()
+    }
+
     /// Reads a byte from the UART device, blocking until a byte is available.
+    /// Bytes already received by `receive()` come first.
     pub fn read_byte(&mut self) -> u8 {
-        self.inner().read_byte()
+        match RX.pop() {
+            Some(byte) => byte,
+            None => self.inner().read_byte(),
+        }
+    }
+
+    /// Switches between raw and cooked mode, returning whether the console
+    /// was in raw mode before.
+    pub fn set_raw(&mut self, raw: bool) -> bool {
+        core::mem::replace(&mut self.raw, raw)
+    }
+
+    /// Returns the next byte of input for a process without blocking, or
+    /// `None` if there isn't one yet. See `next_input()`.
+    pub fn poll_input(&mut self) -> Option<u8> {
+        let raw = self.raw;
+        let mut cooked = self.cooked.take();
+        let byte = next_input(self, raw, &mut cooked);
+        self.cooked = cooked;
+        byte
     }
 
-    /// Writes the byte `byte` to the UART device.
+    /// Moves every byte waiting in the UART into the receive buffer, which
+    /// also clears the UART interrupt. Ctrl-C is held back for
+    /// `interrupted()` rather than buffered. Bytes that arrive while the
+    /// buffer is full are dropped.
+    pub fn receive(&mut self) {
+        while self.inner().has_byte() {
+            let byte = self.inner().read_byte();
+            if byte == CTRL_C {
+                CTRL_C_PENDING.store(true, Ordering::SeqCst);
+            } else {
+                RX.push(byte);
+            }
+        }
+    }
+
+    /// Returns `true` if a Ctrl-C has been received since the last call and
+    /// `intercept` is set, so that the caller can interrupt the foreground
+    /// process. Without `intercept`, the Ctrl-C is buffered as normal input.
+    pub fn interrupted(&mut self, intercept: bool) -> bool {
+        if !CTRL_C_PENDING.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+        if !intercept {
+            RX.push(CTRL_C);
+        }
+        intercept
+    }
+
+    /// Writes the byte `byte` to the UART device and the screen.
     pub fn write_byte(&mut self, byte: u8) {
-        self.inner().write_byte(byte)
+        self.inner().write_byte(byte);
+        if let Some(screen) = &mut self.screen {
+            screen.write_byte(byte);
+        }
     }
 }
 
 impl io::Read for Console {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner().read(buf)
+        if RX.is_empty() {
+            return self.inner().read(buf);
+        }
+
+        let mut amt = 0;
+        while amt < buf.len() {
+            match RX.pop() {
+                Some(byte) => buf[amt] = byte,
+                None => break,
+            }
+            amt += 1;
+        }
+        Ok(amt)
     }
 }
 
 impl io::Write for Console {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner().write(buf)
+        let amt = self.inner().write(buf)?;
+        if let Some(screen) = &mut self.screen {
+            for &byte in &buf[..amt] {
+                screen.write_byte(byte);
+            }
+        }
+        Ok(amt)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -60,13 +261,62 @@ impl io::Write for Console {
 
 impl fmt::Write for Console {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.inner().write_str(s)
+        self.inner().write_str(s)?;
+        if let Some(screen) = &mut self.screen {
+            screen.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+/// The device behind the console: where input is read from and echoed to.
+trait Terminal: fmt::Write {
+    fn has_byte(&mut self) -> bool;
+    fn read_byte(&mut self) -> u8;
+}
+
+impl Terminal for Console {
+    fn has_byte(&mut self) -> bool {
+        Console::has_byte(self)
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        Console::read_byte(self)
+    }
+}
+
+/// Returns the next byte of input from `term` without blocking, or `None` if
+/// there isn't one yet. In `raw` mode, every byte is returned as it arrives.
+/// Otherwise bytes go through the `LineReader` in `cooked`, which is created
+/// on first use, and are only returned once their line has been entered.
+fn next_input<T: Terminal>(term: &mut T, raw: bool, cooked: &mut Option<LineReader>) -> Option<u8> {
+    if raw {
+        return if term.has_byte() { Some(term.read_byte()) } else { None };
+    }
+
+    let reader = cooked.get_or_insert_with(LineReader::new);
+    let mut byte = reader.pop();
+    while byte.is_none() && term.has_byte() {
+        let input = term.read_byte();
+        if reader.feed(input, term) {
+            byte = reader.pop();
+        }
     }
+    byte
 }
 
 /// Global `Console` singleton.
 pub static CONSOLE: Mutex<Console> = Mutex::new(Console::new());
 
+/// ANSI escape sequence that clears the screen and moves the cursor to the
+/// top-left corner.
+pub const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Clears the terminal attached to `out`, which is usually the console.
+pub fn clear<W: fmt::Write>(out: &mut W) -> fmt::Result {
+    out.write_str(CLEAR_SCREEN)
+}
+
 /// Internal function called by the `kprint[ln]!` macros.
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
@@ -94,3 +344,114 @@ pub macro kprintln {
 pub macro kprint($($arg:tt)*) {
     _print(format_args!($($arg)*))
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use alloc::collections::vec_deque::VecDeque;
+    use core::fmt;
+    use super::{next_input, LineReader, RxRing, Terminal, CTRL_D, RX_MAX};
+
+    #[test]
+    fn rx_ring_wraps_and_drops_when_full() {
+        let ring = RxRing::new();
+        assert_eq!(ring.pop(), None);
+
+        // Go around the ring a few times so the indices wrap
+        for round in 0..3 {
+            for i in 0..RX_MAX {
+                assert!(ring.push((i + round) as u8));
+            }
+            assert!(!ring.push(0xff));
+            for i in 0..RX_MAX {
+                assert_eq!(ring.pop(), Some((i + round) as u8));
+            }
+            assert!(ring.is_empty());
+        }
+
+        ring.push(b'a');
+        ring.push(b'b');
+        assert_eq!(ring.pop(), Some(b'a'));
+        ring.push(b'c');
+        assert_eq!(ring.pop(), Some(b'b'));
+        assert_eq!(ring.pop(), Some(b'c'));
+        assert_eq!(ring.pop(), None);
+    }
+
+    fn feed_all(reader: &mut LineReader, bytes: &[u8], out: &mut String) -> Vec<u8> {
+        for &byte in bytes {
+            reader.feed(byte, out);
+        }
+        core::iter::from_fn(|| reader.pop()).collect()
+    }
+
+    #[test]
+    fn line_reader_edits_before_handing_on() {
+        let mut reader = LineReader::new();
+        let mut out = String::new();
+
+        // Nothing is handed on until the line is entered
+        assert_eq!(feed_all(&mut reader, b"ecgo\x08\x08ho hi", &mut out), b"");
+        assert_eq!(feed_all(&mut reader, b"\r", &mut out), b"echo hi\n");
+        assert!(out.starts_with("ecgo\x08"));
+        assert!(out.ends_with("\n"));
+
+        assert_eq!(feed_all(&mut reader, b"junk\x15ok\x7f\x7fbye\n", &mut out), b"bye\n");
+
+        // Ctrl-D only ends input on an empty line
+        assert_eq!(feed_all(&mut reader, &[CTRL_D], &mut out), [CTRL_D]);
+        assert_eq!(feed_all(&mut reader, &[b'x', CTRL_D], &mut out), b"");
+    }
+
+    /// Input typed ahead of time, and everything echoed back.
+    struct FakeTerminal {
+        input: VecDeque<u8>,
+        echoed: String,
+    }
+
+    impl FakeTerminal {
+        fn with(input: &[u8]) -> FakeTerminal {
+            FakeTerminal { input: input.iter().cloned().collect(), echoed: String::new() }
+        }
+    }
+
+    impl fmt::Write for FakeTerminal {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.echoed.push_str(s);
+            Ok(())
+        }
+    }
+
+    impl Terminal for FakeTerminal {
+        fn has_byte(&mut self) -> bool {
+            !self.input.is_empty()
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            self.input.pop_front().unwrap()
+        }
+    }
+
+    #[test]
+    fn raw_input_is_immediate_and_not_echoed() {
+        let mut term = FakeTerminal::with(b"a\x7f\r\x1b");
+        let mut cooked = None;
+        for &byte in b"a\x7f\r\x1b" {
+            assert_eq!(next_input(&mut term, true, &mut cooked), Some(byte));
+        }
+        assert_eq!(next_input(&mut term, true, &mut cooked), None);
+        assert_eq!(term.echoed, "");
+        assert!(cooked.is_none());
+
+        // The same input cooked is held back until it's entered, and echoed
+        let mut term = FakeTerminal::with(b"hi");
+        assert_eq!(next_input(&mut term, false, &mut cooked), None);
+        assert_eq!(term.echoed, "hi");
+        term.input.push_back(b'\r');
+        assert_eq!(next_input(&mut term, false, &mut cooked), Some(b'h'));
+        assert_eq!(next_input(&mut term, false, &mut cooked), Some(b'i'));
+        assert_eq!(next_input(&mut term, false, &mut cooked), Some(b'\n'));
+        assert_eq!(next_input(&mut term, false, &mut cooked), None);
+    }
+}
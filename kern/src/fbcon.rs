@@ -0,0 +1,263 @@
+mod font;
+
+use core::fmt;
+
+use pi::framebuffer::Framebuffer;
+
+use self::font::{glyph, GLYPH_SIZE};
+
+/// Color of text, as `0x00RRGGBB`.
+const FOREGROUND: u32 = 0x00AA_AAAA;
+/// Color of the background.
+const BACKGROUND: u32 = 0x0000_0000;
+
+/// Tab stops are this many columns apart.
+const TAB_WIDTH: usize = 8;
+/// Most parameters kept from an escape sequence.
+const PARAMS_MAX: usize = 2;
+
+/// Where we are in an ANSI escape sequence.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Escape {
+    None,
+    /// Got `ESC`
+    Start,
+    /// Got `ESC [` and the parameters so far
+    Csi { params: [usize; PARAMS_MAX], count: usize },
+}
+
+/// A text console drawn on a framebuffer with an 8x8 font. Understands
+/// newline, carriage return, backspace and tab, plus the ANSI escape
+/// sequences the shell uses: clearing the screen or line and moving the
+/// cursor. The cursor is shown as an inverted cell, and the screen scrolls
+/// up when text runs off the bottom.
+pub struct FbConsole<'a> {
+    fb: Framebuffer<'a>,
+    cols: usize,
+    rows: usize,
+    col: usize,
+    row: usize,
+    escape: Escape,
+}
+
+impl<'a> FbConsole<'a> {
+    /// Returns a console filling `fb`, cleared and with the cursor at the
+    /// top-left corner.
+    pub fn new(fb: Framebuffer<'a>) -> FbConsole<'a> {
+        let cols = fb.width() / GLYPH_SIZE;
+        let rows = fb.height() / GLYPH_SIZE;
+        let mut console = FbConsole { fb, cols, rows, col: 0, row: 0, escape: Escape::None };
+        console.clear();
+        console.toggle_cursor();
+        console
+    }
+
+    /// Writes `byte` at the cursor, or carries out the control character or
+    /// escape sequence it is part of.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.toggle_cursor();
+        self.escape = match self.escape {
+            Escape::None => self.plain(byte),
+            Escape::Start if byte == b'[' => Escape::Csi { params: [0; PARAMS_MAX], count: 0 },
+            Escape::Start => Escape::None,
+            Escape::Csi { mut params, mut count } => match byte {
+                b'0'..=b'9' => {
+                    if count == 0 {
+                        count = 1;
+                    }
+                    if let Some(param) = params.get_mut(count - 1) {
+                        *param = *param * 10 + (byte - b'0') as usize;
+                    }
+                    Escape::Csi { params, count }
+                },
+                b';' => Escape::Csi { params, count: count.max(1) + 1 },
+                _ => {
+                    self.csi(byte, &params[..count.min(PARAMS_MAX)]);
+                    Escape::None
+                },
+            },
+        };
+        self.toggle_cursor();
+    }
+
+    /// Handles a byte outside of an escape sequence and returns the state
+    /// after it.
+    fn plain(&mut self, byte: u8) -> Escape {
+        match byte {
+            0x1b => return Escape::Start,
+            b'\n' => self.newline(),
+            b'\r' => self.col = 0,
+            0x08 => self.col = self.col.saturating_sub(1),
+            b'\t' => {
+                for _ in self.col % TAB_WIDTH..TAB_WIDTH {
+                    self.put(b' ');
+                }
+            },
+            // The bell and other control characters aren't shown
+            0x00..=0x1f | 0x7f => (),
+            _ => self.put(byte),
+        }
+        Escape::None
+    }
+
+    /// Carries out the escape sequence `ESC [ params final`.
+    fn csi(&mut self, final_byte: u8, params: &[usize]) {
+        // Missing parameters, and for moves 0, mean the default
+        let param = |i: usize, default: usize| params.get(i).cloned().unwrap_or(default);
+        let count = param(0, 1).max(1);
+        match final_byte {
+            b'A' => self.row = self.row.saturating_sub(count),
+            b'B' => self.row = (self.row + count).min(self.rows - 1),
+            b'C' => self.col = (self.col + count).min(self.cols - 1),
+            b'D' => self.col = self.col.saturating_sub(count),
+            b'H' | b'f' => {
+                self.row = (param(0, 1).max(1) - 1).min(self.rows - 1);
+                self.col = (param(1, 1).max(1) - 1).min(self.cols - 1);
+            },
+            b'J' => match param(0, 0) {
+                2 => self.clear(),
+                // From the cursor to the end of the screen
+                _ => {
+                    self.clear_line_from(self.col);
+                    let top = (self.row + 1) * GLYPH_SIZE;
+                    let (width, height) = (self.fb.width(), self.fb.height());
+                    self.fb.fill_rect(0, top, width, height, BACKGROUND);
+                },
+            },
+            b'K' => self.clear_line_from(self.col),
+            // Colors and anything else aren't supported
+            _ => (),
+        }
+    }
+
+    /// Draws `byte` at the cursor and moves the cursor on, wrapping to the
+    /// next line at the right edge.
+    fn put(&mut self, byte: u8) {
+        let (x, y) = (self.col * GLYPH_SIZE, self.row * GLYPH_SIZE);
+        for (dy, bits) in glyph(byte).iter().enumerate() {
+            for dx in 0..GLYPH_SIZE {
+                let color = if bits & (1 << dx) != 0 { FOREGROUND } else { BACKGROUND };
+                self.fb.set_pixel(x + dx, y + dy, color);
+            }
+        }
+
+        self.col += 1;
+        if self.col == self.cols {
+            self.newline();
+        }
+    }
+
+    /// Moves the cursor to the start of the next line, scrolling if it is on
+    /// the last one.
+    fn newline(&mut self) {
+        self.col = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.fb.scroll_up(GLYPH_SIZE, BACKGROUND);
+        }
+    }
+
+    /// Clears the screen and moves the cursor to the top-left corner.
+    fn clear(&mut self) {
+        let (width, height) = (self.fb.width(), self.fb.height());
+        self.fb.fill_rect(0, 0, width, height, BACKGROUND);
+        self.col = 0;
+        self.row = 0;
+    }
+
+    /// Clears the current line from column `col` to the right edge.
+    fn clear_line_from(&mut self, col: usize) {
+        let width = self.fb.width();
+        self.fb.fill_rect(col * GLYPH_SIZE, self.row * GLYPH_SIZE, width, GLYPH_SIZE, BACKGROUND);
+    }
+
+    /// Shows or hides the cursor by inverting the cell under it. Every change
+    /// to the screen happens between a pair of calls, so the cell is always
+    /// back to normal while it is drawn on.
+    fn toggle_cursor(&mut self) {
+        let (x, y) = (self.col * GLYPH_SIZE, self.row * GLYPH_SIZE);
+        for dy in 0..GLYPH_SIZE {
+            for dx in 0..GLYPH_SIZE {
+                let color = self.fb.pixel(x + dx, y + dy);
+                self.fb.set_pixel(x + dx, y + dy, color ^ FOREGROUND ^ BACKGROUND);
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Write for FbConsole<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use core::fmt::Write;
+    use pi::framebuffer::Framebuffer;
+
+    use super::font::{glyph, GLYPH_SIZE};
+    use super::{FbConsole, BACKGROUND, FOREGROUND};
+
+    /// Returns whether the cell at `col`, `row` shows `byte`, inverted if
+    /// the cursor is on it.
+    fn shows(console: &FbConsole, col: usize, row: usize, byte: u8, cursor: bool) -> bool {
+        let (on, off) = if cursor { (BACKGROUND, FOREGROUND) } else { (FOREGROUND, BACKGROUND) };
+        glyph(byte).iter().enumerate().all(|(dy, bits)| {
+            (0..GLYPH_SIZE).all(|dx| {
+                let want = if bits & (1 << dx) != 0 { on } else { off };
+                console.fb.pixel(col * GLYPH_SIZE + dx, row * GLYPH_SIZE + dy) == want
+            })
+        })
+    }
+
+    #[test]
+    fn renders_glyphs_and_cursor() {
+        let mut pixels = vec![0x00ff_00ffu32; 32 * 24];
+        let mut console = FbConsole::new(Framebuffer::from_pixels(&mut pixels, 32, 24, 32));
+        assert!(shows(&console, 0, 0, b' ', true));
+
+        console.write_str("Hi!").unwrap();
+        assert!(shows(&console, 0, 0, b'H', false));
+        assert!(shows(&console, 1, 0, b'i', false));
+        assert!(shows(&console, 2, 0, b'!', false));
+        assert!(shows(&console, 3, 0, b' ', true));
+        // A pixel that is set in `H` and one that isn't
+        assert_eq!(console.fb.pixel(0, 0), FOREGROUND);
+        assert_eq!(console.fb.pixel(2, 0), BACKGROUND);
+
+        // Backspace over the `!`, then erase the rest of the line
+        console.write_str("\x08\x1b[K").unwrap();
+        assert!(shows(&console, 2, 0, b' ', true));
+        assert!(shows(&console, 1, 0, b'i', false));
+    }
+
+    #[test]
+    fn wraps_and_scrolls() {
+        // Room for 4x3 characters
+        let mut pixels = vec![0; 32 * 24];
+        let mut console = FbConsole::new(Framebuffer::from_pixels(&mut pixels, 32, 24, 32));
+
+        console.write_str("abcdefg\nxy").unwrap();
+        assert!(shows(&console, 3, 0, b'd', false));
+        assert!(shows(&console, 0, 1, b'e', false));
+        assert!(shows(&console, 0, 2, b'x', false));
+
+        // The next line scrolls the first one off the top
+        console.write_str("\nz").unwrap();
+        assert!(shows(&console, 0, 0, b'e', false));
+        assert!(shows(&console, 1, 1, b'y', false));
+        assert!(shows(&console, 0, 2, b'z', false));
+        assert!(shows(&console, 1, 2, b' ', true));
+
+        console.write_str("\x1b[2J\x1b[2;3Hq").unwrap();
+        assert!(shows(&console, 0, 0, b' ', false));
+        assert!(shows(&console, 2, 1, b'q', false));
+        assert!(shows(&console, 3, 1, b' ', true));
+    }
+}
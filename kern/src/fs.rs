@@ -1,7 +1,9 @@
 pub mod fd;
 pub mod mount_map;
+pub mod pipe;
 
 use alloc::rc::Rc;
+use alloc::vec::Vec;
 use core::fmt::{self, Debug};
 use shim::io;
 use shim::ioerr;
@@ -13,7 +15,7 @@ use blockdev::mount::MountOptions;
 use blockdev::block_device::BlockDevice;
 
 use sd::sd::Sd;
-use self::mount_map::MountMap;
+use self::mount_map::{MountMap, MountUsage};
 use crate::mutex::Mutex;
 use crate::console::kprintln;
 
@@ -103,6 +105,117 @@ impl FileSystem {
         }
     }
 
+    /// Returns `true` if an operation on the mounted filesystems is in
+    /// progress.
+    pub fn is_locked(&self) -> bool {
+        self.0.is_locked()
+    }
+
+    /// Flushes every mounted filesystem and returns how many were flushed.
+    pub fn flush_all(&self) -> usize {
+        match &mut *self.0.lock() {
+            Some(map) => map.flush_all(),
+            None => 0,
+        }
+    }
+
+    /// Returns `true` if `a` and `b` are on the same mounted filesystem.
+    pub fn same_mount<P: AsRef<Path>, Q: AsRef<Path>>(&self, a: P, b: Q) -> bool {
+        match &*self.0.lock() {
+            Some(map) => {
+                let a = map.mount_point(&a.as_ref().to_path_buf());
+                a.is_some() && a == map.mount_point(&b.as_ref().to_path_buf())
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the cluster size in bytes of the filesystem mounted at `path`.
+    pub fn cluster_size<P: AsRef<Path>>(&self, path: P) -> Option<u64> {
+        match &mut *self.0.lock() {
+            Some(map) => match map.route(&path.as_ref().to_path_buf()) {
+                Ok((vfat, _real_path)) => Some(vfat.lock(|vfat| vfat.bytes_per_cluster() as u64)),
+                Err(_) => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Moves the entry at `from` to `to` by rewriting directory entries only.
+    /// Both paths must be absolute and on the same mounted filesystem.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        use fat32::traits::FileSystem;
+
+        let (from, to) = (from.as_ref().to_path_buf(), to.as_ref().to_path_buf());
+        if !self.same_mount(&from, &to) {
+            return ioerr!(InvalidInput, "Can't rename across mounts");
+        }
+        if to.starts_with(&from) {
+            return ioerr!(InvalidInput, "Can't move a directory into itself");
+        }
+
+        let mut map = self.0.lock();
+        let map = match &mut *map {
+            Some(map) => map,
+            None => return ioerr!(Other, "Filesystem must be initialized before calling rename()"),
+        };
+        let (vfat, real_from) = map.route(&from).or_else(|_| ioerr!(NotFound, "Path is not mounted"))?;
+        let vfat = vfat.clone();
+        let real_to = match map.route(&to) {
+            Ok((_, real_to)) => real_to,
+            Err(_) => return ioerr!(NotFound, "Path is not mounted"),
+        };
+
+        let name = match real_to.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return ioerr!(InvalidInput, "Missing destination name"),
+        };
+        let entry = (&vfat).open(&real_from)?;
+        let mut dir = (&vfat).open_dir(real_to.parent().unwrap_or(Path::new("/")))?;
+        dir.rename(entry, name).map(|_| ())
+    }
+
+    /// Adds a hard link at `to` to the file at `from`. Both paths must be
+    /// absolute and on the same mounted filesystem. See `fat32::vfat::Dir::link`
+    /// for what FAT can and can't share between the names.
+    pub fn link<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        use fat32::traits::FileSystem;
+
+        let (from, to) = (from.as_ref().to_path_buf(), to.as_ref().to_path_buf());
+        if !self.same_mount(&from, &to) {
+            return ioerr!(InvalidInput, "Can't link across mounts");
+        }
+
+        let mut map = self.0.lock();
+        let map = match &mut *map {
+            Some(map) => map,
+            None => return ioerr!(Other, "Filesystem must be initialized before calling link()"),
+        };
+        let (vfat, real_from) = map.route(&from).or_else(|_| ioerr!(NotFound, "Path is not mounted"))?;
+        let vfat = vfat.clone();
+        let real_to = match map.route(&to) {
+            Ok((_, real_to)) => real_to,
+            Err(_) => return ioerr!(NotFound, "Path is not mounted"),
+        };
+
+        let name = match real_to.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return ioerr!(InvalidInput, "Missing link name"),
+        };
+        let file = (&vfat).open_file(&real_from)?;
+        let mut dir = (&vfat).open_dir(real_to.parent().unwrap_or(Path::new("/")))?;
+        dir.link(&file, name).map(|_| ())
+    }
+
+    /// Returns the size and free space of every mounted filesystem. For more
+    /// details, see the documentation on `MountMap::usage()`.
+    pub fn usage(&self) -> io::Result<Vec<MountUsage>> {
+        match &mut *self.0.lock() {
+            Some(map) => map.usage(),
+            None => ioerr!(Other, "Filesystem must be initialized before calling usage()"),
+        }
+    }
+
     pub fn lsblk(&self) {
         match &*self.0.lock() {
             Some(map) => kprintln!("{}", map),
@@ -168,6 +281,63 @@ impl FileSystem {
     
 }
 
+/// The FAT32 image that tests mount, unpacked by `lib/fat32/reset_tests.sh`.
+#[cfg(test)]
+const TEST_IMAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../ext/fat32-imgs/mock2.fat32.img");
+
+/// Set while a test is using `FILESYSTEM`. See `FileSystem::initialize_for_test()`.
+#[cfg(test)]
+static TEST_IN_USE: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+/// Lets the next test use `FILESYSTEM` when dropped.
+#[cfg(test)]
+pub struct TestGuard;
+
+#[cfg(test)]
+impl Drop for TestGuard {
+    fn drop(&mut self) {
+        TEST_IN_USE.store(false, core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Returns a copy of the test image held in memory, so that tests can change
+/// it without touching the file or each other.
+#[cfg(test)]
+pub fn test_image() -> io::Cursor<Vec<u8>> {
+    let image = std::fs::read(TEST_IMAGE).expect("missing test image; run lib/fat32/reset_tests.sh");
+    io::Cursor::new(image)
+}
+
+/// Returns a filesystem of its own on a copy of the test image.
+#[cfg(test)]
+pub fn test_vfat() -> PiVFatHandle {
+    VFat::<PiVFatHandle>::from(test_image(), 1, MountOptions::Normal).expect("couldn't mount the test image")
+}
+
+#[cfg(test)]
+impl FileSystem {
+    /// Mounts a copy of the test image at `/` if nothing is mounted yet.
+    /// Tests run on several threads and the kernel's `Mutex` doesn't lock
+    /// between them, so this waits until no other test is using the
+    /// filesystem and keeps it to the caller until the guard is dropped.
+    pub fn initialize_for_test(&self) -> TestGuard {
+        use core::sync::atomic::Ordering;
+
+        while TEST_IN_USE.compare_and_swap(false, true, Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        let guard = TestGuard;
+
+        let mut map = self.0.lock();
+        if map.is_none() {
+            let mut mount_map = MountMap::new();
+            mount_map.mount_root(test_image(), 1, MountOptions::Normal).expect("couldn't mount the test image");
+            *map = Some(mount_map);
+        }
+        guard
+    }
+}
+
 // Implement `fat32::traits::FileSystem` for `&FileSystem`
 impl fat32::traits::FileSystem for &FileSystem {
     /// The type of files in this file system.
@@ -1,5 +1,5 @@
 use hashbrown::HashMap;
-use fat32::vfat::VFat;
+use fat32::vfat::{VFat, VFatHandle};
 use fat32::traits::FileSystem;
 use blockdev::block_device::BlockDevice;
 use blockdev::mount::*;
@@ -19,6 +19,29 @@ pub struct MapEntry {
     options: MountOptions
 }
 
+/// How much space one mounted filesystem has, in bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountUsage {
+    pub mount_point: PathBuf,
+    pub total: u64,
+    pub free: u64,
+}
+
+impl MountUsage {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+
+    /// Returns the share of the space that's used, rounded up to a whole
+    /// percent so that a nearly full filesystem doesn't show as 99%.
+    pub fn percent_used(&self) -> u64 {
+        match self.total {
+            0 => 0,
+            total => (self.used() * 100 + total - 1) / total,
+        }
+    }
+}
+
 pub struct MountMap { 
     map: HashMap<PathBuf, Box<MapEntry>>,
     mbr: MasterBootRecord,
@@ -143,12 +166,33 @@ impl MountMap {
         }
     }
 
-    /// Takes a path and returns the filesystem that's mounted there,
-    /// along with the translated path 
-    /// for example if the mount point is /boot and the path is /boot
-    /// the real path is /, when we're using the filesystem mounted at /boot
-    pub fn route(&mut self, path: &PathBuf) -> Result<(&mut PiVFatHandle, PathBuf), ()> {
-        //kprintln!("routing: {}", path.to_str().unwrap());
+    /// flushes every mounted filesystem to disk without unmounting it
+    /// and returns how many were flushed
+    pub fn flush_all(&mut self) -> usize {
+        for (_path, entry) in self.map.iter() {
+            entry.vfat.flush();
+        }
+        self.map.len()
+    }
+
+    /// Returns the size and free space of every mounted filesystem, sorted
+    /// by mount point. This reads each filesystem's whole FAT.
+    pub fn usage(&mut self) -> io::Result<Vec<MountUsage>> {
+        let mut usage = Vec::new();
+        for (path, entry) in self.map.iter() {
+            let (total, free) = entry.vfat.lock(|vfat| -> io::Result<(u64, u64)> {
+                let cluster = vfat.bytes_per_cluster() as u64;
+                Ok((vfat.total_clusters() as u64 * cluster, vfat.free_clusters()? as u64 * cluster))
+            })?;
+            usage.push(MountUsage { mount_point: path.clone(), total, free });
+        }
+        usage.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+        Ok(usage)
+    }
+
+    /// Returns the mount point that `path` falls under, i.e. the longest
+    /// mount point that's a prefix of it
+    pub fn mount_point(&self, path: &PathBuf) -> Option<PathBuf> {
         // we first get a vector of all the mount points that are prefixes of path
         let candidates: Vec<PathBuf> = self.map.keys().filter(|mount_point| path.starts_with(mount_point)).map(|pb| pb.clone()).collect();
         //kprintln!("candidates {:?}", candidates);
@@ -174,11 +218,21 @@ impl MountMap {
                 }
             });
         //kprintln!("mounted at: {:?}", mounted_at);
-        match mounted_at {
+        mounted_at.cloned()
+    }
+
+    /// Takes a path and returns the filesystem that's mounted there,
+    /// along with the translated path 
+    /// for example if the mount point is /boot and the path is /boot
+    /// the real path is /, when we're using the filesystem mounted at /boot
+    pub fn route(&mut self, path: &PathBuf) -> Result<(&mut PiVFatHandle, PathBuf), ()> {
+        //kprintln!("routing: {}", path.to_str().unwrap());
+        match self.mount_point(path) {
             // unwrap here should be safe because we know for a fact that
             // p is a key of the map
             Some(p) => {
-                Ok((&mut self.map.get_mut(p).unwrap().vfat, Self::translate_path(path, p.into())))
+                let real_path = Self::translate_path(path, &p);
+                Ok((&mut self.map.get_mut(&p).unwrap().vfat, real_path))
             },
             None => Err(())
         }
@@ -0,0 +1,53 @@
+use alloc::collections::vec_deque::VecDeque;
+use shim::io;
+
+/// An in-memory, unidirectional byte channel. Bytes written to the pipe are
+/// read back in the same order; reading from an empty pipe returns `Ok(0)`,
+/// i.e. end of file.
+#[derive(Debug, Default)]
+pub struct Pipe {
+    buf: VecDeque<u8>,
+}
+
+impl Pipe {
+    /// Returns a new, empty pipe.
+    pub fn new() -> Pipe {
+        Pipe { buf: VecDeque::new() }
+    }
+
+    /// Returns the number of bytes waiting to be read.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if there is nothing left to read.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Removes and returns the next byte, if any.
+    pub fn read_byte(&mut self) -> Option<u8> {
+        self.buf.pop_front()
+    }
+}
+
+impl io::Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = core::cmp::min(buf.len(), self.buf.len());
+        for (slot, byte) in buf.iter_mut().zip(self.buf.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+}
+
+impl io::Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend(buf.iter());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
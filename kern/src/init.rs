@@ -4,7 +4,6 @@ use core::mem::zeroed;
 use core::ptr::write_volatile;
 
 mod oom;
-mod panic;
 
 use crate::kmain;
 use crate::param::*;
@@ -34,6 +34,10 @@ context_save:
     stp q2,  q3,  [SP, #-32]!
     stp q0,  q1,  [SP, #-32]!
 
+    mrs x0, FPCR
+    mrs x1, FPSR
+    stp x0, x1, [SP, #-16]!
+
     mrs x0, TPIDR_EL0
     mrs x1, SP_EL0
     mrs x2, SPSR_EL1
@@ -77,6 +81,10 @@ context_restore:
     dsb ish
     isb
 
+    ldp x0, x1, [SP], #16
+    msr FPCR, x0
+    msr FPSR, x1
+
     ldp q0,  q1,  [SP], #32
     ldp q2,  q3,  [SP], #32
     ldp q4,  q5,  [SP], #32
@@ -18,8 +18,10 @@ extern crate alloc;
 
 pub mod allocator;
 pub mod console;
+pub mod fbcon;
 pub mod fs;
 pub mod mutex;
+pub mod panic;
 pub mod shell;
 pub mod param;
 pub mod process;
@@ -68,6 +70,12 @@ fn kmain() -> ! {
         kprintln!("Initializing VM");
         VMM.initialize();
 
+        kprintln!("Initializing framebuffer console");
+        let screen = console::CONSOLE.lock().attach_screen();
+        if let Err(e) = screen {
+            kprintln!("No framebuffer console: {:?}", e);
+        }
+
         kprintln!("Initializing scheduler");
         SCHEDULER.initialize();
         SCHEDULER.start();
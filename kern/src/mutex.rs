@@ -57,6 +57,11 @@ impl<T> Mutex<T> {
         }
     }
 
+    /// Returns `true` if the lock is currently held.
+    pub fn is_locked(&self) -> bool {
+        self.lock.load(Ordering::Relaxed)
+    }
+
     fn unlock(&self) {
         self.lock.store(false, Ordering::Relaxed);
     }
@@ -0,0 +1,176 @@
+use core::fmt;
+#[cfg(not(test))]
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+const ERROR_ASCII_ART: &str = include_str!("init/error_art.txt");
+
+/// Set by the first panic, so that a panic while handling it (say, while
+/// flushing a filesystem) halts right away instead of recursing.
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Marks the kernel as panicking. Returns `false` if it already was.
+fn enter() -> bool {
+    // A load and a store are enough with one core and interrupts masked
+    let first = !PANICKING.load(Ordering::Relaxed);
+    PANICKING.store(true, Ordering::Relaxed);
+    first
+}
+
+/// Where a panic happened and its message, printed with the ASCII art.
+struct Report<'a> {
+    /// File, line and column
+    location: Option<(&'a str, u32, u32)>,
+    message: Option<fmt::Arguments<'a>>,
+}
+
+impl<'a> Report<'a> {
+    #[cfg(not(test))]
+    fn new(info: &'a PanicInfo) -> Report<'a> {
+        Report {
+            location: info.location().map(|l| (l.file(), l.line(), l.column())),
+            message: info.message().cloned(),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", ERROR_ASCII_ART)?;
+        write!(f, "------------------------PANIC------------------------")?;
+        if let Some((file, line, column)) = self.location {
+            write!(f, "\nFILE: {}\nLINE: {}\nCOL:  {}", file, line, column)?;
+        }
+        if let Some(message) = self.message {
+            write!(f, "\n\n{}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// The registers that say the most about where the kernel was, read as the
+/// panic handler starts. `ELR`, `ESR` and `FAR` describe the last exception
+/// taken, which is what panicked if the panic came from a fault handler.
+struct Registers {
+    el: u8,
+    sp: u64,
+    fp: u64,
+    lr: u64,
+    daif: u64,
+    elr: u64,
+    esr: u64,
+    far: u64,
+}
+
+impl Registers {
+    /// Reads the registers of the calling function.
+    #[cfg(not(test))]
+    #[inline(always)]
+    unsafe fn current() -> Registers {
+        use aarch64::*;
+
+        let (fp, lr): (u64, u64);
+        asm!("mov $0, x29" : "=r"(fp) ::: "volatile");
+        asm!("mov $0, x30" : "=r"(lr) ::: "volatile");
+        Registers {
+            el: current_el(),
+            sp: SP.get() as u64,
+            fp,
+            lr,
+            daif: DAIF.get(),
+            elr: ELR_EL1.get(),
+            esr: ESR_EL1.get(),
+            far: FAR_EL1.get(),
+        }
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "EL{}   daif {:#06x}", self.el, self.daif)?;
+        writeln!(f, "sp   {:#018x}  fp   {:#018x}  lr   {:#018x}", self.sp, self.fp, self.lr)?;
+        write!(f, "elr  {:#018x}  esr  {:#018x}  far  {:#018x}", self.elr, self.esr, self.far)
+    }
+}
+
+/// Flushes every mounted filesystem so writes cached in memory make it to
+/// the disk. Skipped if the panic came from inside the allocator or the
+/// filesystem: flushing allocates, and neither could be trusted then.
+#[cfg(not(test))]
+fn flush_filesystems() {
+    use crate::console::kprintln;
+
+    if crate::ALLOCATOR.is_locked() || crate::FILESYSTEM.is_locked() {
+        kprintln!("Not flushing filesystems: panicked while using them");
+        return;
+    }
+    let flushed = crate::FILESYSTEM.flush_all();
+    kprintln!("Flushed {} filesystem(s)", flushed);
+}
+
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    use crate::console::kprintln;
+    use crate::traps::{frame::backtrace, kernel_frame};
+
+    let registers = unsafe { Registers::current() };
+    // Keep the timer from scheduling anything else from here on
+    unsafe { aarch64::cli() };
+
+    if enter() {
+        kprintln!("{}", Report::new(info));
+        kprintln!("\n{}", registers);
+        kprintln!("backtrace:");
+        let sp = registers.sp;
+        backtrace(registers.fp, |fp| kernel_frame(sp, fp), |lr| kprintln!("  {:#018x}", lr));
+        flush_filesystems();
+        kprintln!("Halting");
+    }
+
+    // `wfi` also wakes up for masked interrupts, so keep going back to it
+    loop {
+        aarch64::wfi();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use super::{enter, Registers, Report};
+
+    #[test]
+    fn report_shows_location_and_message() {
+        let text = Report {
+            location: Some(("src/shell.rs", 332, 24)),
+            message: Some(format_args!("ARE YOU THE {}?", "BRAIN SPECIALIST")),
+        }.to_string();
+        assert!(text.ends_with(concat!(
+            "------------------------PANIC------------------------\n",
+            "FILE: src/shell.rs\nLINE: 332\nCOL:  24\n\n",
+            "ARE YOU THE BRAIN SPECIALIST?",
+        )));
+
+        let text = Report { location: None, message: None }.to_string();
+        assert!(text.ends_with("------------------------PANIC------------------------"));
+    }
+
+    #[test]
+    fn registers_are_dumped() {
+        let text = Registers {
+            el: 1, sp: 0x7_ff00, fp: 0x7_ff40, lr: 0x8_1234, daif: 0x3c0,
+            elr: 0x8_0000, esr: 0x9600_0004, far: 0xdead_0000,
+        }.to_string();
+        assert!(text.starts_with("EL1   daif 0x03c0\n"));
+        assert!(text.contains("fp   0x000000000007ff40"));
+        assert!(text.contains("lr   0x0000000000081234"));
+        assert!(text.ends_with("far  0x00000000dead0000"));
+    }
+
+    #[test]
+    fn second_panic_only_halts() {
+        assert!(enter());
+        assert!(!enter());
+        assert!(!enter());
+    }
+}
@@ -26,6 +26,22 @@ pub const USER_MAX_VM_SIZE: usize = 0x4000_0000;
 const_assert_eq!(USER_IMG_BASE.wrapping_add(USER_MAX_VM_SIZE), 0);
 pub const KERN_STACK_BASE: usize = 0x80_000;
 
-/// The `tick` time.
-// FIXME: When you're ready, change this to something more reasonable.
+/// The default `tick` time, i.e. the scheduling quantum.
+///
+/// Shorter quanta make the system more responsive (a ready process waits at
+/// most `(N - 1) * quantum` to run) but spend proportionally more time in the
+/// timer handler and context switches; longer quanta amortize that overhead
+/// but make interactive processes feel sluggish next to CPU-bound ones. The
+/// quantum can be changed at runtime with `sys_sched_setquantum`.
 pub const TICK: Duration = Duration::from_millis(10);
+
+/// Bounds for a quantum set at runtime.
+pub const MIN_QUANTUM: Duration = Duration::from_micros(500);
+pub const MAX_QUANTUM: Duration = Duration::from_secs(1);
+
+/// Range of niceness values a process can have. Lower is higher priority.
+pub const NICE_MIN: i64 = -20;
+pub const NICE_MAX: i64 = 19;
+/// Lowest niceness a process may give itself. There is no privilege model
+/// yet, so no user process may raise its priority above the default.
+pub const NICE_USER_MIN: i64 = 0;
@@ -1,8 +1,11 @@
+pub mod clock;
+mod preempt;
 mod process;
 mod scheduler;
 mod state;
 
-pub use self::process::{Id, Process};
+pub use self::preempt::{preempt_disable, preempt_enable, preemptible};
+pub use self::process::{Id, Process, INIT_PID, INIT_PROGRAM};
 pub use self::scheduler::GlobalScheduler;
 pub use self::state::State;
 pub use crate::param::TICK;
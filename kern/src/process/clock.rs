@@ -0,0 +1,43 @@
+use core::time::Duration;
+
+/// Returns the time since boot, as read from the system timer.
+#[cfg(not(test))]
+pub fn now() -> Duration {
+    pi::timer::current_time()
+}
+
+/// Arms the system timer alarm to go off at `deadline`.
+#[cfg(not(test))]
+pub fn set_alarm(deadline: Duration) {
+    pi::timer::Alarm::new().set(deadline)
+}
+
+// Tests can't touch the timer registers, so they get a clock of their own
+// that only moves when the test moves it, one per test thread.
+#[cfg(test)]
+std::thread_local! {
+    static NOW: core::cell::Cell<Duration> = core::cell::Cell::new(Duration::default());
+    static ALARM: core::cell::Cell<Option<Duration>> = core::cell::Cell::new(None);
+}
+
+#[cfg(test)]
+pub fn now() -> Duration {
+    NOW.with(|now| now.get())
+}
+
+#[cfg(test)]
+pub fn set_alarm(deadline: Duration) {
+    ALARM.with(|alarm| alarm.set(Some(deadline)))
+}
+
+/// Moves the test clock forward by `by`.
+#[cfg(test)]
+pub fn advance(by: Duration) {
+    NOW.with(|now| now.set(now.get() + by))
+}
+
+/// Returns the deadline the alarm was last armed for in this test.
+#[cfg(test)]
+pub fn alarm() -> Option<Duration> {
+    ALARM.with(|alarm| alarm.get())
+}
@@ -0,0 +1,30 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of nested `preempt_disable()` calls currently in effect.
+static PREEMPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Prevents the timer interrupt from switching processes until the matching
+/// `preempt_enable()`. Calls nest: preemption resumes only once every
+/// `preempt_disable()` has been balanced.
+///
+/// Unlike `Mutex`, this protects against the timer IRQ handler running on the
+/// same core in the middle of an update, which a lock can't do since the
+/// interrupted code can never release it.
+pub fn preempt_disable() {
+    PREEMPT_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Re-enables preemption disabled by `preempt_disable()`.
+///
+/// # Panics
+///
+/// Panics if preemption isn't currently disabled.
+pub fn preempt_enable() {
+    let old = PREEMPT_COUNT.fetch_sub(1, Ordering::SeqCst);
+    assert!(old > 0, "preempt_enable() without matching preempt_disable()");
+}
+
+/// Returns `true` if the timer interrupt may switch processes right now.
+pub fn preemptible() -> bool {
+    PREEMPT_COUNT.load(Ordering::SeqCst) == 0
+}
@@ -1,10 +1,12 @@
 use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::vec::Vec;
 use alloc::sync::Arc;
 use hashbrown::HashMap;
-use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU64;
+use core::time::Duration;
 use shim::io;
-use shim::path::Path;
+use shim::path::{Component, Path, PathBuf};
 
 use aarch64;
 
@@ -18,21 +20,53 @@ use kernel_api::{OsError, OsResult};
 /// Type alias for the type of a process ID.
 pub type Id = u64;
 
+/// ID of the first process started by the kernel, init. Orphaned processes
+/// are handed to init so that it can reap them.
+pub const INIT_PID: Id = 1;
+
+/// Program run as the init process.
+pub const INIT_PROGRAM: &str = "/bin/shell";
+
 /// A structure that represents the complete state of a process.
 #[derive(Debug)]
 pub struct Process {
+    /// Name of the program this process is running
+    pub name: String,
     /// The saved trap frame of a process.
     pub context: Box<TrapFrame>,
     /// The page table describing the Virtual Memory of the process
     pub vmap: Box<UserPageTable>,
     /// The scheduling state of the process.
     pub state: State,
-    /// Reference to tell us if the process has died
-    pub dead: Arc<AtomicBool>,
+    /// Reference to tell us if and how the process has died: `STATUS_DEAD`
+    /// is set once it terminates, and the low bits hold its raw `ExitStatus`
+    pub dead: Arc<AtomicU64>,
+    /// ID of the process that forked this one, if any
+    pub parent: Option<Id>,
+    /// Child this process is blocked on in `wait_pid`, reaped when it wakes
+    pub waiting_on: Option<Id>,
+    /// Whether this process is blocked in `wait_any`, reaping whichever child
+    /// exits first when it wakes
+    pub waiting_any: bool,
+    /// Total time spent running on the CPU, in microseconds
+    pub cpu_ticks: u64,
+    /// When the process was last switched in
+    pub scheduled_at: Duration,
+    /// When the process last became ready to run, if it's waiting in the queue
+    pub ready_since: Option<Duration>,
+    /// Niceness in `[NICE_MIN, NICE_MAX]`; nicer processes get shorter slices
+    pub nice: i64,
+    /// Consecutive quanta that ended in preemption without a syscall
+    pub quanta_without_syscall: u64,
     /// Table of available file descriptors
     pub fd_table: LocalFdTable,
     /// Environment variables
     pub env: HashMap<String, String>,
+    /// Working directory that relative paths are resolved against
+    pub cwd: PathBuf,
+    /// Console mode (raw or not) to restore when this process exits, if it
+    /// has changed the mode
+    pub saved_console_raw: Option<bool>,
     /// Last allocated page (other than the stack)
     pub last_page: VirtualAddr,
 }
@@ -47,12 +81,23 @@ impl Process {
         let tf = TrapFrame::default();
         let state = State::Ready;
         Ok(Process {
+            name: String::new(),
             context: Box::new(tf),
             vmap: Box::new(UserPageTable::new()),
             state,
-            dead: Arc::new(AtomicBool::new(false)),
+            dead: Arc::new(AtomicU64::new(0)),
+            parent: None,
+            waiting_on: None,
+            waiting_any: false,
+            cpu_ticks: 0,
+            scheduled_at: Duration::default(),
+            ready_since: None,
+            nice: 0,
+            quanta_without_syscall: 0,
             fd_table: LocalFdTable::new(),
             env: HashMap::new(),
+            cwd: PathBuf::from("/"),
+            saved_console_raw: None,
             last_page: VirtualAddr::from(0),
         })
     }
@@ -62,16 +107,46 @@ impl Process {
         let new_vmap = Box::new(self.vmap.duplicate());
         new_ctx.ttbr1 = new_vmap.get_baddr().as_u64();
         Process {
+            name: self.name.clone(),
             context: new_ctx,
             vmap: new_vmap,
             state: State::Ready,
-            dead: Arc::new(AtomicBool::new(false)),
+            dead: Arc::new(AtomicU64::new(0)),
+            parent: Some(self.context.tpidr),
+            waiting_on: None,
+            waiting_any: false,
+            cpu_ticks: 0,
+            scheduled_at: Duration::default(),
+            ready_since: None,
+            nice: self.nice,
+            quanta_without_syscall: 0,
             fd_table: self.fd_table.clone(),
             env: self.env.clone(),
+            cwd: self.cwd.clone(),
+            saved_console_raw: None,
             last_page: self.last_page.clone(),
         }
     }
 
+    /// Resolves `path` against this process's working directory and removes
+    /// any `.` and `..` components. Returns `None` if `..` would go above the
+    /// root directory.
+    pub fn resolve_path(&self, path: &str) -> Option<PathBuf> {
+        let mut resolved = PathBuf::from("/");
+        for comp in self.cwd.join(path).components() {
+            match comp {
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return None;
+                    }
+                },
+                Component::Normal(n) => resolved.push(n),
+                _ => (),
+            }
+        }
+        Some(resolved)
+    }
+
     /// Load a program stored in the given path by calling `do_load()` method.
     /// Set trapframe `context` corresponding to the its page table.
     /// `sp` - the address of stack top
@@ -84,7 +159,9 @@ impl Process {
     pub fn load<P: AsRef<Path>>(pn: P) -> OsResult<Process> {
         use crate::VMM;
 
+        let name = Self::program_name(pn.as_ref());
         let mut p = Process::do_load(pn)?;
+        p.name = name;
 
         // Set trapframe for the process.
         p.context.sp = Self::get_stack_top().as_u64();
@@ -96,6 +173,14 @@ impl Process {
         Ok(p)
     }
 
+    /// Like `load()`, but also passes `args` to the program the same way
+    /// `exec` does, so processes started by the kernel can receive argv.
+    pub fn load_with_args<P: AsRef<Path>>(pn: P, args: &[&str]) -> OsResult<Process> {
+        let mut p = Process::load(pn)?;
+        p.init_args(args);
+        Ok(p)
+    }
+
     /// Creates a process and open a file with given path.
     /// Allocates one page for stack with read/write permission, and N pages with read/write/execute
     /// permission to load file's contents.
@@ -146,6 +231,7 @@ impl Process {
 
         self.vmap.try_alloc(Self::get_stack_base(), PagePerm::RW);
 
+        let name = Self::program_name(pn.as_ref());
         let entry = FILESYSTEM.open(pn)?;
         let file_size = entry.metadata().size;
         let mut file = match entry.into_file() {
@@ -170,6 +256,8 @@ impl Process {
             page[0..num_bytes].copy_from_slice(&buffer[0..num_bytes]);
         }
 
+        self.name = name;
+
         // Set trapframe for the process.
         self.context.sp = Self::get_stack_top().as_u64();
         self.context.elr = Self::get_image_base().as_u64();
@@ -180,8 +268,57 @@ impl Process {
         Ok(())
     }
 
+    /// Returns the basename of the executable at `pn`, used as the process name.
+    fn program_name(pn: &Path) -> String {
+        pn.file_name()
+          .and_then(|name| name.to_str())
+          .map(String::from)
+          .unwrap_or_default()
+    }
+
     /// Pass args to an existing program
     pub fn init_args(&mut self, mut args: &[&str]) {
+        use kernel_api::ARG_MAX;
+        use fat32::util::SliceExt;
+
+        if args.len() > ARG_MAX {
+            crate::kprintln!("[WARNING]: Attempted to pass too many args to program. Only the first {} will be passed.", ARG_MAX);
+            args = &args[0..ARG_MAX];
+        } else if args.len() == 0 {
+            self.context.xs[0] = 0u64;
+            self.context.xs[1] = 0u64;
+            return;
+        }
+
+        let (buffer_start_addr, mut buffer) = Self::arg_block(args);
+
+        let mut base_addr = buffer_start_addr;
+        while base_addr < Self::get_stack_top().as_usize() {
+            let va = VirtualAddr::from(base_addr);
+            let page = self.vmap.try_alloc(va, PagePerm::RW);
+
+            let idx = base_addr - buffer_start_addr;
+            let char_buffer = unsafe { &mut buffer.cast_mut::<u8>() };
+            page.copy_from_slice(&char_buffer[idx..idx.saturating_add(PAGE_SIZE)]);
+
+            base_addr = base_addr.saturating_add(PAGE_SIZE);
+        }
+
+        let stack_va = VirtualAddr::from(buffer_start_addr - PAGE_SIZE);
+        let _ = self.vmap.try_alloc(stack_va, PagePerm::RW);
+
+        // The stack starts right below the arguments so it can't grow into them
+        // and stays 16-byte aligned
+        self.context.sp = buffer_start_addr as u64;
+        self.context.xs[0] = args.len() as u64;
+        self.context.xs[1] = buffer_start_addr as u64;
+    }
+
+    /// Lays out `args` for the top of the stack and returns the address the
+    /// block starts at, which is page-aligned, along with its contents in whole
+    /// pages: the `(len, ptr)` of each argument followed by the bytes they
+    /// point to.
+    fn arg_block(args: &[&str]) -> (usize, Vec<(usize, *const u8)>) {
         /* What needs to happen? Given an array of arguments:
            1. Allocate a big buffer
            2. Copy each string into the buffer & store the offset for each string
@@ -191,7 +328,6 @@ impl Process {
                  (i.e. from the bottom of the stack). This is easier if we can know
                  the final size of the buffer before creating it.
         */
-        use kernel_api::ARG_MAX;
         use alloc::vec;
         use fat32::util::SliceExt;
         use liballoc::util::{align_up, align_down};
@@ -200,15 +336,6 @@ impl Process {
         let buffer_array_size = fat_pointer_size * args.len();
         let mut buffer_size = buffer_array_size;
 
-        if args.len() > ARG_MAX {
-            crate::kprintln!("[WARNING]: Attempted to pass too many args to program. Only the first {} will be passed.", ARG_MAX);
-            args = &args[0..ARG_MAX];
-        } else if args.len() == 0 {
-            self.context.xs[0] = 0u64;
-            self.context.xs[1] = 0u64;
-            return;
-        }
-
         for arg in args {
             buffer_size += arg.len();
         }
@@ -228,24 +355,7 @@ impl Process {
             buf_addr += len;
         }
 
-        let mut base_addr = buffer_start_addr;
-        while base_addr < Self::get_stack_top().as_usize() {
-            let va = VirtualAddr::from(base_addr);
-            let page = self.vmap.try_alloc(va, PagePerm::RW);
-
-            let idx = base_addr - buffer_start_addr;
-            let char_buffer = unsafe { &mut buffer.cast_mut::<u8>() };
-            page.copy_from_slice(&char_buffer[idx..idx.saturating_add(PAGE_SIZE)]);
-
-            base_addr = base_addr.saturating_add(PAGE_SIZE);
-        }
-
-        let stack_va = VirtualAddr::from(buffer_start_addr - PAGE_SIZE);
-        let _ = self.vmap.try_alloc(stack_va, PagePerm::RW);
-
-        self.context.sp -= buffer_size as u64;
-        self.context.xs[0] = args.len() as u64;
-        self.context.xs[1] = buffer_start_addr as u64;
+        (buffer_start_addr, buffer)
     }
 
     pub fn page_fault(&mut self, addr: usize) -> bool {
@@ -283,6 +393,25 @@ impl Process {
         VirtualAddr::from(align_down(usize::max_value(), 16))
     }
 
+    /// Returns the total CPU time used by this process, including the time
+    /// slice it is currently running in, if any.
+    pub fn cpu_time(&self) -> Duration {
+        let mut micros = self.cpu_ticks;
+        if let State::Running = self.state {
+            let now = crate::process::clock::now();
+            micros += now.checked_sub(self.scheduled_at).unwrap_or_default().as_micros() as u64;
+        }
+        Duration::from_micros(micros)
+    }
+
+    /// Returns the length of this process's time slice given the scheduler's
+    /// base `quantum`: twice the quantum at `NICE_MIN`, the quantum itself at
+    /// niceness 0, and 1/20th of it at `NICE_MAX`.
+    pub fn time_slice(&self, quantum: Duration) -> Duration {
+        let weight = (20 - self.nice) as u32; // 1..=40
+        quantum * weight / 20
+    }
+
     /// Returns `true` if this process is ready to be scheduled.
     ///
     /// This functions returns `true` only if one of the following holds:
@@ -316,3 +445,47 @@ impl Process {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fat32::util::SliceExt;
+    use shim::path::{Path, PathBuf};
+    use crate::param::PAGE_SIZE;
+    use super::Process;
+
+    #[test]
+    fn program_name_is_the_file_name() {
+        assert_eq!(Process::program_name(Path::new("/bin/fib")), "fib");
+        assert_eq!(Process::program_name(Path::new("shell")), "shell");
+        assert_eq!(Process::program_name(Path::new("/")), "");
+    }
+
+    #[test]
+    fn args_are_laid_out_at_the_new_stack_pointer() {
+        let (sp, block) = Process::arg_block(&["fib", "10"]);
+        assert_eq!(sp % PAGE_SIZE, 0);
+        assert!(sp < Process::get_stack_top().as_usize());
+
+        // The program reads argv as `argc` `(len, ptr)` pairs starting at `sp`
+        let argc = 2;
+        let bytes = unsafe { block.cast::<u8>() };
+        let argv: Vec<&str> = block[..argc].iter().map(|&(len, ptr)| {
+            let offset = ptr as usize - sp;
+            core::str::from_utf8(&bytes[offset..offset + len]).unwrap()
+        }).collect();
+        assert_eq!(argv, ["fib", "10"]);
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_cwd() {
+        let mut process = Process::new().unwrap();
+        assert_eq!(process.resolve_path("bin"), Some(PathBuf::from("/bin")));
+
+        process.cwd = PathBuf::from("/bin");
+        assert_eq!(process.resolve_path("ls"), Some(PathBuf::from("/bin/ls")));
+        assert_eq!(process.resolve_path("."), Some(PathBuf::from("/bin")));
+        assert_eq!(process.resolve_path("../foo/./bar"), Some(PathBuf::from("/foo/bar")));
+        assert_eq!(process.resolve_path("/fstab"), Some(PathBuf::from("/fstab")));
+        assert_eq!(process.resolve_path("../.."), None);
+    }
+}
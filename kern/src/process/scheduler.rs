@@ -1,14 +1,22 @@
 use alloc::boxed::Box;
+use alloc::collections::binary_heap::BinaryHeap;
 use alloc::collections::vec_deque::VecDeque;
 
+use alloc::string::String;
 use alloc::sync::Arc;
-use core::sync::atomic::{AtomicBool, Ordering};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::time::Duration;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use aarch64::*;
+use kernel_api::{ExitStatus, ProcRecord, ProcState, SIGINT, SIGKILL, STATUS_DEAD};
 
 use crate::mutex::Mutex;
-use crate::param::TICK;
-use crate::process::{Id, Process, State};
+use crate::param::{TICK, MIN_QUANTUM, MAX_QUANTUM, NICE_MIN, NICE_MAX, NICE_USER_MIN};
+use crate::process::{clock, Id, Process, State, INIT_PID, INIT_PROGRAM};
+use crate::process::{preempt_disable, preempt_enable, preemptible};
+use crate::console::{kprintln, CONSOLE};
 use crate::traps::TrapFrame;
 
 /*
@@ -19,24 +27,38 @@ extern fn start_shell() {
 }
 */
 
-/// Process scheduler for the entire machine.
+/// Process scheduler for the entire machine. The flag is set while the
+/// internal scheduler is being used, to catch re-entrant mutation.
 #[derive(Debug)]
-pub struct GlobalScheduler(Mutex<Option<Scheduler>>);
+pub struct GlobalScheduler(Mutex<Option<Scheduler>>, AtomicBool);
 
 impl GlobalScheduler {
     /// Returns an uninitialized wrapper around a local scheduler.
     pub const fn uninitialized() -> GlobalScheduler {
-        GlobalScheduler(Mutex::new(None))
+        GlobalScheduler(Mutex::new(None), AtomicBool::new(false))
     }
 
     /// Enter a critical region and execute the provided closure with the
-    /// internal scheduler.
+    /// internal scheduler. Preemption is disabled for the duration, so the
+    /// timer interrupt can't switch processes halfway through an update.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called re-entrantly, e.g. from within `f`. The kernel
+    /// `Mutex` doesn't catch this, and it would let two updates to the queue
+    /// interleave.
     pub fn critical<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut Scheduler) -> R,
     {
+        preempt_disable();
         let mut guard = self.0.lock();
-        f(guard.as_mut().expect("scheduler uninitialized"))
+        assert!(!self.1.swap(true, Ordering::SeqCst), "scheduler entered re-entrantly");
+        let result = f(guard.as_mut().expect("scheduler uninitialized"));
+        self.1.store(false, Ordering::SeqCst);
+        drop(guard);
+        preempt_enable();
+        result
     }
 
     /// Adds a process to the scheduler's queue and returns that process's ID.
@@ -55,8 +77,9 @@ impl GlobalScheduler {
         self.critical(move |scheduler| scheduler.fork(tf))
     }
 
-    /// Get an atomic handle that lets us determine whether a process has died
-    pub fn get_dead_handle(&self, pid: Id) -> Option<Arc<AtomicBool>> {
+    /// Get an atomic handle that lets us determine whether and how a process
+    /// has died
+    pub fn get_dead_handle(&self, pid: Id) -> Option<Arc<AtomicU64>> {
         self.critical(move |scheduler| scheduler.get_dead_handle(pid))
     }
 
@@ -69,21 +92,165 @@ impl GlobalScheduler {
         self.switch_to(tf)
     }
 
+    /// Preempts the running process at the end of its time slice, or kills it
+    /// if the watchdog decides it has run away. For more details, see the
+    /// documentation on `Scheduler::charge_quantum()`.
+    pub fn preempt(&self, tf: &mut TrapFrame) -> Id {
+        let runaway = self.critical(|scheduler| scheduler.charge_quantum());
+        if let Some((pid, quanta)) = runaway {
+            kprintln!("watchdog: killing process {} after {} quanta without a syscall", pid, quanta);
+            if self.kill(ExitStatus::Killed(SIGKILL), tf).is_some() {
+                return tf.tpidr;
+            }
+        }
+        self.switch(State::Ready, tf)
+    }
+
+    /// Wakes every sleeper whose deadline has passed and rearms the alarm for
+    /// the next one. If any woke, the running process is switched out so they
+    /// run right away rather than at the end of its time slice, and the tick is
+    /// rearmed so the process switched to gets a whole time slice of its own.
+    pub fn wake_sleepers(&self, tf: &mut TrapFrame) {
+        let woke = self.critical(|scheduler| scheduler.wake_sleepers());
+        if woke {
+            self.switch(State::Ready, tf);
+            pi::timer::tick_in(self.next_tick());
+        }
+    }
+
+    /// Sets how many consecutive quanta a process may use without a syscall
+    /// before it is killed, or disables the watchdog if `None`. Returns the
+    /// previous limit. The watchdog is off until this enables it, since
+    /// long computations legitimately go without syscalls.
+    pub fn set_watchdog(&self, quanta: Option<u64>) -> Option<u64> {
+        self.critical(|scheduler| core::mem::replace(&mut scheduler.watchdog, quanta))
+    }
+
+    /// Returns how long until the next timer interrupt should fire: the rest
+    /// of the running process's time slice. Sleepers are woken by the alarm.
+    pub fn next_tick(&self) -> Duration {
+        self.critical(|scheduler| scheduler.next_tick())
+    }
+
+    /// Switches `tf` to the next ready process, idling until one becomes
+    /// ready if there is none. Returns the ID of the process switched to.
     pub fn switch_to(&self, tf: &mut TrapFrame) -> Id {
         loop {
             let rtn = self.critical(|scheduler| scheduler.switch_to(tf));
             if let Some(id) = rtn {
                 return id;
             }
-            wfi();
+            self.idle();
         }
     }
 
+    /// Runs when no process is ready: rearms the timer for a quantum, so
+    /// `Waiting` processes get polled, and waits for an interrupt (which may be
+    /// the alarm for the next sleeper). Rearming also acknowledges the pending timer match,
+    /// which would otherwise wake `wfi` immediately and spin the CPU.
+    fn idle(&self) {
+        use pi::timer;
+
+        timer::tick_in(self.next_tick());
+        wfi();
+    }
+
     /// Kills currently running process and returns that process's ID.
     /// For more details, see the documentaion on `Scheduler::kill()`.
     #[must_use]
-    pub fn kill(&self, tf: &mut TrapFrame) -> Option<Id> {
-        self.critical(|scheduler| scheduler.kill(tf))
+    pub fn kill(&self, status: ExitStatus, tf: &mut TrapFrame) -> Option<Id> {
+        self.critical(|scheduler| scheduler.kill(status, tf))
+    }
+
+    /// Kills the process `pid` with `SIGKILL` and returns its ID. If `pid` is
+    /// the running process, this is the same as `kill()` and `tf` is switched
+    /// to the next process. For more details, see the documentation on
+    /// `Scheduler::kill_pid()`.
+    #[must_use]
+    pub fn kill_pid(&self, pid: Id, tf: &mut TrapFrame) -> Option<Id> {
+        let status = ExitStatus::Killed(SIGKILL);
+        if pid == tf.tpidr {
+            self.kill(status, tf)
+        } else {
+            self.critical(|scheduler| scheduler.kill_pid(pid))
+        }
+    }
+
+    /// Sets the process that Ctrl-C interrupts, or clears it if `None`.
+    /// Returns `false` if there is no such process.
+    pub fn set_foreground(&self, pid: Option<Id>) -> bool {
+        self.critical(|scheduler| scheduler.set_foreground(pid))
+    }
+
+    /// Kills the foreground process with `SIGINT` after Ctrl-C, and returns
+    /// its ID. If it is the running process, `tf` is switched to the next
+    /// process. Returns `None` if there is no foreground process.
+    pub fn interrupt(&self, tf: &mut TrapFrame) -> Option<Id> {
+        let interrupted = self.critical(|scheduler| scheduler.interrupt_foreground());
+        if interrupted.is_some() {
+            return interrupted;
+        }
+
+        // Only the running process is left for `interrupt_foreground` to skip
+        match self.critical(|scheduler| scheduler.foreground) {
+            Some(pid) if pid == tf.tpidr => self.kill(ExitStatus::Killed(SIGINT), tf),
+            _ => None,
+        }
+    }
+
+    /// Kills the foreground process if a Ctrl-C has been received and the
+    /// shell is waiting on one; otherwise the Ctrl-C is left as input.
+    /// Returns `true` if that switched `tf` away from the running process.
+    fn handle_ctrl_c(&self, tf: &mut TrapFrame) -> bool {
+        let intercept = self.critical(|scheduler| scheduler.foreground.is_some());
+        let interrupted = CONSOLE.lock().interrupted(intercept);
+        let running = tf.tpidr;
+        interrupted && self.interrupt(tf) == Some(running)
+    }
+
+    /// Kills every process, e.g. before halting the machine. For more details,
+    /// see the documentation on `Scheduler::kill_all()`.
+    pub fn kill_all(&self) -> usize {
+        self.critical(|scheduler| scheduler.kill_all())
+    }
+
+    /// Returns the ID of the current init process.
+    pub fn init_pid(&self) -> Id {
+        self.critical(|scheduler| scheduler.init)
+    }
+
+    /// Collects whichever child of `parent` exited first. For more details,
+    /// see the documentation on `Scheduler::reap_any()`.
+    pub fn reap_any(&self, parent: Id) -> Option<(Id, u64)> {
+        self.critical(|scheduler| scheduler.reap_any(parent))
+    }
+
+    /// Returns the dead handles of every live child of `parent`.
+    pub fn children_dead_handles(&self, parent: Id) -> Vec<Arc<AtomicU64>> {
+        self.critical(|scheduler| scheduler.children_dead_handles(parent))
+    }
+
+    /// Sets the scheduling quantum and returns the previous one.
+    /// For more details, see the documentation on `Scheduler::set_quantum()`.
+    pub fn set_quantum(&self, quantum: Duration) -> Duration {
+        self.critical(|scheduler| scheduler.set_quantum(quantum))
+    }
+
+    /// Adjusts the niceness of the running process. For more details, see the
+    /// documentation on `Scheduler::nice()`.
+    pub fn nice(&self, delta: i64) -> Option<i64> {
+        self.critical(|scheduler| scheduler.nice(delta))
+    }
+
+    /// Returns the average time processes spent ready but not running.
+    pub fn average_latency(&self) -> Duration {
+        self.critical(|scheduler| scheduler.average_latency())
+    }
+
+    /// Collects the raw exit status of the zombie `pid` on behalf of `parent`.
+    /// For more details, see the documentation on `Scheduler::reap()`.
+    pub fn reap(&self, parent: Id, pid: Id) -> Result<Option<u64>, ()> {
+        self.critical(|scheduler| scheduler.reap(parent, pid))
     }
     
     /// Starts executing processes in user space using timer interrupt based
@@ -92,7 +259,11 @@ impl GlobalScheduler {
         use pi::timer;
         use pi::interrupt::{Controller, Interrupt};
    
-        Controller::new().enable(Interrupt::Timer1);
+        CONSOLE.lock().enable_rx_interrupt();
+        let mut controller = Controller::new();
+        controller.enable(Interrupt::Timer1);
+        controller.enable(Interrupt::Timer3);
+        controller.enable(Interrupt::Aux);
         timer::tick_in(TICK);
 
         let mut trap_frame = TrapFrame::default();
@@ -130,26 +301,105 @@ impl GlobalScheduler {
         *self.0.lock() = Some(Scheduler::new());
 
         crate::IRQ.register(Interrupt::Timer1, Box::new(|tf: &mut TrapFrame| {
-            timer::tick_in(TICK);
-            crate::SCHEDULER.switch(State::Ready, tf);
+            // Kernel code in a critical section was interrupted: let it finish
+            // and try again on a short tick
+            if !preemptible() {
+                timer::tick_in(MIN_QUANTUM);
+                return;
+            }
+            timer::tick_in(crate::SCHEDULER.next_tick());
+
+            // Picks up a Ctrl-C that arrived during a critical section
+            if crate::SCHEDULER.handle_ctrl_c(tf) {
+                return;
+            }
+            crate::SCHEDULER.preempt(tf);
+        }));
+
+        crate::IRQ.register(Interrupt::Timer3, Box::new(|tf: &mut TrapFrame| {
+            let mut alarm = timer::Alarm::new();
+            alarm.clear();
+            // Go off again shortly, once the critical section is over
+            if !preemptible() {
+                alarm.set(timer::current_time());
+                return;
+            }
+            crate::SCHEDULER.wake_sleepers(tf);
         }));
 
-        let a = Process::load("/bin/shell").expect("couldn't load shell");
+        crate::IRQ.register(Interrupt::Aux, Box::new(|tf: &mut TrapFrame| {
+            CONSOLE.lock().receive();
+            // The scheduler can't be entered from a critical section, so
+            // leave any Ctrl-C for the next timer tick
+            if preemptible() {
+                crate::SCHEDULER.handle_ctrl_c(tf);
+            }
+        }));
+
+        let a = Process::load(INIT_PROGRAM).expect("couldn't load shell");
         self.add(a).expect("Couldn't get PID");
     }
 }
 
+/// Puts the console back into the mode it was in before `process` changed
+/// it, if it did.
+fn restore_console(process: &Process) {
+    if let Some(raw) = process.saved_console_raw {
+        CONSOLE.lock().set_raw(raw);
+    }
+}
+
+/// What remains of a process after it exits, kept until its parent reaps it.
+#[derive(Debug)]
+struct Zombie {
+    pid: Id,
+    parent: Id,
+    code: u64,
+    name: String,
+}
+
 #[derive(Debug)]
 pub struct Scheduler {
     processes: VecDeque<Process>,
+    zombies: Vec<Zombie>,
+    /// Min-heap of `(wake deadline, pid)` for every `State::Sleeping` process.
+    sleepers: BinaryHeap<Reverse<(Duration, Id)>>,
+    /// ID of the init process, which adopts orphans
+    init: Id,
+    /// How long a process runs before it is preempted
+    quantum: Duration,
+    /// Consecutive quanta without a syscall after which a process is killed,
+    /// if the watchdog has been enabled
+    watchdog: Option<u64>,
+    /// Process that Ctrl-C on the console kills, set by the shell while it
+    /// waits on a command
+    foreground: Option<Id>,
+    /// Number of switches to a process that was waiting in the ready queue
+    switches: u64,
+    /// Sum of the time those processes spent ready before running
+    total_latency: Duration,
     last_id: Option<Id>,
 }
 
+#[cfg(test)]
+std::thread_local! {
+    /// Number of sleeper deadlines `wake_sleepers` has looked at in this test.
+    static DEADLINES_CHECKED: core::cell::Cell<usize> = core::cell::Cell::new(0);
+}
+
 impl Scheduler {
     /// Returns a new `Scheduler` with an empty queue.
     fn new() -> Scheduler {
         Scheduler {
             processes: VecDeque::new(),
+            zombies: Vec::new(),
+            sleepers: BinaryHeap::new(),
+            init: INIT_PID,
+            quantum: TICK,
+            watchdog: None,
+            foreground: None,
+            switches: 0,
+            total_latency: Duration::default(),
             last_id: None,
         }
     }
@@ -162,7 +412,10 @@ impl Scheduler {
     /// It is the caller's responsibility to ensure that the first time `switch`
     /// is called, that process is executing on the CPU.
     fn add(&mut self, mut process: Process) -> Option<Id> {
-        let pid = self.last_id.and_then(|x| x.checked_add(1)).unwrap_or_default();
+        let pid = match self.last_id {
+            Some(last_id) => last_id.checked_add(1)?,
+            None => INIT_PID,
+        };
 
         process.context.tpidr = pid;
         self.processes.push_back(process);
@@ -181,7 +434,7 @@ impl Scheduler {
         }
     }
 
-    fn get_dead_handle(&mut self, pid: Id) -> Option<Arc<AtomicBool>> {
+    fn get_dead_handle(&mut self, pid: Id) -> Option<Arc<AtomicU64>> {
         let mut dead = None;
         for process in self.processes.iter() {
             if process.context.tpidr == pid {
@@ -210,6 +463,18 @@ impl Scheduler {
         let process = self.processes.pop_front();
         match process {
             Some(mut p @ Process { state: State::Running, .. }) => {
+                let now = clock::now();
+                let ran_for = now.checked_sub(p.scheduled_at).unwrap_or_default();
+                p.cpu_ticks += ran_for.as_micros() as u64;
+
+                match new_state {
+                    State::Sleeping { until, .. } => {
+                        self.sleepers.push(Reverse((until, p.context.tpidr)));
+                        self.arm_alarm();
+                    },
+                    State::Ready => p.ready_since = Some(now),
+                    _ => (),
+                }
                 p.state = new_state;
                 p.context = Box::new(*tf);
                 self.processes.push_back(p);
@@ -227,16 +492,38 @@ impl Scheduler {
     /// If there is no process to switch to, returns `None`. Otherwise, returns
     /// `Some` of the next process`s process ID.
     fn switch_to(&mut self, tf: &mut TrapFrame) -> Option<Id> {
-        // Find the first ready process
-        let index = self.processes.iter_mut().position(|item: &mut Process| -> bool {
-            item.is_ready()
-        })?;
+        self.wake_sleepers();
+
+        // Find the first ready process, or go idle if there is none
+        let index = self.next_ready()?;
         // Remove it from the queue
         let mut next_process = self.processes.remove(index)?;
         let pid = next_process.context.tpidr;
         
+        // Collect the child it was blocked on in `wait_pid`
+        if let Some(child) = next_process.waiting_on.take() {
+            if let Ok(Some(code)) = self.reap(pid, child) {
+                next_process.context.xs[0] = code;
+                next_process.context.xs[7] = 1; // Success
+            }
+        }
+        // Or the first of its children to exit, if it was in `wait_any`
+        if core::mem::replace(&mut next_process.waiting_any, false) {
+            if let Some((child, code)) = self.reap_any(pid) {
+                next_process.context.xs[0] = child;
+                next_process.context.xs[1] = code;
+                next_process.context.xs[7] = 1; // Success
+            }
+        }
+
         // Set it to running & restore its context to the target trap frame
+        let now = clock::now();
+        if let Some(ready_since) = next_process.ready_since.take() {
+            self.switches += 1;
+            self.total_latency += now.checked_sub(ready_since).unwrap_or_default();
+        }
         next_process.state = State::Running;
+        next_process.scheduled_at = now;
         *tf = *next_process.context;
         // Push it to the front of the queue
         self.processes.push_front(next_process);
@@ -244,14 +531,127 @@ impl Scheduler {
         Some(pid)
     }
 
+    /// Returns the queue index of the first process that is ready to run, or
+    /// `None` if the CPU should idle.
+    fn next_ready(&mut self) -> Option<usize> {
+        self.processes.iter_mut().position(|item: &mut Process| -> bool {
+            item.is_ready()
+        })
+    }
+
+    /// Moves every sleeper whose deadline has passed back to `Ready`, storing
+    /// the time it actually slept as the return value of its `sleep` call.
+    ///
+    /// Only expired deadlines are touched, so the cost of a call with no
+    /// wakeups is a single heap peek regardless of how many processes sleep.
+    /// Rearms the alarm for the next deadline, and returns whether any
+    /// process woke up.
+    fn wake_sleepers(&mut self) -> bool {
+        let mut woke = false;
+        let now = clock::now();
+        while let Some(&Reverse((until, pid))) = self.sleepers.peek() {
+            #[cfg(test)]
+            DEADLINES_CHECKED.with(|checked| checked.set(checked.get() + 1));
+            if until > now {
+                break;
+            }
+            self.sleepers.pop();
+
+            // The sleeper may have been killed in the meantime
+            let sleeper = self.processes.iter_mut().find(|p| p.context.tpidr == pid);
+            if let Some(p) = sleeper {
+                if let State::Sleeping { since, .. } = p.state {
+                    p.context.xs[0] = (now - since).as_millis() as u64;
+                    p.context.xs[7] = 1; // Success
+                    p.state = State::Ready;
+                    p.ready_since = Some(now);
+                    woke = true;
+                }
+            }
+        }
+        self.arm_alarm();
+        woke
+    }
+
+    /// Sets the timer alarm to go off at the earliest sleeper deadline, if
+    /// there is a sleeper. A stale alarm left over from a sleeper that was
+    /// killed just finds nothing to wake.
+    fn arm_alarm(&self) {
+        if let Some(&Reverse((until, _))) = self.sleepers.peek() {
+            clock::set_alarm(until);
+        }
+    }
+
+    /// Returns the running process's time slice, or a quantum if nothing is
+    /// running. Sleep deadlines have their own alarm, so they never cut a
+    /// time slice short.
+    fn next_tick(&self) -> Duration {
+        match self.processes.front() {
+            Some(p @ Process { state: State::Running, .. }) => p.time_slice(self.quantum),
+            _ => self.quantum,
+        }
+    }
+
+    /// Counts a quantum that the running process used up without making a
+    /// syscall. Returns its ID and the count if that reaches the watchdog
+    /// limit, meaning the process should be killed as a runaway.
+    fn charge_quantum(&mut self) -> Option<(Id, u64)> {
+        let limit = self.watchdog?;
+        match self.processes.front_mut() {
+            Some(p @ Process { state: State::Running, .. }) => {
+                p.quanta_without_syscall += 1;
+                if p.quanta_without_syscall >= limit {
+                    Some((p.context.tpidr, p.quanta_without_syscall))
+                } else {
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Adjusts the niceness of the running process by `delta`, clamped to
+    /// `[NICE_USER_MIN, NICE_MAX]`, and returns the new niceness.
+    fn nice(&mut self, delta: i64) -> Option<i64> {
+        let process = self.processes.front_mut()?;
+        let lowest = core::cmp::max(NICE_MIN, NICE_USER_MIN);
+        let nice = process.nice.saturating_add(delta);
+        process.nice = core::cmp::min(core::cmp::max(nice, lowest), NICE_MAX);
+        Some(process.nice)
+    }
+
+    /// Sets the quantum used for preemption, clamped to
+    /// `[MIN_QUANTUM, MAX_QUANTUM]`, and returns the previous quantum. The new
+    /// quantum takes effect from the next timer interrupt. Also resets the
+    /// latency counters so they describe the new setting.
+    fn set_quantum(&mut self, quantum: Duration) -> Duration {
+        let quantum = core::cmp::min(core::cmp::max(quantum, MIN_QUANTUM), MAX_QUANTUM);
+        self.switches = 0;
+        self.total_latency = Duration::default();
+        core::mem::replace(&mut self.quantum, quantum)
+    }
+
+    /// Returns the average time a preempted or woken process waited in the
+    /// ready queue before being switched back in.
+    fn average_latency(&self) -> Duration {
+        match self.switches {
+            0 => Duration::default(),
+            n => self.total_latency / n as u32,
+        }
+    }
+
     /// Kills currently running process by scheduling out the current process
-    /// as `Dead` state. Removes the dead process from the queue, drop the
-    /// dead process's instance, and returns the dead process's process ID.
-    fn kill(&mut self, tf: &mut TrapFrame) -> Option<Id> {
-        if self.schedule_out(State::Dead, tf) {
+    /// as a `Zombie` with exit status `status`. Removes the process from the
+    /// queue, drops its instance, and leaves a zombie record behind for its
+    /// parent to collect with `reap()`. Returns the dead process's process ID.
+    fn kill(&mut self, status: ExitStatus, tf: &mut TrapFrame) -> Option<Id> {
+        let code = status.as_u64();
+        if self.schedule_out(State::Zombie(code), tf) {
             let killed = self.processes.pop_back()?;
             let pid = killed.context.tpidr;
-            killed.dead.store(true, Ordering::Relaxed);
+            self.bury(pid, killed.parent, code, &killed.name);
+            restore_console(&killed);
+            killed.dead.store(STATUS_DEAD | code, Ordering::Relaxed);
             core::mem::drop(killed); // Force dropping the instance NOW
             self.switch_to(tf);
             Some(pid)
@@ -259,4 +659,540 @@ impl Scheduler {
             None
         }
     }
-}
\ No newline at end of file
+
+    /// Kills the process `pid`, which must not be the running process, with
+    /// `SIGKILL`. The process is removed from the queue wherever it is and in
+    /// whatever state (e.g. blocked in `Waiting`), its dead handle is marked,
+    /// and its resources, including its page table, are freed immediately.
+    /// Returns the killed process's ID, or `None` if there is no such process
+    /// or it is currently running.
+    fn kill_pid(&mut self, pid: Id) -> Option<Id> {
+        self.terminate(pid, ExitStatus::Killed(SIGKILL))
+    }
+
+    /// Like `kill_pid()`, but with exit status `status`.
+    fn terminate(&mut self, pid: Id, status: ExitStatus) -> Option<Id> {
+        let index = self.processes.iter().position(|p| p.context.tpidr == pid)?;
+        if let State::Running = self.processes[index].state {
+            return None;
+        }
+
+        let code = status.as_u64();
+        let killed = self.processes.remove(index)?;
+        self.bury(pid, killed.parent, code, &killed.name);
+        restore_console(&killed);
+        killed.dead.store(STATUS_DEAD | code, Ordering::Relaxed);
+        core::mem::drop(killed); // Frees its page table NOW
+        Some(pid)
+    }
+
+    /// Sets the process that Ctrl-C kills, or clears it if `pid` is `None`.
+    /// Returns `false`, leaving it unchanged, if there is no process `pid`.
+    fn set_foreground(&mut self, pid: Option<Id>) -> bool {
+        if let Some(pid) = pid {
+            if !self.processes.iter().any(|p| p.context.tpidr == pid) {
+                return false;
+            }
+        }
+        self.foreground = pid;
+        true
+    }
+
+    /// Kills the foreground process with `SIGINT`, the way Ctrl-C does, and
+    /// returns its ID. Its parent sees it exit like any other killed process.
+    /// Returns `None` if there is no foreground process or it is currently
+    /// running, since killing the running process needs its trap frame.
+    fn interrupt_foreground(&mut self) -> Option<Id> {
+        let pid = self.foreground?;
+        self.terminate(pid, ExitStatus::Killed(SIGINT))
+    }
+
+    /// Kills every process with `SIGKILL`, including the running one, without
+    /// leaving zombies or restarting init. Only meant for shutting down: the
+    /// scheduler has nothing to switch to afterwards. Returns the number of
+    /// processes killed.
+    fn kill_all(&mut self) -> usize {
+        let code = STATUS_DEAD | ExitStatus::Killed(SIGKILL).as_u64();
+        let count = self.processes.len();
+        for process in self.processes.drain(..) {
+            process.dead.store(code, Ordering::Relaxed);
+        }
+        self.zombies.clear();
+        self.sleepers.clear();
+        count
+    }
+
+    /// Records that `pid` exited with `code` so that `parent` can collect it,
+    /// and hands all of `pid`'s children (alive or zombie) over to init. If
+    /// `pid` is init itself, a fresh init is started to adopt them.
+    fn bury(&mut self, pid: Id, parent: Option<Id>, code: u64, name: &str) {
+        if pid == self.init {
+            self.restart_init();
+        }
+        if self.foreground == Some(pid) {
+            self.foreground = None;
+        }
+
+        let init = self.init;
+        for zombie in self.zombies.iter_mut().filter(|zombie| zombie.parent == pid) {
+            zombie.parent = init;
+        }
+        for process in self.processes.iter_mut().filter(|p| p.parent == Some(pid)) {
+            process.parent = Some(init);
+        }
+
+        // Nobody can ever wait on a process without a parent
+        if let Some(parent) = parent {
+            self.zombies.push(Zombie { pid, parent, code, name: String::from(name) });
+        }
+    }
+
+    /// Loads a new init process to replace one that exited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `INIT_PROGRAM` can't be loaded, since orphans could never be
+    /// reaped without an init.
+    fn restart_init(&mut self) {
+        kprintln!("init (pid {}) exited, restarting {}", self.init, INIT_PROGRAM);
+        let process = Process::load(INIT_PROGRAM).expect("couldn't restart init");
+        self.init = self.add(process).expect("couldn't schedule init");
+    }
+
+    /// Writes a record for every process (including zombies) into `buf`,
+    /// stopping once it is full. Returns the total number of processes.
+    pub fn proc_list(&self, buf: &mut [ProcRecord]) -> usize {
+        let live = self.processes.iter().map(|p| {
+            let mut record = ProcRecord {
+                pid: p.context.tpidr,
+                parent: p.parent.unwrap_or(0),
+                state: match p.state {
+                    State::Ready => ProcState::Ready,
+                    State::Running => ProcState::Running,
+                    State::Waiting(_) | State::Sleeping { .. } => ProcState::Waiting,
+                    State::Dead | State::Zombie(_) => ProcState::Zombie,
+                }.as_u64(),
+                cpu_ticks: p.cpu_time().as_micros() as u64,
+                ..Default::default()
+            };
+            record.set_name(&p.name);
+            record
+        });
+        let dead = self.zombies.iter().map(|z| {
+            let mut record = ProcRecord {
+                pid: z.pid,
+                parent: z.parent,
+                state: ProcState::Zombie.as_u64(),
+                ..Default::default()
+            };
+            record.set_name(&z.name);
+            record
+        });
+
+        let mut count = 0;
+        for record in live.chain(dead) {
+            if let Some(slot) = buf.get_mut(count) {
+                *slot = record;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Removes the oldest zombie record among `parent`'s children and returns
+    /// its ID and raw exit status, so children are collected in the order they
+    /// exited. Returns `None` if none of them has exited yet.
+    fn reap_any(&mut self, parent: Id) -> Option<(Id, u64)> {
+        let index = self.zombies.iter().position(|zombie| zombie.parent == parent)?;
+        let zombie = self.zombies.remove(index);
+        Some((zombie.pid, zombie.code))
+    }
+
+    fn children_dead_handles(&self, parent: Id) -> Vec<Arc<AtomicU64>> {
+        self.processes.iter()
+            .filter(|p| p.parent == Some(parent))
+            .map(|p| p.dead.clone())
+            .collect()
+    }
+
+    /// Removes the zombie record for `pid` and returns its raw exit status, if `pid`
+    /// is a child of `parent` that has exited. Returns `Ok(None)` if the child
+    /// is still alive, and `Err(())` if `pid` isn't a child of `parent`.
+    fn reap(&mut self, parent: Id, pid: Id) -> Result<Option<u64>, ()> {
+        let zombie = self.zombies.iter()
+            .position(|zombie| zombie.pid == pid && zombie.parent == parent);
+        if let Some(index) = zombie {
+            return Ok(Some(self.zombies.remove(index).code));
+        }
+
+        let alive = self.processes.iter()
+            .any(|p| p.context.tpidr == pid && p.parent == Some(parent));
+        if alive { Ok(None) } else { Err(()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `slices` time slices the way the timer interrupt would: switches
+    /// to the next ready process, lets it use up its whole time slice and
+    /// preempts it. Returns the ID of the process that ran in each slice.
+    fn run_slices(scheduler: &mut Scheduler, slices: usize) -> Vec<Id> {
+        let mut tf = TrapFrame::default();
+        (0..slices).map(|_| {
+            let pid = scheduler.switch_to(&mut tf).unwrap();
+            clock::advance(scheduler.next_tick());
+            assert!(scheduler.schedule_out(State::Ready, &mut tf));
+            pid
+        }).collect()
+    }
+
+    #[test]
+    fn busy_processes_get_equal_cpu_time() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(Process::new().unwrap()).unwrap();
+        scheduler.add(Process::new().unwrap()).unwrap();
+
+        run_slices(&mut scheduler, 100);
+        let ticks: Vec<u64> = scheduler.processes.iter().map(|p| p.cpu_ticks).collect();
+        assert_eq!(ticks, [50 * TICK.as_micros() as u64; 2]);
+    }
+
+    #[test]
+    fn processes_interleave_at_any_quantum() {
+        let mut scheduler = Scheduler::new();
+        let pids: Vec<Id> = (0..3).map(|_| scheduler.add(Process::new().unwrap()).unwrap()).collect();
+        let round_robin: Vec<Id> = pids.iter().chain(pids.iter()).cloned().collect();
+
+        for &quantum in &[Duration::from_millis(1), Duration::from_millis(50)] {
+            scheduler.set_quantum(quantum);
+            let start = clock::now();
+            assert_eq!(run_slices(&mut scheduler, 6), round_robin);
+            assert_eq!(clock::now() - start, quantum * 6);
+        }
+        assert_eq!(scheduler.processes.iter().map(|p| p.cpu_ticks).collect::<Vec<_>>(), [102_000; 3]);
+
+        // Out-of-range quanta are clamped
+        assert_eq!(scheduler.set_quantum(Duration::from_micros(1)), Duration::from_millis(50));
+        assert_eq!(scheduler.set_quantum(TICK), MIN_QUANTUM);
+    }
+
+    #[test]
+    fn others_run_while_one_waits_for_input() {
+        let mut scheduler = Scheduler::new();
+        // Stands in for the console: 0 until a byte is typed
+        let typed = Arc::new(AtomicU64::new(0));
+
+        let mut reader = Process::new().unwrap();
+        let console = typed.clone();
+        reader.state = State::Waiting(Box::new(move |p| match console.swap(0, Ordering::Relaxed) {
+            0 => false,
+            byte => {
+                p.context.xs[0] = byte;
+                true
+            },
+        }));
+        let reader = scheduler.add(reader).unwrap();
+        let worker = scheduler.add(Process::new().unwrap()).unwrap();
+
+        assert_eq!(run_slices(&mut scheduler, 3), [worker; 3]);
+
+        typed.store(b'q' as u64, Ordering::Relaxed);
+        assert_eq!(run_slices(&mut scheduler, 3), [reader, worker, reader]);
+        let reader = scheduler.processes.iter().find(|p| p.context.tpidr == reader).unwrap();
+        assert_eq!(reader.context.xs[0], b'q' as u64);
+    }
+
+    #[test]
+    fn niceness_shrinks_cpu_share() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(Process::new().unwrap()).unwrap();
+        scheduler.add(Process::new().unwrap()).unwrap();
+        // The first process asks to be nicer; it can't make itself less nice
+        assert_eq!(scheduler.nice(10), Some(10));
+        assert_eq!(scheduler.nice(-20), Some(NICE_USER_MIN));
+        assert_eq!(scheduler.nice(10), Some(10));
+
+        run_slices(&mut scheduler, 20);
+        let ticks: Vec<u64> = scheduler.processes.iter().map(|p| p.cpu_ticks).collect();
+        assert_eq!(ticks[0] * 2, ticks[1]);
+        assert_eq!(ticks[0] + ticks[1], 10 * (TICK / 2 + TICK).as_micros() as u64);
+    }
+
+    #[test]
+    fn proc_list_reports_caller_as_running() {
+        let mut scheduler = Scheduler::new();
+        let mut ps = Process::new().unwrap();
+        ps.name = String::from("ps");
+        let caller = scheduler.add(ps).unwrap();
+        let other = scheduler.add(Process::new().unwrap()).unwrap();
+
+        // `ps` makes the syscall while it is the running process
+        scheduler.switch_to(&mut TrapFrame::default()).unwrap();
+        clock::advance(Duration::from_micros(250));
+
+        let mut records = [ProcRecord::default(); 4];
+        assert_eq!(scheduler.proc_list(&mut records), 2);
+        assert_eq!((records[0].pid, records[0].state(), records[0].name()), (caller, ProcState::Running, "ps"));
+        assert_eq!(records[0].cpu_ticks, 250);
+        assert_eq!((records[1].pid, records[1].state()), (other, ProcState::Ready));
+    }
+
+    #[test]
+    #[should_panic(expected = "scheduler entered re-entrantly")]
+    fn nested_critical_section_is_caught() {
+        let global = GlobalScheduler(Mutex::new(Some(Scheduler::new())), AtomicBool::new(false));
+        assert_eq!(global.add(Process::new().unwrap()), Some(INIT_PID));
+        global.critical(|_| global.critical(|scheduler| scheduler.add(Process::new().unwrap())));
+    }
+
+    #[test]
+    fn kill_waiting_process_by_pid() {
+        let mut scheduler = Scheduler::new();
+        let parent = scheduler.add(Process::new().unwrap()).unwrap();
+
+        let mut child = Process::new().unwrap();
+        child.parent = Some(parent);
+        child.state = State::Waiting(Box::new(|_| false));
+        let pid = scheduler.add(child).unwrap();
+        let dead = scheduler.get_dead_handle(pid).unwrap();
+
+        assert_eq!(scheduler.kill_pid(pid), Some(pid));
+        assert_eq!(scheduler.processes.len(), 1);
+        assert!(scheduler.get_dead_handle(pid).is_none());
+        assert_eq!(dead.load(Ordering::Relaxed), STATUS_DEAD | ExitStatus::Killed(SIGKILL).as_u64());
+        assert_eq!(scheduler.reap(parent, pid), Ok(Some(ExitStatus::Killed(SIGKILL).as_u64())));
+
+        // Already gone
+        assert_eq!(scheduler.kill_pid(pid), None);
+    }
+
+    #[test]
+    fn reap_child_that_died_before_the_wait() {
+        let mut scheduler = Scheduler::new();
+        let parent = scheduler.add(Process::new().unwrap()).unwrap();
+        let mut child = Process::new().unwrap();
+        child.parent = Some(parent);
+        let pid = scheduler.add(child).unwrap();
+
+        assert_eq!(scheduler.reap(parent, pid), Ok(None));
+        scheduler.terminate(pid, ExitStatus::Exited(7)).unwrap();
+        assert_eq!(scheduler.zombies.len(), 1);
+
+        assert_eq!(scheduler.reap(parent, pid), Ok(Some(ExitStatus::Exited(7).as_u64())));
+        assert!(scheduler.zombies.is_empty());
+        // Nothing is left to collect a second time
+        assert_eq!(scheduler.reap(parent, pid), Err(()));
+    }
+
+    #[test]
+    fn exit_code_is_distinct_from_signal() {
+        use kernel_api::SIGSEGV;
+
+        let mut scheduler = Scheduler::new();
+        let parent = scheduler.add(Process::new().unwrap()).unwrap();
+        let mut children = [0; 2];
+        for child in children.iter_mut() {
+            let mut process = Process::new().unwrap();
+            process.parent = Some(parent);
+            *child = scheduler.add(process).unwrap();
+        }
+        let handles: Vec<_> = children.iter().map(|&pid| scheduler.get_dead_handle(pid).unwrap()).collect();
+
+        scheduler.terminate(children[0], ExitStatus::Exited(3)).unwrap();
+        scheduler.terminate(children[1], ExitStatus::Killed(SIGSEGV)).unwrap();
+
+        let statuses: Vec<_> = handles.iter().map(|dead| {
+            let raw = dead.load(Ordering::Relaxed);
+            assert!(raw & STATUS_DEAD != 0);
+            ExitStatus::from(raw & !STATUS_DEAD)
+        }).collect();
+        assert_eq!(statuses, [ExitStatus::Exited(3), ExitStatus::Killed(SIGSEGV)]);
+
+        let reaped = children.iter().map(|&pid| scheduler.reap(parent, pid).unwrap().map(ExitStatus::from));
+        assert_eq!(reaped.collect::<Vec<_>>(), [Some(ExitStatus::Exited(3)), Some(ExitStatus::Killed(SIGSEGV))]);
+
+        // Even the same number is told apart
+        assert_ne!(ExitStatus::Exited(SIGSEGV).as_u64(), ExitStatus::Killed(SIGSEGV).as_u64());
+    }
+
+    #[test]
+    fn orphans_are_adopted_by_init() {
+        let mut scheduler = Scheduler::new();
+        let init = scheduler.add(Process::new().unwrap()).unwrap();
+        assert_eq!(init, INIT_PID);
+
+        let mut parent = Process::new().unwrap();
+        parent.parent = Some(init);
+        let parent = scheduler.add(parent).unwrap();
+        let mut children = [0; 2];
+        for child in children.iter_mut() {
+            let mut process = Process::new().unwrap();
+            process.parent = Some(parent);
+            *child = scheduler.add(process).unwrap();
+        }
+        // One child has already exited but hasn't been reaped
+        scheduler.terminate(children[1], ExitStatus::Exited(0)).unwrap();
+
+        scheduler.terminate(parent, ExitStatus::Exited(0)).unwrap();
+        let orphan = scheduler.processes.iter().find(|p| p.context.tpidr == children[0]).unwrap();
+        assert_eq!(orphan.parent, Some(INIT_PID));
+        assert_eq!(scheduler.reap(INIT_PID, children[1]), Ok(Some(0)));
+        assert_eq!(scheduler.reap(INIT_PID, parent), Ok(Some(0)));
+        assert_eq!(scheduler.reap(INIT_PID, children[0]), Ok(None));
+    }
+
+    #[test]
+    fn reap_any_in_completion_order() {
+        let mut scheduler = Scheduler::new();
+        let parent = scheduler.add(Process::new().unwrap()).unwrap();
+
+        let mut children = [0; 2];
+        for child in children.iter_mut() {
+            let mut process = Process::new().unwrap();
+            process.parent = Some(parent);
+            *child = scheduler.add(process).unwrap();
+        }
+        assert_eq!(scheduler.children_dead_handles(parent).len(), 2);
+        assert_eq!(scheduler.reap_any(parent), None);
+
+        scheduler.kill_pid(children[1]).unwrap();
+        scheduler.kill_pid(children[0]).unwrap();
+
+        let code = ExitStatus::Killed(SIGKILL).as_u64();
+        assert_eq!(scheduler.reap_any(parent), Some((children[1], code)));
+        assert_eq!(scheduler.reap_any(parent), Some((children[0], code)));
+        assert_eq!(scheduler.reap_any(parent), None);
+    }
+
+    #[test]
+    fn watchdog_fires_on_tight_loop() {
+        let mut scheduler = Scheduler::new();
+        let mut looper = Process::new().unwrap();
+        looper.state = State::Running;
+        let pid = scheduler.add(looper).unwrap();
+
+        // Off by default, so CPU-bound programs run as long as they need
+        for _ in 0..10 {
+            assert_eq!(scheduler.charge_quantum(), None);
+        }
+
+        scheduler.watchdog = Some(3);
+        scheduler.processes[0].quanta_without_syscall = 0;
+
+        assert_eq!(scheduler.charge_quantum(), None);
+        assert_eq!(scheduler.charge_quantum(), None);
+        assert_eq!(scheduler.charge_quantum(), Some((pid, 3)));
+
+        // A syscall resets the count
+        scheduler.processes[0].quanta_without_syscall = 0;
+        assert_eq!(scheduler.charge_quantum(), None);
+
+        scheduler.watchdog = None;
+        for _ in 0..10 {
+            assert_eq!(scheduler.charge_quantum(), None);
+        }
+    }
+
+    #[test]
+    fn idle_with_only_a_sleeper() {
+        let mut scheduler = Scheduler::new();
+        let pid = scheduler.add(Process::new().unwrap()).unwrap();
+        let mut tf = TrapFrame::default();
+        assert_eq!(scheduler.switch_to(&mut tf), Some(pid));
+
+        // The only process goes to sleep: the alarm is armed for its deadline
+        // and nothing is switched in, so `GlobalScheduler::switch_to` idles
+        let start = clock::now();
+        let until = start + Duration::from_secs(60);
+        assert!(scheduler.schedule_out(State::Sleeping { since: start, until }, &mut tf));
+        assert_eq!(clock::alarm(), Some(until));
+        tf.xs[0] = 0xdead;
+        assert_eq!(scheduler.switch_to(&mut tf), None);
+        assert_eq!(tf.xs[0], 0xdead);
+
+        // Still nothing before the alarm fires
+        clock::advance(Duration::from_secs(59));
+        assert_eq!(scheduler.switch_to(&mut tf), None);
+
+        // The alarm wakes the CPU at the deadline and the sleeper runs again,
+        // told how long it slept
+        clock::advance(Duration::from_secs(1));
+        assert_eq!(scheduler.switch_to(&mut tf), Some(pid));
+        assert_eq!(tf.xs[0], 60_000);
+        assert_eq!(tf.xs[7], 1);
+    }
+
+    /// Puts `count` processes to sleep for 1, 2, ... seconds, then checks
+    /// that ticks look at no more deadlines than are due.
+    fn check_tick_only_peeks(count: u64) {
+        let mut scheduler = Scheduler::new();
+        let start = clock::now();
+        let mut pids = Vec::new();
+        for i in 1..=count {
+            let mut sleeper = Process::new().unwrap();
+            let until = start + Duration::from_secs(i);
+            sleeper.state = State::Sleeping { since: start, until };
+            let pid = scheduler.add(sleeper).unwrap();
+            scheduler.sleepers.push(Reverse((until, pid)));
+            pids.push(pid);
+        }
+        let take_checked = || DEADLINES_CHECKED.with(|checked| checked.replace(0));
+        take_checked();
+
+        // Nothing is due yet: only the earliest deadline is looked at, the
+        // heap is left as it was and the alarm is armed for that deadline
+        clock::advance(Duration::from_millis(500));
+        assert!(!scheduler.wake_sleepers());
+        assert_eq!(take_checked(), 1);
+        assert_eq!(scheduler.sleepers.len(), count as usize);
+        assert_eq!(clock::alarm(), Some(start + Duration::from_secs(1)));
+        assert_eq!(scheduler.next_ready(), None);
+
+        // Once the first deadline passes, only the top of the heap is taken,
+        // plus a look at the next deadline if there is one
+        clock::advance(Duration::from_millis(500));
+        assert!(scheduler.wake_sleepers());
+        assert_eq!(take_checked(), if count > 1 { 2 } else { 1 });
+        assert_eq!(scheduler.sleepers.len(), count as usize - 1);
+        assert_eq!(scheduler.next_ready(), Some(0));
+        assert_eq!(scheduler.processes[0].context.tpidr, pids[0]);
+        assert_eq!(scheduler.processes[0].context.xs[0], 1000);
+    }
+
+    #[test]
+    fn tick_without_expired_sleepers_only_peeks() {
+        check_tick_only_peeks(1);
+        check_tick_only_peeks(64);
+    }
+
+    #[test]
+    fn ctrl_c_kills_foreground_sleeper() {
+        let mut scheduler = Scheduler::new();
+        let shell = scheduler.add(Process::new().unwrap()).unwrap();
+
+        // The shell runs `sleep` in the foreground and waits for it
+        let mut sleep = Process::new().unwrap();
+        sleep.parent = Some(shell);
+        let until = Duration::from_secs(3600);
+        sleep.state = State::Sleeping { since: Duration::default(), until };
+        let pid = scheduler.add(sleep).unwrap();
+        scheduler.sleepers.push(Reverse((until, pid)));
+        let dead = scheduler.get_dead_handle(pid).unwrap();
+        scheduler.processes[0].state = State::Waiting(Box::new(move |_| {
+            dead.load(Ordering::Relaxed) & STATUS_DEAD != 0
+        }));
+        assert!(scheduler.set_foreground(Some(pid)));
+        assert_eq!(scheduler.next_ready(), None);
+
+        assert_eq!(scheduler.interrupt_foreground(), Some(pid));
+        assert_eq!(scheduler.foreground, None);
+        // The shell's wait completes, so it can print the prompt again
+        assert_eq!(scheduler.next_ready(), Some(0));
+        assert_eq!(scheduler.reap(shell, pid), Ok(Some(ExitStatus::Killed(SIGINT).as_u64())));
+
+        assert_eq!(scheduler.interrupt_foreground(), None);
+        assert!(!scheduler.set_foreground(Some(pid)));
+    }
+}
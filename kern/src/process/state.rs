@@ -1,4 +1,5 @@
 use core::fmt;
+use core::time::Duration;
 
 use alloc::boxed::Box;
 
@@ -17,10 +18,16 @@ pub enum State {
     Ready,
     /// The process is waiting on an event to occur before it can be scheduled.
     Waiting(EventPollFn),
+    /// The process is asleep until the system timer reaches `until`. Sleepers
+    /// are tracked by the scheduler's deadline queue rather than polled.
+    Sleeping { since: Duration, until: Duration },
     /// The process is currently running.
     Running,
     /// The process is currently dead (ready to be reclaimed).
     Dead,
+    /// The process has exited with the given code, but its parent has not
+    /// yet collected it with `wait_pid`.
+    Zombie(u64),
 }
 
 impl fmt::Debug for State {
@@ -29,7 +36,9 @@ impl fmt::Debug for State {
             State::Ready => write!(f, "State::Ready"),
             State::Running => write!(f, "State::Running"),
             State::Waiting(_) => write!(f, "State::Waiting"),
+            State::Sleeping { .. } => write!(f, "State::Sleeping"),
             State::Dead => write!(f, "State::Dead"),
+            State::Zombie(code) => write!(f, "State::Zombie({})", code),
         }
     }
 }
@@ -1,6 +1,8 @@
-use shim::io;
-use shim::path::{PathBuf, Component};
+use core::fmt::{self, Write};
+use shim::{io, ioerr, newioerr};
+use shim::path::{Path, PathBuf, Component};
 use alloc::string::String;
+use alloc::collections::BTreeMap;
 
 use stack_vec::StackVec;
 
@@ -10,13 +12,21 @@ use fat32::traits::{Dir, Entry, FileSystem, Metadata, BlockDevice};
 use fat32::vfat::BiosParameterBlock;
 use fat32::mbr::MasterBootRecord;
 use blockdev::mount::*;
+use coreutils::glob::glob_match;
+use coreutils::wc::WordCount;
 use aes128::edevice::EncryptedDevice;
 
+use alloc::format;
+use alloc::vec;
 use alloc::vec::Vec;
 
 use sd::sd::Sd;
 
-use crate::console::{kprint, kprintln, CONSOLE};
+pub mod line_editor;
+
+use self::line_editor::LineEditor;
+use crate::console::{self, kprint, kprintln, CONSOLE, CTRL_D};
+use crate::fs::pipe::Pipe;
 use crate::FILESYSTEM;
 
 /// Error type for `Command` parse failures.
@@ -24,32 +34,278 @@ use crate::FILESYSTEM;
 enum Error {
     Empty,
     TooManyArgs,
+    TooLong,
+    MissingRedirectTarget,
+    UnterminatedQuote,
+}
+
+/// How running a line of input turned out.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Success,
+    Failure,
+    /// The line asked the shell to exit.
+    Exit,
+}
+
+/// State that lasts for as long as a shell runs.
+struct Session {
+    cwd: PathBuf,
+    /// Words that are replaced when used as a command name, set with `alias`
+    aliases: BTreeMap<String, String>,
+    /// Exit status of the last command line, 0 if it succeeded, which `$?`
+    /// expands to
+    status: u64,
+}
+
+impl Session {
+    fn new() -> Session {
+        Session { cwd: PathBuf::from("/"), aliases: BTreeMap::new(), status: 0 }
+    }
+
+    /// Replaces the first word of `command` if it's an alias. Only the command
+    /// name is expanded, never its arguments, and an alias's value isn't
+    /// expanded again.
+    fn expand_alias(&self, command: &str) -> String {
+        let trimmed = command.trim_start();
+        let name = split_unquoted(trimmed, ' ')[0];
+        match self.aliases.get(name) {
+            Some(value) => format!("{}{}", value, &trimmed[name.len()..]),
+            None => String::from(command),
+        }
+    }
+}
+
+/// How a command in a list is joined to the one before it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Connector {
+    /// `;`, or the start of the line: always run
+    Then,
+    /// `&&`: run if the previous command succeeded
+    And,
+    /// `||`: run if the previous command failed
+    Or,
+}
+
+/// A file that a command's output is redirected to.
+#[derive(Debug, PartialEq)]
+enum Redirect<'a> {
+    /// `> path`: replace the file's contents
+    Truncate(&'a str),
+    /// `>> path`: add to the end of the file
+    Append(&'a str),
+}
+
+/// Where a builtin reads its input from.
+enum Stdin<'a> {
+    Console,
+    Pipe(&'a mut Pipe),
+}
+
+impl<'a> Stdin<'a> {
+    /// Returns the next input byte, or `None` at end of input. On the
+    /// console, input goes through the cooked-mode `console::LineReader`
+    /// and ends with Ctrl-D on an empty line.
+    fn read_byte(&mut self) -> Option<u8> {
+        match self {
+            Stdin::Console => {
+                // Edited a line at a time, just like input to processes
+                let byte = loop {
+                    if let Some(byte) = CONSOLE.lock().poll_input() {
+                        break byte
+                    }
+                };
+                if byte == CTRL_D { None } else { Some(byte) }
+            },
+            Stdin::Pipe(pipe) => pipe.read_byte(),
+        }
+    }
+
+    /// Reads one line, including its trailing newline if there is one, into
+    /// `line`. Returns `false` once there is no more input.
+    fn read_line(&mut self, line: &mut Vec<u8>) -> bool {
+        line.clear();
+        loop {
+            match self.read_byte() {
+                None => return !line.is_empty(),
+                Some(byte) => {
+                    line.push(byte);
+                    if byte == b'\n' {
+                        return true
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'a> io::Read for Stdin<'a> {
+    /// Reads no further than the end of the current line, so that console
+    /// input is passed on as soon as each line is entered.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.read_byte() {
+                None => break,
+                Some(byte) => {
+                    buf[n] = byte;
+                    n += 1;
+                    if byte == b'\n' {
+                        break
+                    }
+                },
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Where a builtin writes its output to. Errors are still reported directly
+/// on the console.
+enum Stdout<'a> {
+    Console,
+    Pipe(&'a mut Pipe),
+    File(fat32::vfat::File<crate::fs::PiVFatHandle>),
+}
+
+impl<'a> fmt::Write for Stdout<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        use io::Write;
+
+        match self {
+            Stdout::Console => kprint!("{}", s),
+            Stdout::Pipe(pipe) => { pipe.write(s.as_bytes()).map_err(|_| fmt::Error)?; },
+            Stdout::File(file) => file.write_all(s.as_bytes()).map_err(|_| fmt::Error)?,
+        }
+        Ok(())
+    }
+}
+
+/// A command built into the shell, as listed by `help`.
+struct Builtin {
+    name: &'static str,
+    usage: &'static str,
+    about: &'static str,
+}
+
+/// Every command the shell understands. `Command::eval` only dispatches
+/// commands listed here, so a builtin missing from this table can't run.
+const BUILTINS: &[Builtin] = &[
+    Builtin { name: "help", usage: "help", about: "list the builtin commands" },
+    Builtin { name: "clear", usage: "clear", about: "clear the screen" },
+    Builtin { name: "echo", usage: "echo [arg]*", about: "print the arguments" },
+    Builtin { name: "sleep", usage: "sleep <ms>", about: "sleep for the given number of milliseconds" },
+    Builtin { name: "pwd", usage: "pwd", about: "print the working directory" },
+    Builtin { name: "cd", usage: "cd <dir>", about: "change the working directory" },
+    Builtin { name: "ls", usage: "ls [-a] [dir]", about: "list a directory; -a includes hidden entries" },
+    Builtin { name: "cat", usage: "cat [-n] [file]*", about: "print files or stdin; -n numbers lines" },
+    Builtin { name: "du", usage: "du [-c] [path]", about: "show the size of each directory; -c counts whole clusters" },
+    Builtin { name: "tree", usage: "tree [-a] [-L <depth>] [dir]", about: "list a directory recursively" },
+    Builtin { name: "grep", usage: "grep <pattern> [file]*", about: "print lines containing the pattern" },
+    Builtin { name: "wc", usage: "wc [file]*", about: "count lines, words and bytes" },
+    Builtin { name: "mkdir", usage: "mkdir <dir>", about: "create a directory" },
+    Builtin { name: "touch", usage: "touch <file>+", about: "create empty files" },
+    Builtin { name: "append", usage: "append <file> <contents>", about: "add a line to the end of a file" },
+    Builtin { name: "rm", usage: "rm <path>+", about: "delete files or empty directories" },
+    Builtin { name: "cp", usage: "cp [-r] <source> <destination>", about: "copy a file; -r copies directories" },
+    Builtin { name: "mv", usage: "mv <source> <destination>", about: "move or rename a file or directory" },
+    Builtin { name: "lsblk", usage: "lsblk", about: "list partitions and mount points" },
+    Builtin { name: "df", usage: "df", about: "show used and free space on each mount" },
+    Builtin { name: "mount", usage: "mount <part> <path> [-p <pw>]", about: "mount a partition, encrypted with -p" },
+    Builtin { name: "umount", usage: "umount <path>", about: "unmount a partition" },
+    Builtin { name: "mkcrypt", usage: "mkcrypt {header|full} <part> <pw>", about: "encrypt a partition" },
+    Builtin { name: "alias", usage: "alias [name[=command]]*", about: "define or list command aliases" },
+    Builtin { name: "unalias", usage: "unalias <name>+", about: "remove command aliases" },
+    Builtin { name: "sh", usage: "sh [-e] <script>", about: "run a script; -e stops at the first error" },
+    Builtin { name: "source", usage: "source [-e] <script>", about: "same as sh" },
+    Builtin { name: "lsatag", usage: "lsatag", about: "print the ATAGs from the bootloader" },
+    Builtin { name: "memorymap", usage: "memorymap", about: "print the available memory" },
+    Builtin { name: "testalloc", usage: "testalloc", about: "exercise the allocator" },
+    Builtin { name: "write_file_test", usage: "write_file_test", about: "write a test file in the working directory" },
+    Builtin { name: "panic", usage: "panic", about: "panic the kernel" },
+    Builtin { name: "exit", usage: "exit", about: "leave the shell" },
+];
+
+/// Prints every builtin with its usage and a description.
+fn help(stdout: &mut Stdout) {
+    let width = BUILTINS.iter().map(|builtin| builtin.usage.len()).max().unwrap_or(0);
+    for builtin in BUILTINS {
+        let _ = writeln!(stdout, "{:width$}  {}", builtin.usage, builtin.about, width = width);
+    }
 }
 
 /// A structure representing a single shell command.
 struct Command<'a> {
     args: StackVec<'a, &'a str>,
+    redirect: Option<Redirect<'a>>,
+    /// File to read stdin from, given with `< path`
+    input: Option<&'a str>,
 }
 
 impl<'a> Command<'a> {
-    /// Parse a command from a string `s` using `buf` as storage for the
+    /// Parse a command from a string `s`, copying the arguments with quotes
+    /// and escapes removed into `text` and using `buf` as storage for the
     /// arguments.
     ///
+    /// Arguments are separated by spaces, except inside single or double
+    /// quotes. A backslash escapes the next character, except inside single
+    /// quotes; inside double quotes it only escapes `"`, `\` and `$`.
+    ///
     /// # Errors
     ///
     /// If `s` contains no arguments, returns `Error::Empty`. If there are more
-    /// arguments than `buf` can hold, returns `Error::TooManyArgs`.
-    fn parse(s: &'a str, buf: &'a mut [&'a str]) -> Result<Command<'a>, Error> {
+    /// arguments than `buf` can hold, returns `Error::TooManyArgs`, and if
+    /// they don't fit in `text`, `Error::TooLong`. If `>`, `>>` or `<` isn't
+    /// followed by a path, returns `Error::MissingRedirectTarget`. If a quote
+    /// is never closed, returns `Error::UnterminatedQuote`.
+    fn parse(s: &str, text: &'a mut [u8], buf: &'a mut [&'a str]) -> Result<Command<'a>, Error> {
+        enum Slot { Arg, Truncate, Append, Input }
+
+        // First unescape every word into `text`, remembering where each went
+        let mut slots = Vec::new();
+        let mut len = 0;
+        let mut words = split_unquoted(s, ' ').into_iter().filter(|a| !a.is_empty());
+        while let Some(word) = words.next() {
+            // Accept both `> path` and `>path`
+            let (slot, rest) = if word.starts_with(">>") {
+                (Slot::Append, &word[2..])
+            } else if word.starts_with('>') {
+                (Slot::Truncate, &word[1..])
+            } else if word.starts_with('<') {
+                (Slot::Input, &word[1..])
+            } else {
+                (Slot::Arg, word)
+            };
+            let raw = match (&slot, rest) {
+                (Slot::Arg, _) => rest,
+                (_, "") => words.next().ok_or(Error::MissingRedirectTarget)?,
+                (_, target) => target,
+            };
+
+            let start = len;
+            len = unescape(raw, text, len)?;
+            slots.push((slot, start, len));
+        }
+
+        let text: &'a [u8] = text;
         let mut args = StackVec::new(buf);
-        for arg in s.split(' ').filter(|a| !a.is_empty()) {
-            args.push(arg).map_err(|_| Error::TooManyArgs)?;
+        let mut redirect = None;
+        let mut input = None;
+        for (slot, start, end) in slots {
+            let word = core::str::from_utf8(&text[start..end]).unwrap_or_default();
+            match slot {
+                Slot::Arg => args.push(word).map_err(|_| Error::TooManyArgs)?,
+                Slot::Truncate => redirect = Some(Redirect::Truncate(word)),
+                Slot::Append => redirect = Some(Redirect::Append(word)),
+                Slot::Input => input = Some(word),
+            }
         }
 
         if args.is_empty() {
             return Err(Error::Empty);
         }
 
-        Ok(Command { args })
+        Ok(Command { args, redirect, input })
     }
 
     /// Returns this command's path. This is equivalent to the first argument.
@@ -57,13 +313,22 @@ impl<'a> Command<'a> {
         self.args[0]
     }
 
-    fn eval(&self, cwd: &mut PathBuf) {
+    /// Runs the command. Returns `false` if it failed.
+    fn eval(&self, session: &mut Session, stdin: &mut Stdin, stdout: &mut Stdout) -> bool {
+        let cwd = &mut session.cwd;
+        if !BUILTINS.iter().any(|builtin| builtin.name == self.path()) {
+            kprintln!("unknown command: {} (try help)", self.path());
+            return false
+        }
+
         match self.path() {
+            "help" => help(stdout),
+            "clear" => { let _ = console::clear(stdout); },
             "echo" => {
-                for arg in &self.args[1..] {
-                    kprint!("{} ", arg);
+                for (i, arg) in self.args[1..].iter().enumerate() {
+                    let _ = write!(stdout, "{}{}", if i > 0 { " " } else { "" }, arg);
                 }
-                kprintln!("");
+                let _ = writeln!(stdout);
             },
             "panic" => panic!("ARE YOU THE BRAIN SPECIALIST?"),
             "lsatag" => {
@@ -92,7 +357,7 @@ impl<'a> Command<'a> {
                         Ok(span) => span,
                         Err(_) => {
                             kprintln!("Couldn't parse time");
-                            return
+                            return false
                         }
                     };
                     let slept = kernel_api::syscall::sleep(Duration::from_millis(span));
@@ -107,25 +372,89 @@ impl<'a> Command<'a> {
             "pwd" => pwd(cwd),
             "cd" => { 
                 if self.args.len() > 1 {
-                    cd(cwd, self.args[1]);
+                    return cd(cwd, self.args[1]);
                 }
             },
-            "ls" => ls(cwd, &self.args[1..]),
-            "cat" => cat(cwd, &self.args[1..]),
+            "ls" => ls(cwd, &self.args[1..], stdout),
+            "cat" => cat(cwd, &self.args[1..], stdin, stdout),
+            "grep" => grep(cwd, &self.args[1..], stdin, stdout),
+            "wc" => wc(cwd, &self.args[1..], stdin, stdout),
+            "du" => return du(cwd, &self.args[1..], stdout),
+            "tree" => return tree(cwd, &self.args[1..], stdout),
             "mkdir" => mkdir(cwd, &self.args[1..]),
             "write_file_test" => write_file_test(cwd),
             "touch" => touch(cwd, &self.args[1..]),
             "rm" => rm(cwd, &self.args[1..]),
+            "cp" => cp(cwd, &self.args[1..]),
+            "mv" => mv(cwd, &self.args[1..]),
             "append" => append(cwd, &self.args[1..]),
             "lsblk" => FILESYSTEM.lsblk(),
+            "df" => return df(stdout),
             "mount" => mount(cwd, &self.args[1..]),
             "umount" => umount(cwd, &self.args[1]),
             "mkcrypt" => encrypt_part(&self.args[1..]),
-            path => kprintln!("unknown command: {}", path)
+            "alias" => return alias(&mut session.aliases, &self.args[1..], stdout),
+            "unalias" => return unalias(&mut session.aliases, &self.args[1..]),
+            "sh" | "source" => return source(session, &self.args[1..]),
+            path => {
+                kprintln!("{}: listed in BUILTINS but not implemented", path);
+                return false
+            }
         }
+        true
     }
 }
 
+/// Defines an alias for each `name=command` argument and prints the alias
+/// for each plain `name`: `alias [name[=command]]*`. With no arguments, prints
+/// every alias.
+fn alias(aliases: &mut BTreeMap<String, String>, args: &[&str], stdout: &mut Stdout) -> bool {
+    if args.is_empty() {
+        for (name, value) in aliases.iter() {
+            let _ = writeln!(stdout, "alias {}='{}'", name, value);
+        }
+        return true
+    }
+
+    let mut succeeded = true;
+    for arg in args {
+        match arg.find('=') {
+            Some(0) => {
+                kprintln!("alias: missing name: {}", arg);
+                succeeded = false;
+            },
+            Some(eq) => {
+                aliases.insert(String::from(&arg[..eq]), String::from(&arg[eq + 1..]));
+            },
+            None => match aliases.get(*arg) {
+                Some(value) => { let _ = writeln!(stdout, "alias {}='{}'", arg, value); },
+                None => {
+                    kprintln!("alias: {}: not found", arg);
+                    succeeded = false;
+                },
+            },
+        }
+    }
+    succeeded
+}
+
+/// Removes the given aliases: `unalias <name>+`.
+fn unalias(aliases: &mut BTreeMap<String, String>, args: &[&str]) -> bool {
+    if args.is_empty() {
+        kprintln!("USAGE: unalias [name]+");
+        return false
+    }
+
+    let mut succeeded = true;
+    for name in args {
+        if aliases.remove(*name).is_none() {
+            kprintln!("unalias: {}: not found", name);
+            succeeded = false;
+        }
+    }
+    succeeded
+}
+
 fn pwd(cwd: &mut PathBuf) {
     let path = cwd.as_path();
     let path_str = path.to_str().expect("Failed to get working directory");
@@ -167,7 +496,7 @@ fn cd(cwd: &mut PathBuf, path: &str) -> bool {
     return true
 }
 
-fn ls(cwd: &PathBuf, args: &[&str]) {
+fn ls(cwd: &PathBuf, args: &[&str], stdout: &mut Stdout) {
     let mut rel_dir = cwd.clone();
     let mut changed_dir = false;
     let mut show_hidden = false;
@@ -194,58 +523,325 @@ fn ls(cwd: &PathBuf, args: &[&str]) {
     let dir = entry.as_dir().expect("Expected directory, found file");
     for item in dir.entries().expect("Couldn't get a dir iterator") {
         if show_hidden || !item.metadata().hidden() {
-            kprintln!("{}", item.metadata())
+            let _ = writeln!(stdout, "{}", item.metadata());
         }
     }
 }
 
-fn cat(cwd: &PathBuf, args: &[&str]) {
-    fn cat_one(cwd: &PathBuf, path: &str) {
-        use core::str;
-        use io::Read;
-        use alloc::slice::SliceConcatExt;
+/// Prints the total size of every directory under a path, and of the path
+/// itself: `du [-c] [path]`. With `-c`, files count as the whole clusters
+/// they take up on disk.
+fn du(cwd: &PathBuf, args: &[&str], stdout: &mut Stdout) -> bool {
+    let (whole_clusters, args) = match args.first() {
+        Some(&"-c") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() > 1 {
+        kprintln!("USAGE: du [-c] [path]");
+        return false
+    }
+
+    let path = match get_abs_path(cwd, args.first().cloned().unwrap_or(".")) {
+        Some(path) => path,
+        None => return false,
+    };
+    let path_str = path.to_str().unwrap_or_default();
+    let entry = match FILESYSTEM.open(&path) {
+        Ok(entry) => entry,
+        Err(e) => {
+            kprintln!("du: {}: {:?}", path_str, e);
+            return false
+        },
+    };
 
-        let mut rel_dir = cwd.clone();
+    let cluster_size = if whole_clusters { FILESYSTEM.cluster_size(&path) } else { None };
+    let mut print = |dir: &str, size: u64| { let _ = writeln!(stdout, "{:>10}  {}", size, dir); };
+    match fat32::traits::disk_usage(&entry, path_str, cluster_size, &mut print) {
+        Ok(size) if !entry.is_dir() => {
+            let _ = writeln!(stdout, "{:>10}  {}", size, path_str);
+            true
+        },
+        Ok(_) => true,
+        Err(e) => {
+            kprintln!("du: {}: {:?}", path_str, e);
+            false
+        },
+    }
+}
 
-        let parts = path.split('/').collect::<Vec<&str>>();
+/// A directory entry as shown by `tree`.
+struct TreeNode {
+    name: String,
+    /// The entries of a directory, or `None` for a file
+    children: Option<Vec<TreeNode>>,
+}
 
-        let dir = parts[0..parts.len()-1].join("/");
-        if !cd(&mut rel_dir, &dir) {
-            return
+/// Lists a directory and everything under it: `tree [-a] [-L depth] [dir]`.
+/// Hidden entries are skipped unless `-a` is given, and `-L` limits how many
+/// levels deep the listing goes.
+fn tree(cwd: &PathBuf, args: &[&str], stdout: &mut Stdout) -> bool {
+    let mut show_hidden = false;
+    let mut max_depth = None;
+    let mut dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-a" => show_hidden = true,
+            "-L" => match args.next().map(|depth| depth.parse::<usize>()) {
+                Some(Ok(depth)) if depth > 0 => max_depth = Some(depth),
+                _ => {
+                    kprintln!("tree: -L needs a depth of at least 1");
+                    return false
+                },
+            },
+            path if dir.is_none() => dir = Some(path),
+            _ => {
+                kprintln!("USAGE: tree [-a] [-L depth] [dir]");
+                return false
+            },
         }
+    }
 
-        rel_dir.push(parts[parts.len()-1]);
-        let entry = FILESYSTEM.open(rel_dir.as_path()).expect("Couldn't open file");
-        if !entry.is_file() {
-            kprintln!("Can't cat a directory {}!", path);
-            return
+    let path = match get_abs_path(cwd, dir.unwrap_or(".")) {
+        Some(path) => path,
+        None => return false,
+    };
+    let path_str = path.to_str().unwrap_or_default();
+    let root = FILESYSTEM.open(&path)
+        .and_then(|entry| read_tree(&entry, String::from(path_str), show_hidden, max_depth));
+    match root {
+        Ok(root) => {
+            render_tree(&root, stdout);
+            true
+        },
+        Err(e) => {
+            kprintln!("tree: {}: {:?}", path_str, e);
+            false
+        },
+    }
+}
+
+/// Reads `entry` and, if it's a directory, the entries under it down to
+/// `max_depth` levels, sorted by name. Hidden entries are left out unless
+/// `show_hidden` is set.
+fn read_tree(entry: &fat32::vfat::Entry<crate::fs::PiVFatHandle>, name: String, show_hidden: bool, max_depth: Option<usize>) -> io::Result<TreeNode> {
+    let dir = match entry.as_dir() {
+        Some(dir) => dir,
+        None => return Ok(TreeNode { name, children: None }),
+    };
+    if max_depth == Some(0) {
+        return Ok(TreeNode { name, children: Some(Vec::new()) });
+    }
+
+    let mut children = Vec::new();
+    for child in dir.entries()? {
+        let child_name = child.name();
+        if child_name == "." || child_name == ".." || (!show_hidden && child.metadata().hidden()) {
+            continue
         }
-        let mut file = entry.into_file().expect("Expected file, found directory");
-        
-        loop {
-            let mut buffer = [0u8; 256];
-            match file.read(&mut buffer) {
-                Ok(0) => break,
-                Ok(n) => {
-                    let string = str::from_utf8(&buffer[0..n]);
-                    match string {
-                        Ok(string) => kprint!("{}", string),
-                        Err(_) => {
-                            kprintln!("Couldn't parse {} as UTF-8", path);
-                            return
-                        },
-                    }
-                },
-                Err(e) => {
-                    kprintln!("Error when reading file {}: {:?}", path, e);
-                    return
-                }
+        children.push(read_tree(&child, String::from(child_name), show_hidden, max_depth.map(|depth| depth - 1))?);
+    }
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(TreeNode { name, children: Some(children) })
+}
+
+/// Prints `root` and the entries under it, one per line, with box-drawing
+/// lines connecting each entry to its parent, followed by a count of the
+/// directories and files below `root`.
+fn render_tree(root: &TreeNode, stdout: &mut Stdout) {
+    fn render_children(node: &TreeNode, prefix: &mut String, counts: &mut (usize, usize), stdout: &mut Stdout) {
+        let children = match &node.children {
+            Some(children) => children,
+            None => return,
+        };
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let _ = writeln!(stdout, "{}{}{}", prefix, if last { "└── " } else { "├── " }, child.name);
+            match child.children {
+                Some(_) => counts.0 += 1,
+                None => counts.1 += 1,
             }
+
+            let len = prefix.len();
+            prefix.push_str(if last { "    " } else { "│   " });
+            render_children(child, prefix, counts, stdout);
+            prefix.truncate(len);
         }
     }
 
-    for arg in args {
-        cat_one(cwd, arg)
+    let _ = writeln!(stdout, "{}", root.name);
+    let mut counts = (0, 0);
+    render_children(root, &mut String::new(), &mut counts, stdout);
+    let _ = writeln!(stdout, "\n{} directories, {} files", counts.0, counts.1);
+}
+
+/// Prints the given files, or stdin if there are none: `cat [-n] [file]*`.
+/// With `-n`, lines are numbered.
+fn cat(cwd: &PathBuf, args: &[&str], stdin: &mut Stdin, stdout: &mut Stdout) {
+    let (number_lines, paths) = match args.first() {
+        Some(&"-n") => (true, &args[1..]),
+        _ => (false, args),
+    };
+
+    // With no files, copy stdin to stdout
+    if paths.is_empty() {
+        if let Err(e) = cat_stream(stdin, number_lines, stdout) {
+            kprintln!("Error when reading input: {:?}", e);
+        }
+        return;
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        let file = get_abs_path(cwd, path)
+            .and_then(|abs_path| FILESYSTEM.open(abs_path.as_path()).ok());
+        match file.map(|entry| entry.into_file()) {
+            Some(Some(file)) => files.push((*path, file)),
+            Some(None) => kprintln!("Can't cat a directory {}!", path),
+            None => kprintln!("{}: No such file", path),
+        }
+    }
+    cat_files(&mut files, paths.len() > 1, number_lines, stdout);
+}
+
+/// Writes each of `files` to `stdout`. With `headers`, each one is preceded
+/// by a `==> name <==` line. With `number_lines`, the numbering starts over
+/// in each file.
+fn cat_files<R: io::Read>(files: &mut [(&str, R)], headers: bool, number_lines: bool, stdout: &mut Stdout) {
+    for (i, (name, file)) in files.iter_mut().enumerate() {
+        if headers {
+            let _ = writeln!(stdout, "{}==> {} <==", if i > 0 { "\n" } else { "" }, name);
+        }
+        if let Err(e) = cat_stream(file, number_lines, stdout) {
+            kprintln!("Error when reading file {}: {:?}", name, e);
+        }
+    }
+}
+
+/// Writes everything read from `src` to `stdout`, prefixing each line with
+/// its number if `number_lines`. A multibyte character split across two
+/// reads is held back until the rest of it arrives.
+fn cat_stream<R: io::Read>(src: &mut R, number_lines: bool, stdout: &mut Stdout) -> io::Result<()> {
+    let mut buffer = [0u8; 256];
+    // Read bytes that haven't been written yet
+    let mut pending = Vec::new();
+    let mut line = 1;
+    let mut at_line_start = true;
+
+    loop {
+        let n = src.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buffer[..n]);
+
+        let valid = match core::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            // Only the end of a character is missing, so wait for more
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return ioerr!(InvalidData, "not valid UTF-8"),
+        };
+        let mut rest = core::str::from_utf8(&pending[..valid]).unwrap_or_default();
+        while !rest.is_empty() {
+            if number_lines && at_line_start {
+                let _ = write!(stdout, "{:>6}\t", line);
+                line += 1;
+            }
+            let end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+            let _ = write!(stdout, "{}", &rest[..end]);
+            at_line_start = rest[..end].ends_with('\n');
+            rest = &rest[end..];
+        }
+        pending.drain(..valid);
+    }
+
+    if !pending.is_empty() {
+        return ioerr!(InvalidData, "ends partway through a UTF-8 character");
+    }
+    Ok(())
+}
+
+/// Prints every line of the given files, or of stdin if there are none, that
+/// contains `args[0]`.
+fn grep(cwd: &PathBuf, args: &[&str], stdin: &mut Stdin, stdout: &mut Stdout) {
+    if args.len() < 1 {
+        kprintln!("USAGE: grep [pattern] [filename]*");
+        return;
+    }
+    let pattern = args[0];
+
+    let mut print_matches = |text: &str| {
+        for line in text.lines().filter(|line| line.contains(pattern)) {
+            let _ = writeln!(stdout, "{}", line);
+        }
+    };
+
+    if args.len() == 1 {
+        let mut line = Vec::new();
+        while stdin.read_line(&mut line) {
+            print_matches(&String::from_utf8_lossy(&line));
+        }
+        return;
+    }
+
+    for path in &args[1..] {
+        if let Some(contents) = read_file(cwd, path) {
+            print_matches(&String::from_utf8_lossy(&contents));
+        }
+    }
+}
+
+/// Prints the number of lines, words and bytes in each of the given files, or
+/// in stdin if there are none.
+fn wc(cwd: &PathBuf, args: &[&str], stdin: &mut Stdin, stdout: &mut Stdout) {
+    fn count(bytes: &[u8]) -> (usize, usize, usize) {
+        let mut count = WordCount::new();
+        count.feed(bytes);
+        (count.lines, count.words, count.bytes)
+    }
+
+    if args.is_empty() {
+        let mut contents = Vec::new();
+        while let Some(byte) = stdin.read_byte() {
+            contents.push(byte);
+        }
+        let (lines, words, bytes) = count(&contents);
+        let _ = writeln!(stdout, "{:>7} {:>7} {:>7}", lines, words, bytes);
+        return;
+    }
+
+    for path in args {
+        if let Some(contents) = read_file(cwd, path) {
+            let (lines, words, bytes) = count(&contents);
+            let _ = writeln!(stdout, "{:>7} {:>7} {:>7} {}", lines, words, bytes, path);
+        }
+    }
+}
+
+/// Reads the whole file at `path`, relative to `cwd`, reporting any error on
+/// the console.
+fn read_file(cwd: &PathBuf, path: &str) -> Option<Vec<u8>> {
+    use io::Read;
+
+    let abs_path = get_abs_path(cwd, path)?;
+    let mut file = match FILESYSTEM.open_file(abs_path.as_path()) {
+        Ok(file) => file,
+        Err(_) => {
+            kprintln!("{}: No such file", path);
+            return None
+        }
+    };
+    let mut contents = Vec::new();
+    let mut buffer = [0u8; 256];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Some(contents),
+            Ok(n) => contents.extend_from_slice(&buffer[0..n]),
+            Err(e) => {
+                kprintln!("Error when reading file {}: {:?}", path, e);
+                return None
+            }
+        }
     }
 }
 
@@ -410,6 +1006,241 @@ fn rm(cwd: &PathBuf, args: &[&str]) {
     }
 }
 
+/// The size of the chunks that `cp` copies files in.
+const COPY_CHUNK: usize = 512;
+
+/// Copies `src` to `dst`. If `dst` is a directory, the copy is placed inside
+/// it under the name of `src`. Directories are only copied with `-r`.
+fn cp(cwd: &PathBuf, args: &[&str]) {
+    let (recursive, args) = match args.first() {
+        Some(&"-r") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() != 2 {
+        kprintln!("USAGE: cp [-r] [source] [destination]");
+        return;
+    }
+
+    let (src, dst) = match transfer_paths(cwd, args[0], args[1]) {
+        Some(paths) => paths,
+        None => return
+    };
+
+    if let Err(e) = copy_entry(&FILESYSTEM, &src, &FILESYSTEM, &dst, recursive, false) {
+        kprintln!("cp: {}: {:?}", args[0], e);
+    }
+    FILESYSTEM.flush_fs(dst);
+}
+
+/// Moves `src` to `dst`. If `dst` is a directory, `src` is moved inside it.
+/// Within a mount only the directory entries are rewritten; across mounts
+/// `src` is copied, keeping its timestamps, and then deleted.
+fn mv(cwd: &PathBuf, args: &[&str]) {
+    if args.len() != 2 {
+        kprintln!("USAGE: mv [source] [destination]");
+        return;
+    }
+
+    let (src, dst) = match transfer_paths(cwd, args[0], args[1]) {
+        Some(paths) => paths,
+        None => return
+    };
+
+    let moved = if FILESYSTEM.same_mount(&src, &dst) {
+        FILESYSTEM.rename(&src, &dst)
+    } else {
+        move_entry(&FILESYSTEM, &src, &FILESYSTEM, &dst)
+    };
+    if let Err(e) = moved {
+        kprintln!("mv: {}: {:?}", args[0], e);
+    }
+    FILESYSTEM.flush_fs(src);
+    FILESYSTEM.flush_fs(dst);
+}
+
+/// Resolves the source and destination of `cp` or `mv` to absolute paths. If
+/// the destination is a directory, the source keeps its name inside it.
+fn transfer_paths(cwd: &PathBuf, src: &str, dst: &str) -> Option<(PathBuf, PathBuf)> {
+    let src_path = get_abs_path(cwd, src)?;
+    let mut dst_path = get_abs_path(cwd, dst)?;
+
+    let dst_is_dir = FILESYSTEM.open(dst_path.as_path()).map(|entry| entry.is_dir()).unwrap_or(false);
+    if dst_is_dir {
+        match src_path.file_name() {
+            Some(name) => dst_path.push(name),
+            None => {
+                kprintln!("can't move or copy /");
+                return None;
+            },
+        }
+    }
+    if dst_path.starts_with(&src_path) {
+        kprintln!("can't move or copy {} into itself", src);
+        return None;
+    }
+
+    Some((src_path, dst_path))
+}
+
+/// A filesystem that `cp` and `mv` copy within or between: the mounted
+/// filesystems as a whole, or a single `VFat` on its own.
+trait CopyFs: Copy + FileSystem<
+    File = fat32::vfat::File<crate::fs::PiVFatHandle>,
+    Dir = fat32::vfat::Dir<crate::fs::PiVFatHandle>,
+    Entry = fat32::vfat::Entry<crate::fs::PiVFatHandle>,
+> {}
+
+impl<F> CopyFs for F where F: Copy + FileSystem<
+    File = fat32::vfat::File<crate::fs::PiVFatHandle>,
+    Dir = fat32::vfat::Dir<crate::fs::PiVFatHandle>,
+    Entry = fat32::vfat::Entry<crate::fs::PiVFatHandle>,
+> {}
+
+/// Moves the entry at `src` on `src_fs` to `dst` on `dst_fs` by copying it,
+/// keeping its timestamps, and then deleting the original. This is how `mv`
+/// moves between mounts, where directory entries can't just be rewritten.
+fn move_entry<S: CopyFs, D: CopyFs>(src_fs: S, src: &Path, dst_fs: D, dst: &Path) -> io::Result<()> {
+    copy_entry(src_fs, src, dst_fs, dst, true, true)?;
+    remove_entry(src_fs, src)
+}
+
+/// Copies the file or, if `recursive`, the directory tree at `src` on
+/// `src_fs` to `dst` on `dst_fs`. With `preserve`, copied files keep their
+/// attributes and timestamps.
+fn copy_entry<S: CopyFs, D: CopyFs>(src_fs: S, src: &Path, dst_fs: D, dst: &Path, recursive: bool, preserve: bool) -> io::Result<()> {
+    let entry = src_fs.open(src)?;
+    if !entry.is_dir() {
+        let mut file = entry.into_file().expect("Expected file, found directory");
+        let mut copy = create_file(dst_fs, dst)?;
+        copy_stream(&mut file, &mut copy)?;
+        if preserve {
+            copy.meta = fat32::vfat::Metadata {
+                name: copy.meta.name.clone(),
+                size: copy.meta.size,
+                ..file.meta.clone()
+            };
+            copy.update_entry()?;
+        }
+        return Ok(());
+    }
+    if !recursive {
+        return ioerr!(InvalidInput, "is a directory (use cp -r)");
+    }
+
+    let parent = dst.parent().unwrap_or(Path::new("/"));
+    let name = dst.file_name().and_then(|name| name.to_str())
+        .ok_or(newioerr!(InvalidInput, "missing directory name"))?;
+    let mut parent_dir = dst_fs.open_dir(parent)?;
+    if parent_dir.find(name).is_err() {
+        parent_dir.create(fat32::vfat::Metadata {
+            name: String::from(name),
+            attributes: fat32::vfat::Attributes::default_dir(),
+            ..Default::default()
+        })?;
+    }
+
+    for child in src_fs.open_dir(src)?.entries()? {
+        let name = child.name();
+        if name == "." || name == ".." {
+            continue;
+        }
+        copy_entry(src_fs, &src.join(name), dst_fs, &dst.join(name), true, preserve)?;
+    }
+    Ok(())
+}
+
+/// Deletes the file or the whole directory tree at `path` on `fs`.
+fn remove_entry<F: CopyFs>(fs: F, path: &Path) -> io::Result<()> {
+    use fat32::traits::File;
+
+    let entry = fs.open(path)?;
+    if !entry.is_dir() {
+        return entry.into_file().expect("Expected file, found directory").delete();
+    }
+
+    let mut dir = entry.into_dir().expect("Expected directory, found file");
+    for child in dir.entries()? {
+        let name = child.name();
+        if name != "." && name != ".." {
+            remove_entry(fs, &path.join(name))?;
+        }
+    }
+    dir.delete()
+}
+
+/// Copies everything that can be read from `src` into `dst` in
+/// `COPY_CHUNK`-byte pieces until end of file, then flushes `dst`. Returns the
+/// number of bytes copied.
+fn copy_stream<R: io::Read, W: io::Write>(src: &mut R, dst: &mut W) -> io::Result<u64> {
+    let mut buffer = [0u8; COPY_CHUNK];
+    let mut copied = 0;
+    loop {
+        match src.read(&mut buffer)? {
+            0 => break,
+            n => {
+                dst.write_all(&buffer[..n])?;
+                copied += n as u64;
+            },
+        }
+    }
+    dst.flush()?;
+    Ok(copied)
+}
+
+/// Creates an empty file at the absolute path `path` on `fs`, replacing any
+/// file that's already there.
+fn create_file<F: CopyFs>(fs: F, path: &Path) -> io::Result<fat32::vfat::File<crate::fs::PiVFatHandle>> {
+    use fat32::traits::File;
+
+    if let Ok(entry) = fs.open(path) {
+        // There's no truncate, so start over with a new file
+        match entry.into_file() {
+            Some(mut file) => file.delete()?,
+            None => return ioerr!(InvalidInput, "is a directory"),
+        }
+    }
+
+    let parent = path.parent().unwrap_or(Path::new("/"));
+    let name = path.file_name().and_then(|name| name.to_str())
+        .ok_or(newioerr!(InvalidInput, "missing file name"))?;
+    fs.open_dir(parent)?
+        .create(fat32::vfat::Metadata {
+            name: String::from(name),
+            ..Default::default()
+        })?
+        .into_file()
+        .ok_or(newioerr!(InvalidData, "created a directory instead of a file"))
+}
+
+/// Prints the size, used and free space of every mounted filesystem.
+fn df(stdout: &mut Stdout) -> bool {
+    match FILESYSTEM.usage() {
+        Ok(usage) => {
+            let _ = write!(stdout, "{}", DfTable(&usage));
+            true
+        },
+        Err(e) => {
+            kprintln!("df: {:?}", e);
+            false
+        },
+    }
+}
+
+/// `df`'s output: one line per mount, with sizes in KiB.
+struct DfTable<'a>(&'a [crate::fs::mount_map::MountUsage]);
+
+impl<'a> fmt::Display for DfTable<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>10} {:>10} {:>10} {:>4}  MOUNT POINT", "SIZE (KiB)", "USED", "FREE", "USE%")?;
+        for mount in self.0 {
+            writeln!(f, "{:>10} {:>10} {:>10} {:>3}%  {}",
+                mount.total / 1024, mount.used() / 1024, mount.free / 1024, mount.percent_used(),
+                mount.mount_point.to_str().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
 fn mount(cwd: &PathBuf, args: &[&str]) {
     if args.len() < 2 {
         kprintln!("not enough arguments!\nusage: mount <part> <path> -p <pw>");
@@ -592,44 +1423,839 @@ fn encrypt_sectors(first: u64, last: u64, key: &str) -> Result<(), ()> {
 /// Starts a shell using `prefix` as the prefix for each line. This function
 /// never returns.
 pub fn shell(prefix: &str) {
-    use core::str;
+    let mut session = Session::new();
+    let mut editor = LineEditor::new();
 
-    let mut path_buf = PathBuf::from("/");
+    if FILESYSTEM.open(RC_FILE).is_ok() {
+        source(&mut session, &[RC_FILE]);
+    }
 
     loop {
-        let mut text_idx = 0;
-        let mut text_buf = [0u8; 512];
-        let mut args_buf = [""; 64];
-        kprint!("[{}]{} ", path_buf.to_str().unwrap_or_default(), prefix);
+        kprint!("[{}]{} ", session.cwd.to_str().unwrap_or_default(), prefix);
         loop {
             let byte = CONSOLE.lock().read_byte();
-            if byte == b'\n' || byte == b'\r' {
+            let complete = |word: &str| complete_path(&session.cwd, word);
+            if editor.feed(byte, &mut *CONSOLE.lock(), complete) {
                 break;
-            } else if byte == 8 || byte == 127 { // backspace
-                if text_idx > 0 {
-                    text_idx -= 1;
-                    text_buf[text_idx] = b' ';
-                    kprint!("\x08 \x08");
-                }
-            } else if byte != b'\t' && (byte < 32 || byte > 127) { // invisible
-                kprint!("\x07"); // ring bell
-            } else if text_idx < text_buf.len() {
-                text_buf[text_idx] = byte;
-                text_idx += 1;
-                kprint!("{}", byte as char);
             }
         }
         kprintln!("");
-        let buf_str = str::from_utf8(&text_buf[..text_idx]).unwrap_or_default();
-        match Command::parse(buf_str, &mut args_buf) {
-            Err(Error::Empty) => (),
-            Err(Error::TooManyArgs) => kprintln!("error: too many arguments"),
-            Ok(cmd) =>
+        let line = editor.take_line();
+        if run_line(&line, &mut session) == Outcome::Exit {
+            return
+        }
+    }
+}
+
+/// Splits `s` at every `sep` that isn't inside quotes or escaped with a
+/// backslash. Quotes and backslashes are kept in the pieces.
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => (),
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (None, c) if c == sep => {
+                pieces.push(&s[start..i]);
+                start = i + c.len_utf8();
+            },
+            _ => (),
+        }
+    }
+    pieces.push(&s[start..]);
+    pieces
+}
+
+/// Copies `word` into `text` starting at `len` with quotes removed and escapes
+/// resolved, and returns the new length of `text`.
+fn unescape(word: &str, text: &mut [u8], mut len: usize) -> Result<usize, Error> {
+    let mut put = |c: char| -> Result<(), Error> {
+        let end = len + c.len_utf8();
+        if end > text.len() {
+            return Err(Error::TooLong);
+        }
+        c.encode_utf8(&mut text[len..end]);
+        len = end;
+        Ok(())
+    };
+
+    let mut quote = None;
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => put(c)?,
+            (_, '\\') => match chars.next() {
+                Some(next) if quote.is_none() || next == '"' || next == '\\' || next == '$' => put(next)?,
+                Some(next) => {
+                    put('\\')?;
+                    put(next)?;
+                },
+                None => put('\\')?,
+            },
+            (_, c) => put(c)?,
+        }
+    }
+
+    match quote {
+        Some(_) => Err(Error::UnterminatedQuote),
+        None => Ok(len),
+    }
+}
+
+/// Returns the sorted `names` that match `pattern`. Hidden names (starting
+/// with `.`) only match patterns that start with `.` too.
+fn glob_matches<'a, I: Iterator<Item = &'a str>>(pattern: &str, names: I) -> Vec<String> {
+    let mut matches: Vec<String> = names
+        .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+        .filter(|name| glob_match(pattern.as_bytes(), name.as_bytes()))
+        .map(String::from)
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Returns `name` with a `\\` before every character that the shell would
+/// otherwise treat specially, so it parses back as one word that is exactly
+/// `name`.
+fn escape(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        if !c.is_alphanumeric() && !"._-+,:@%=/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Replaces every unquoted word of `line` containing `*` or `?` in its last
+/// path component with the matching entries of that directory, relative to
+/// `cwd` unless absolute. Matches are escaped, so names with spaces or quotes
+/// stay one argument. Words that match nothing are left as they are.
+fn expand_globs(cwd: &PathBuf, line: &str) -> String {
+    let mut expanded = String::new();
+    for word in split_unquoted(line, ' ').into_iter().filter(|word| !word.is_empty()) {
+        if !expanded.is_empty() {
+            expanded.push(' ');
+        }
+
+        // Quoted or escaped words are never expanded
+        if word.contains(|c| c == '\'' || c == '"' || c == '\\') {
+            expanded.push_str(word);
+            continue;
+        }
+
+        let (dir_part, pattern) = match word.rfind('/') {
+            Some(i) => (&word[..i + 1], &word[i + 1..]),
+            None => ("", word),
+        };
+        if !pattern.contains(|c| c == '*' || c == '?') {
+            expanded.push_str(word);
+            continue;
+        }
+
+        let dir_path = if dir_part.starts_with('/') { PathBuf::from(dir_part) } else { cwd.join(dir_part) };
+        let names: Vec<String> = match FILESYSTEM.open_dir(dir_path.as_path()).and_then(|dir| dir.entries()) {
+            Ok(entries) => entries.map(|entry| String::from(entry.name())).collect(),
+            Err(_) => Vec::new(),
+        };
+        let matches = glob_matches(pattern, names.iter().map(|name| name.as_str()));
+        if matches.is_empty() {
+            expanded.push_str(word);
+        }
+        for (i, name) in matches.iter().enumerate() {
+            if i > 0 {
+                expanded.push(' ');
+            }
+            expanded.push_str(dir_part);
+            expanded.push_str(&escape(name));
+        }
+    }
+    expanded
+}
+
+/// Returns every path `word` could be completed to, relative to `cwd` unless
+/// it is absolute. Directories end with a `/` so completion can continue into
+/// them.
+fn complete_path(cwd: &PathBuf, word: &str) -> Vec<String> {
+    let (dir_part, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..i + 1], &word[i + 1..]),
+        None => ("", word),
+    };
+    let dir_path = if dir_part.starts_with('/') {
+        PathBuf::from(dir_part)
+    } else {
+        cwd.join(dir_part)
+    };
+
+    let dir = match FILESYSTEM.open_dir(dir_path.as_path()) {
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let entries = match dir.entries() {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter(|entry| entry.name() != "." && entry.name() != "..")
+        .filter(|entry| entry.name().starts_with(prefix))
+        .map(|entry| {
+            let mut candidate = String::from(dir_part);
+            candidate.push_str(entry.name());
+            if entry.is_dir() {
+                candidate.push('/');
+            }
+            candidate
+        })
+        .collect()
+}
+
+/// Opens the target of a redirection for writing, creating the file if it
+/// doesn't exist. A `Truncate` target is emptied first; an `Append` target is
+/// positioned at its end.
+fn open_redirect(cwd: &PathBuf, redirect: &Redirect) -> Option<fat32::vfat::File<crate::fs::PiVFatHandle>> {
+    use io::{Seek, SeekFrom};
+
+    let (path, append) = match *redirect {
+        Redirect::Truncate(path) => (path, false),
+        Redirect::Append(path) => (path, true),
+    };
+    let abs_path = get_abs_path(cwd, path)?;
+
+    if append {
+        match FILESYSTEM.open(abs_path.as_path()).map(|entry| entry.into_file()) {
+            Ok(Some(mut file)) => {
+                file.seek(SeekFrom::End(0)).ok()?;
+                return Some(file);
+            },
+            Ok(None) => {
+                kprintln!("{}: Is a directory", path);
+                return None;
+            },
+            Err(_) => (),
+        }
+    }
+
+    match create_file(&FILESYSTEM, &abs_path) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            kprintln!("{}: couldn't create file: {:?}", path, e);
+            None
+        },
+    }
+}
+
+/// Evaluates one line of input, which may be a list of pipelines separated
+/// by `;`, `&&` or `||`, recording whether each one failed for `$?`. A
+/// pipeline skipped by `&&` or `||` leaves the status as it was, and the line
+/// fails if the last pipeline that ran did.
+fn run_line(line: &str, session: &mut Session) -> Outcome {
+    let mut outcome = Outcome::Success;
+    for (connector, pipeline) in split_commands(line) {
+        let run = match connector {
+            Connector::Then => true,
+            Connector::And => outcome == Outcome::Success,
+            Connector::Or => outcome == Outcome::Failure,
+        };
+        if !run {
+            continue
+        }
+
+        outcome = run_pipeline(pipeline, session);
+        session.status = match outcome {
+            Outcome::Failure => 1,
+            Outcome::Success | Outcome::Exit => 0,
+        };
+        if outcome == Outcome::Exit {
+            break
+        }
+    }
+    outcome
+}
+
+/// Splits `line` into commands at every `;`, `&&` and `||` that isn't inside
+/// quotes or escaped, pairing each command with the connector before it.
+fn split_commands(line: &str) -> Vec<(Connector, &str)> {
+    let mut commands = Vec::new();
+    let mut connector = Connector::Then;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        let next = match (quote, c) {
+            (Some('\''), '\'') => { quote = None; None },
+            (Some('\''), _) => None,
+            (_, '\\') => { escaped = true; None },
+            (Some('"'), '"') => { quote = None; None },
+            (Some(_), _) => None,
+            (None, '\'') | (None, '"') => { quote = Some(c); None },
+            (None, ';') => Some((Connector::Then, 1)),
+            (None, '&') if chars.peek().map(|&(_, c)| c) == Some('&') => Some((Connector::And, 2)),
+            (None, '|') if chars.peek().map(|&(_, c)| c) == Some('|') => Some((Connector::Or, 2)),
+            _ => None,
+        };
+
+        if let Some((next, len)) = next {
+            commands.push((connector, &line[start..i]));
+            connector = next;
+            start = i + len;
+            if len == 2 {
+                chars.next();
+            }
+        }
+    }
+    commands.push((connector, &line[start..]));
+    commands
+}
+
+/// Evaluates a pipeline of commands separated by `|`. Each command's output
+/// is buffered in a pipe that becomes the next command's input. A pipeline
+/// fails if its last command does.
+fn run_pipeline(line: &str, session: &mut Session) -> Outcome {
+    let segments = split_unquoted(line, '|');
+    let mut input: Option<Pipe> = None;
+    let mut succeeded = true;
+
+    for (i, segment) in segments.iter().enumerate() {
+        let mut args_buf = [""; 64];
+        let mut output = if i + 1 < segments.len() { Some(Pipe::new()) } else { None };
+
+        let segment = expand_status(&session.expand_alias(segment), session.status);
+        let segment = expand_globs(&session.cwd, &segment);
+        let mut text_buf = vec![0u8; segment.len()];
+        match Command::parse(&segment, &mut text_buf, &mut args_buf) {
+            Err(Error::Empty) => {
+                if segments.len() > 1 {
+                    kprintln!("error: empty command in pipeline");
+                    return Outcome::Failure
+                }
+                return Outcome::Success
+            },
+            Err(Error::TooManyArgs) => {
+                kprintln!("error: too many arguments");
+                return Outcome::Failure
+            },
+            Err(Error::TooLong) => {
+                kprintln!("error: command too long");
+                return Outcome::Failure
+            },
+            Err(Error::MissingRedirectTarget) => {
+                kprintln!("error: expected a file name after >, >> or <");
+                return Outcome::Failure
+            },
+            Err(Error::UnterminatedQuote) => {
+                kprintln!("error: unterminated quote");
+                return Outcome::Failure
+            },
+            Ok(cmd) => {
                 if cmd.args[0] == "exit" {
-                    return
-                } else {
-                    cmd.eval(&mut path_buf)
+                    return Outcome::Exit
                 }
+
+                // `< path` takes precedence over a pipe. The file is small enough
+                // to buffer whole, so it's fed to the command through a pipe
+                if let Some(path) = cmd.input {
+                    match read_file(&session.cwd, path) {
+                        Some(contents) => {
+                            use io::Write;
+                            let mut pipe = Pipe::new();
+                            let _ = pipe.write(&contents);
+                            input = Some(pipe);
+                        },
+                        None => return Outcome::Failure,
+                    }
+                }
+                let mut stdin = match input.as_mut() {
+                    Some(pipe) => Stdin::Pipe(pipe),
+                    None => Stdin::Console,
+                };
+                let mut stdout = match (&cmd.redirect, output.as_mut()) {
+                    (Some(redirect), _) => match open_redirect(&session.cwd, redirect) {
+                        Some(file) => Stdout::File(file),
+                        None => return Outcome::Failure,
+                    },
+                    (None, Some(pipe)) => Stdout::Pipe(pipe),
+                    (None, None) => Stdout::Console,
+                };
+                succeeded = cmd.eval(session, &mut stdin, &mut stdout);
+                if let Stdout::File(mut file) = stdout {
+                    use io::Write;
+                    let _ = file.flush();
+                }
+            },
+        }
+
+        input = output;
+    }
+
+    if succeeded { Outcome::Success } else { Outcome::Failure }
+}
+
+/// Replaces every `$?` in `line` with `status`, except inside single quotes
+/// or after a backslash.
+fn expand_status(line: &str, status: u64) -> String {
+    let mut expanded = String::new();
+    let (mut single_quoted, mut double_quoted) = (false, false);
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if !single_quoted => {
+                expanded.push(c);
+                if let Some(next) = chars.next() {
+                    expanded.push(next);
+                }
+                continue
+            },
+            '\'' if !double_quoted => single_quoted = !single_quoted,
+            '"' if !single_quoted => double_quoted = !double_quoted,
+            '$' if !single_quoted && chars.peek() == Some(&'?') => {
+                chars.next();
+                expanded.push_str(&format!("{}", status));
+                continue
+            },
+            _ => (),
+        }
+        expanded.push(c);
+    }
+    expanded
+}
+
+/// Script run by the shell when it starts, if it exists.
+const RC_FILE: &str = "/rc";
+
+/// Returns the commands in the shell script `text`, one per line, with
+/// comments starting at an unquoted `#` and blank lines left out.
+fn script_commands(text: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    for line in text.lines() {
+        let words: Vec<&str> = split_unquoted(line.trim(), ' ')
+            .into_iter()
+            .filter(|word| !word.is_empty())
+            .take_while(|word| !word.starts_with('#'))
+            .collect();
+        if !words.is_empty() {
+            commands.push(words.join(" "));
+        }
+    }
+    commands
+}
+
+/// Runs each line of the script at `args[0]` as a command: `sh [-e] path`.
+/// With `-e`, the script stops at the first command that fails. Returns
+/// `false` if the script couldn't be read or, with `-e`, stopped early.
+fn source(session: &mut Session, args: &[&str]) -> bool {
+    let (stop_on_error, args) = match args.first() {
+        Some(&"-e") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() != 1 {
+        kprintln!("USAGE: sh [-e] [script]");
+        return false;
+    }
+
+    let contents = match read_file(&session.cwd, args[0]) {
+        Some(contents) => contents,
+        None => return false,
+    };
+    for (i, command) in script_commands(&String::from_utf8_lossy(&contents)).iter().enumerate() {
+        match run_line(command, session) {
+            Outcome::Success => (),
+            Outcome::Failure if !stop_on_error => (),
+            Outcome::Failure => {
+                kprintln!("{}: stopped at command {}: {}", args[0], i + 1, command);
+                return false;
+            },
+            Outcome::Exit => break,
+        }
+    }
+    true
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replaces the file at the absolute path `path` on `FILESYSTEM` with
+    /// `contents`.
+    fn write_test_file(path: &str, contents: &[u8]) {
+        use io::Write;
+
+        let mut file = create_file(&FILESYSTEM, Path::new(path)).unwrap();
+        file.write_all(contents).unwrap();
+        file.flush().unwrap();
+    }
+
+    #[test]
+    fn grep_reads_piped_input() {
+        let _fs = FILESYSTEM.initialize_for_test();
+        write_test_file("/grep_in.txt", b"foo\nbar\nfoobar\n");
+
+        let mut session = Session::new();
+        let line = "cat /grep_in.txt | grep foo > /grep_out.txt";
+        assert_eq!(run_pipeline(line, &mut session), Outcome::Success);
+        assert_eq!(read_file(&session.cwd, "/grep_out.txt").unwrap(), b"foo\nfoobar\n");
+    }
+
+    #[test]
+    fn parse_output_redirection() {
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse("echo hello > /foo.txt", &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &["echo", "hello"]);
+        assert_eq!(cmd.redirect, Some(Redirect::Truncate("/foo.txt")));
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse("echo hello >>/foo.txt", &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &["echo", "hello"]);
+        assert_eq!(cmd.redirect, Some(Redirect::Append("/foo.txt")));
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        assert!(Command::parse("echo hello >", &mut text_buf, &mut args_buf).is_err());
+    }
+
+    #[test]
+    fn redirection_writes_and_appends_to_files() {
+        let _fs = FILESYSTEM.initialize_for_test();
+        let mut session = Session::new();
+
+        assert_eq!(run_line("echo hello > /redirect.txt", &mut session), Outcome::Success);
+        assert_eq!(run_line("echo world >> /redirect.txt", &mut session), Outcome::Success);
+        assert_eq!(read_file(&session.cwd, "/redirect.txt").unwrap(), b"hello\nworld\n");
+
+        // `>` starts the file over
+        assert_eq!(run_line("echo again > /redirect.txt", &mut session), Outcome::Success);
+        assert_eq!(read_file(&session.cwd, "/redirect.txt").unwrap(), b"again\n");
+
+        // `>>` creates a file that isn't there yet
+        assert_eq!(run_line("echo new >> /appended.txt", &mut session), Outcome::Success);
+        assert_eq!(read_file(&session.cwd, "/appended.txt").unwrap(), b"new\n");
+    }
+
+    #[test]
+    fn parse_quotes_and_escapes() {
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse(r#"echo "a b" c"#, &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &["echo", "a b", "c"]);
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let line = r#"touch my\ file.txt 'it''s' "say \"hi\"""#;
+        let cmd = Command::parse(line, &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &["touch", "my file.txt", "its", r#"say "hi""#]);
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        match Command::parse(r#"echo "a b"#, &mut text_buf, &mut args_buf) {
+            Err(Error::UnterminatedQuote) => (),
+            _ => panic!("expected an unterminated quote error"),
+        }
+    }
+
+    #[test]
+    fn copy_preserves_contents() {
+        use io::{Read, Write};
+
+        // Span several chunks, ending partway through the last one
+        let contents: Vec<u8> = (0..COPY_CHUNK * 3 + 17).map(|i| (i * 7 % 256) as u8).collect();
+        let mut src = Pipe::new();
+        src.write_all(&contents).unwrap();
+        let mut dst = Pipe::new();
+
+        assert_eq!(copy_stream(&mut src, &mut dst).unwrap(), contents.len() as u64);
+        assert!(src.is_empty());
+
+        let mut copied = Vec::new();
+        dst.read_to_end(&mut copied).unwrap();
+        assert_eq!(copied, contents);
+    }
+
+    #[test]
+    fn move_between_filesystems_copies_then_deletes() {
+        use io::{Read, Write};
+        use fat32::vfat::VFatHandle;
+
+        let (src_fs, dst_fs) = (crate::fs::test_vfat(), crate::fs::test_vfat());
+        let free = |fs: &crate::fs::PiVFatHandle| fs.lock(|vfat| vfat.free_clusters()).unwrap();
+        let cluster = src_fs.lock(|vfat| vfat.bytes_per_cluster());
+        let free_before = free(&src_fs);
+
+        // Several clusters, ending partway through the last one
+        let contents: Vec<u8> = (0..cluster * 3 + 17).map(|i| (i * 7 % 256) as u8).collect();
+        let mut file = create_file(&src_fs, Path::new("/moved.bin")).unwrap();
+        file.write_all(&contents).unwrap();
+        file.flush().unwrap();
+        let free_written = free(&src_fs);
+        assert!(free_written < free_before);
+
+        move_entry(&src_fs, Path::new("/moved.bin"), &dst_fs, Path::new("/moved.bin")).unwrap();
+
+        let mut moved = Vec::new();
+        (&dst_fs).open_file("/moved.bin").unwrap().read_to_end(&mut moved).unwrap();
+        assert_eq!(moved, contents);
+        assert!((&src_fs).open("/moved.bin").is_err());
+        assert_eq!(free(&src_fs), free_written + 4);
+    }
+
+    #[test]
+    fn script_runs_each_line_in_order() {
+        let _fs = FILESYSTEM.initialize_for_test();
+        let mut session = Session::new();
+
+        write_test_file("/make_and_list.sh", b"touch /from_script.txt\nls / > /script_ls.txt\n");
+        assert!(source(&mut session, &["/make_and_list.sh"]));
+
+        assert!(FILESYSTEM.open("/from_script.txt").is_ok());
+        let listing = read_file(&session.cwd, "/script_ls.txt").unwrap();
+        assert!(String::from_utf8_lossy(&listing).contains("from_script.txt"));
+    }
+
+    #[test]
+    fn script_skips_comments_and_blank_lines() {
+        let script = "# make a file\ntouch /new.txt   # then list it\n\n  ls /\necho '# not a comment'\n";
+        assert_eq!(script_commands(script), vec![
+            String::from("touch /new.txt"),
+            String::from("ls /"),
+            String::from("echo '# not a comment'"),
+        ]);
+    }
+
+    #[test]
+    fn cat_numbers_each_file_from_one() {
+        use io::{Read, Write};
+
+        let (mut first, mut second) = (Pipe::new(), Pipe::new());
+        first.write_all(b"alpha\nbeta\n").unwrap();
+        // The multibyte character straddles the 256-byte read buffer
+        let mut long_line = vec![b'x'; 255];
+        long_line.extend_from_slice("\u{e9}\n".as_bytes());
+        second.write_all(&long_line).unwrap();
+        second.write_all(b"gamma").unwrap();
+
+        let mut output = Pipe::new();
+        let mut files = [("first", first), ("second", second)];
+        cat_files(&mut files, true, true, &mut Stdout::Pipe(&mut output));
+
+        let mut text = String::new();
+        output.read_to_string(&mut text).unwrap();
+        let expected = format!(
+            "==> first <==\n     1\talpha\n     2\tbeta\n\n==> second <==\n     1\t{}\u{e9}\n     2\tgamma",
+            "x".repeat(255),
+        );
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn help_lists_builtins() {
+        use io::Read;
+
+        let mut output = Pipe::new();
+        help(&mut Stdout::Pipe(&mut output));
+
+        let mut text = String::new();
+        output.read_to_string(&mut text).unwrap();
+        assert_eq!(text.lines().count(), BUILTINS.len());
+        assert!(text.lines().any(|line| line.starts_with("mount <part> <path>")));
+    }
+
+    #[test]
+    fn alias_expands_only_the_command_name() {
+        use io::Read;
+
+        let mut session = Session::new();
+        let mut output = Pipe::new();
+        assert!(alias(&mut session.aliases, &["ll=ls -a"], &mut Stdout::Pipe(&mut output)));
+        assert_eq!(session.expand_alias("ll"), "ls -a");
+        assert_eq!(session.expand_alias("  ll /bin"), "ls -a /bin");
+        assert_eq!(session.expand_alias("echo ll"), "echo ll");
+        assert_eq!(session.expand_alias("'ll' /bin"), "'ll' /bin");
+
+        assert!(alias(&mut session.aliases, &["ll"], &mut Stdout::Pipe(&mut output)));
+        let mut text = String::new();
+        output.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "alias ll='ls -a'\n");
+
+        assert!(unalias(&mut session.aliases, &["ll"]));
+        assert_eq!(session.expand_alias("ll"), "ll");
+        assert!(!unalias(&mut session.aliases, &["ll"]));
+    }
+
+    #[test]
+    fn clear_writes_ansi_sequence() {
+        use io::Read;
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse("clear", &mut text_buf, &mut args_buf).unwrap();
+        let mut output = Pipe::new();
+        assert!(cmd.eval(&mut Session::new(), &mut Stdin::Console, &mut Stdout::Pipe(&mut output)));
+
+        let mut result = Vec::new();
+        output.read_to_end(&mut result).unwrap();
+        assert_eq!(&result[..], b"\x1b[2J\x1b[H");
+    }
+
+    #[test]
+    fn tree_draws_nested_entries() {
+        use io::Read;
+
+        let file = |name: &str| TreeNode { name: String::from(name), children: None };
+        let dir = |name: &str, children: Vec<TreeNode>| TreeNode { name: String::from(name), children: Some(children) };
+        let root = dir("/docs", vec![
+            dir("notes", vec![file("a.txt"), dir("old", vec![file("b.txt")])]),
+            dir("empty", vec![]),
+            file("readme"),
+        ]);
+
+        let mut output = Pipe::new();
+        render_tree(&root, &mut Stdout::Pipe(&mut output));
+        let mut text = String::new();
+        output.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "\
+/docs
+├── notes
+│   ├── a.txt
+│   └── old
+│       └── b.txt
+├── empty
+└── readme
+
+3 directories, 3 files
+");
+    }
+
+    #[test]
+    fn df_reports_each_mount() {
+        use crate::fs::mount_map::MountUsage;
+
+        let usage = [
+            MountUsage { mount_point: PathBuf::from("/"), total: 64 * 1024 * 1024, free: 48 * 1024 * 1024 },
+            MountUsage { mount_point: PathBuf::from("/boot"), total: 4096, free: 1 },
+        ];
+        assert_eq!(usage[0].used() + usage[0].free, usage[0].total);
+        assert_eq!(usage[0].percent_used(), 25);
+        // Nearly full rounds up
+        assert_eq!(usage[1].percent_used(), 100);
+
+        assert_eq!(format!("{}", DfTable(&usage)), "\
+SIZE (KiB)       USED       FREE USE%  MOUNT POINT
+     65536      16384      49152  25%  /
+         4          3          0 100%  /boot
+");
+    }
+
+    #[test]
+    fn status_of_failed_command() {
+        use io::Read;
+
+        let mut session = Session::new();
+        assert_eq!(run_line("nosuchcommand", &mut session), Outcome::Failure);
+        assert_eq!(session.status, 1);
+
+        let line = expand_status("echo $? '$?' \\$?", session.status);
+        assert_eq!(line, "echo 1 '$?' \\$?");
+        assert_eq!(expand_status(r#"echo "it's $?""#, 2), r#"echo "it's 2""#);
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse(&line, &mut text_buf, &mut args_buf).unwrap();
+        let mut output = Pipe::new();
+        assert!(cmd.eval(&mut session, &mut Stdin::Console, &mut Stdout::Pipe(&mut output)));
+        let mut text = String::new();
+        output.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "1 $? $?\n");
+
+        assert_eq!(run_line("echo ok", &mut session), Outcome::Success);
+        assert_eq!(session.status, 0);
+    }
+
+    #[test]
+    fn split_commands_respects_quotes() {
+        assert_eq!(split_commands(r#"echo 'a;b' ; echo "c && d" && ls a|grep b||pwd"#), vec![
+            (Connector::Then, "echo 'a;b' "),
+            (Connector::Then, r#" echo "c && d" "#),
+            (Connector::And, " ls a|grep b"),
+            (Connector::Or, "pwd"),
+        ]);
+        assert_eq!(split_commands(r"echo a\;b"), vec![(Connector::Then, r"echo a\;b")]);
+    }
+
+    #[test]
+    fn semicolons_run_every_command() {
+        let mut session = Session::new();
+        assert_eq!(run_line("alias ll=ls ; alias la=\"ls -a\";unalias ll", &mut session), Outcome::Success);
+        assert!(!session.aliases.contains_key("ll"));
+        assert_eq!(session.aliases.get("la").map(|value| value.as_str()), Some("ls -a"));
+
+        // A failure doesn't stop the list, but decides `&&` and `||`
+        let line = "nosuchcommand ; alias a=1 && nosuchcommand && alias b=2 || alias c=3";
+        assert_eq!(run_line(line, &mut session), Outcome::Success);
+        assert!(session.aliases.contains_key("a"));
+        assert!(!session.aliases.contains_key("b"));
+        assert!(session.aliases.contains_key("c"));
+
+        assert_eq!(run_line("alias d=4 || alias e=5", &mut session), Outcome::Success);
+        assert!(session.aliases.contains_key("d"));
+        assert!(!session.aliases.contains_key("e"));
+    }
+
+    #[test]
+    fn glob_expands_to_sorted_matches() {
+        let names = ["notes", "b.txt", ".hidden.txt", "c.txt", "a.txt", "a.txt.bak"];
+        let matches = glob_matches("*.txt", names.iter().cloned());
+        assert_eq!(matches, ["a.txt", "b.txt", "c.txt"]);
+
+        assert_eq!(glob_matches("?.txt", names.iter().cloned()), ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(glob_matches(".*", names.iter().cloned()), [".hidden.txt"]);
+        assert!(glob_matches("*.rs", names.iter().cloned()).is_empty());
+    }
+
+    #[test]
+    fn glob_matches_stay_one_argument_each() {
+        let names = ["my notes.txt", "it's \"done\".txt", ">out|$x;&*?\\", "plain.txt"];
+        let line = names.iter().map(|name| escape(name)).collect::<Vec<_>>().join(" ");
+        assert_eq!(escape("plain.txt"), "plain.txt");
+
+        let (mut text_buf, mut args_buf) = ([0u8; 128], [""; 64]);
+        let cmd = Command::parse(&line, &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &names[..]);
+        assert!(cmd.redirect.is_none());
+        assert_eq!(split_unquoted(&line, '|').len(), 1);
+    }
+
+    #[test]
+    fn wc_counts_redirected_input() {
+        use io::Write;
+
+        let (mut text_buf, mut args_buf) = ([0u8; 64], [""; 64]);
+        let cmd = Command::parse("wc < /words.txt", &mut text_buf, &mut args_buf).unwrap();
+        assert_eq!(&cmd.args[..], &["wc"]);
+        assert_eq!(cmd.input, Some("/words.txt"));
+
+        // What `run_line` feeds `wc` after reading /words.txt
+        let mut input = Pipe::new();
+        input.write(b"one two\nthree\n").unwrap();
+        let mut output = Pipe::new();
+        let mut session = Session::new();
+        cmd.eval(&mut session, &mut Stdin::Pipe(&mut input), &mut Stdout::Pipe(&mut output));
+
+        let mut result = Vec::new();
+        while let Some(byte) = output.read_byte() {
+            result.push(byte);
         }
+        assert_eq!(&result[..], b"      2       3      14\n");
     }
 }
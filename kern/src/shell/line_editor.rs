@@ -0,0 +1,425 @@
+use alloc::collections::vec_deque::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Maximum length of a command line.
+pub const LINE_MAX: usize = 512;
+
+/// Number of previous command lines remembered for recall.
+pub const HISTORY_MAX: usize = 32;
+
+const BELL: &str = "\x07";
+const ESC: u8 = 0x1b;
+/// Ctrl-U: erase everything before the cursor.
+const KILL_LINE: u8 = 0x15;
+/// Ctrl-W: erase the word before the cursor.
+const KILL_WORD: u8 = 0x17;
+
+/// Where we are in an ANSI escape sequence.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Escape {
+    None,
+    /// Got `ESC`
+    Start,
+    /// Got `ESC [`
+    Csi,
+    /// Got `ESC [` and a digit, expecting `~`
+    CsiParam(u8),
+}
+
+/// Line editing for the shell's input: collects bytes into a command line,
+/// echoing them to the console, moves the cursor with the left/right arrow
+/// keys and Home/End, recalls previous lines with the up/down arrow keys,
+/// erases with backspace, Ctrl-U and Ctrl-W, and completes the word before
+/// the cursor with TAB.
+pub struct LineEditor {
+    line: Vec<u8>,
+    /// Index into `line` where typed bytes are inserted
+    cursor: usize,
+    history: VecDeque<String>,
+    /// Index into `history` of the line being shown, if any
+    recalled: Option<usize>,
+    /// What was typed before recalling history, restored by scrolling back down
+    draft: Vec<u8>,
+    escape: Escape,
+}
+
+impl LineEditor {
+    /// Returns a new editor with an empty line and no history.
+    pub fn new() -> LineEditor {
+        LineEditor {
+            line: Vec::new(),
+            cursor: 0,
+            history: VecDeque::new(),
+            recalled: None,
+            draft: Vec::new(),
+            escape: Escape::None,
+        }
+    }
+
+    /// Handles one input byte, echoing its effect to `out`. Returns `true`
+    /// once the line is complete, after which it can be collected with
+    /// `take_line()`.
+    ///
+    /// On TAB, `complete` is called with the word being typed and should
+    /// return every full word it could complete to.
+    pub fn feed<W, C>(&mut self, byte: u8, out: &mut W, complete: C) -> bool
+    where
+        W: Write,
+        C: FnOnce(&str) -> Vec<String>,
+    {
+        match self.escape {
+            Escape::Start if byte == b'[' => {
+                self.escape = Escape::Csi;
+                return false
+            },
+            Escape::Start => {
+                // A lone ESC: drop it and treat this byte normally
+                self.escape = Escape::None;
+                let _ = out.write_str(BELL);
+            },
+            Escape::Csi if byte.is_ascii_digit() => {
+                self.escape = Escape::CsiParam(byte);
+                return false
+            },
+            Escape::Csi => {
+                self.escape = Escape::None;
+                match byte {
+                    b'A' => self.history_up(out),
+                    b'B' => self.history_down(out),
+                    b'C' => self.move_right(out),
+                    b'D' => self.move_left(out),
+                    b'H' => self.move_home(out),
+                    b'F' => self.move_end(out),
+                    _ => { let _ = out.write_str(BELL); },
+                }
+                return false
+            },
+            Escape::CsiParam(param) => {
+                // Terminals disagree on Home and End: accept `ESC [ 1 ~` and
+                // `ESC [ 7 ~` for Home, `ESC [ 4 ~` and `ESC [ 8 ~` for End
+                self.escape = Escape::None;
+                match (param, byte) {
+                    (b'1', b'~') | (b'7', b'~') => self.move_home(out),
+                    (b'4', b'~') | (b'8', b'~') => self.move_end(out),
+                    _ => { let _ = out.write_str(BELL); },
+                }
+                return false
+            },
+            Escape::None => (),
+        }
+
+        if byte == b'\n' || byte == b'\r' {
+            return true
+        } else if byte == b'\t' {
+            self.complete(out, complete);
+        } else if byte == ESC {
+            self.escape = Escape::Start;
+        } else if byte == 8 || byte == 127 { // backspace
+            self.delete_before_cursor(out);
+        } else if byte == KILL_LINE {
+            while self.cursor > 0 {
+                self.delete_before_cursor(out);
+            }
+        } else if byte == KILL_WORD {
+            // Trailing spaces go along with the word, like in a terminal
+            while self.cursor > 0 && self.line[self.cursor - 1] == b' ' {
+                self.delete_before_cursor(out);
+            }
+            while self.cursor > 0 && self.line[self.cursor - 1] != b' ' {
+                self.delete_before_cursor(out);
+            }
+        } else if byte < 32 || byte > 127 { // invisible
+            let _ = out.write_str(BELL);
+        } else if self.line.len() < LINE_MAX {
+            self.insert(&[byte], out);
+        }
+        false
+    }
+
+    /// Returns the completed line and remembers it in the history.
+    pub fn take_line(&mut self) -> String {
+        let line = String::from_utf8(core::mem::replace(&mut self.line, Vec::new()))
+            .unwrap_or_default();
+        self.cursor = 0;
+        self.recalled = None;
+        self.draft.clear();
+        self.escape = Escape::None;
+
+        let repeated = self.history.back().map(|last| *last == line).unwrap_or(false);
+        if !line.trim().is_empty() && !repeated {
+            if self.history.len() == HISTORY_MAX {
+                self.history.pop_front();
+            }
+            self.history.push_back(line.clone());
+        }
+        line
+    }
+
+    /// Returns the line typed so far.
+    pub fn line(&self) -> &[u8] {
+        &self.line
+    }
+
+    /// Returns the position of the cursor within `line()`.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Inserts `bytes` at the cursor and redraws the rest of the line after
+    /// them, leaving the cursor just past the inserted bytes.
+    fn insert<W: Write>(&mut self, bytes: &[u8], out: &mut W) {
+        let tail: Vec<u8> = self.line.drain(self.cursor..).collect();
+        self.line.extend_from_slice(bytes);
+        self.line.extend_from_slice(&tail);
+        self.cursor += bytes.len();
+
+        for &byte in bytes.iter().chain(tail.iter()) {
+            let _ = out.write_char(byte as char);
+        }
+        for _ in 0..tail.len() {
+            let _ = out.write_str("\x08");
+        }
+    }
+
+    /// Removes the byte before the cursor, shifting the rest of the line left.
+    fn delete_before_cursor<W: Write>(&mut self, out: &mut W) {
+        if self.cursor == 0 {
+            return
+        }
+        self.cursor -= 1;
+        self.line.remove(self.cursor);
+
+        let _ = out.write_str("\x08");
+        for &byte in self.line[self.cursor..].iter() {
+            let _ = out.write_char(byte as char);
+        }
+        // Blank out the last column, which the line no longer reaches
+        let _ = out.write_str(" \x08");
+        for _ in self.cursor..self.line.len() {
+            let _ = out.write_str("\x08");
+        }
+    }
+
+    fn move_left<W: Write>(&mut self, out: &mut W) {
+        if self.cursor == 0 {
+            let _ = out.write_str(BELL);
+            return
+        }
+        self.cursor -= 1;
+        let _ = out.write_str("\x08");
+    }
+
+    fn move_right<W: Write>(&mut self, out: &mut W) {
+        if self.cursor == self.line.len() {
+            let _ = out.write_str(BELL);
+            return
+        }
+        let _ = out.write_char(self.line[self.cursor] as char);
+        self.cursor += 1;
+    }
+
+    fn move_home<W: Write>(&mut self, out: &mut W) {
+        for _ in 0..self.cursor {
+            let _ = out.write_str("\x08");
+        }
+        self.cursor = 0;
+    }
+
+    fn move_end<W: Write>(&mut self, out: &mut W) {
+        for &byte in self.line[self.cursor..].iter() {
+            let _ = out.write_char(byte as char);
+        }
+        self.cursor = self.line.len();
+    }
+
+    /// Extends the word before the cursor to the longest prefix shared by all
+    /// of its completions, adding a space if there is exactly one that isn't
+    /// a directory. Rings the bell if that doesn't make any progress.
+    fn complete<W, C>(&mut self, out: &mut W, complete: C)
+    where
+        W: Write,
+        C: FnOnce(&str) -> Vec<String>,
+    {
+        let start = self.line[..self.cursor].iter().rposition(|&b| b == b' ').map(|i| i + 1).unwrap_or(0);
+        let word = String::from_utf8_lossy(&self.line[start..self.cursor]).into_owned();
+        let candidates = complete(&word);
+        if candidates.is_empty() {
+            let _ = out.write_str(BELL);
+            return
+        }
+
+        let mut common = candidates[0].as_str();
+        for candidate in &candidates[1..] {
+            let shared = common.bytes().zip(candidate.bytes()).take_while(|(a, b)| a == b).count();
+            common = &common[..shared];
+        }
+
+        let mut extension = match common.get(word.len()..) {
+            Some(rest) if common.starts_with(word.as_str()) => String::from(rest),
+            _ => String::new(),
+        };
+        if candidates.len() == 1 && !common.ends_with('/') {
+            extension.push(' ');
+        }
+        if extension.is_empty() || self.line.len() + extension.len() > LINE_MAX {
+            let _ = out.write_str(BELL);
+            return
+        }
+
+        self.insert(extension.as_bytes(), out);
+    }
+
+    fn history_up<W: Write>(&mut self, out: &mut W) {
+        let index = match self.recalled {
+            None if self.history.is_empty() => None,
+            None => {
+                self.draft = self.line.clone();
+                Some(self.history.len() - 1)
+            },
+            Some(0) => None,
+            Some(i) => Some(i - 1),
+        };
+
+        match index {
+            Some(i) => {
+                self.recalled = Some(i);
+                let line = self.history[i].clone().into_bytes();
+                self.replace_line(line, out);
+            },
+            None => { let _ = out.write_str(BELL); },
+        }
+    }
+
+    fn history_down<W: Write>(&mut self, out: &mut W) {
+        match self.recalled {
+            None => { let _ = out.write_str(BELL); },
+            Some(i) if i + 1 < self.history.len() => {
+                self.recalled = Some(i + 1);
+                let line = self.history[i + 1].clone().into_bytes();
+                self.replace_line(line, out);
+            },
+            Some(_) => {
+                self.recalled = None;
+                let draft = core::mem::replace(&mut self.draft, Vec::new());
+                self.replace_line(draft, out);
+            },
+        }
+    }
+
+    /// Erases the current line from the screen and shows `line` instead.
+    fn replace_line<W: Write>(&mut self, line: Vec<u8>, out: &mut W) {
+        self.move_end(out);
+        for _ in 0..self.line.len() {
+            let _ = out.write_str("\x08 \x08");
+        }
+        self.line = line;
+        self.cursor = self.line.len();
+        for &byte in self.line.iter() {
+            let _ = out.write_char(byte as char);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(editor: &mut LineEditor, bytes: &[u8]) -> bool {
+        let mut out = String::new();
+        bytes.iter().fold(false, |_, &byte| editor.feed(byte, &mut out, |_| Vec::new()))
+    }
+
+    fn complete_root(word: &str) -> Vec<String> {
+        ["/bin/", "/boot/", "/fstab"].iter()
+            .filter(|entry| entry.starts_with(word))
+            .map(|entry| String::from(*entry))
+            .collect()
+    }
+
+    #[test]
+    fn up_arrow_recalls_previous_line() {
+        let mut editor = LineEditor::new();
+        assert!(feed_all(&mut editor, b"ls /bin\r"));
+        assert_eq!(editor.take_line(), "ls /bin");
+        assert!(feed_all(&mut editor, b"pwd\r"));
+        assert_eq!(editor.take_line(), "pwd");
+
+        feed_all(&mut editor, b"ec");
+        feed_all(&mut editor, b"\x1b[A");
+        assert_eq!(editor.line(), b"pwd");
+        feed_all(&mut editor, b"\x1b[A");
+        assert_eq!(editor.line(), b"ls /bin");
+        feed_all(&mut editor, b"\x1b[B\x1b[B");
+        assert_eq!(editor.line(), b"ec");
+    }
+
+    #[test]
+    fn tab_completes_paths() {
+        let mut editor = LineEditor::new();
+        let mut out = String::new();
+        feed_all(&mut editor, b"ls /bi");
+        editor.feed(b'\t', &mut out, complete_root);
+        assert_eq!(editor.line(), b"ls /bin/");
+
+        // Ambiguous: only the shared prefix is filled in
+        let mut editor = LineEditor::new();
+        feed_all(&mut editor, b"ls /");
+        editor.feed(b'\t', &mut out, complete_root);
+        assert_eq!(editor.line(), b"ls /");
+        assert!(out.ends_with(BELL));
+        feed_all(&mut editor, b"f");
+        editor.feed(b'\t', &mut out, complete_root);
+        assert_eq!(editor.line(), b"ls /fstab ");
+    }
+
+    #[test]
+    fn insert_in_middle_of_line() {
+        let mut editor = LineEditor::new();
+        feed_all(&mut editor, b"ls /bn");
+        feed_all(&mut editor, b"\x1b[D");
+        assert_eq!(editor.cursor(), 5);
+
+        let mut out = String::new();
+        editor.feed(b'i', &mut out, |_| Vec::new());
+        assert_eq!(editor.line(), b"ls /bin");
+        assert_eq!(editor.cursor(), 6);
+        // The tail is redrawn and the cursor moved back over it
+        assert_eq!(out, "in\x08");
+
+        feed_all(&mut editor, b"\x1b[H\x7f\x1b[Cx\x1b[F");
+        assert_eq!(editor.line(), b"lxs /bin");
+        assert_eq!(editor.cursor(), 8);
+        feed_all(&mut editor, b"\x1b[1~\x1b[C\x1b[C\x7f");
+        assert_eq!(editor.line(), b"ls /bin");
+        assert!(feed_all(&mut editor, b"\x1b[4~\r"));
+        assert_eq!(editor.take_line(), "ls /bin");
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn ctrl_u_and_ctrl_w_erase_before_cursor() {
+        let mut editor = LineEditor::new();
+        feed_all(&mut editor, b"cat /a /b  ");
+        feed_all(&mut editor, &[KILL_WORD]);
+        assert_eq!(editor.line(), b"cat /a ");
+        feed_all(&mut editor, &[KILL_WORD, KILL_WORD]);
+        assert_eq!(editor.line(), b"");
+        feed_all(&mut editor, &[KILL_WORD]);
+        assert_eq!(editor.line(), b"");
+
+        // Only what's before the cursor goes
+        feed_all(&mut editor, b"ls /bin\x1b[D\x1b[D\x1b[D");
+        feed_all(&mut editor, &[KILL_LINE]);
+        assert_eq!(editor.line(), b"bin");
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn lone_escape_is_dropped() {
+        let mut editor = LineEditor::new();
+        feed_all(&mut editor, b"a\x1bb");
+        assert_eq!(editor.line(), b"ab");
+    }
+}
@@ -1,15 +1,22 @@
-mod frame;
 mod syndrome;
 mod syscall;
 
+pub mod frame;
 pub mod irq;
 pub use self::frame::TrapFrame;
 
-use pi::interrupt::{Controller, Interrupt};
+use core::mem::size_of;
 
+use pi::interrupt::Controller;
+use kernel_api::{ExitStatus, SIGSEGV};
+
+use self::frame::backtrace;
 use self::syndrome::{Syndrome, Fault};
 use self::syscall::handle_syscall;
 use crate::console::kprintln;
+use crate::param::{KERN_STACK_BASE, PAGE_MASK, USER_IMG_BASE};
+use crate::process::Process;
+use crate::vm::VirtualAddr;
 
 #[repr(u16)]
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -69,23 +76,80 @@ pub extern "C" fn handle_exception(info: Info, esr: u32, tf: &mut TrapFrame) {
             Syndrome::DataAbort { kind: Fault::Translation, level: 3 } =>
                 {
                     if !crate::SCHEDULER.with_running(move |p| p.page_fault(addr)).unwrap() {
-                        crate::SCHEDULER.kill(tf);
+                        fault(info, syndrome, addr, tf);
                     }
                 },
             Syndrome::InstructionAbort { kind: Fault::Translation, level: 3 } =>
                 {
                     if !crate::SCHEDULER.with_running(move |p| p.page_fault(addr)).unwrap() {
-                        crate::SCHEDULER.kill(tf);
+                        fault(info, syndrome, addr, tf);
                     }
                 },
+            Syndrome::DataAbort { .. } | Syndrome::InstructionAbort { .. } |
+            Syndrome::PCAlignmentFault | Syndrome::SpAlignmentFault => fault(info, syndrome, addr, tf),
             _ => kprintln!("Detected syndrome {:?} ({:b}, FAR = {:x})", syndrome, esr, addr),
         }
     } else if info.kind == Kind::Irq {
         let controller = Controller::new();
-        for int in Interrupt::iter() {
-            if controller.is_pending(*int) {
-                crate::IRQ.invoke(*int, tf);
-            }
-        }
+        crate::IRQ.dispatch(|int| controller.is_pending(int), tf);
+    }
+}
+
+/// Handles a fault that can't be recovered from: prints the registers at the
+/// time of the fault and a backtrace, then kills the faulting process with
+/// `SIGSEGV`, or halts if the fault was in the kernel itself. A kernel fault
+/// on a user address was made on behalf of the running process (say, through
+/// a bad syscall argument), so that process is killed too.
+fn fault(info: Info, syndrome: Syndrome, addr: usize, tf: &mut TrapFrame) {
+    let from_user = info.source == Source::LowerAArch64;
+    // The kernel's own stack pointer was just above the trap frame
+    let sp = if from_user {
+        tf.sp
+    } else {
+        tf as *const TrapFrame as u64 + size_of::<TrapFrame>() as u64
+    };
+
+    kprintln!("{:?} at {:#x} ({:?})", syndrome, addr, info.source);
+    kprintln!("{}", tf.dump(sp));
+    kprintln!("backtrace:");
+    let print = |lr| kprintln!("  {:#018x}", lr);
+    let fp = tf.xs[29];
+
+    if from_user {
+        crate::SCHEDULER.with_running(|p| backtrace(fp, |fp| user_frame(&*p, fp), print));
+    } else {
+        backtrace(fp, |fp| kernel_frame(sp, fp), print);
+    }
+
+    if from_user || addr >= USER_IMG_BASE {
+        let _ = crate::SCHEDULER.kill(ExitStatus::Killed(SIGSEGV), tf);
+    } else {
+        panic!("{:?} in the kernel at {:#x}", syndrome, addr);
+    }
+}
+
+/// Reads the frame record at `fp` on the kernel stack, which runs from `sp`
+/// up to `KERN_STACK_BASE`.
+pub fn kernel_frame(sp: u64, fp: u64) -> Option<(u64, u64)> {
+    if fp < sp || fp + 16 > KERN_STACK_BASE as u64 {
+        return None;
+    }
+    let record = fp as *const u64;
+    unsafe { Some((*record, *record.add(1))) }
+}
+
+/// Reads the frame record at `fp` in `process`'s memory, if that page is
+/// mapped. Its page table is the one in use, since it just faulted.
+fn user_frame(process: &Process, fp: u64) -> Option<(u64, u64)> {
+    let fp = fp as usize;
+    if fp < USER_IMG_BASE {
+        return None;
+    }
+    // Records are 16-byte aligned, so one never straddles two pages
+    let page = VirtualAddr::from((fp & PAGE_MASK) - USER_IMG_BASE);
+    if !process.vmap.is_valid(page) {
+        return None;
     }
+    let record = fp as *const u64;
+    unsafe { Some((*record, *record.add(1))) }
 }
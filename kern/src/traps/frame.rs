@@ -1,3 +1,5 @@
+use core::fmt;
+
 #[repr(C)]
 #[derive(Default, Copy, Clone, Debug)]
 pub struct TrapFrame {
@@ -7,8 +9,130 @@ pub struct TrapFrame {
     pub spsr: u64,
     pub sp: u64,
     pub tpidr: u64,
+    pub fpcr: u64,
+    pub fpsr: u64,
     pub qs: [u128; 32],
     pub xs: [u64; 30],
     pub lr: u64,
     pub xzr: u64,
 }
+
+/// Most frame records `backtrace` follows before giving up.
+const BACKTRACE_DEPTH: usize = 16;
+
+impl TrapFrame {
+    /// Returns a `Display`able dump of the registers saved in this frame,
+    /// with `sp` as the stack pointer at the time of the exception.
+    pub fn dump(&self, sp: u64) -> RegisterDump<'_> {
+        RegisterDump { frame: self, sp }
+    }
+}
+
+/// A register dump of a `TrapFrame`. See `TrapFrame::dump()`.
+pub struct RegisterDump<'a> {
+    frame: &'a TrapFrame,
+    sp: u64,
+}
+
+impl<'a> fmt::Display for RegisterDump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tf = self.frame;
+        writeln!(f, "  pc: {:#018x}   sp: {:#018x} spsr: {:#010x}", tf.elr, self.sp, tf.spsr)?;
+        // x0-x29, then the link register as x30, three to a line
+        for (i, x) in tf.xs.iter().chain(Some(&tf.lr)).enumerate() {
+            if i > 0 {
+                f.write_str(if i % 3 == 0 { "\n" } else { " " })?;
+            }
+            let pad = if i < 10 { "  " } else { " " };
+            write!(f, "{}x{}: {:#018x}", pad, i, x)?;
+        }
+        write!(f, "\nttbr0: {:#018x} ttbr1: {:#018x}", tf.ttbr0, tf.ttbr1)
+    }
+}
+
+/// Follows the chain of frame records starting at frame pointer `fp` and
+/// calls `f` with each return address, innermost first. `read` returns the
+/// `(caller's fp, return address)` pair stored at a frame record, or `None`
+/// if that address can't be read safely.
+///
+/// The walk stops at a null or misaligned frame pointer, at one that doesn't
+/// move up the stack (so a corrupt chain can't loop), or after
+/// `BACKTRACE_DEPTH` frames. Code built without frame pointers just gives a
+/// short or empty backtrace.
+pub fn backtrace<R, F>(mut fp: u64, read: R, mut f: F)
+    where R: Fn(u64) -> Option<(u64, u64)>, F: FnMut(u64)
+{
+    for _ in 0..BACKTRACE_DEPTH {
+        if fp == 0 || fp % 16 != 0 {
+            return;
+        }
+        let (next, lr) = match read(fp) {
+            Some(record) => record,
+            None => return,
+        };
+        if lr == 0 {
+            return;
+        }
+        f(lr);
+        if next <= fp {
+            return;
+        }
+        fp = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use super::{backtrace, TrapFrame, BACKTRACE_DEPTH};
+
+    #[test]
+    fn dump_shows_every_register() {
+        let mut tf = TrapFrame::default();
+        tf.elr = 0xffff_ffff_c000_1234;
+        tf.xs[5] = 0xdead_beef;
+        tf.xs[29] = 0x1000;
+        tf.lr = 0xffff_ffff_c000_0800;
+        let dump = tf.dump(0xffff_ffff_ffff_0000).to_string();
+
+        assert!(dump.contains("pc: 0xffffffffc0001234"));
+        assert!(dump.contains("sp: 0xffffffffffff0000"));
+        assert!(dump.contains("  x5: 0x00000000deadbeef"));
+        assert!(dump.contains(" x29: 0x0000000000001000"));
+        assert!(dump.contains(" x30: 0xffffffffc0000800"));
+        // pc/sp, 31 registers three to a line, then the page tables
+        assert_eq!(dump.lines().count(), 1 + 11 + 1);
+    }
+
+    #[test]
+    fn backtrace_stops_on_bad_frames() {
+        // Frame records at 0x100 -> 0x140 -> 0x180 -> end of the chain
+        let read = |fp| match fp {
+            0x100 => Some((0x140, 0xa)),
+            0x140 => Some((0x180, 0xb)),
+            0x180 => Some((0, 0xc)),
+            _ => None,
+        };
+        let mut lrs = Vec::new();
+        backtrace(0x100, read, |lr| lrs.push(lr));
+        assert_eq!(lrs, [0xa, 0xb, 0xc]);
+
+        // Unreadable, misaligned, and null frame pointers
+        for &fp in &[0x200, 0x108, 0] {
+            let mut lrs = Vec::new();
+            backtrace(fp, read, |lr| lrs.push(lr));
+            assert!(lrs.is_empty());
+        }
+
+        // A chain that loops back on itself
+        let mut lrs = Vec::new();
+        backtrace(0x100, |fp| Some((fp, 0xa)), |lr| lrs.push(lr));
+        assert_eq!(lrs, [0xa]);
+
+        // A chain that never ends
+        let mut lrs = Vec::new();
+        backtrace(0x100, |fp| Some((fp + 16, 0xa)), |lr| lrs.push(lr));
+        assert_eq!(lrs.len(), BACKTRACE_DEPTH);
+    }
+}
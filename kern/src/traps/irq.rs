@@ -7,6 +7,17 @@ use crate::traps::TrapFrame;
 pub type IrqHandler = Box<dyn FnMut(&mut TrapFrame) + Send>;
 pub type IrqHandlers = [Option<IrqHandler>; Interrupt::MAX];
 
+/// The table of IRQ handlers, one per `Interrupt`.
+///
+/// To service an interrupt, register a handler for it with `register()` and
+/// then enable it in the interrupt controller with `Controller::enable()`.
+/// On each IRQ exception, `dispatch()` runs the handler of every interrupt
+/// that is pending, so several lines going off at once are all serviced by a
+/// single exception. A handler must acknowledge its interrupt at the device,
+/// or the line stays pending and the exception is taken again right away.
+/// Handlers run with IRQs masked and may switch processes by rewriting the
+/// trap frame; handlers that run after that in the same exception see the
+/// new process's frame.
 pub struct Irq(Mutex<Option<IrqHandlers>>);
 
 impl Irq {
@@ -15,10 +26,11 @@ impl Irq {
     }
 
     pub fn initialize(&self) {
-        *self.0.lock() = Some([None, None, None, None, None, None, None, None]);
+        *self.0.lock() = Some([None, None, None, None, None, None, None, None, None]);
     }
 
-    /// Register an irq handler for an interrupt.
+    /// Register an irq handler for an interrupt, replacing any handler it
+    /// already had.
     /// The caller should assure that `initialize()` has been called before calling this function.
     pub fn register(&self, int: Interrupt, handler: IrqHandler) {
         let idx = Interrupt::to_index(int);
@@ -43,4 +55,59 @@ impl Irq {
             None => panic!("Must initialize IRQ before invoking")
         }
     }
+
+    /// Executes the irq handler of every interrupt for which `is_pending`
+    /// returns `true`, in `Interrupt::iter()` order. Every pending interrupt
+    /// must have a handler registered.
+    pub fn dispatch<F: Fn(Interrupt) -> bool>(&self, is_pending: F, tf: &mut TrapFrame) {
+        for &int in Interrupt::iter() {
+            if is_pending(int) {
+                self.invoke(int, tf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use pi::interrupt::Interrupt;
+
+    use super::Irq;
+    use crate::traps::TrapFrame;
+
+    fn counting_handler(irq: &Irq, int: Interrupt) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let handler_count = count.clone();
+        irq.register(int, Box::new(move |_: &mut TrapFrame| {
+            handler_count.fetch_add(1, Ordering::Relaxed);
+        }));
+        count
+    }
+
+    #[test]
+    fn dispatch_services_every_pending_interrupt() {
+        let irq = Irq::uninitialized();
+        irq.initialize();
+        let timer = counting_handler(&irq, Interrupt::Timer1);
+        let alarm = counting_handler(&irq, Interrupt::Timer3);
+        let uart = counting_handler(&irq, Interrupt::Aux);
+        let mut tf = TrapFrame::default();
+
+        // The tick and a received byte go off together
+        irq.dispatch(|int| int == Interrupt::Timer1 || int == Interrupt::Aux, &mut tf);
+        assert_eq!(timer.load(Ordering::Relaxed), 1);
+        assert_eq!(alarm.load(Ordering::Relaxed), 0);
+        assert_eq!(uart.load(Ordering::Relaxed), 1);
+
+        irq.dispatch(|int| int == Interrupt::Aux, &mut tf);
+        assert_eq!(timer.load(Ordering::Relaxed), 1);
+        assert_eq!(uart.load(Ordering::Relaxed), 2);
+
+        irq.dispatch(|_| false, &mut tf);
+        assert_eq!(timer.load(Ordering::Relaxed), 1);
+        assert_eq!(uart.load(Ordering::Relaxed), 2);
+    }
 }
@@ -1,8 +1,9 @@
 use alloc::boxed::Box;
+use core::sync::atomic::AtomicU64;
 use alloc::string::String;
 use shim::path::PathBuf;
 
-use crate::console::CONSOLE;
+use crate::console::{kprintln, CONSOLE};
 use crate::FILESYSTEM;
 use crate::process::State;
 use crate::traps::TrapFrame;
@@ -13,9 +14,10 @@ use blockdev::mount::MountOptions;
 
 /// Kills current process.
 ///
-/// This system call does not take parameter and does not return any value.
-pub fn sys_exit(tf: &mut TrapFrame) {
-    let _ = SCHEDULER.kill(tf);
+/// This system call takes one parameter: the exit code to report to the
+/// parent process. It does not return any value.
+pub fn sys_exit(code: u64, tf: &mut TrapFrame) {
+    let _ = SCHEDULER.kill(ExitStatus::Exited(code), tf);
 }
 
 /// Sleep for `ms` milliseconds.
@@ -26,18 +28,14 @@ pub fn sys_exit(tf: &mut TrapFrame) {
 /// parameter: the approximate true elapsed time from when `sleep` was called to
 /// when `sleep` returned.
 pub fn sys_sleep(ms: u32, tf: &mut TrapFrame) {
+    use core::time::Duration;
     use pi::timer;
-    let start_time = timer::current_time().as_millis() as u64;
-
-    let is_ready = Box::new(move |p: &mut crate::process::Process| {
-        let now = timer::current_time().as_millis() as u64;
-        let diff = now - start_time;
-        p.context.xs[0] = diff;
-        p.context.xs[7] = 1;
-        diff >= ms as u64
-    });
 
-    SCHEDULER.switch(State::Waiting(is_ready), tf);
+    let since = timer::current_time();
+    let until = since + Duration::from_millis(ms as u64);
+
+    // The scheduler's deadline queue wakes us and fills in the elapsed time
+    SCHEDULER.switch(State::Sleeping { since, until }, tf);
 }
 
 /// Returns current process's ID.
@@ -97,24 +95,72 @@ pub fn sys_exec(program: *const u8, program_len: usize, args: *const &str, args_
     }
 }
 
+/// Waits for the child process `pid` to exit.
+///
+/// This system call takes one parameter: the ID of the child to wait for.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the raw `ExitStatus` of the child, telling whether it exited
+/// normally or was killed. If the child already exited, it is returned
+/// immediately.
 pub fn sys_wait_pid(pid: u64, tf: &mut TrapFrame) {
     use core::sync::atomic::Ordering;
 
-    match SCHEDULER.get_dead_handle(pid as crate::process::Id) {
-        Some(dead) => {
+    let pid = pid as crate::process::Id;
+    match SCHEDULER.reap(tf.tpidr, pid) {
+        Ok(Some(code)) => {
+            tf.xs[0] = code;
             tf.xs[7] = 1; // Success
-            let is_ready = Box::new(move |_: &mut crate::process::Process| {
-                dead.load(Ordering::Relaxed)
-            });
-
-            SCHEDULER.switch(State::Waiting(is_ready), tf);
         },
-        None => {
-            tf.xs[7] = 0; // Unknown error
-        }
+        Ok(None) => match SCHEDULER.get_dead_handle(pid) {
+            Some(dead) => {
+                // The scheduler reaps the child and fills in x0 when we wake
+                SCHEDULER.with_running(|process| process.waiting_on = Some(pid));
+                let is_ready = Box::new(move |_: &mut crate::process::Process| {
+                    dead.load(Ordering::Relaxed) & STATUS_DEAD != 0
+                });
+
+                SCHEDULER.switch(State::Waiting(is_ready), tf);
+            },
+            None => tf.xs[7] = OsError::NoEntry as u64,
+        },
+        Err(()) => tf.xs[7] = OsError::NoEntry as u64, // Not our child
     }
 }
 
+/// Waits for any child of the current process to exit.
+///
+/// This system call does not take parameters.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters: the ID of the child that exited and its raw exit status.
+/// Children are collected in the order they exited. Returns `NoEntry` if the
+/// current process has no children.
+pub fn sys_wait_any(tf: &mut TrapFrame) {
+    use core::sync::atomic::Ordering;
+
+    if let Some((pid, code)) = SCHEDULER.reap_any(tf.tpidr) {
+        tf.xs[0] = pid;
+        tf.xs[1] = code;
+        tf.xs[7] = 1; // Success
+        return;
+    }
+
+    let children = SCHEDULER.children_dead_handles(tf.tpidr);
+    if children.is_empty() {
+        tf.xs[7] = OsError::NoEntry as u64;
+        return;
+    }
+
+    // The scheduler reaps the child and fills in x0/x1 when we wake
+    SCHEDULER.with_running(|process| process.waiting_any = true);
+    let is_ready = Box::new(move |_: &mut crate::process::Process| {
+        children.iter().any(|dead| dead.load(Ordering::Relaxed) & STATUS_DEAD != 0)
+    });
+
+    SCHEDULER.switch(State::Waiting(is_ready), tf);
+}
+
 pub fn sys_request_page(num_pages: u64, tf: &mut TrapFrame) {
     use crate::param::PAGE_SIZE;
     use crate::vm::{PagePerm, VirtualAddr};
@@ -131,6 +177,163 @@ pub fn sys_request_page(num_pages: u64, tf: &mut TrapFrame) {
     });
 }
 
+/// Sets the scheduler's time quantum.
+///
+/// This system call takes one parameter: the new quantum in microseconds, or
+/// 0 to leave it unchanged. The quantum is clamped to a sane range.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameters:
+///  - the previous quantum, in microseconds
+///  - the average scheduling latency under the previous quantum, in
+///    microseconds.
+pub fn sys_sched_setquantum(micros: u64, tf: &mut TrapFrame) {
+    use core::time::Duration;
+
+    let latency = SCHEDULER.average_latency();
+    let old = SCHEDULER.set_quantum(Duration::from_micros(micros));
+    if micros == 0 {
+        SCHEDULER.set_quantum(old);
+    }
+
+    tf.xs[0] = old.as_micros() as u64;
+    tf.xs[1] = latency.as_micros() as u64;
+    tf.xs[7] = 1; // Success
+}
+
+/// Sets how many quanta in a row a process may run without making a syscall
+/// before the scheduler kills it as a runaway.
+///
+/// This system call takes one parameter: the limit, or 0 to turn the
+/// watchdog off, which is how the system starts.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the previous limit, or 0 if the watchdog was off.
+pub fn sys_sched_setwatchdog(quanta: u64, tf: &mut TrapFrame) {
+    let quanta = if quanta == 0 { None } else { Some(quanta) };
+    tf.xs[0] = SCHEDULER.set_watchdog(quanta).unwrap_or(0);
+    tf.xs[7] = 1; // Success
+}
+
+/// Kills a process with `SIGKILL`.
+///
+/// This system call takes one parameter: the ID of the process to kill, which
+/// may be the calling process itself. It does not return any value.
+pub fn sys_kill(pid: u64, tf: &mut TrapFrame) {
+    // When killing ourselves, `tf` belongs to another process afterwards
+    let suicide = pid == tf.tpidr;
+    match SCHEDULER.kill_pid(pid, tf) {
+        Some(_) if suicide => (),
+        Some(_) => tf.xs[7] = 1, // Success
+        None => tf.xs[7] = OsError::NoEntry as u64,
+    }
+}
+
+/// Sets the process that Ctrl-C on the console kills with `SIGINT`.
+///
+/// This system call takes one parameter: the ID of the process, or 0 to clear
+/// it. It does not return any value, and fails with `NoEntry` if there is no
+/// such process.
+pub fn sys_set_foreground(pid: u64, tf: &mut TrapFrame) {
+    let pid = if pid == 0 { None } else { Some(pid) };
+    if SCHEDULER.set_foreground(pid) {
+        tf.xs[7] = 1; // Success
+    } else {
+        tf.xs[7] = OsError::NoEntry as u64;
+    }
+}
+
+/// Shuts the system down.
+///
+/// This system call does not take parameters. Only the init process may call
+/// it; other callers get `NoAccess`. Every process is killed and every mounted
+/// filesystem is flushed before the CPU stops, so on success this system call
+/// never returns.
+pub fn sys_halt(tf: &mut TrapFrame) {
+    if tf.tpidr != SCHEDULER.init_pid() {
+        tf.xs[7] = OsError::NoAccess as u64;
+        return;
+    }
+
+    shut_down("Halting", || SCHEDULER.kill_all(), || FILESYSTEM.flush_all(), || loop {
+        aarch64::wfi();
+    });
+}
+
+/// Restarts the system.
+///
+/// This system call does not take parameters. Like `sys_halt`, only the init
+/// process may call it; other callers get `NoAccess`. Every process is killed
+/// and every mounted filesystem is flushed first, then the watchdog resets
+/// the board, so on success this system call never returns.
+pub fn sys_reboot(tf: &mut TrapFrame) {
+    let init = SCHEDULER.init_pid();
+    let result = reboot(tf.tpidr, init, || SCHEDULER.kill_all(), || FILESYSTEM.flush_all(), || {
+        pi::watchdog::reset_now()
+    });
+    if let Err(e) = result {
+        tf.xs[7] = e as u64;
+    }
+}
+
+/// Shuts down with `shut_down` and then resets the board with `reset`, if
+/// `caller` is the init process `init`. Otherwise returns `NoAccess` without
+/// doing anything.
+fn reboot<K, F, R>(caller: u64, init: u64, kill_all: K, flush_all: F, reset: R) -> OsResult<()>
+    where K: FnOnce() -> usize, F: FnOnce() -> usize, R: FnOnce()
+{
+    if caller != init {
+        return Err(OsError::NoAccess);
+    }
+
+    shut_down("Rebooting", kill_all, flush_all, reset);
+    Ok(())
+}
+
+/// Kills every process with `kill_all`, then flushes every mounted filesystem
+/// with `flush_all`, then stops the machine with `stop`. Processes go first so
+/// that none of them can write to a filesystem after it has been flushed.
+fn shut_down<K, F, S>(action: &str, kill_all: K, flush_all: F, stop: S)
+    where K: FnOnce() -> usize, F: FnOnce() -> usize, S: FnOnce()
+{
+    let killed = kill_all();
+    let flushed = flush_all();
+    kprintln!("Killed {} processes, flushed {} filesystems. {}.", killed, flushed, action);
+    stop();
+}
+
+/// Adjusts the priority of the current process, like Unix `nice`.
+///
+/// This system call takes one parameter: the amount to add to the process's
+/// niceness. Higher niceness means a lower priority. The result is clamped,
+/// and processes may not make themselves less nice than the default.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the new niceness.
+pub fn sys_nice(delta: i64, tf: &mut TrapFrame) {
+    match SCHEDULER.nice(delta) {
+        Some(nice) => {
+            tf.xs[0] = nice as u64;
+            tf.xs[7] = 1; // Success
+        },
+        None => tf.xs[7] = 0, // Unknown error
+    }
+}
+
+/// Snapshots the process table.
+///
+/// This system call takes two parameters: a pointer to an array of
+/// `ProcRecord`s and the number of records it can hold.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: the total number of processes. If this is larger than the
+/// buffer, only the first records were written and the caller should retry.
+pub fn sys_proc_list(buf: *mut ProcRecord, buf_len: usize, tf: &mut TrapFrame) {
+    let buf_slice = unsafe { core::slice::from_raw_parts_mut(buf, buf_len) };
+    tf.xs[0] = SCHEDULER.critical(|scheduler| scheduler.proc_list(buf_slice)) as u64;
+    tf.xs[7] = 1; // Success
+}
+
 /// Returns current time.
 ///
 /// This system call does not take parameter.
@@ -149,16 +352,112 @@ pub fn sys_time(tf: &mut TrapFrame) {
     tf.xs[7] = 1; // success
 }
 
+/// Seconds since the Unix epoch at boot. There's no real-time clock, so this
+/// is 0 until `sys_time_set_wall` is called.
+static BOOT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the wall-clock time.
+///
+/// This system call does not take parameter.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameter:
+///  - seconds since the Unix epoch
+///  - fractional part of the current time, in nanoseconds.
+pub fn sys_time_wall(tf: &mut TrapFrame) {
+    use core::sync::atomic::Ordering;
+
+    let time = pi::timer::current_time();
+    tf.xs[0] = BOOT_EPOCH.load(Ordering::Relaxed) + time.as_secs();
+    tf.xs[1] = time.subsec_nanos() as u64;
+    tf.xs[7] = 1; // success
+}
+
+/// Sets the wall-clock time.
+///
+/// This system call takes one parameter: the current number of seconds since
+/// the Unix epoch. It does not return any value, and fails with
+/// `InvalidArgument` if that would put boot before the epoch.
+pub fn sys_time_set_wall(secs: u64, tf: &mut TrapFrame) {
+    use core::sync::atomic::Ordering;
+
+    match secs.checked_sub(pi::timer::current_time().as_secs()) {
+        Some(epoch) => {
+            BOOT_EPOCH.store(epoch, Ordering::Relaxed);
+            tf.xs[7] = 1; // success
+        },
+        None => tf.xs[7] = OsError::InvalidArgument as u64,
+    }
+}
+
+/// Returns the CPU time used by the current process.
+///
+/// This system call does not take parameter.
+///
+/// In addition to the usual status value, this system call returns two
+/// parameter:
+///  - CPU time as seconds
+///  - fractional part of the CPU time, in nanoseconds.
+pub fn sys_times(tf: &mut TrapFrame) {
+    match SCHEDULER.with_running(|process| process.cpu_time()) {
+        Some(time) => {
+            tf.xs[0] = time.as_secs();
+            tf.xs[1] = time.subsec_nanos() as u64;
+            tf.xs[7] = 1; // success
+        },
+        None => tf.xs[7] = 0, // Unknown error
+    }
+}
+
 /// Read from console.
 ///
 /// This system call does not take parameter.
 ///
+/// If no input is available yet, the process waits (letting others run) until
+/// a byte arrives. In cooked mode, that's once a whole line has been typed;
+/// see `Console::poll_input()`.
+///
 /// In addition to the usual status value, this system call returns one
 /// parameters:
 ///  - the read character
 pub fn sys_input(tf: &mut TrapFrame) {
-    tf.xs[0] = CONSOLE.lock().read_byte() as u64;
-    tf.xs[7] = 1; // success
+    if let Some(byte) = CONSOLE.lock().poll_input() {
+        tf.xs[0] = byte as u64;
+        tf.xs[7] = 1; // success
+        return
+    }
+
+    let is_ready = Box::new(|p: &mut crate::process::Process| {
+        match CONSOLE.lock().poll_input() {
+            Some(byte) => {
+                p.context.xs[0] = byte as u64;
+                p.context.xs[7] = 1; // success
+                true
+            },
+            None => false,
+        }
+    });
+
+    SCHEDULER.switch(State::Waiting(is_ready), tf);
+}
+
+/// Switches the console between raw and cooked mode.
+///
+/// This system call takes one parameter: nonzero for raw mode, where input is
+/// delivered without being echoed, or zero for cooked mode. The mode in effect
+/// before a process first changes it is restored when that process exits.
+///
+/// In addition to the usual status value, this system call returns one
+/// parameter: 1 if the console was in raw mode before, 0 if not.
+pub fn sys_tcsetattr(raw: u64, tf: &mut TrapFrame) {
+    let was_raw = CONSOLE.lock().set_raw(raw != 0);
+    SCHEDULER.with_running(|process| {
+        if process.saved_console_raw.is_none() {
+            process.saved_console_raw = Some(was_raw);
+        }
+    });
+    tf.xs[0] = was_raw as u64;
+    tf.xs[7] = 1; // Success
 }
 
 /// Write to console.
@@ -231,31 +530,113 @@ pub fn sys_env_set(var_ptr: *const u8, var_len: usize, val_ptr: *const u8, val_l
     }
 }
 
-fn parse_path(path_ptr: *const u8, path_len: usize) -> Option<PathBuf> {
-    use shim::path::Component;
-    fn canonicalize(path: PathBuf) -> Option<PathBuf> {
-        let mut new_path = PathBuf::new();
-        for comp in path.components() {
-            match comp {
-                Component::ParentDir => {
-                    let res = new_path.pop();
-                    if !res {
-                        return None;
-                    }
-                },
-                Component::Normal(n) => new_path = new_path.join(n),
-                Component::RootDir => new_path = ["/"].iter().collect(),
-                _ => ()
-            };
+/// Removes an environment variable from the current process.
+///
+/// This system call takes one parameter: the name of the variable. It does not
+/// return any value, and fails with `NoEntry` if the variable isn't set.
+pub fn sys_env_unset(var_ptr: *const u8, var_len: usize, tf: &mut TrapFrame) {
+    let var_slice = unsafe { core::slice::from_raw_parts(var_ptr, var_len) };
+    let var_string = match core::str::from_utf8(var_slice) {
+        Ok(var_string) => var_string,
+        Err(_) => {
+            tf.xs[7] = 70; // Invalid argument
+            return
         }
-        Some(new_path)
+    };
+
+    match SCHEDULER.with_running(|process| process.env.remove(var_string)) {
+        Some(Some(_)) => tf.xs[7] = 1, // Success
+        Some(None) => tf.xs[7] = 10, // No entry
+        None => tf.xs[7] = 0, // Unknown error
     }
+}
 
+/// Lists the environment variables of the current process.
+///
+/// This system call takes one parameter: a buffer to copy the variables into,
+/// packed by `pack_env_vars` in order of name. It returns the length of all of
+/// the variables, which is larger than the buffer if they didn't all fit.
+pub fn sys_env_vars(buf_ptr: *mut u8, buf_len: usize, tf: &mut TrapFrame) {
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr, buf_len) };
+
+    match SCHEDULER.with_running(|process| {
+        let mut vars: alloc::vec::Vec<(&str, &str)> = process.env.iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        vars.sort();
+        pack_env_vars(vars, buf)
+    }) {
+        Some(len) => {
+            tf.xs[0] = len as u64;
+            tf.xs[7] = 1; // Success
+        },
+        None => tf.xs[7] = 0, // Unknown error
+    }
+}
+
+/// Reads the path passed to a syscall, resolving it against the running
+/// process's working directory.
+fn parse_path(path_ptr: *const u8, path_len: usize) -> Option<PathBuf> {
     let path_slice = unsafe { core::slice::from_raw_parts(path_ptr, path_len) };
     let path_string = core::str::from_utf8(path_slice).ok()?;
-    let raw_path = PathBuf::from(path_string);
-    if !raw_path.is_absolute() { return None }
-    canonicalize(raw_path)
+    SCHEDULER.with_running(|process| process.resolve_path(path_string)).and_then(|x| x)
+}
+
+/// Changes the working directory of the current process.
+///
+/// This system call takes one parameter: the path of the new working
+/// directory, which may be relative to the current one. It fails with
+/// `NoEntry` if there's nothing at that path and `InvalidArgument` if it
+/// isn't a directory.
+pub fn sys_chdir(path_ptr: *const u8, path_len: usize, tf: &mut TrapFrame) {
+    use fat32::traits::{Entry, FileSystem};
+
+    let path = match parse_path(path_ptr, path_len) {
+        Some(path) => path,
+        None => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+    };
+
+    match FILESYSTEM.open(&path) {
+        Ok(ref entry) if entry.is_dir() => (),
+        Ok(_) => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+        Err(_) => {
+            tf.xs[7] = 10; // No entry
+            return
+        },
+    }
+
+    match SCHEDULER.with_running(|process| process.cwd = path) {
+        Some(_) => tf.xs[7] = 1, // Success
+        None => tf.xs[7] = 0, // Unknown error
+    }
+}
+
+/// Returns the working directory of the current process.
+///
+/// This system call takes one parameter: a buffer to copy the path into. It
+/// returns the length of the path, or fails with `NoMemory` if the buffer is
+/// too small.
+pub fn sys_getcwd(buf_ptr: *mut u8, buf_len: usize, tf: &mut TrapFrame) {
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf_ptr, buf_len) };
+
+    let cwd = SCHEDULER.with_running(|process| {
+        process.cwd.to_str().map(String::from)
+    }).and_then(|x| x);
+    match cwd {
+        Some(cwd) if cwd.len() <= buf.len() => {
+            buf[0..cwd.len()].copy_from_slice(cwd.as_bytes());
+            tf.xs[0] = cwd.len() as u64;
+            tf.xs[7] = 1; // Success
+        },
+        Some(_) => tf.xs[7] = 20, // No memory
+        None => tf.xs[7] = 0, // Unknown error
+    }
 }
 
 pub fn sys_fs_create(path_ptr: *const u8, path_len: usize, kind: EntryKind, tf: &mut TrapFrame) {
@@ -379,6 +760,113 @@ pub fn sys_fs_delete(path_ptr: *const u8, path_len: usize, tf: &mut TrapFrame) {
     }
 }
 
+/// Returns the metadata of a file or directory.
+///
+/// This system call takes two parameters: the path of the entry, which may be
+/// relative to the working directory, and a `StatBuf` to fill in. It fails
+/// with `NoEntry` if there is nothing at the path.
+pub fn sys_fs_metadata(path_ptr: *const u8, path_len: usize, buf: *mut StatBuf, tf: &mut TrapFrame) {
+    use fat32::traits::FileSystem;
+    use shim::io;
+
+    let path = match parse_path(path_ptr, path_len) {
+        Some(path) => path,
+        None => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+    };
+
+    match FILESYSTEM.open(path) {
+        Ok(entry) => {
+            unsafe { *buf = fat32::traits::stat(&entry) };
+            tf.xs[7] = 1; // Success
+        },
+        Err(e) => match e.kind() {
+            io::ErrorKind::NotFound => tf.xs[7] = 10, // No entry
+            _                       => tf.xs[7] = 0,  // Unknown
+        },
+    }
+}
+
+/// Moves the entry at one path to another without copying its contents.
+///
+/// Both paths must be on the same mounted filesystem; otherwise the call fails
+/// with `CrossDevice` and the caller has to copy the entry instead.
+pub fn sys_fs_rename(from_ptr: *const u8, from_len: usize, to_ptr: *const u8, to_len: usize, tf: &mut TrapFrame) {
+    use shim::io;
+
+    let (from, to) = match (parse_path(from_ptr, from_len), parse_path(to_ptr, to_len)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+    };
+
+    if !FILESYSTEM.same_mount(&from, &to) {
+        tf.xs[7] = OsError::CrossDevice as u64;
+        return
+    }
+
+    match FILESYSTEM.rename(&from, &to) {
+        Ok(_) => {
+            FILESYSTEM.flush_fs(&to);
+            tf.xs[7] = 1; // Success
+        },
+        Err(e) => match e.kind() {
+            io::ErrorKind::NotFound      => tf.xs[7] = 10,  // No entry
+            io::ErrorKind::AlreadyExists => tf.xs[7] = 60,  // File exists
+            io::ErrorKind::InvalidInput  => tf.xs[7] = 70,  // Invalid argument
+            _                            => tf.xs[7] = 0,   // Unknown
+        },
+    }
+}
+
+/// Adds a second name for the file at one path, sharing its contents.
+///
+/// This system call takes two parameters: the path of an existing file and
+/// the path of the new entry, either of which may be relative to the working
+/// directory. It fails with `NoEntry` if there is no file at the first path,
+/// `FileExists` if there is already an entry at the second, `InvalidArgument`
+/// if the file is empty, and `CrossDevice` if the paths are on different
+/// mounted filesystems.
+///
+/// FAT has no link counts, so links are best effort: both entries are
+/// flagged `LINKED` in the reserved byte 12 of their directory entries, and
+/// deleting or truncating a flagged entry only frees its clusters once
+/// `count_links` finds no other entry pointing at them. Other FAT
+/// implementations ignore the flag and see two files sharing clusters.
+pub fn sys_fs_link(from_ptr: *const u8, from_len: usize, to_ptr: *const u8, to_len: usize, tf: &mut TrapFrame) {
+    use shim::io;
+
+    let (from, to) = match (parse_path(from_ptr, from_len), parse_path(to_ptr, to_len)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+    };
+
+    if !FILESYSTEM.same_mount(&from, &to) {
+        tf.xs[7] = OsError::CrossDevice as u64;
+        return
+    }
+
+    match FILESYSTEM.link(&from, &to) {
+        Ok(_) => {
+            FILESYSTEM.flush_fs(&to);
+            tf.xs[7] = 1; // Success
+        },
+        Err(e) => match e.kind() {
+            io::ErrorKind::NotFound      => tf.xs[7] = 10,  // No entry
+            io::ErrorKind::AlreadyExists => tf.xs[7] = 60,  // File exists
+            io::ErrorKind::InvalidInput  => tf.xs[7] = 70,  // Invalid argument
+            _                            => tf.xs[7] = 0,   // Unknown
+        },
+    }
+}
+
 pub fn sys_file_seek(fd: Fd, mode: u64, offset: i64, tf: &mut TrapFrame) {
     use shim::{io, ioerr};
     use io::Seek;
@@ -423,6 +911,43 @@ pub fn sys_file_read(fd: Fd, buf: *mut u8, buf_len: usize, tf: &mut TrapFrame) {
     }
 }
 
+pub fn sys_file_write(fd: Fd, buf: *const u8, buf_len: usize, tf: &mut TrapFrame) {
+    use shim::{io, ioerr};
+    use io::Write;
+    use fat32::traits::Entry;
+
+    let buf_slice = unsafe { core::slice::from_raw_parts(buf, buf_len) };
+    let err = SCHEDULER.with_running(|process| {
+        process.fd_table.critical(&fd, move |entry| -> io::Result<usize> {
+            if entry.is_dir() { return ioerr!(InvalidInput, "Can't write to a directory") }
+            entry.as_file_mut().expect("Unable to open file as file").write(buf_slice)
+        }).and_then(|x| x)
+    });
+
+    match err {
+        Some(Ok(n)) => {
+            tf.xs[0] = n as u64;
+            tf.xs[7] = 1; // Success
+        },
+        _ => tf.xs[7] = 0, // Unknown
+    }
+}
+
+/// Writes any cached changes to the filesystem that `path` is mounted on
+/// back to disk.
+pub fn sys_fs_flush(path_ptr: *const u8, path_len: usize, tf: &mut TrapFrame) {
+    let path = match parse_path(path_ptr, path_len) {
+        Some(path) => path,
+        None => {
+            tf.xs[7] = 70; // Invalid argument
+            return
+        },
+    };
+
+    FILESYSTEM.flush_fs(path);
+    tf.xs[7] = 1; // Success
+}
+
 pub fn sys_fs_lsblk() {
     FILESYSTEM.lsblk();
 }
@@ -523,31 +1048,56 @@ pub fn sys_dir_entry(path_ptr: *const u8,
 }
 
 pub fn handle_syscall(num: u16, tf: &mut TrapFrame) {
+    // Any syscall shows the watchdog that the process isn't stuck in a loop
+    SCHEDULER.with_running(|process| process.quanta_without_syscall = 0);
+
     match num as usize {
-        SYS_EXIT => sys_exit(tf),
+        SYS_EXIT => sys_exit(tf.xs[0], tf),
         SYS_SLEEP => sys_sleep(tf.xs[0] as u32, tf),
         SYS_GETPID => sys_getpid(tf),
         SYS_FORK => sys_fork(tf),
         SYS_EXEC => sys_exec(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *const &str, tf.xs[3] as usize, tf),
         SYS_WAIT_PID => sys_wait_pid(tf.xs[0], tf),
         SYS_REQUEST_PAGE => sys_request_page(tf.xs[0], tf),
+        SYS_SCHED_SETQUANTUM => sys_sched_setquantum(tf.xs[0], tf),
+        SYS_SCHED_SETWATCHDOG => sys_sched_setwatchdog(tf.xs[0], tf),
+        SYS_NICE => sys_nice(tf.xs[0] as i64, tf),
+        SYS_KILL => sys_kill(tf.xs[0], tf),
+        SYS_HALT => sys_halt(tf),
+        SYS_REBOOT => sys_reboot(tf),
+        SYS_WAIT_ANY => sys_wait_any(tf),
+        SYS_SET_FOREGROUND => sys_set_foreground(tf.xs[0], tf),
+        SYS_PROC_LIST => sys_proc_list(tf.xs[0] as *mut ProcRecord, tf.xs[1] as usize, tf),
 
         SYS_TIME => sys_time(tf),
+        SYS_TIMES => sys_times(tf),
+        SYS_TIME_WALL => sys_time_wall(tf),
+        SYS_TIME_SET_WALL => sys_time_set_wall(tf.xs[0], tf),
         SYS_INPUT => sys_input(tf),
         SYS_OUTPUT => sys_output(tf.xs[0] as u8, tf),
+        SYS_TCSETATTR => sys_tcsetattr(tf.xs[0], tf),
         SYS_ENV_GET => sys_env_get(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *mut u8, tf.xs[3] as usize, tf),
         SYS_ENV_SET => sys_env_set(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *const u8, tf.xs[3] as usize, tf),
+        SYS_ENV_UNSET => sys_env_unset(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
+        SYS_ENV_VARS => sys_env_vars(tf.xs[0] as *mut u8, tf.xs[1] as usize, tf),
+        SYS_CHDIR => sys_chdir(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
+        SYS_GETCWD => sys_getcwd(tf.xs[0] as *mut u8, tf.xs[1] as usize, tf),
 
         SYS_FS_CREATE => sys_fs_create(tf.xs[0] as *const u8, tf.xs[1] as usize, EntryKind::from(tf.xs[2]), tf),
         SYS_FS_OPEN => sys_fs_open(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
         SYS_FS_CLOSE => sys_fs_close(Fd::from(tf.xs[0]), tf),
         SYS_FS_DELETE => sys_fs_delete(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
+        SYS_FS_METADATA => sys_fs_metadata(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *mut StatBuf, tf),
+        SYS_FS_FLUSH => sys_fs_flush(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
         SYS_FS_LSBLK => sys_fs_lsblk(),
         SYS_FS_MOUNT => sys_fs_mount(tf.xs[0] as usize, tf.xs[1] as *const u8, tf.xs[2] as usize, tf.xs[3] != 0, tf),
         SYS_FS_UNMOUNT => sys_fs_unmount(tf.xs[0] as *const u8, tf.xs[1] as usize, tf),
+        SYS_FS_RENAME => sys_fs_rename(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *const u8, tf.xs[3] as usize, tf),
+        SYS_FS_LINK => sys_fs_link(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *const u8, tf.xs[3] as usize, tf),
 
         SYS_FILE_SEEK => sys_file_seek(Fd::from(tf.xs[0]), tf.xs[1], tf.xs[2] as i64, tf),
         SYS_FILE_READ => sys_file_read(Fd::from(tf.xs[0]), tf.xs[1] as *mut u8, tf.xs[2] as usize, tf),
+        SYS_FILE_WRITE => sys_file_write(Fd::from(tf.xs[0]), tf.xs[1] as *const u8, tf.xs[2] as usize, tf),
         SYS_DIR_ENTRY => sys_dir_entry(tf.xs[0] as *const u8, tf.xs[1] as usize, tf.xs[2] as *mut u8, tf.xs[3] as usize, tf.xs[4] as usize, tf),
 
         _ => {
@@ -555,3 +1105,43 @@ pub fn handle_syscall(num: u16, tf: &mut TrapFrame) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use kernel_api::OsError;
+    use super::{reboot, shut_down};
+
+    #[test]
+    fn halt_flushes_mounts_before_stopping() {
+        let steps = RefCell::new(Vec::new());
+        shut_down("Halting", || {
+            steps.borrow_mut().push("kill");
+            3
+        }, || {
+            steps.borrow_mut().push("flush");
+            2
+        }, || steps.borrow_mut().push("halt"));
+        assert_eq!(*steps.borrow(), ["kill", "flush", "halt"]);
+    }
+
+    #[test]
+    fn reboot_flushes_mounts_before_reset() {
+        let steps = RefCell::new(Vec::new());
+        let reboot_as = |caller| reboot(caller, 1, || {
+            steps.borrow_mut().push("kill");
+            3
+        }, || {
+            steps.borrow_mut().push("flush");
+            2
+        }, || steps.borrow_mut().push("reset"));
+
+        // Only init may reboot, and nothing happens for anyone else
+        assert_eq!(reboot_as(7), Err(OsError::NoAccess));
+        assert!(steps.borrow().is_empty());
+
+        assert_eq!(reboot_as(1), Ok(()));
+        assert_eq!(*steps.borrow(), ["kill", "flush", "reset"]);
+    }
+}
@@ -201,8 +201,10 @@ impl KernPageTable {
     /// Returns a new `KernPageTable`. `KernPageTable` should have a `Pagetable`
     /// created with `KERN_RW` permission.
     ///
-    /// Set L3entry of ARM physical address starting at 0x00000000 for RAM and
-    /// physical address range from `IO_BASE` to `IO_BASE_END` for peripherals.
+    /// Set L3entry of ARM physical address starting at 0x00000000 for RAM,
+    /// the VideoCore's memory above it (where the framebuffer lives) as
+    /// non-cacheable, and physical address range from `IO_BASE` to
+    /// `IO_BASE_END` for peripherals.
     /// Each L3 entry should have correct value for lower attributes[10:0] as well
     /// as address[47:16]. Refer to the definition of `RawL3Entry` in `vmsa.rs` for
     /// more details.
@@ -230,6 +232,25 @@ impl KernPageTable {
             mem_idx += PAGE_SIZE;
         }
 
+        // The VideoCore reads the framebuffer straight from memory, so it
+        // mustn't sit in the CPU's caches
+        while mem_idx < IO_BASE {
+            let mut entry = RawL3Entry::new(0);
+
+            entry.set_value(EntryValid::Valid, RawL3Entry::VALID);
+            entry.set_value(PageType::Page, RawL3Entry::TYPE);
+            entry.set_value(EntryPerm::KERN_RW, RawL3Entry::AP);
+            entry.set_value(1, RawL3Entry::AF);
+            entry.set_value(EntryAttr::Nc, RawL3Entry::ATTR);
+            entry.set_value(EntrySh::OSh, RawL3Entry::SH);
+
+            let addr = mem_idx >> 16;
+            entry.set_value(addr as u64, RawL3Entry::ADDR);
+
+            pt.set_entry(VirtualAddr::from(mem_idx), entry);
+            mem_idx += PAGE_SIZE;
+        }
+
         mem_idx = IO_BASE;
         
         while mem_idx < IO_BASE_END {
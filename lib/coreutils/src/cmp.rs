@@ -0,0 +1,27 @@
+//! Finding where two inputs differ, for `cmp`.
+
+/// Returns the offset of the first byte where `a` and `b` differ, like `cmp`.
+/// If one is a prefix of the other, that's the length of the shorter one.
+/// Returns `None` if they're identical.
+pub fn first_mismatch(a: &[u8], b: &[u8]) -> Option<usize> {
+    match a.iter().zip(b).position(|(x, y)| x != y) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::first_mismatch;
+
+    #[test]
+    fn first_mismatch_finds_differences_and_short_inputs() {
+        assert_eq!(first_mismatch(b"hello", b"hello"), None);
+        assert_eq!(first_mismatch(b"", b""), None);
+        assert_eq!(first_mismatch(b"hello", b"help!"), Some(3));
+        assert_eq!(first_mismatch(b"hello", b"hello world"), Some(5));
+        assert_eq!(first_mismatch(b"hello world", b"hello"), Some(5));
+        assert_eq!(first_mismatch(b"", b"x"), Some(0));
+    }
+}
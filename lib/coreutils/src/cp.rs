@@ -0,0 +1,153 @@
+//! Copying files and directory trees, for `cp`.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use kernel_api::{OsError, OsResult};
+
+/// The filesystem operations `cp` is built from.
+pub trait Fs {
+    /// Returns `true` if there is a directory at `path`.
+    fn is_dir(&mut self, path: &str) -> bool;
+    /// Creates an empty directory at `path`.
+    fn create_dir(&mut self, path: &str) -> OsResult<()>;
+    /// Returns the names of the entries in the directory `path`, including
+    /// `.` and `..` if the filesystem has them.
+    fn entries(&mut self, path: &str) -> OsResult<Vec<String>>;
+    /// Copies the contents of the file `src` to a new file `dst`, replacing
+    /// any file already there.
+    fn copy_file(&mut self, src: &str, dst: &str) -> OsResult<()>;
+}
+
+/// Returns `name` inside the directory `dir`.
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Returns `true` if copying the absolute path `src` to the absolute path
+/// `dst` would put the copy inside `src` itself, so a recursive copy would
+/// never end.
+pub fn is_within(dst: &str, src: &str) -> bool {
+    dst == src || dst.starts_with(&join(src, ""))
+}
+
+/// Copies the file at `src` to `dst`, or with `recursive` also the directory
+/// tree at `src`, creating `dst` and everything below it.
+///
+/// # Errors
+///
+/// Returns `OsError::InvalidArgument` if `src` is a directory and
+/// `recursive` isn't set, and any error from `fs` as soon as it happens.
+pub fn copy<F: Fs>(fs: &mut F, src: &str, dst: &str, recursive: bool) -> OsResult<()> {
+    if !fs.is_dir(src) {
+        return fs.copy_file(src, dst);
+    }
+    if !recursive {
+        return Err(OsError::InvalidArgument);
+    }
+
+    fs.create_dir(dst)?;
+    for name in fs.entries(src)? {
+        if name != "." && name != ".." {
+            copy(fs, &join(src, &name), &join(dst, &name), true)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use kernel_api::{OsError, OsResult};
+    use super::{copy, is_within, Fs};
+
+    /// Files and their contents, and directories, by absolute path.
+    #[derive(Default)]
+    struct FakeFs {
+        files: BTreeMap<String, Vec<u8>>,
+        dirs: Vec<String>,
+    }
+
+    impl FakeFs {
+        fn with(dirs: &[&str], files: &[(&str, &[u8])]) -> FakeFs {
+            FakeFs {
+                files: files.iter().map(|&(path, data)| (String::from(path), data.to_vec())).collect(),
+                dirs: dirs.iter().map(|&dir| String::from(dir)).collect(),
+            }
+        }
+
+        fn parent_exists(&self, path: &str) -> bool {
+            let parent = &path[..path.rfind('/').unwrap()];
+            parent.is_empty() || self.dirs.iter().any(|dir| dir == parent)
+        }
+    }
+
+    impl Fs for FakeFs {
+        fn is_dir(&mut self, path: &str) -> bool {
+            self.dirs.iter().any(|dir| dir == path)
+        }
+
+        fn create_dir(&mut self, path: &str) -> OsResult<()> {
+            if !self.parent_exists(path) || self.is_dir(path) {
+                return Err(OsError::IoError);
+            }
+            self.dirs.push(String::from(path));
+            Ok(())
+        }
+
+        fn entries(&mut self, path: &str) -> OsResult<Vec<String>> {
+            let prefix = format!("{}/", path);
+            let children = self.dirs.iter().chain(self.files.keys())
+                .filter(|child| child.starts_with(&prefix) && !child[prefix.len()..].contains('/'))
+                .map(|child| String::from(&child[prefix.len()..]));
+            Ok([".", ".."].iter().map(|&name| String::from(name)).chain(children).collect())
+        }
+
+        fn copy_file(&mut self, src: &str, dst: &str) -> OsResult<()> {
+            let data = self.files.get(src).ok_or(OsError::NoEntry)?.clone();
+            if !self.parent_exists(dst) {
+                return Err(OsError::NoEntry);
+            }
+            self.files.insert(String::from(dst), data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copies_a_two_level_tree() {
+        let mut fs = FakeFs::with(&["/src", "/src/sub", "/src/empty"], &[
+            ("/src/a.txt", b"top level\n"),
+            ("/src/sub/b.txt", b"second level\n"),
+            ("/src/sub/c.bin", &[0, 1, 2, 255]),
+        ]);
+        assert_eq!(copy(&mut fs, "/src", "/dst", false), Err(OsError::InvalidArgument));
+        assert!(!fs.is_dir("/dst"));
+
+        copy(&mut fs, "/src", "/dst", true).unwrap();
+        for dir in &["/dst", "/dst/sub", "/dst/empty"] {
+            assert!(fs.is_dir(dir), "{} wasn't created", dir);
+        }
+        for &(src, dst) in &[("/src/a.txt", "/dst/a.txt"), ("/src/sub/b.txt", "/dst/sub/b.txt"),
+                             ("/src/sub/c.bin", "/dst/sub/c.bin")] {
+            assert_eq!(fs.files.get(dst), fs.files.get(src), "{} differs", dst);
+        }
+        assert_eq!(fs.files.len(), 6);
+        assert_eq!(fs.dirs.len(), 6);
+    }
+
+    #[test]
+    fn copying_into_itself_is_caught() {
+        assert!(is_within("/src", "/src"));
+        assert!(is_within("/src/sub/src", "/src"));
+        assert!(is_within("/a", "/"));
+        assert!(!is_within("/srcs", "/src"));
+        assert!(!is_within("/dst", "/src"));
+    }
+}
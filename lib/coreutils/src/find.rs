@@ -0,0 +1,115 @@
+//! Walking a directory tree for the entries that pass some tests, for `find`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use kernel_api::{EntryKind, OsError, OsResult};
+
+use crate::cp::join;
+use crate::glob::glob_match;
+
+/// The tests an entry has to pass to be printed.
+pub struct Filter<'a> {
+    /// Shell pattern the last component of a path has to match
+    pub name: Option<&'a str>,
+    pub kind: Option<EntryKind>,
+}
+
+impl<'a> Filter<'a> {
+    pub fn matches(&self, path: &str, kind: &EntryKind) -> bool {
+        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        let name_matches = self.name.map(|pattern| glob_match(pattern.as_bytes(), name.as_bytes())).unwrap_or(true);
+        let kind_matches = self.kind.as_ref().map(|want| want == kind).unwrap_or(true);
+        name_matches && kind_matches
+    }
+}
+
+/// What a walk turned up at one path.
+#[derive(Debug, PartialEq)]
+pub enum Found<'a> {
+    /// The path passed the filter.
+    Match(&'a str),
+    /// The path, or the directory's entries, couldn't be read.
+    Error(&'a str, OsError),
+}
+
+/// Calls `found` with `path` if it passes `filter`, then walks everything
+/// under it. `kind_of` looks up what a path is, and `children` lists the
+/// names in a directory, leaving out `.` and `..`.
+pub fn walk<K, C, F>(path: &str, filter: &Filter, kind_of: &mut K, children: &mut C, found: &mut F)
+    where K: FnMut(&str) -> OsResult<EntryKind>,
+          C: FnMut(&str) -> OsResult<Vec<String>>,
+          F: FnMut(Found)
+{
+    let kind = match kind_of(path) {
+        Ok(kind) => kind,
+        Err(e) => return found(Found::Error(path, e)),
+    };
+
+    if filter.matches(path, &kind) {
+        found(Found::Match(path));
+    }
+    if kind != EntryKind::Dir {
+        return
+    }
+
+    match children(path) {
+        Ok(names) => for name in names {
+            walk(&join(path, &name), filter, kind_of, children, found);
+        },
+        Err(e) => found(Found::Error(path, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use kernel_api::{EntryKind, OsError};
+    use super::{walk, Filter, Found};
+
+    /// Directories, and the files under them, by absolute path.
+    const DIRS: &[&str] = &["/", "/docs", "/docs/old", "/src"];
+    const FILES: &[&str] = &["/a.txt", "/docs/b.txt", "/docs/old/c.txt", "/docs/d.md", "/src/e.txt.bak"];
+
+    fn find(root: &str, filter: &Filter) -> Vec<String> {
+        let mut kind_of = |path: &str| {
+            if DIRS.contains(&path) {
+                Ok(EntryKind::Dir)
+            } else if FILES.contains(&path) {
+                Ok(EntryKind::File)
+            } else {
+                Err(OsError::NoEntry)
+            }
+        };
+        let mut children = |dir: &str| {
+            let prefix = if dir == "/" { String::from("/") } else { format!("{}/", dir) };
+            Ok(DIRS.iter().chain(FILES.iter())
+                .filter(|path| path.len() > prefix.len() && path.starts_with(&prefix))
+                .filter(|path| !path[prefix.len()..].contains('/'))
+                .map(|path| String::from(&path[prefix.len()..]))
+                .collect())
+        };
+
+        let mut paths = Vec::new();
+        walk(root, filter, &mut kind_of, &mut children, &mut |found| match found {
+            Found::Match(path) => paths.push(String::from(path)),
+            Found::Error(path, e) => paths.push(format!("{}: {:?}", path, e)),
+        });
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn finds_every_txt_file_in_a_tree() {
+        let txt = Filter { name: Some("*.txt"), kind: Some(EntryKind::File) };
+        assert_eq!(find("/", &txt), ["/a.txt", "/docs/b.txt", "/docs/old/c.txt"]);
+        assert_eq!(find("/docs", &txt), ["/docs/b.txt", "/docs/old/c.txt"]);
+
+        let dirs = Filter { name: None, kind: Some(EntryKind::Dir) };
+        assert_eq!(find("/", &dirs), ["/", "/docs", "/docs/old", "/src"]);
+
+        let all = Filter { name: None, kind: None };
+        assert_eq!(find("/missing", &all), ["/missing: NoEntry"]);
+    }
+}
@@ -0,0 +1,31 @@
+//! Matching names against shell patterns, for the shell and `find`.
+
+/// Returns `true` if `name` matches the shell pattern `pattern`, where `*`
+/// matches any run of characters and `?` matches exactly one.
+pub fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_match_stars_and_question_marks() {
+        assert!(glob_match(b"*.txt", b"notes.txt"));
+        assert!(glob_match(b"*.txt", b".txt"));
+        assert!(!glob_match(b"*.txt", b"notes.txt.bak"));
+        assert!(glob_match(b"?.rs", b"a.rs"));
+        assert!(!glob_match(b"?.rs", b"ab.rs"));
+        assert!(glob_match(b"a*b*c", b"aXbYbZc"));
+        assert!(glob_match(b"*", b""));
+    }
+}
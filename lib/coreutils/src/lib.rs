@@ -0,0 +1,27 @@
+//! Logic shared by the user programs, and with the kernel shell, that doesn't
+//! make syscalls itself, so that it can be tested on the host.
+
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(any(feature = "alloc", test))]
+extern crate alloc;
+
+pub mod cmp;
+pub mod glob;
+pub mod lines;
+pub mod path;
+pub mod seq;
+pub mod sleep;
+pub mod sort;
+pub mod tee;
+pub mod uniq;
+pub mod uptime;
+pub mod wc;
+pub mod xxd;
+pub mod yes;
+#[cfg(any(feature = "alloc", test))]
+pub mod cp;
+#[cfg(any(feature = "alloc", test))]
+pub mod find;
+#[cfg(any(feature = "alloc", test))]
+pub mod shell;
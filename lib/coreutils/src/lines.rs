@@ -0,0 +1,71 @@
+//! Finding where the first or last lines of some text end or begin, for `head`
+//! and `tail`.
+
+/// Returns the length of the first `n` lines of `text`, including their
+/// newlines, or `None` if `text` has fewer than `n` complete lines.
+pub fn first_lines_len(text: &[u8], n: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(0);
+    }
+
+    text.iter()
+        .enumerate()
+        .filter(|&(_, &byte)| byte == b'\n')
+        .nth(n - 1)
+        .map(|(i, _)| i + 1)
+}
+
+/// Returns the offset where the last `n` lines of `text` begin, or `None` if
+/// `text` has no more than `n` lines, in which case they may begin before it.
+/// A trailing newline ends the last line rather than starting an empty one.
+pub fn last_lines_start(text: &[u8], n: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(text.len());
+    }
+
+    let body = match text.last() {
+        Some(b'\n') => &text[..text.len() - 1],
+        _ => text,
+    };
+    body.iter()
+        .enumerate()
+        .rev()
+        .filter(|&(_, &byte)| byte == b'\n')
+        .nth(n - 1)
+        .map(|(i, _)| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_lines_len, last_lines_start};
+
+    #[test]
+    fn first_lines_len_stops_after_n_lines() {
+        assert_eq!(first_lines_len(b"a\nbb\nccc\nd\n", 2), Some(5));
+        assert_eq!(first_lines_len(b"a\nbb\n", 0), Some(0));
+        assert_eq!(first_lines_len(b"", 1), None);
+    }
+
+    #[test]
+    fn first_lines_len_with_exactly_n_lines() {
+        assert_eq!(first_lines_len(b"a\nbb\nccc\n", 3), Some(9));
+        // The last line isn't complete without its newline
+        assert_eq!(first_lines_len(b"a\nbb\nccc", 3), None);
+        assert_eq!(first_lines_len(b"a\nbb\n", 3), None);
+    }
+
+    #[test]
+    fn last_lines_start_finds_last_n_lines() {
+        assert_eq!(last_lines_start(b"a\nbb\nccc\nd\n", 2), Some(5));
+        assert_eq!(last_lines_start(b"a\nbb\nccc\nd", 2), Some(5));
+        assert_eq!(last_lines_start(b"a\nbb\n", 0), Some(5));
+        assert_eq!(last_lines_start(b"", 1), None);
+    }
+
+    #[test]
+    fn last_lines_start_with_exactly_n_lines() {
+        assert_eq!(last_lines_start(b"a\nbb\nccc\n", 3), None);
+        assert_eq!(last_lines_start(b"a\nbb\nccc", 3), None);
+        assert_eq!(last_lines_start(b"\na\nbb\nccc\n", 3), Some(1));
+    }
+}
@@ -0,0 +1,122 @@
+//! Looking up programs in `$PATH`, for the shell and `env`, and splitting
+//! paths, for `basename` and `dirname`.
+
+/// Searches the colon-separated directories in `path` for a file called
+/// `name`, trying each directory in order. `is_file` is called with each
+/// candidate path. Returns the first candidate it accepts, written into `buf`.
+/// Empty entries and candidates that don't fit in `buf` are skipped.
+pub fn search_path<'a, F: FnMut(&str) -> bool>(
+    path: &str,
+    name: &str,
+    buf: &'a mut [u8],
+    mut is_file: F,
+) -> Option<&'a str> {
+    let mut found = None;
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let sep = if dir.ends_with('/') { "" } else { "/" };
+        let len = dir.len() + sep.len() + name.len();
+        if len > buf.len() {
+            continue;
+        }
+
+        buf[..dir.len()].copy_from_slice(dir.as_bytes());
+        buf[dir.len()..dir.len() + sep.len()].copy_from_slice(sep.as_bytes());
+        buf[dir.len() + sep.len()..len].copy_from_slice(name.as_bytes());
+        let candidate = core::str::from_utf8(&buf[..len]).unwrap();
+        if is_file(candidate) {
+            found = Some(len);
+            break;
+        }
+    }
+
+    match found {
+        Some(len) => core::str::from_utf8(&buf[..len]).ok(),
+        None => None,
+    }
+}
+
+/// Returns the last component of `path`, with `suffix` removed from its end
+/// unless that would leave nothing. Trailing slashes are ignored, so `/a/b/`
+/// gives `b`, and `/` gives itself.
+pub fn basename<'a>(path: &'a str, suffix: &str) -> &'a str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { "" } else { "/" };
+    }
+
+    let name = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    if name != suffix && name.ends_with(suffix) {
+        &name[..name.len() - suffix.len()]
+    } else {
+        name
+    }
+}
+
+/// Returns `path` with its last component removed. Trailing slashes are
+/// ignored, so `/a/b/` gives `/a`. The parent of `/` is `/`, and a bare
+/// name's parent is the working directory, `.`.
+pub fn dirname(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return if path.is_empty() { "." } else { "/" };
+    }
+
+    match trimmed.rfind('/') {
+        Some(i) => match trimmed[..i].trim_end_matches('/') {
+            "" => "/",
+            parent => parent,
+        },
+        None => ".",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{basename, dirname, search_path};
+
+    #[test]
+    fn search_path_finds_first_match() {
+        let files = ["/bin/fib", "/bin/ls", "/usr/bin/ls"];
+        let exists = |path: &str| files.contains(&path);
+        let mut buf = [0u8; 64];
+
+        assert_eq!(search_path("/bin/", "fib", &mut buf, exists), Some("/bin/fib"));
+        assert_eq!(search_path("/usr/bin:/bin", "fib", &mut buf, exists), Some("/bin/fib"));
+        assert_eq!(search_path("/usr/bin::/bin", "ls", &mut buf, exists), Some("/usr/bin/ls"));
+        assert_eq!(search_path("/bin", "cat", &mut buf, exists), None);
+        assert_eq!(search_path("", "fib", &mut buf, exists), None);
+    }
+
+    #[test]
+    fn search_path_skips_candidates_too_long_for_buf() {
+        let mut buf = [0u8; 8];
+        assert_eq!(search_path("/usr/local/bin:/bin", "fib", &mut buf, |_| true), Some("/bin/fib"));
+    }
+
+    #[test]
+    fn basename_is_the_last_component() {
+        assert_eq!(basename("/a/b/c", ""), "c");
+        assert_eq!(basename("/a/b/c/", ""), "c");
+        assert_eq!(basename("c", ""), "c");
+        assert_eq!(basename("/", ""), "/");
+        assert_eq!(basename("//", ""), "/");
+        assert_eq!(basename("", ""), "");
+        assert_eq!(basename("/a/..", ""), "..");
+
+        assert_eq!(basename("/src/main.rs", ".rs"), "main");
+        assert_eq!(basename("/src/main.rs", ".c"), "main.rs");
+        assert_eq!(basename("/src/.rs", ".rs"), ".rs");
+    }
+
+    #[test]
+    fn dirname_drops_the_last_component() {
+        assert_eq!(dirname("/a/b/c"), "/a/b");
+        assert_eq!(dirname("/a/b/c/"), "/a/b");
+        assert_eq!(dirname("/a//c"), "/a");
+        assert_eq!(dirname("a/b"), "a");
+        assert_eq!(dirname("/a"), "/");
+        assert_eq!(dirname("/"), "/");
+        assert_eq!(dirname("c"), ".");
+        assert_eq!(dirname(""), ".");
+    }
+}
@@ -0,0 +1,40 @@
+//! Counting from one number to another, for `seq`.
+
+/// Iterates from `first` up or down to `last` in steps of `incr`, like `seq`.
+/// Returns `None` if `incr` is zero or points away from `last`.
+pub fn seq(first: i64, incr: i64, last: i64) -> Option<impl Iterator<Item = i64>> {
+    if incr == 0 || (incr > 0 && first > last) || (incr < 0 && first < last) {
+        return None;
+    }
+    let mut next = Some(first);
+    Some(core::iter::from_fn(move || {
+        let value = next?;
+        let done = if incr > 0 { value > last } else { value < last };
+        if done {
+            return None;
+        }
+        next = value.checked_add(incr);
+        Some(value)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::seq;
+
+    #[test]
+    fn seq_counts_up_and_down() {
+        assert!(seq(1, 1, 5).unwrap().eq(1..=5));
+        assert!(seq(1, 2, 6).unwrap().eq([1, 3, 5].iter().cloned()));
+        assert!(seq(5, -2, -1).unwrap().eq([5, 3, 1, -1].iter().cloned()));
+        assert!(seq(3, 1, 3).unwrap().eq(3..=3));
+        assert!(seq(i64::max_value() - 1, 1, i64::max_value()).unwrap().count() == 2);
+    }
+
+    #[test]
+    fn seq_rejects_increments_going_the_wrong_way() {
+        assert!(seq(1, 0, 5).is_none());
+        assert!(seq(1, -1, 5).is_none());
+        assert!(seq(5, 1, 1).is_none());
+    }
+}
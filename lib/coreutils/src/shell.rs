@@ -0,0 +1,313 @@
+//! The parts of the user shell that run programs and keep track of jobs.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use kernel_api::{ExitStatus, OsResult, SIGINT};
+
+/// A command started in the background with `&`.
+pub struct Job {
+    pub id: usize,
+    pub pid: u64,
+    pub command: String,
+}
+
+/// What the shell needs from the kernel to run programs. Messages for the
+/// user are written to it as to the console.
+pub trait System: fmt::Write {
+    /// Finds the program `name` would run, or `None` if there is none.
+    fn resolve(&mut self, name: &str) -> Option<String>;
+    /// Starts `program` with `args` and niceness `niceness` in a child
+    /// process, and returns the child's ID.
+    fn spawn(&mut self, niceness: i64, program: &str, args: &[&str]) -> OsResult<u64>;
+    /// Waits for the child `pid` to exit and collects it.
+    fn wait(&mut self, pid: u64) -> OsResult<ExitStatus>;
+    /// Makes Ctrl-C kill `pid`, or nothing if `pid` is 0.
+    fn set_foreground(&mut self, pid: u64);
+    /// Returns the IDs of processes that have exited but not been collected.
+    fn zombies(&mut self) -> Vec<u64>;
+}
+
+/// Returns the exit status `$?` reports for a program that ended with
+/// `status`: its exit code, or 128 plus the signal that killed it.
+pub fn exit_code(status: ExitStatus) -> u64 {
+    match status {
+        ExitStatus::Exited(code) => code,
+        ExitStatus::Killed(signal) => 128 + signal,
+    }
+}
+
+/// Runs a program and returns its exit status. Programs started in the
+/// background with a trailing `&` count as a success, and are added to `jobs`
+/// instead of being waited for.
+pub fn run_niced<S: System>(sys: &mut S, niceness: i64, program: &str, args: &[&str], jobs: &mut Vec<Job>) -> u64 {
+    // A trailing `&` runs the program in the background
+    let (args, background) = match args.last() {
+        Some(&"&") => (&args[0..args.len() - 1], true),
+        _ => (args, false),
+    };
+
+    let program = match sys.resolve(program) {
+        Some(path) => path,
+        None => {
+            let _ = writeln!(sys, "{}: command not found", program);
+            return 127
+        },
+    };
+
+    match sys.spawn(niceness, &program, args) {
+        Ok(pid) if background => {
+            let id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+            let mut command = program.clone();
+            for arg in args {
+                command.push(' ');
+                command.push_str(arg);
+            }
+            let _ = writeln!(sys, "[{}] {}", id, pid);
+            jobs.push(Job { id, pid, command });
+            0
+        },
+        Ok(pid) => {
+            // Ctrl-C kills the program while we wait for it
+            sys.set_foreground(pid);
+            let status = sys.wait(pid);
+            sys.set_foreground(0);
+            match status {
+                Ok(status) => {
+                    if status == ExitStatus::Killed(SIGINT) {
+                        let _ = writeln!(sys, "^C");
+                    }
+                    exit_code(status)
+                },
+                Err(e) => {
+                    let _ = writeln!(sys, "Failed to wait for process: {:?}", e);
+                    1
+                },
+            }
+        },
+        Err(e) => {
+            let _ = writeln!(sys, "Error running while {}: {:?}", program, e);
+            1
+        },
+    }
+}
+
+/// Reaps background jobs that have finished and reports them.
+pub fn reap_jobs<S: System>(sys: &mut S, jobs: &mut Vec<Job>) {
+    if jobs.is_empty() { return }
+
+    let zombies = sys.zombies();
+    let (done, running): (Vec<Job>, Vec<Job>) = jobs.drain(..).partition(|job| zombies.contains(&job.pid));
+    *jobs = running;
+    for job in done {
+        if let Ok(status) = sys.wait(job.pid) {
+            let _ = writeln!(sys, "[{}] Done ({:?})    {}", job.id, status, job.command);
+        }
+    }
+}
+
+/// Whether `$NAME` expands to the literal text `$NAME` when `NAME` isn't set,
+/// instead of to nothing.
+const KEEP_UNKNOWN_VARS: bool = false;
+
+/// Returns the name and value a command of the form `NAME=value` sets, or
+/// `None` if `program` and `args` aren't an assignment.
+pub fn assignment<'a>(program: &'a str, args: &[&str]) -> Option<(&'a str, &'a str)> {
+    match program.find('=') {
+        Some(eq) if eq > 0 && args.is_empty() => Some((&program[..eq], &program[eq + 1..])),
+        _ => None,
+    }
+}
+
+/// Replaces every `$NAME` or `${NAME}` in `arg` with the value `lookup`
+/// returns for `NAME`, and `$?` with `status`, the exit status of the last
+/// command. `\$` produces a literal `$`.
+pub fn expand_vars<F: FnMut(&str) -> Option<String>>(arg: &str, status: u64, mut lookup: F) -> String {
+    let chars: Vec<char> = arg.chars().collect();
+    let mut expanded = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() && chars[i + 1] == '$' => {
+                expanded.push('$');
+                i += 2;
+            },
+            '$' if i + 1 < chars.len() && chars[i + 1] == '?' => {
+                expanded.push_str(&format!("{}", status));
+                i += 2;
+            },
+            '$' => {
+                let braced = i + 1 < chars.len() && chars[i + 1] == '{';
+                let start = if braced { i + 2 } else { i + 1 };
+                let mut end = start;
+                while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_') {
+                    end += 1;
+                }
+                let closed = !braced || (end < chars.len() && chars[end] == '}');
+                if end == start || !closed {
+                    // Not a variable reference after all
+                    expanded.push('$');
+                    i += 1;
+                    continue;
+                }
+
+                let name: String = chars[start..end].iter().collect();
+                match lookup(&name) {
+                    Some(value) => expanded.push_str(&value),
+                    None if KEEP_UNKNOWN_VARS => expanded.extend(chars[i..end + braced as usize].iter()),
+                    None => (),
+                }
+                i = end + braced as usize;
+            },
+            c => {
+                expanded.push(c);
+                i += 1;
+            },
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use kernel_api::{ExitStatus, OsError, OsResult};
+    use super::{assignment, expand_vars, reap_jobs, run_niced, System};
+
+    /// Pretends to be the kernel. Programs in `/bin` exit with status 1 if
+    /// they are called `false` and 0 otherwise, as soon as they are waited
+    /// for or `finish()` is called.
+    #[derive(Default)]
+    pub struct FakeSystem {
+        pub output: String,
+        /// Children that haven't been collected, and whether they've exited
+        pub children: Vec<(u64, ExitStatus, bool)>,
+        /// Children collected with `wait`, in order
+        pub waited: Vec<u64>,
+        pub foreground: u64,
+        last_pid: u64,
+    }
+
+    impl FakeSystem {
+        pub fn finish(&mut self, pid: u64) {
+            self.children.iter_mut().find(|child| child.0 == pid).unwrap().2 = true;
+        }
+    }
+
+    impl fmt::Write for FakeSystem {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.output.push_str(s);
+            Ok(())
+        }
+    }
+
+    impl System for FakeSystem {
+        fn resolve(&mut self, name: &str) -> Option<String> {
+            match name {
+                "true" | "false" | "sleep" => Some(alloc::format!("/bin/{}", name)),
+                _ => None,
+            }
+        }
+
+        fn spawn(&mut self, _niceness: i64, program: &str, _args: &[&str]) -> OsResult<u64> {
+            // The shell itself is process 1
+            let pid = self.last_pid.max(1) + 1;
+            self.last_pid = pid;
+            let code = if program == "/bin/false" { 1 } else { 0 };
+            self.children.push((pid, ExitStatus::Exited(code), false));
+            Ok(pid)
+        }
+
+        fn wait(&mut self, pid: u64) -> OsResult<ExitStatus> {
+            let index = self.children.iter().position(|child| child.0 == pid).ok_or(OsError::NoEntry)?;
+            self.waited.push(pid);
+            Ok(self.children.remove(index).1)
+        }
+
+        fn set_foreground(&mut self, pid: u64) {
+            self.foreground = pid;
+        }
+
+        fn zombies(&mut self) -> Vec<u64> {
+            self.children.iter().filter(|child| child.2).map(|child| child.0).collect()
+        }
+    }
+
+    #[test]
+    fn background_job_returns_to_the_prompt() {
+        let mut sys = FakeSystem::default();
+        let mut jobs = Vec::new();
+
+        assert_eq!(run_niced(&mut sys, 0, "sleep", &["10", "&"], &mut jobs), 0);
+        assert_eq!(sys.output, "[1] 2\n");
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].command, "/bin/sleep 10");
+        // Nothing was waited for, so the shell gets back to its prompt
+        assert!(sys.waited.is_empty());
+        assert_eq!(sys.foreground, 0);
+
+        // Still running: nothing to report
+        sys.output.clear();
+        reap_jobs(&mut sys, &mut jobs);
+        assert_eq!((sys.output.as_str(), jobs.len()), ("", 1));
+
+        sys.finish(2);
+        reap_jobs(&mut sys, &mut jobs);
+        assert_eq!(sys.output, "[1] Done (Exited(0))    /bin/sleep 10\n");
+        assert!(jobs.is_empty());
+        assert_eq!(sys.waited, [2]);
+    }
+
+    #[test]
+    fn foreground_program_is_waited_for() {
+        let mut sys = FakeSystem::default();
+        let mut jobs = Vec::new();
+
+        assert_eq!(run_niced(&mut sys, 0, "false", &[], &mut jobs), 1);
+        assert_eq!(sys.waited, [2]);
+        assert!(jobs.is_empty());
+
+        assert_eq!(run_niced(&mut sys, 0, "nope", &[], &mut jobs), 127);
+        assert_eq!(sys.output, "nope: command not found\n");
+    }
+
+    #[test]
+    fn variables_expand_in_arguments() {
+        // FOO=bar; echo $FOO
+        let mut env: Vec<(String, String)> = Vec::new();
+        let (name, value) = assignment("FOO=bar", &[]).unwrap();
+        env.push((String::from(name), String::from(value)));
+        let lookup = |name: &str| env.iter().find(|var| var.0 == name).map(|var| var.1.clone());
+        assert_eq!(expand_vars("$FOO", 0, lookup), "bar");
+
+        assert_eq!(expand_vars("${FOO}baz", 0, lookup), "barbaz");
+        assert_eq!(expand_vars("$FOO/$FOO", 0, lookup), "bar/bar");
+        assert_eq!(expand_vars("\\$FOO", 0, lookup), "$FOO");
+        assert_eq!(expand_vars("$UNSET.", 0, lookup), ".");
+        assert_eq!(expand_vars("${FOO", 0, lookup), "${FOO");
+        assert_eq!(expand_vars("$", 0, lookup), "$");
+
+        // Not assignments
+        assert_eq!(assignment("=bar", &[]), None);
+        assert_eq!(assignment("FOO=bar", &["x"]), None);
+        assert_eq!(assignment("FOO=", &[]), Some(("FOO", "")));
+    }
+
+    #[test]
+    fn status_is_that_of_the_last_program() {
+        let mut sys = FakeSystem::default();
+        let mut jobs = Vec::new();
+        let no_vars = |_: &str| None;
+
+        let status = run_niced(&mut sys, 0, "true", &[], &mut jobs);
+        assert_eq!(expand_vars("$?", status, no_vars), "0");
+        let status = run_niced(&mut sys, 0, "false", &[], &mut jobs);
+        assert_eq!(expand_vars("$?", status, no_vars), "1");
+        let status = run_niced(&mut sys, 0, "true", &[], &mut jobs);
+        assert_eq!(expand_vars("status=$?", status, no_vars), "status=0");
+    }
+}
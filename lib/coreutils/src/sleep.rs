@@ -0,0 +1,45 @@
+//! Parsing how long to sleep, for `sleep`.
+
+/// Parses a number of seconds with an optional fraction, like `2` or `0.5`.
+/// Digits past nanosecond precision are ignored.
+pub fn parse_seconds(s: &str) -> Option<core::time::Duration> {
+    let (whole, frac) = match s.find('.') {
+        Some(dot) => (&s[..dot], &s[dot + 1..]),
+        None => (s, ""),
+    };
+    if whole.is_empty() && frac.is_empty() {
+        return None;
+    }
+    if !whole.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let secs = if whole.is_empty() { 0 } else { whole.parse::<u64>().ok()? };
+    let mut nanos = 0;
+    for i in 0..9 {
+        let digit = frac.as_bytes().get(i).map(|b| b - b'0').unwrap_or(0);
+        nanos = nanos * 10 + digit as u32;
+    }
+    Some(core::time::Duration::new(secs, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_seconds;
+
+    #[test]
+    fn parse_seconds_accepts_fractions() {
+        use core::time::Duration;
+
+        assert_eq!(parse_seconds("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_seconds("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_seconds(".25"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_seconds("3."), Some(Duration::from_secs(3)));
+        assert_eq!(parse_seconds("1.0000000019"), Some(Duration::new(1, 1)));
+        assert_eq!(parse_seconds(""), None);
+        assert_eq!(parse_seconds("."), None);
+        assert_eq!(parse_seconds("-1"), None);
+        assert_eq!(parse_seconds("1.2.3"), None);
+        assert_eq!(parse_seconds("1s"), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! Ordering lines, for `sort`.
+
+/// Returns the number at the start of `line` for `sort -n`: optional leading
+/// whitespace, an optional `-`, and digits. Lines without one count as 0.
+fn leading_number(line: &str) -> i128 {
+    let line = line.trim_start();
+    let (negative, digits) = if line.starts_with('-') {
+        (true, &line[1..])
+    } else {
+        (false, line)
+    };
+    let mut value: i128 = 0;
+    for digit in digits.bytes().take_while(u8::is_ascii_digit) {
+        value = value.saturating_mul(10).saturating_add((digit - b'0') as i128);
+    }
+    if negative { -value } else { value }
+}
+
+/// Orders two lines for `sort`, by their leading numbers if `numeric` and
+/// otherwise byte by byte. Lines with equal numbers are ordered byte by byte.
+pub fn compare_lines(a: &str, b: &str, numeric: bool) -> core::cmp::Ordering {
+    if numeric {
+        leading_number(a).cmp(&leading_number(b)).then_with(|| a.cmp(b))
+    } else {
+        a.cmp(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compare_lines;
+
+    #[test]
+    fn compare_lines_sorts_shuffled_input() {
+        let mut lines = ["pear", "apple", "Zebra", "banana", "apple pie"];
+        lines.sort_unstable_by(|a, b| compare_lines(a, b, false));
+        assert_eq!(lines, ["Zebra", "apple", "apple pie", "banana", "pear"]);
+
+        lines.sort_unstable_by(|a, b| compare_lines(a, b, false).reverse());
+        assert_eq!(lines, ["pear", "banana", "apple pie", "apple", "Zebra"]);
+    }
+
+    #[test]
+    fn compare_lines_sorts_numerically() {
+        let mut lines = ["10", "9", "-3 below", "100", "none", " 42", "9"];
+        lines.sort_unstable_by(|a, b| compare_lines(a, b, true));
+        assert_eq!(lines, ["-3 below", "none", "9", "9", "10", " 42", "100"]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Copying input to both stdout and a file, for `tee`.
+
+use kernel_api::OsResult;
+
+/// The longest line that is held back before being written out.
+pub const LINE_MAX: usize = 512;
+
+/// Somewhere `tee` writes its input.
+pub trait Sink {
+    /// Writes all of `buf`, or returns an error.
+    fn write_all(&mut self, buf: &[u8]) -> OsResult<()>;
+}
+
+/// Copies `input` to `stdout` and, if there is one, to `file` until the input
+/// runs out. Each line is written whole rather than byte by byte, unless it's
+/// longer than `LINE_MAX`. If writing to the file fails, the rest of the
+/// input goes to stdout alone and the error is returned at the end.
+pub fn tee<I, O, F>(input: I, stdout: &mut O, mut file: Option<&mut F>) -> OsResult<()>
+    where I: IntoIterator<Item = u8>, O: Sink, F: Sink
+{
+    let mut input = input.into_iter();
+    let mut line = [0u8; LINE_MAX];
+    let mut result = Ok(());
+    loop {
+        let mut len = 0;
+        let mut ended = true;
+        for byte in &mut input {
+            line[len] = byte;
+            len += 1;
+            if byte == b'\n' || len == LINE_MAX {
+                ended = false;
+                break
+            }
+        }
+
+        if len == 0 { return result }
+
+        stdout.write_all(&line[..len])?;
+        if let Some(out) = file.as_mut() {
+            if let Err(e) = out.write_all(&line[..len]) {
+                result = Err(e);
+                file = None;
+            }
+        }
+
+        if ended { return result }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tee, Sink, LINE_MAX};
+    use kernel_api::{OsError, OsResult};
+
+    /// Keeps every write, failing once it has `limit` bytes.
+    struct FakeSink {
+        data: Vec<u8>,
+        writes: usize,
+        limit: usize,
+    }
+
+    impl FakeSink {
+        fn new() -> FakeSink {
+            FakeSink::with_limit(usize::max_value())
+        }
+
+        fn with_limit(limit: usize) -> FakeSink {
+            FakeSink { data: Vec::new(), writes: 0, limit }
+        }
+    }
+
+    impl Sink for FakeSink {
+        fn write_all(&mut self, buf: &[u8]) -> OsResult<()> {
+            if self.data.len() + buf.len() > self.limit {
+                return Err(OsError::IoError);
+            }
+            self.data.extend_from_slice(buf);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tee_copies_input_to_stdout_and_file() {
+        let input = b"first line\nsecond\nno newline".to_vec();
+        let (mut stdout, mut file) = (FakeSink::new(), FakeSink::new());
+        assert_eq!(tee(input.clone(), &mut stdout, Some(&mut file)), Ok(()));
+        assert_eq!(stdout.data, input);
+        assert_eq!(file.data, input);
+        // A write per line, not per byte
+        assert_eq!(file.writes, 3);
+
+        // Lines longer than the buffer are split rather than dropped
+        let input = vec![b'x'; LINE_MAX + 1];
+        let (mut stdout, mut file) = (FakeSink::new(), FakeSink::new());
+        assert_eq!(tee(input.clone(), &mut stdout, Some(&mut file)), Ok(()));
+        assert_eq!(stdout.data, input);
+        assert_eq!(file.data, input);
+    }
+
+    #[test]
+    fn tee_keeps_writing_stdout_without_the_file() {
+        let input = b"one\ntwo\nthree\n".to_vec();
+
+        // The file couldn't be opened
+        let mut stdout = FakeSink::new();
+        assert_eq!(tee(input.clone(), &mut stdout, None::<&mut FakeSink>), Ok(()));
+        assert_eq!(stdout.data, input);
+
+        // The file fills up partway through
+        let (mut stdout, mut file) = (FakeSink::new(), FakeSink::with_limit(6));
+        assert_eq!(tee(input.clone(), &mut stdout, Some(&mut file)), Err(OsError::IoError));
+        assert_eq!(stdout.data, input);
+        assert_eq!(file.data, b"one\n");
+    }
+}
@@ -0,0 +1,33 @@
+//! Collapsing repeated lines, for `uniq`.
+
+/// Collapses runs of equal adjacent items, as `uniq` does with lines, into
+/// each item paired with how many times it was repeated.
+pub fn adjacent_runs<I>(items: I) -> impl Iterator<Item = (I::Item, usize)>
+    where I: IntoIterator, I::Item: PartialEq
+{
+    let mut items = items.into_iter().peekable();
+    core::iter::from_fn(move || {
+        let item = items.next()?;
+        let mut count = 1;
+        while items.peek() == Some(&item) {
+            items.next();
+            count += 1;
+        }
+        Some((item, count))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::adjacent_runs;
+
+    #[test]
+    fn adjacent_runs_collapse_consecutive_duplicates() {
+        let lines = ["a", "a", "b", "a", "c", "c", "c"];
+        let runs = [("a", 2), ("b", 1), ("a", 1), ("c", 3)];
+        assert!(adjacent_runs(lines.iter().cloned()).eq(runs.iter().cloned()));
+
+        let empty: [&str; 0] = [];
+        assert_eq!(adjacent_runs(empty.iter()).count(), 0);
+    }
+}
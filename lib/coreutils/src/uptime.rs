@@ -0,0 +1,42 @@
+//! Formatting how long the system has been up, for `uptime`.
+
+/// Formats a duration in whole seconds as `Xd Yh Zm Ws`, leaving out the
+/// leading units that are zero.
+#[derive(Copy, Clone, Debug)]
+pub struct HumanDuration(pub core::time::Duration);
+
+impl core::fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let secs = self.0.as_secs();
+        let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+        if days > 0 {
+            write!(f, "{}d ", days)?;
+        }
+        if days > 0 || hours > 0 {
+            write!(f, "{}h ", hours)?;
+        }
+        if secs >= 60 {
+            write!(f, "{}m ", minutes)?;
+        }
+        write!(f, "{}s", secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use super::HumanDuration;
+
+    #[test]
+    fn human_duration_skips_leading_zero_units() {
+        let check = |secs, expected: &str| {
+            assert_eq!(format!("{}", HumanDuration(Duration::from_secs(secs))), expected);
+        };
+        check(0, "0s");
+        check(59, "59s");
+        check(60, "1m 0s");
+        check(3600, "1h 0m 0s");
+        check(86400 + 5, "1d 0h 0m 5s");
+        check(2 * 86400 + 3 * 3600 + 4 * 60 + 5, "2d 3h 4m 5s");
+    }
+}
@@ -0,0 +1,82 @@
+//! Tallying lines, words and bytes, for `wc`.
+
+/// Running line, word and byte tallies over a stream of bytes, as printed by
+/// `wc`. Words are runs of bytes separated by ASCII whitespace, so a word or a
+/// multibyte character split between two calls to `feed` is counted once.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WordCount {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    in_word: bool,
+}
+
+impl WordCount {
+    pub fn new() -> WordCount {
+        WordCount::default()
+    }
+
+    /// Adds the next chunk of the stream to the tallies.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if byte == b'\n' {
+                self.lines += 1;
+            }
+            if byte.is_ascii_whitespace() {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.in_word = true;
+                self.words += 1;
+            }
+        }
+        self.bytes += chunk.len();
+    }
+
+    /// Adds the tallies of `other` to these ones.
+    pub fn add(&mut self, other: &WordCount) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WordCount;
+
+    fn count_in_chunks(input: &[u8], chunk_size: usize) -> WordCount {
+        let mut count = WordCount::new();
+        for chunk in input.chunks(chunk_size) {
+            count.feed(chunk);
+        }
+        count
+    }
+
+    #[test]
+    fn word_count_counts_lines_words_and_bytes() {
+        let input = "hello world\n  two\tspaces  \n\nlast".as_bytes();
+        let count = count_in_chunks(input, input.len());
+        assert_eq!((count.lines, count.words, count.bytes), (3, 5, input.len()));
+
+        let empty = WordCount::new();
+        assert_eq!((empty.lines, empty.words, empty.bytes), (0, 0, 0));
+    }
+
+    #[test]
+    fn word_count_is_independent_of_chunking() {
+        let input = "caf\u{e9} na\u{ef}ve \u{1f600}\n\u{65e5}\u{672c}\u{8a9e} text\n".as_bytes();
+        let whole = count_in_chunks(input, input.len());
+        assert_eq!((whole.lines, whole.words, whole.bytes), (2, 5, input.len()));
+        for chunk_size in 1..input.len() {
+            assert_eq!(count_in_chunks(input, chunk_size), whole);
+        }
+    }
+
+    #[test]
+    fn word_count_add_sums_tallies() {
+        let mut total = WordCount::new();
+        total.add(&count_in_chunks(b"one two\n", 3));
+        total.add(&count_in_chunks(b"three\n", 3));
+        assert_eq!((total.lines, total.words, total.bytes), (2, 3, 14));
+    }
+}
@@ -0,0 +1,65 @@
+//! Formatting rows of a hex dump, for `xxd`.
+
+/// Bytes shown on each row of a hex dump.
+pub const HEX_ROW_LEN: usize = 16;
+/// Longest row `hex_row` writes.
+pub const HEX_ROW_MAX: usize = 9 + HEX_ROW_LEN / 2 * 5 + 2 + HEX_ROW_LEN;
+
+/// Writes the row of an `xxd`-style dump for `bytes` found at `offset` into
+/// `buf`: the offset, the bytes in hex in pairs, and the bytes that are
+/// printable ASCII. At most `HEX_ROW_LEN` bytes are shown.
+pub fn hex_row<'a>(offset: u64, bytes: &[u8], buf: &'a mut [u8; HEX_ROW_MAX]) -> &'a str {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let bytes = &bytes[..core::cmp::min(bytes.len(), HEX_ROW_LEN)];
+    let mut len = 0;
+    let mut push = |byte: u8| {
+        buf[len] = byte;
+        len += 1;
+    };
+
+    for shift in (0..8).rev() {
+        push(HEX[(offset >> (shift * 4)) as usize & 0xf]);
+    }
+    push(b':');
+    for i in 0..HEX_ROW_LEN {
+        if i % 2 == 0 {
+            push(b' ');
+        }
+        match bytes.get(i) {
+            Some(&byte) => {
+                push(HEX[(byte >> 4) as usize]);
+                push(HEX[(byte & 0xf) as usize]);
+            }
+            None => {
+                push(b' ');
+                push(b' ');
+            }
+        }
+    }
+    push(b' ');
+    push(b' ');
+    for &byte in bytes {
+        push(if byte.is_ascii_graphic() || byte == b' ' { byte } else { b'.' });
+    }
+
+    core::str::from_utf8(&buf[..len]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex_row, HEX_ROW_MAX};
+
+    #[test]
+    fn hex_row_formats_offset_hex_and_ascii() {
+        let mut buf = [0u8; HEX_ROW_MAX];
+        assert_eq!(
+            hex_row(0x10, b"Hello, world!\n\x00\xff", &mut buf),
+            "00000010: 4865 6c6c 6f2c 2077 6f72 6c64 210a 00ff  Hello, world!..."
+        );
+        assert_eq!(
+            hex_row(0x1230, b"abc", &mut buf),
+            "00001230: 6162 63                                  abc"
+        );
+    }
+}
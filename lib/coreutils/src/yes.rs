@@ -0,0 +1,43 @@
+//! The line `yes` repeats.
+
+use core::fmt;
+
+/// The line `yes` prints for `args`: its words joined with spaces, or `y` if
+/// there are none.
+#[derive(Copy, Clone, Debug)]
+pub struct Line<'a>(pub &'a [&'a str]);
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("y");
+        }
+
+        for (i, arg) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(arg)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Line;
+
+    #[test]
+    fn line_defaults_to_y_and_joins_words() {
+        assert_eq!(format!("{}", Line(&[])), "y");
+        assert_eq!(format!("{}", Line(&["no"])), "no");
+        assert_eq!(format!("{}", Line(&["hello", "", "world"])), "hello  world");
+
+        // What the first lines of `yes ok` look like
+        let mut output = String::new();
+        for _ in 0..3 {
+            output.push_str(&format!("{}\n", Line(&["ok"])));
+        }
+        assert_eq!(output, "ok\nok\nok\n");
+    }
+}
@@ -1,12 +1,39 @@
+use crate::highlighting::{self, Highlighter};
 use crate::Row;
 use std::fs;
 use crate::Position;
+use crate::SearchDirection;
 use std::io::{Error, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+enum Edit {
+    // `appended_row` is set if inserting the text added a row at the end
+    Insert { at: Position, text: String, appended_row: bool },
+    Delete { at: Position, text: String },
+}
 
-#[derive(Default)]
 pub struct Document {
     rows: Vec<Row>,
     pub file_name: Option<String>,
+    highlighter: Option<Box<dyn Highlighter>>,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    // How many edits could be undone when the document was last saved, or
+    // `None` if that state can't be reached anymore
+    saved: Option<usize>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self {
+            rows: Vec::new(),
+            file_name: None,
+            highlighter: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+        }
+    }
 }
 
 impl Document {
@@ -16,10 +43,32 @@ impl Document {
         for value in contents.lines() {
             rows.push(Row::from(value));
         }
-        Ok(Self{
+        let mut document = Self{
             rows,
             file_name: Some(filename.to_string()),
-        })
+            highlighter: highlighting::for_file(filename),
+            ..Self::default()
+        };
+        document.highlight_rows(0, document.len());
+        Ok(document)
+    }
+    pub fn toggle_highlighting(&mut self) -> bool {
+        self.highlighter = match self.highlighter {
+            Some(_) => None,
+            None => match &self.file_name {
+                Some(file_name) => highlighting::for_file(file_name),
+                None => None,
+            }
+            .or_else(|| Some(Box::new(highlighting::Rust))),
+        };
+        self.highlight_rows(0, self.len());
+        self.highlighter.is_some()
+    }
+    fn highlight_rows(&mut self, start: usize, end: usize) {
+        let highlighter = self.highlighter.as_deref();
+        for row in self.rows.iter_mut().take(end).skip(start) {
+            row.highlight(highlighter);
+        }
     }
     pub fn row(&self, index: usize) -> Option<&Row> {
         self.rows.get(index)
@@ -42,9 +91,33 @@ impl Document {
         self.rows.insert(location.y + 1, new_row);
 
     }
+    pub fn is_dirty(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
     pub fn insert(&mut self, location: &Position, c: char) {
+        if location.y > self.len() {
+            return;
+        }
+        let appended_row = location.y == self.len();
+        self.insert_char(location, c);
+
+        // Characters typed one after another are undone together
+        if self.saved != Some(self.undo.len()) && c != '\n' {
+            if let Some(Edit::Insert { at, text, .. }) = self.undo.last_mut() {
+                if !text.ends_with('\n') && at.y == location.y
+                    && at.x + text.graphemes(true).count() == location.x {
+                    text.push(c);
+                    self.discard_redo();
+                    return;
+                }
+            }
+        }
+        self.record(Edit::Insert { at: location.clone(), text: c.to_string(), appended_row });
+    }
+    fn insert_char(&mut self, location: &Position, c: char) {
         if c == '\n' {
             self.insert_newline(location);
+            self.highlight_rows(location.y, location.y + 2);
             return;
         }
         if location.y == self.len() {
@@ -55,8 +128,25 @@ impl Document {
             let row = self.rows.get_mut(location.y).unwrap();
             row.insert(location.x, c);
         }
+        self.highlight_rows(location.y, location.y + 1);
     }
     pub fn delete(&mut self, location: &Position) {
+        let len = self.len();
+        if location.y >= len {
+            return;
+        }
+        let row = &self.rows[location.y];
+        let text = if location.x == row.len() && location.y < len - 1 {
+            "\n".to_string()
+        } else if let Some(grapheme) = row.grapheme(location.x) {
+            grapheme.to_string()
+        } else {
+            return;
+        };
+        self.delete_char(location);
+        self.record(Edit::Delete { at: location.clone(), text });
+    }
+    fn delete_char(&mut self, location: &Position) {
         let len = self.len();
         if location.y >= len {
             return;
@@ -69,16 +159,251 @@ impl Document {
             let row = self.rows.get_mut(location.y).unwrap();
             row.delete(location.x);
         }
+        self.highlight_rows(location.y, location.y + 1);
     }
-    pub fn save(&self) -> Result<(), Error> {
-        if let Some(file_name) = &self.file_name {
-            let mut file = fs::File::create(file_name)?;
-            for row in &self.rows {
-                file.write_all(row.as_bytes())?;
-                file.write_all(b"\n");
+    fn discard_redo(&mut self) {
+        if let Some(saved) = self.saved {
+            if saved > self.undo.len() {
+                self.saved = None;
             }
         }
+        self.redo.clear();
+    }
+    fn record(&mut self, edit: Edit) {
+        self.discard_redo();
+        self.undo.push(edit);
+    }
+    fn insert_text(&mut self, at: &Position, text: &str) -> Position {
+        let mut position = at.clone();
+        for c in text.chars() {
+            self.insert_char(&position, c);
+            if c == '\n' {
+                position = Position { x: 0, y: position.y + 1 };
+            } else {
+                position.x += 1;
+            }
+        }
+        position
+    }
+    fn delete_text(&mut self, at: &Position, text: &str) {
+        for _ in text.graphemes(true) {
+            self.delete_char(at);
+        }
+    }
+    pub fn undo(&mut self) -> Option<Position> {
+        let edit = self.undo.pop()?;
+        match &edit {
+            Edit::Insert { at, appended_row: true, .. } => {
+                self.rows.remove(at.y);
+            }
+            Edit::Insert { at, text, .. } => self.delete_text(at, text),
+            Edit::Delete { at, text } => {
+                self.insert_text(at, text);
+            }
+        }
+        let position = match &edit {
+            Edit::Insert { at, .. } | Edit::Delete { at, .. } => at.clone(),
+        };
+        self.redo.push(edit);
+        Some(position)
+    }
+    pub fn redo(&mut self) -> Option<Position> {
+        let edit = self.redo.pop()?;
+        let position = match &edit {
+            Edit::Insert { at, text, .. } => self.insert_text(at, text),
+            Edit::Delete { at, text } => {
+                self.delete_text(at, text);
+                at.clone()
+            }
+        };
+        self.undo.push(edit);
+        Some(position)
+    }
+    pub fn save(&mut self) -> Result<(), Error> {
+        if let Some(file_name) = self.file_name.clone() {
+            self.save_as(&file_name)?;
+        }
         Ok(())
     }
+    pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
+        let mut file = fs::File::create(file_name)?;
+        for row in &self.rows {
+            file.write_all(row.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        self.file_name = Some(file_name.to_string());
+        self.saved = Some(self.undo.len());
+        Ok(())
+    }
+    pub fn find(&self, query: &str, at: &Position, direction: SearchDirection) -> Option<Position> {
+        if at.y >= self.len() {
+            return None;
+        }
+        let mut position = Position { x: at.x, y: at.y };
+        let rows = match direction {
+            SearchDirection::Forward => self.len() - at.y,
+            SearchDirection::Backward => at.y + 1,
+        };
+        for _ in 0..rows {
+            let row = self.rows.get(position.y)?;
+            if let Some(x) = row.find(query, position.x, direction) {
+                position.x = x;
+                return Some(position);
+            }
+            match direction {
+                SearchDirection::Forward => {
+                    position.y = position.y.saturating_add(1);
+                    position.x = 0;
+                }
+                SearchDirection::Backward => {
+                    if position.y == 0 {
+                        break;
+                    }
+                    position.y -= 1;
+                    position.x = self.rows[position.y].len();
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Document;
+    use crate::{Position, Row, SearchDirection};
+
+    fn document(lines: &[&str]) -> Document {
+        Document {
+            rows: lines.iter().map(|line| Row::from(*line)).collect(),
+            ..Document::default()
+        }
+    }
+
+    fn lines(doc: &Document) -> Vec<String> {
+        doc.rows.iter().map(|row| String::from_utf8(row.as_bytes().to_vec()).unwrap()).collect()
+    }
+
+    fn type_text(doc: &mut Document, at: Position, text: &str) {
+        let mut position = at;
+        for c in text.chars() {
+            doc.insert(&position, c);
+            position = match c {
+                '\n' => Position { x: 0, y: position.y + 1 },
+                _ => Position { x: position.x + 1, y: position.y },
+            };
+        }
+    }
+
+    #[test]
+    fn save_as_writes_fresh_file() {
+        let dir = std::env::temp_dir().join(format!("editor-save-as-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("new.rs");
+        let path = path.to_str().unwrap();
 
+        let mut doc = document(&["fn main() {", "}"]);
+        type_text(&mut doc, Position { x: 11, y: 0 }, " ");
+        assert!(doc.is_dirty());
+        doc.save_as(path).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "fn main() { \n}\n");
+        assert_eq!(doc.file_name.as_deref(), Some(path));
+        assert!(!doc.is_dirty());
+
+        // A later save goes to the new name
+        doc.delete(&Position { x: 11, y: 0 });
+        doc.save().unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "fn main() {\n}\n");
+
+        // A name that can't be written to leaves the document as it was
+        assert!(doc.save_as(dir.to_str().unwrap()).is_err());
+        assert_eq!(doc.file_name.as_deref(), Some(path));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn undo_restores_original() {
+        let original = ["fn main() {", "}"];
+        let mut doc = document(&original);
+        assert!(!doc.is_dirty());
+
+        // Typed characters are undone in one go
+        type_text(&mut doc, Position { x: 11, y: 0 }, " 42");
+        assert_eq!(lines(&doc), ["fn main() { 42", "}"]);
+        assert!(doc.is_dirty());
+        assert_eq!(doc.undo(), Some(Position { x: 11, y: 0 }));
+        assert_eq!(lines(&doc), original);
+        assert!(!doc.is_dirty());
+        assert_eq!(doc.undo(), None);
+
+        assert_eq!(doc.redo(), Some(Position { x: 14, y: 0 }));
+        assert_eq!(lines(&doc), ["fn main() { 42", "}"]);
+        assert_eq!(doc.redo(), None);
+        doc.undo();
+
+        // A new line, typing in the middle of a row, deleting and adding rows
+        // at the end
+        type_text(&mut doc, Position { x: 11, y: 0 }, "\n    x");
+        type_text(&mut doc, Position { x: 3, y: 0 }, "my_");
+        doc.delete(&Position { x: 14, y: 0 });
+        doc.delete(&Position { x: 0, y: 0 });
+        type_text(&mut doc, Position { x: 0, y: 2 }, "\n");
+        type_text(&mut doc, Position { x: 0, y: 3 }, "// end");
+        assert_eq!(lines(&doc), ["n my_main() {    x", "}", "", "// end"]);
+        while doc.undo().is_some() {}
+        assert_eq!(lines(&doc), original);
+        assert!(!doc.is_dirty());
+
+        while doc.redo().is_some() {}
+        assert_eq!(lines(&doc), ["n my_main() {    x", "}", "", "// end"]);
+    }
+
+    #[test]
+    fn undoing_to_the_saved_state_is_clean() {
+        let mut doc = document(&["abc"]);
+        type_text(&mut doc, Position { x: 3, y: 0 }, "de");
+        doc.saved = Some(doc.undo.len());
+        assert!(!doc.is_dirty());
+
+        // Typing on after saving starts a new undo unit
+        type_text(&mut doc, Position { x: 5, y: 0 }, "f");
+        assert!(doc.is_dirty());
+        doc.undo();
+        assert_eq!(lines(&doc), ["abcde"]);
+        assert!(!doc.is_dirty());
+        doc.undo();
+        assert!(doc.is_dirty());
+        doc.redo();
+        assert!(!doc.is_dirty());
+
+        // Once the saved state is undone and replaced, it's gone for good
+        doc.undo();
+        type_text(&mut doc, Position { x: 0, y: 0 }, "z");
+        doc.undo();
+        assert_eq!(lines(&doc), ["abc"]);
+        assert!(doc.is_dirty());
+    }
+
+    #[test]
+    fn find_from_position() {
+        let doc = document(&["fn main() {", "    let main = 1;", "}", "main"]);
+        let find = |x, y, direction| doc.find("main", &Position { x, y }, direction);
+
+        assert_eq!(find(0, 0, SearchDirection::Forward), Some(Position { x: 3, y: 0 }));
+        // A match at the position itself counts, one just past it doesn't
+        assert_eq!(find(3, 0, SearchDirection::Forward), Some(Position { x: 3, y: 0 }));
+        assert_eq!(find(4, 0, SearchDirection::Forward), Some(Position { x: 8, y: 1 }));
+        assert_eq!(find(9, 1, SearchDirection::Forward), Some(Position { x: 0, y: 3 }));
+        assert_eq!(find(1, 3, SearchDirection::Forward), None);
+
+        assert_eq!(find(0, 3, SearchDirection::Backward), Some(Position { x: 8, y: 1 }));
+        assert_eq!(find(11, 1, SearchDirection::Backward), Some(Position { x: 3, y: 0 }));
+        assert_eq!(find(12, 1, SearchDirection::Backward), Some(Position { x: 8, y: 1 }));
+        assert_eq!(find(3, 0, SearchDirection::Backward), None);
+
+        assert_eq!(doc.find("missing", &Position::default(), SearchDirection::Forward), None);
+        assert_eq!(doc.find("", &Position::default(), SearchDirection::Forward), None);
+        assert_eq!(find(0, 4, SearchDirection::Forward), None);
+    }
 }
\ No newline at end of file
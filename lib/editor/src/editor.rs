@@ -3,12 +3,22 @@ use crate::Document;
 use crate::Row;
 use termion::event::Key;
 use termion::color;
+use std::cmp;
 use std::env;
+use unicode_segmentation::UnicodeSegmentation;
 use std::time::Duration;
 use std::time::Instant;
 
 const STATUS_BACK_COLOR: color::Rgb = color::Rgb(239, 239, 239);
 const STATUS_FRONT_COLOR: color::Rgb = color::Rgb(63, 63, 63);
+const MATCH_BACK_COLOR: color::Rgb = color::Rgb(38, 139, 210);
+const GUTTER_FRONT_COLOR: color::Rgb = color::Rgb(128, 128, 128);
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SearchDirection {
+    Forward,
+    Backward,
+}
 
 pub struct Editor{
     should_quit: bool,
@@ -17,9 +27,12 @@ pub struct Editor{
     offset: Position,
     document: Document,
     status: Status,
+    // Length of the search match at the cursor, while searching
+    search_match: Option<usize>,
+    line_numbers: bool,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -54,10 +67,20 @@ impl Editor {
     }
 
     pub fn default() -> Self {
-        let args: Vec<String> = env::args().collect();
-        let mut initial_status = String::from("Press Ctrl-Q to quit or Ctrl-S to save");
-        let document = if args.len() > 1 {
-            let file_name = &args[1];
+        let mut line_numbers = false;
+        let mut file_name = None;
+        for arg in env::args().skip(1) {
+            match arg.as_str() {
+                "-n" | "--line-numbers" => line_numbers = true,
+                _ => {
+                    if file_name.is_none() {
+                        file_name = Some(arg);
+                    }
+                }
+            }
+        }
+        let mut initial_status = String::from("Press Ctrl-Q to quit, Ctrl-S to save or Ctrl-F to find");
+        let document = if let Some(file_name) = file_name {
             let doc = Document::open(&file_name);
             if doc.is_ok() {
                 doc.unwrap()
@@ -75,6 +98,8 @@ impl Editor {
             cursor_position: Position::default(),
             offset: Position::default(),
             status: Status::from(initial_status),
+            search_match: None,
+            line_numbers,
         }
     }
 
@@ -89,7 +114,7 @@ impl Editor {
             self.draw_status_bar();
             self.draw_message_bar();
             Terminal::cursor_position(&Position{
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
+                x: screen_column(self.cursor_position.x, self.offset.x, self.gutter_width()),
                 y: self.cursor_position.y.saturating_sub(self.offset.y),
             });
         }
@@ -97,17 +122,123 @@ impl Editor {
         Terminal::flush()
     }
 
+    fn prompt<C>(&mut self, prompt: &str, mut callback: C) -> Result<Option<String>, std::io::Error>
+    where
+        C: FnMut(&mut Self, Key, &str),
+    {
+        let mut result = String::new();
+        loop {
+            self.status = Status::from(format!("{}{}", prompt, result));
+            self.refresh_screen()?;
+            let key = Terminal::read_key()?;
+            match key {
+                Key::Backspace => {
+                    result.pop();
+                }
+                Key::Char('\n') => break,
+                Key::Char(c) => {
+                    if !c.is_control() {
+                        result.push(c);
+                    }
+                }
+                Key::Esc => {
+                    result.truncate(0);
+                    break;
+                }
+                _ => (),
+            }
+            callback(self, key, &result);
+        }
+        self.status = Status::from(String::new());
+        if result.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(result))
+    }
+
+    fn save_as(&mut self) {
+        let file_name = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
+        let file_name = match file_name {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => {
+                self.status = Status::from("Save aborted.".to_string());
+                return;
+            }
+        };
+        match self.document.save_as(&file_name) {
+            Ok(()) => self.status = Status::from("File saved successfully.".to_string()),
+            Err(error) => self.status = Status::from(format!("Error writing {}: {}", file_name, error)),
+        }
+    }
+
+    fn search(&mut self) {
+        let old_position = self.cursor_position.clone();
+        let old_offset = self.offset.clone();
+        let mut direction = SearchDirection::Forward;
+        let query = self
+            .prompt("Search (ESC to cancel, arrows to navigate): ", |editor, key, query| {
+                let mut moved = false;
+                match key {
+                    Key::Right | Key::Down => {
+                        direction = SearchDirection::Forward;
+                        editor.move_cursor(Key::Right);
+                        moved = true;
+                    }
+                    Key::Left | Key::Up => direction = SearchDirection::Backward,
+                    _ => direction = SearchDirection::Forward,
+                }
+                if let Some(position) = editor.document.find(query, &editor.cursor_position, direction) {
+                    editor.cursor_position = position;
+                    editor.scroll();
+                    editor.search_match = Some(query.graphemes(true).count());
+                } else {
+                    if moved {
+                        editor.move_cursor(Key::Left);
+                    }
+                    editor.search_match = None;
+                }
+            })
+            .unwrap_or(None);
+        self.search_match = None;
+        if query.is_none() {
+            self.cursor_position = old_position;
+            self.offset = old_offset;
+            self.scroll();
+        }
+    }
+
     fn process_keypress(&mut self) -> Result<(), std::io::Error> {
         let pressed_key = Terminal::read_key()?;
         match pressed_key {
             Key::Ctrl('q') => self.should_quit = true,
+            Key::Ctrl('f') => self.search(),
+            Key::Ctrl('n') => self.line_numbers = !self.line_numbers,
+            Key::Ctrl('t') => {
+                let message = if self.document.toggle_highlighting() {
+                    "Syntax highlighting on."
+                } else {
+                    "Syntax highlighting off."
+                };
+                self.status = Status::from(message.to_string());
+            }
+            Key::Ctrl('z') => match self.document.undo() {
+                Some(position) => self.cursor_position = position,
+                None => self.status = Status::from("Nothing to undo.".to_string()),
+            },
+            Key::Ctrl('y') => match self.document.redo() {
+                Some(position) => self.cursor_position = position,
+                None => self.status = Status::from("Nothing to redo.".to_string()),
+            },
             Key::Ctrl('s') => {
-                if self.document.save().is_ok() {
+                if self.document.file_name.is_none() {
+                    self.save_as();
+                } else if self.document.save().is_ok() {
                     self.status = Status::from("File saved successfully.".to_string());
                 } else {
                     self.status = Status::from("Error writing file!".to_string());
                 }
             }
+            Key::Alt('s') => self.save_as(),
             Key::Char(c) => {
                 self.document.insert(&self.cursor_position, c);
                 self.move_cursor(Key::Right);
@@ -128,7 +259,7 @@ impl Editor {
 
     fn scroll(&mut self) {
         let Position {x, y} = self.cursor_position;
-        let width = self.terminal.size().width as usize;
+        let width = self.text_width();
         let height = self.terminal.size().height as usize;
         let mut offset = &mut self.offset;
         if y < offset.y {
@@ -191,21 +322,67 @@ impl Editor {
         self.cursor_position = Position{x,y}
     }
 
+    fn gutter_width(&self) -> usize {
+        if self.line_numbers {
+            gutter_width(self.document.len())
+        } else {
+            0
+        }
+    }
+
+    fn text_width(&self) -> usize {
+        (self.terminal.size().width as usize).saturating_sub(self.gutter_width())
+    }
+
+    fn draw_gutter(&self, line: Option<usize>) {
+        let width = self.gutter_width();
+        if width == 0 {
+            return;
+        }
+        Terminal::set_front_color(GUTTER_FRONT_COLOR);
+        match line {
+            Some(line) => print!("{:>1$} ", line, width - 1),
+            None => print!("{}", " ".repeat(width)),
+        }
+        Terminal::reset_front_color();
+    }
+
     pub fn draw_row(&self, row: &Row) {
-        let width = self.terminal.size().width as usize;
+        let width = self.text_width();
         let start = self.offset.x;
         let end = self.offset.x + width;
         let row = row.render(start, end);
         println!("{}\r", row)
     }
 
+    fn draw_row_with_match(&self, row: &Row, at: usize, len: usize) {
+        let width = self.text_width();
+        let start = self.offset.x;
+        let end = self.offset.x + width;
+        let match_start = cmp::max(at, start);
+        let match_end = cmp::max(at + len, start);
+        print!("{}", row.render(start, cmp::min(match_start, end)));
+        Terminal::set_back_color(MATCH_BACK_COLOR);
+        print!("{}", row.render(match_start, cmp::min(match_end, end)));
+        Terminal::reset_back_color();
+        println!("{}\r", row.render(match_end, end))
+    }
+
     fn draw_rows(&self) {
         let height = self.terminal.size().height;
         for terminal_row in 0..height {
             Terminal::clear_current_line();
-            if let Some(row) = self.document.row(terminal_row as usize + self.offset.y) {
-                self.draw_row(row);
+            let index = terminal_row as usize + self.offset.y;
+            if let Some(row) = self.document.row(index) {
+                self.draw_gutter(Some(index + 1));
+                match self.search_match {
+                    Some(len) if index == self.cursor_position.y => {
+                        self.draw_row_with_match(row, self.cursor_position.x, len)
+                    }
+                    _ => self.draw_row(row),
+                }
             } else {
+                self.draw_gutter(None);
                 println!("~\r");
             }
         }
@@ -219,7 +396,12 @@ impl Editor {
             file_name = name.clone();
             file_name.truncate(20);
         }
-        status = format!("{} - {} lines", file_name, self.document.len());
+        let modified_indicator = if self.document.is_dirty() {
+            " (modified)"
+        } else {
+            ""
+        };
+        status = format!("{} - {} lines{}", file_name, self.document.len(), modified_indicator);
         let line_indicator = format!(
             "{}/{}", self.cursor_position.y.saturating_add(1), self.document.len()
         );
@@ -247,7 +429,43 @@ impl Editor {
     }
 }
 
+fn gutter_width(line_count: usize) -> usize {
+    // The widest line number, and a space after it
+    let mut digits = 1;
+    let mut rest = line_count / 10;
+    while rest > 0 {
+        digits += 1;
+        rest /= 10;
+    }
+    digits + 1
+}
+
+fn screen_column(x: usize, offset_x: usize, gutter_width: usize) -> usize {
+    x.saturating_sub(offset_x).saturating_add(gutter_width)
+}
+
 fn die(e: std::io::Error) {
     Terminal::clear_screen();
     panic!(e);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gutter_width, screen_column};
+
+    #[test]
+    fn gutter_grows_with_line_count() {
+        assert_eq!(gutter_width(0), 2);
+        assert_eq!(gutter_width(9), 2);
+        assert_eq!(gutter_width(10), 3);
+        assert_eq!(gutter_width(99), 3);
+        assert_eq!(gutter_width(100), 4);
+        assert_eq!(gutter_width(12_345), 6);
+
+        // Text starts right after the gutter, whatever the scroll offset
+        assert_eq!(screen_column(0, 0, 0), 0);
+        assert_eq!(screen_column(0, 0, gutter_width(120)), 4);
+        assert_eq!(screen_column(10, 4, gutter_width(120)), 10);
+        assert_eq!(screen_column(7, 7, gutter_width(5)), 2);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+use termion::color;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Type {
+    None,
+    Number,
+    String,
+    Comment,
+    Keyword,
+}
+
+impl Type {
+    pub fn to_color(self) -> Option<color::Rgb> {
+        match self {
+            Type::None => None,
+            Type::Number => Some(color::Rgb(220, 163, 163)),
+            Type::String => Some(color::Rgb(211, 54, 130)),
+            Type::Comment => Some(color::Rgb(133, 153, 0)),
+            Type::Keyword => Some(color::Rgb(181, 137, 0)),
+        }
+    }
+}
+
+// Graphemes `start..end` of a row are of type `kind`
+#[derive(PartialEq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub kind: Type,
+}
+
+pub trait Highlighter {
+    fn highlight(&self, line: &str) -> Vec<Span>;
+}
+
+pub fn for_file(file_name: &str) -> Option<Box<dyn Highlighter>> {
+    if file_name.ends_with(".rs") {
+        Some(Box::new(Rust))
+    } else {
+        None
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+
+pub struct Rust;
+
+impl Highlighter for Rust {
+    fn highlight(&self, line: &str) -> Vec<Span> {
+        let graphemes: Vec<&str> = line.graphemes(true).collect();
+        let is_word = |index: usize| {
+            match graphemes.get(index) {
+                Some(g) => g.chars().all(|c| c.is_alphanumeric() || c == '_'),
+                None => false,
+            }
+        };
+        let is = |index: usize, text: &str| graphemes.get(index) == Some(&text);
+        let mut spans = Vec::new();
+        let mut i = 0;
+        while i < graphemes.len() {
+            let start = i;
+            let kind = if is(i, "/") && is(i + 1, "/") {
+                i = graphemes.len();
+                Type::Comment
+            } else if is(i, "/") && is(i + 1, "*") {
+                i += 2;
+                while i < graphemes.len() && !(is(i, "*") && is(i + 1, "/")) {
+                    i += 1;
+                }
+                i = (i + 2).min(graphemes.len());
+                Type::Comment
+            } else if is(i, "\"") {
+                i += 1;
+                while i < graphemes.len() && !is(i, "\"") {
+                    i += if is(i, "\\") { 2 } else { 1 };
+                }
+                i = (i + 1).min(graphemes.len());
+                Type::String
+            } else if is(i, "'") && (is(i + 2, "'") || (is(i + 1, "\\") && is(i + 3, "'"))) {
+                // A character literal rather than a lifetime
+                i += if is(i + 2, "'") { 3 } else { 4 };
+                Type::String
+            } else if graphemes[i].chars().all(|c| c.is_ascii_digit()) && (i == 0 || !is_word(i - 1)) {
+                let is_digits = |index: usize| match graphemes.get(index) {
+                    Some(g) => g.chars().all(|c| c.is_ascii_digit()),
+                    None => false,
+                };
+                while is_word(i) || (is(i, ".") && is_digits(i + 1)) {
+                    i += 1;
+                }
+                Type::Number
+            } else if is_word(i) {
+                while is_word(i) {
+                    i += 1;
+                }
+                let word: String = graphemes[start..i].concat();
+                if RUST_KEYWORDS.contains(&&word[..]) {
+                    Type::Keyword
+                } else {
+                    continue;
+                }
+            } else {
+                i += 1;
+                continue;
+            };
+            spans.push(Span { start, end: i, kind });
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Highlighter, Rust, Span, Type};
+
+    fn span(start: usize, end: usize, kind: Type) -> Span {
+        Span { start, end, kind }
+    }
+
+    #[test]
+    fn rust_line_spans() {
+        let line = r#"let mut x2 = "a \"b\"" + 1.5 + 0..10; // fn "not code""#;
+        assert_eq!(Rust.highlight(line), vec![
+            span(0, 3, Type::Keyword),
+            span(4, 7, Type::Keyword),
+            span(13, 22, Type::String),
+            span(25, 28, Type::Number),
+            span(31, 32, Type::Number),
+            span(34, 36, Type::Number),
+            span(38, 54, Type::Comment),
+        ]);
+
+        // Keywords inside identifiers, lifetimes and block comments
+        let line = "fn r#fnord<'a>(c: char) /* 'x' */ -> u8 { b'\\n' }";
+        assert_eq!(Rust.highlight(line), vec![
+            span(0, 2, Type::Keyword),
+            span(24, 33, Type::Comment),
+            span(43, 47, Type::String),
+        ]);
+
+        assert_eq!(Rust.highlight(""), vec![]);
+        assert_eq!(Rust.highlight("\"unterminated"), vec![span(0, 13, Type::String)]);
+    }
+}
@@ -2,7 +2,9 @@ mod editor;
 mod terminal;
 mod document;
 mod row;
+mod highlighting;
 pub use editor::Position;
+pub use editor::SearchDirection;
 pub use document::Document;
 pub use row::Row;
 pub use terminal::Terminal;
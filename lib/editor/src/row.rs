@@ -1,9 +1,13 @@
+use crate::highlighting::{self, Highlighter};
+use crate::SearchDirection;
 use std::cmp;
+use termion::color;
 use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Default)]
 pub struct Row {
     string: String,
+    highlighting: Vec<highlighting::Type>,
     len: usize,
 }
 
@@ -11,6 +15,7 @@ impl From<&str> for Row {
     fn from(slice: &str) -> Self {
         let mut row = Self {
             string: String::from(slice),
+            highlighting: Vec::new(),
             len: 0,
         };
         row.update_len();
@@ -23,15 +28,39 @@ impl Row {
         let end = cmp::min(end, self.string.len());
         let start = cmp::min(start, end);
         let mut result = String::new();
-        for string in self.string[..].graphemes(true).skip(start).take(end - start) {
+        let mut current = highlighting::Type::None;
+        for (index, string) in self.string[..].graphemes(true).enumerate().skip(start).take(end - start) {
+            let highlighting_type = self.highlighting.get(index).cloned().unwrap_or(highlighting::Type::None);
+            if highlighting_type != current {
+                match highlighting_type.to_color() {
+                    Some(rgb) => result.push_str(&format!("{}", color::Fg(rgb))),
+                    None => result.push_str(&format!("{}", color::Fg(color::Reset))),
+                }
+                current = highlighting_type;
+            }
             if string == "\t" {
                 result.push_str(" ");
             } else {
                 result.push_str(string);
             }
         }
+        if current != highlighting::Type::None {
+            result.push_str(&format!("{}", color::Fg(color::Reset)));
+        }
         result
     }
+    pub fn highlight(&mut self, highlighter: Option<&dyn Highlighter>) {
+        self.highlighting.clear();
+        if let Some(highlighter) = highlighter {
+            self.highlighting.resize(self.len, highlighting::Type::None);
+            for span in highlighter.highlight(&self.string) {
+                let end = cmp::min(span.end, self.len);
+                for highlighting_type in &mut self.highlighting[cmp::min(span.start, end)..end] {
+                    *highlighting_type = span.kind;
+                }
+            }
+        }
+    }
     pub fn len(&self) -> usize {
         self.len
     }
@@ -46,7 +75,7 @@ impl Row {
             self.string.push(c);
         } else {
             let mut result: String = self.string[..].graphemes(true).take(location).collect();
-            let remainder: String = self.string[..].graphemes(true).take(location).collect();
+            let remainder: String = self.string[..].graphemes(true).skip(location).collect();
             result.push(c);
             result.push_str(&remainder);
             self.string = result;
@@ -75,7 +104,30 @@ impl Row {
         self.update_len();
         Self::from(&remainder[..])
     }
+    pub fn grapheme(&self, index: usize) -> Option<&str> {
+        self.string[..].graphemes(true).nth(index)
+    }
     pub fn as_bytes(&self) -> &[u8] {
         self.string.as_bytes()
     }
+    pub fn find(&self, query: &str, at: usize, direction: SearchDirection) -> Option<usize> {
+        if at > self.len || query.is_empty() {
+            return None;
+        }
+        let (start, end) = match direction {
+            SearchDirection::Forward => (at, self.len),
+            SearchDirection::Backward => (0, at),
+        };
+        let substring: String = self.string[..].graphemes(true).skip(start).take(end - start).collect();
+        let matching_byte_index = match direction {
+            SearchDirection::Forward => substring.find(query),
+            SearchDirection::Backward => substring.rfind(query),
+        }?;
+        for (grapheme_index, (byte_index, _)) in substring[..].grapheme_indices(true).enumerate() {
+            if matching_byte_index == byte_index {
+                return Some(start + grapheme_index);
+            }
+        }
+        None
+    }
 }
\ No newline at end of file
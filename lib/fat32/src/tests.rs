@@ -558,3 +558,263 @@ fn test_write_file() {
     assert_eq!(test_file.read(&mut read_buf).unwrap(), final_buf.len());
     assert_eq!(read_buf, final_buf);
 }
+
+#[test]
+// depends on working file creation
+fn test_rename_file() {
+    use shim::io::Write;
+    use shim::io::Read;
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    // Clean up after any earlier run so the names are free
+    for path in &["/rename_src.txt", "/rename_dst.txt"] {
+        if let Ok(entry) = vfat.open(path) {
+            entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+        }
+    }
+
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let created = root.create(vfat::Metadata {
+        name: String::from("rename_src.txt"),
+        ..Default::default()
+    }).expect("Couldn't create /rename_src.txt");
+    let contents = "moved without copying\n".as_bytes();
+    let mut file = created.into_file().expect("couldn't open /rename_src.txt as file");
+    assert_eq!(file.write(contents).unwrap(), contents.len());
+    let start = file.start;
+
+    let entry = vfat.open("/rename_src.txt").expect("couldn't open /rename_src.txt");
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let renamed = root.rename(entry, "rename_dst.txt").expect("couldn't rename");
+    assert_eq!(renamed.name(), "rename_dst.txt");
+
+    expect_variant!(vfat.open("/rename_src.txt"), Err(ref e) if e.kind() == io::ErrorKind::NotFound);
+    let mut moved = vfat.open_file("/rename_dst.txt").expect("couldn't open /rename_dst.txt");
+    assert_eq!(moved.start, start);
+    let mut read_buf = vec![0u8; contents.len()];
+    moved.read_exact(&mut read_buf).expect("couldn't read renamed file");
+    assert_eq!(&read_buf[..], contents);
+
+    moved.delete().expect("couldn't clean up /rename_dst.txt");
+}
+
+#[test]
+// depends on working file and directory creation
+fn test_disk_usage() {
+    use shim::io::Write;
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    // Only build the tree once, so the image doesn't grow on every run
+    if vfat.open("/du_test").is_err() {
+        let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+        let mut du_test = root.create(vfat::Metadata {
+            name: String::from("du_test"),
+            attributes: vfat::Attributes::default_dir(),
+            ..Default::default()
+        }).expect("Couldn't create /du_test").into_dir().expect("expected a directory");
+
+        let mut sub = du_test.create(vfat::Metadata {
+            name: String::from("sub"),
+            attributes: vfat::Attributes::default_dir(),
+            ..Default::default()
+        }).expect("Couldn't create /du_test/sub").into_dir().expect("expected a directory");
+
+        let create_file = |dir: &mut vfat::Dir<StdVFatHandle>, name: &str, size: usize| {
+            let mut file = dir.create(vfat::Metadata {
+                name: String::from(name),
+                ..Default::default()
+            }).expect("Couldn't create file").into_file().expect("expected a file");
+            file.write_all(&vec![b'x'; size]).expect("Couldn't write file");
+        };
+        create_file(&mut du_test, "a.txt", 100);
+        create_file(&mut sub, "b.txt", 1000);
+    }
+
+    let entry = vfat.open("/du_test").expect("Couldn't open /du_test");
+    let mut totals = Vec::new();
+    let total = disk_usage(&entry, "/du_test", None, &mut |path, size| totals.push((String::from(path), size)))
+        .expect("Couldn't walk /du_test");
+    assert_eq!(total, 1100);
+    assert_eq!(totals, vec![(String::from("/du_test/sub"), 1000), (String::from("/du_test"), 1100)]);
+
+    let cluster = vfat.lock(|vfat: &mut VFat<StdVFatHandle>| vfat.bytes_per_cluster()) as u64;
+    let on_disk = disk_usage(&entry, "/du_test", Some(cluster), &mut |_, _| ()).expect("Couldn't walk /du_test");
+    let round_up = |size: u64| (size + cluster - 1) / cluster * cluster;
+    assert_eq!(on_disk, round_up(100) + round_up(1000));
+}
+
+#[test]
+// depends on working file creation and deletion
+fn test_free_clusters() {
+    use shim::io::Write;
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    if let Ok(entry) = vfat.open("/free_clusters.txt") {
+        entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+    }
+
+    let count = |vfat: &StdVFatHandle| vfat.lock(|vfat: &mut VFat<StdVFatHandle>| {
+        (vfat.total_clusters(), vfat.free_clusters().expect("couldn't read the FAT"), vfat.bytes_per_cluster())
+    });
+    let (total, free_before, cluster) = count(&vfat);
+    assert!(free_before > 3 && free_before < total);
+
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let mut file = root.create(vfat::Metadata {
+        name: String::from("free_clusters.txt"),
+        ..Default::default()
+    }).expect("Couldn't create /free_clusters.txt").into_file().expect("expected a file");
+    file.write_all(&vec![b'x'; 3 * cluster]).expect("Couldn't write /free_clusters.txt");
+
+    // The root directory may have needed another cluster for the new entry
+    let (_, free_after, _) = count(&vfat);
+    assert!(free_before - free_after >= 3 && free_before - free_after <= 4,
+        "{} clusters were allocated for a 3 cluster file", free_before - free_after);
+
+    file.delete().expect("couldn't clean up /free_clusters.txt");
+    let (_, free_deleted, _) = count(&vfat);
+    assert_eq!(free_deleted, free_after + 3);
+}
+
+#[test]
+// depends on working file creation
+fn test_stat() {
+    use shim::io::Write;
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    if let Ok(entry) = vfat.open("/stat_test.txt") {
+        entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+    }
+
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let created = root.create(vfat::Metadata {
+        name: String::from("stat_test.txt"),
+        ..Default::default()
+    }).expect("Couldn't create /stat_test.txt");
+    let contents = "known contents\n".as_bytes();
+    let mut file = created.into_file().expect("couldn't open /stat_test.txt as file");
+    assert_eq!(file.write(contents).unwrap(), contents.len());
+
+    let entry = vfat.open("/stat_test.txt").expect("couldn't open /stat_test.txt");
+    let buf = stat(&entry);
+    assert_eq!(buf.size, contents.len() as u64);
+    assert_eq!(buf.kind(), kernel_api::EntryKind::File);
+    assert_eq!(buf.attributes, 0);
+
+    let root = vfat.open("/").expect("couldn't open /");
+    let buf = stat(&root);
+    assert_eq!(buf.size, 0);
+    assert_eq!(buf.kind(), kernel_api::EntryKind::Dir);
+
+    entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+}
+
+#[test]
+// depends on working file creation
+fn test_link_file() {
+    use shim::io::{Read, Seek, SeekFrom, Write};
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    for path in &["/link_src.txt", "/link_dst.txt"] {
+        if let Ok(entry) = vfat.open(path) {
+            entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+        }
+    }
+
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let created = root.create(vfat::Metadata {
+        name: String::from("link_src.txt"),
+        ..Default::default()
+    }).expect("Couldn't create /link_src.txt");
+    let mut file = created.into_file().expect("couldn't open /link_src.txt as file");
+    file.write_all(b"hello through src\n").expect("couldn't write /link_src.txt");
+
+    // Only linked entries are flagged, so only they look for other links
+    let linked = |file: &vfat::File<StdVFatHandle>| vfat.lock(|vfat: &mut VFat<StdVFatHandle>| {
+        vfat::dir::VFatRegularDirEntry::is_linked(vfat, file.entry.unwrap().end)
+    }).expect("couldn't read the entry's flags");
+    assert!(!linked(&file));
+
+    let src = vfat.open_file("/link_src.txt").expect("couldn't open /link_src.txt");
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    root.link(&src, "link_dst.txt").expect("couldn't link");
+    expect_variant!(root.link(&src, "link_dst.txt"), Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists);
+    assert!(linked(&src));
+    assert!(linked(&vfat.open_file("/link_dst.txt").expect("couldn't open /link_dst.txt")));
+    // Handles opened before the link keep the flag when they write
+    file.update_entry().expect("couldn't update /link_src.txt");
+    assert!(linked(&src));
+
+    // Overwrite through one name and read through the other
+    let mut dst = vfat.open_file("/link_dst.txt").expect("couldn't open /link_dst.txt");
+    assert_eq!(dst.start, src.start);
+    dst.seek(SeekFrom::Start(14)).expect("couldn't seek");
+    dst.write_all(b"dst\n").expect("couldn't write /link_dst.txt");
+
+    let mut src = vfat.open_file("/link_src.txt").expect("couldn't open /link_src.txt");
+    let mut contents = String::new();
+    src.read_to_string(&mut contents).expect("couldn't read /link_src.txt");
+    assert_eq!(contents, "hello through dst\n");
+
+    // The contents outlive the first name
+    src.delete().expect("couldn't delete /link_src.txt");
+    let mut dst = vfat.open_file("/link_dst.txt").expect("couldn't open /link_dst.txt");
+    let mut contents = String::new();
+    dst.read_to_string(&mut contents).expect("couldn't read /link_dst.txt");
+    assert_eq!(contents, "hello through dst\n");
+    let free_before = vfat.lock(|vfat: &mut VFat<StdVFatHandle>| vfat.free_clusters()).unwrap();
+    dst.delete().expect("couldn't delete /link_dst.txt");
+    let free_after = vfat.lock(|vfat: &mut VFat<StdVFatHandle>| vfat.free_clusters()).unwrap();
+    assert_eq!(free_after, free_before + 1);
+}
+
+#[test]
+// depends on working file creation and deletion
+fn test_set_len_file() {
+    use shim::io::{Read, Seek, SeekFrom, Write};
+
+    let vfat = vfat_from_resource!("mock2.fat32.img");
+    if let Ok(entry) = vfat.open("/set_len.bin") {
+        entry.into_file().expect("expected a file").delete().expect("couldn't clean up");
+    }
+    let cluster = vfat.lock(|vfat: &mut VFat<StdVFatHandle>| vfat.bytes_per_cluster());
+    let free = |vfat: &StdVFatHandle| vfat.lock(|vfat: &mut VFat<StdVFatHandle>| vfat.free_clusters()).unwrap();
+
+    // Write a large image, then a small one over it, like the bootloader does
+    let mut root = vfat.open_dir("/").expect("Couldn't get / as dir");
+    let mut file = root.create(vfat::Metadata {
+        name: String::from("set_len.bin"),
+        ..Default::default()
+    }).expect("Couldn't create /set_len.bin").into_file().expect("expected a file");
+    file.write_all(&vec![b'L'; 3 * cluster + 10]).expect("couldn't write the large image");
+    let free_large = free(&vfat);
+
+    let small = vec![b's'; cluster + 5];
+    let mut file = vfat.open_file("/set_len.bin").expect("couldn't open /set_len.bin");
+    file.write_all(&small).expect("couldn't write the small image");
+    file.set_len(small.len() as u64).expect("couldn't truncate");
+    assert_eq!(free(&vfat), free_large + 2);
+
+    let mut file = vfat.open_file("/set_len.bin").expect("couldn't open /set_len.bin");
+    assert_eq!(file.size(), small.len() as u64);
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).expect("couldn't read /set_len.bin");
+    assert_eq!(contents, small);
+
+    // Extending fills with zeros and keeps the position
+    file.seek(SeekFrom::Start(1)).expect("couldn't seek");
+    file.set_len(small.len() as u64 + 3).expect("couldn't extend");
+    assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 1);
+    let mut file = vfat.open_file("/set_len.bin").expect("couldn't open /set_len.bin");
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).expect("couldn't read /set_len.bin");
+    assert_eq!(&contents[..small.len()], &small[..]);
+    assert_eq!(&contents[small.len()..], &[0, 0, 0]);
+
+    // Truncating to nothing frees every cluster
+    file.set_len(0).expect("couldn't truncate to 0");
+    assert_eq!(free(&vfat), free_large + 4);
+    assert_eq!(vfat.open_file("/set_len.bin").unwrap().size(), 0);
+
+    file.delete().expect("couldn't clean up /set_len.bin");
+}
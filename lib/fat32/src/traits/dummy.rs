@@ -37,6 +37,9 @@ impl File for Dummy {
     fn delete(&mut self) -> io::Result<()> {
         panic!("Dummy")
     }
+    fn set_len(&mut self, _len: u64) -> io::Result<()> {
+        panic!("Dummy")
+    }
 }
 
 /// Trait implemented by directories in a file system.
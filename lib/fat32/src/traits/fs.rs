@@ -11,6 +11,10 @@ pub trait File: io::Read + io::Write + io::Seek + Sized {
 
     /// Delete the file & its contents from the filesystem
     fn delete(&mut self) -> io::Result<()>;
+
+    /// Truncates or extends the file to `len` bytes. Bytes added by
+    /// extending it read as zeros.
+    fn set_len(&mut self, len: u64) -> io::Result<()>;
 }
 
 /// Trait implemented by directories in a file system.
@@ -1,8 +1,12 @@
 mod dummy;
 mod fs;
 mod metadata;
+mod stat;
+mod usage;
 
 pub use blockdev::block_device::BlockDevice;
 pub use self::dummy::Dummy;
 pub use self::fs::{Dir, Entry, File, FileSystem};
 pub use self::metadata::{Metadata, Timestamp};
+pub use self::stat::stat;
+pub use self::usage::disk_usage;
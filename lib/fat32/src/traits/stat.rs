@@ -0,0 +1,37 @@
+use kernel_api::{DateTime, EntryKind, StatBuf, STAT_HIDDEN, STAT_READ_ONLY};
+
+use crate::traits::{Entry, File, Metadata, Timestamp};
+
+fn date_time<T: Timestamp>(ts: T) -> DateTime {
+    DateTime {
+        year: ts.year() as u64,
+        month: ts.month(),
+        day: ts.day(),
+        hour: ts.hour(),
+        minute: ts.minute(),
+        second: ts.second(),
+    }
+}
+
+/// Returns the metadata of `entry` in the layout `SYS_FS_METADATA` hands to
+/// user programs.
+pub fn stat<E: Entry>(entry: &E) -> StatBuf {
+    let meta = entry.metadata();
+
+    let mut attributes = 0;
+    if meta.read_only() {
+        attributes |= STAT_READ_ONLY;
+    }
+    if meta.hidden() {
+        attributes |= STAT_HIDDEN;
+    }
+
+    let kind = if entry.is_dir() { EntryKind::Dir } else { EntryKind::File };
+    StatBuf {
+        size: entry.as_file().map(|file| file.size()).unwrap_or(0),
+        kind: kind.as_u64(),
+        attributes,
+        created: date_time(meta.created()),
+        modified: date_time(meta.modified()),
+    }
+}
@@ -0,0 +1,48 @@
+use alloc::string::String;
+use shim::io;
+
+use crate::traits::{Dir, Entry, File};
+
+/// Returns the total size in bytes of the files under `entry`, or of `entry`
+/// itself if it is a file. With `cluster_size`, each file's size is rounded up
+/// to whole clusters, which is the space it actually takes up on disk.
+/// Directories' own entries aren't counted.
+///
+/// `visit` is called with the path and total of every directory, children
+/// before their parent, where `path` names `entry`. `.` and `..` are skipped.
+pub fn disk_usage<E>(
+    entry: &E,
+    path: &str,
+    cluster_size: Option<u64>,
+    visit: &mut dyn FnMut(&str, u64),
+) -> io::Result<u64>
+where
+    E: Entry,
+    E::Dir: Dir<Entry = E>,
+{
+    let dir = match entry.as_dir() {
+        Some(dir) => dir,
+        None => {
+            let size = entry.as_file().map(|file| file.size()).unwrap_or(0);
+            return Ok(match cluster_size {
+                Some(0) | None => size,
+                Some(cluster) => (size + cluster - 1) / cluster * cluster,
+            });
+        }
+    };
+
+    let mut total = 0;
+    for child in dir.entries()? {
+        let name = child.name();
+        if name == "." || name == ".." {
+            continue;
+        }
+
+        let mut child_path = String::from(path.trim_end_matches('/'));
+        child_path.push('/');
+        child_path.push_str(name);
+        total += disk_usage(&child, &child_path, cluster_size, visit)?;
+    }
+    visit(path, total);
+    Ok(total)
+}
@@ -29,7 +29,7 @@ pub struct VFatRegularDirEntry {
     name: [u8; 8],
     ext:  [u8; 3],
     attrs: Attributes,
-    __r0: u8,
+    flags: u8,
     created_millis: u8,
     created: Timestamp,
     last_accessed: Date,
@@ -40,23 +40,59 @@ pub struct VFatRegularDirEntry {
 }
 const_assert_size!(VFatRegularDirEntry, 32);
 
-impl<HANDLE: VFatHandle> From<&File<HANDLE>> for VFatRegularDirEntry {
-    fn from(file: &File<HANDLE>) -> VFatRegularDirEntry {
-        let (name, ext) = get_short_name(file.meta.name.clone());
+/// Set in `VFatRegularDirEntry::flags`, a byte FAT reserves, once `Dir::link`
+/// has pointed a second entry at the same contents. Only entries with the
+/// flag need to look for other links before freeing or truncating their
+/// contents.
+const LINKED: u8 = 1 << 7;
+/// Offset of `flags` in a `VFatRegularDirEntry`.
+const FLAGS_OFFSET: usize = 12;
+
+impl VFatRegularDirEntry {
+    /// Returns the entry describing `meta` with its contents at `start`.
+    fn new(meta: &Metadata, start: Cluster) -> VFatRegularDirEntry {
+        let (name, ext) = get_short_name(meta.name.clone());
         VFatRegularDirEntry {
             name,
             ext,
-            attrs: file.meta.attributes,
-            __r0: 0,
+            attrs: meta.attributes,
+            flags: 0,
             created_millis: 0, // force this field to 0 for now
-            created: file.meta.created,
-            last_accessed: file.meta.accessed.date,
-            cluster_high: (file.start.num() >> 16) as u16,
-            modified: file.meta.modified,
-            cluster_low: (file.start.num() & 0xFFFF) as u16,
-            size: file.meta.size as u32
+            created: meta.created,
+            last_accessed: meta.accessed.date,
+            cluster_high: (start.num() >> 16) as u16,
+            modified: meta.modified,
+            cluster_low: (start.num() & 0xFFFF) as u16,
+            size: meta.size as u32
         }
     }
+
+    /// Returns `true` if the regular entry at `pos` has been linked to.
+    pub fn is_linked<HANDLE: VFatHandle>(vfat: &mut VFat<HANDLE>, pos: Pos) -> io::Result<bool> {
+        let mut flags = [0u8];
+        vfat.read_cluster(pos.cluster, pos.offset + FLAGS_OFFSET, &mut flags)?;
+        Ok(flags[0] & LINKED != 0)
+    }
+
+    /// Marks the regular entry at `pos` as linked to.
+    fn mark_linked<HANDLE: VFatHandle>(vfat: &mut VFat<HANDLE>, pos: Pos) -> io::Result<()> {
+        let mut flags = [0u8];
+        vfat.read_cluster(pos.cluster, pos.offset + FLAGS_OFFSET, &mut flags)?;
+        flags[0] |= LINKED;
+        vfat.write_cluster(pos.cluster, pos.offset + FLAGS_OFFSET, &flags)?;
+        Ok(())
+    }
+
+    /// Sets the flags of this entry to mark it as linked to or not.
+    pub fn set_linked(&mut self, linked: bool) {
+        self.flags = if linked { LINKED } else { 0 };
+    }
+}
+
+impl<HANDLE: VFatHandle> From<&File<HANDLE>> for VFatRegularDirEntry {
+    fn from(file: &File<HANDLE>) -> VFatRegularDirEntry {
+        VFatRegularDirEntry::new(&file.meta, file.start)
+    }
 }
 
 #[repr(C, packed)]
@@ -152,6 +188,179 @@ impl<HANDLE: VFatHandle> Dir<HANDLE> {
         ioerr!(NotFound, "Could not find entry with provided name")
     }
 
+    /// Moves `entry` into `self` under the name `name`. Only the directory
+    /// entries are rewritten: the contents aren't copied and the rest of the
+    /// metadata is kept.
+    ///
+    /// # Errors
+    ///
+    /// If an entry named `name` already exists in `self`, an error of
+    /// `AlreadyExists` is returned.
+    ///
+    /// If `entry` has no directory entry of its own, i.e. it is the root
+    /// directory, an error of `InvalidInput` is returned.
+    pub fn rename(&mut self, entry: Entry<HANDLE>, name: &str) -> io::Result<Entry<HANDLE>> {
+        use crate::util::SliceExt;
+
+        let (start, mut meta, old_range) = match entry {
+            Entry::File(file) => (file.start, file.meta, file.entry),
+            Entry::Dir(dir) => (dir.start, dir.meta, dir.entry),
+        };
+        let (old_start, old_end) = match old_range {
+            Some(Range { start, end }) => (start, end),
+            None => return ioerr!(InvalidInput, "Cannot move the root directory"),
+        };
+        if self.find(name).is_ok() {
+            return ioerr!(AlreadyExists, "An entry with that name already exists");
+        }
+        meta.name = String::from(name);
+
+        // Create the new entries as an empty file so that no cluster gets
+        // allocated, then point them at the existing contents
+        let placeholder = traits::Dir::create(self, Metadata {
+            name: meta.name.clone(),
+            ..Default::default()
+        })?;
+        let new_range = match placeholder {
+            Entry::File(File { entry: Some(range), .. }) => range,
+            _ => return ioerr!(Other, "Couldn't create the new directory entry"),
+        };
+
+        let mut reg_entry = VFatRegularDirEntry::new(&meta, start);
+        // The root directory is referred to as cluster 0 from `..`
+        let parent = match self.entry {
+            Some(_) => self.start,
+            None => Cluster::from(0),
+        };
+        self.vfat.lock(|vfat: &mut VFat<HANDLE>| -> io::Result<()> {
+            // The moved entry still shares its contents with any links
+            reg_entry.set_linked(VFatRegularDirEntry::is_linked(vfat, old_end)?);
+            let reg_entry_buf: &[u8] = unsafe { &core::mem::transmute::<VFatRegularDirEntry, [u8; 32]>(reg_entry) };
+            vfat.write_cluster(new_range.end.cluster, new_range.end.offset, reg_entry_buf)?;
+
+            // A moved directory's `..` has to point at its new parent
+            if meta.attributes.is_dir() {
+                let mut buf = vec![0u8; 2 * 32];
+                vfat.read_cluster(start, 0, &mut buf)?;
+                {
+                    let entries = unsafe { buf.cast_mut::<VFatRegularDirEntry>() };
+                    entries[1].cluster_high = (parent.num() >> 16) as u16;
+                    entries[1].cluster_low = (parent.num() & 0xFFFF) as u16;
+                }
+                vfat.write_cluster(start, 0, &buf)?;
+            }
+
+            Dir::invalidate_entries(vfat, old_start)
+        })?;
+
+        if meta.attributes.is_dir() {
+            Ok(Entry::Dir(Dir {
+                vfat: self.vfat.clone(),
+                start,
+                meta,
+                entry: Some(new_range),
+            }))
+        } else {
+            Ok(Entry::File(File {
+                vfat: self.vfat.clone(),
+                start,
+                meta,
+                entry: Some(new_range),
+                pos: Pos {
+                    cluster: start,
+                    offset: 0,
+                },
+                amt_read: 0,
+            }))
+        }
+    }
+
+    /// Adds a second directory entry named `name` to `self` that shares the
+    /// contents of `file`, as a best-effort hard link.
+    ///
+    /// FAT has no link counts, so the entries aren't tied together: each keeps
+    /// its own size and timestamps, and data appended through one name is only
+    /// visible in full through that name. Both entries are flagged as linked,
+    /// and deleting a flagged entry keeps the contents as long as another
+    /// entry still points at them; see `Dir::count_links`.
+    ///
+    /// # Errors
+    ///
+    /// If an entry named `name` already exists in `self`, an error of
+    /// `AlreadyExists` is returned.
+    ///
+    /// If `file` is empty, there are no contents to share and an error of
+    /// `InvalidInput` is returned.
+    pub fn link(&mut self, file: &File<HANDLE>, name: &str) -> io::Result<Entry<HANDLE>> {
+        if file.start.num() == 0 {
+            return ioerr!(InvalidInput, "Can't link an empty file");
+        }
+        if self.find(name).is_ok() {
+            return ioerr!(AlreadyExists, "An entry with that name already exists");
+        }
+        let mut meta = file.meta.clone();
+        meta.name = String::from(name);
+
+        // Like `rename`, create an empty placeholder and point it at the
+        // contents, but leave the original entries in place
+        let placeholder = traits::Dir::create(self, Metadata {
+            name: meta.name.clone(),
+            ..Default::default()
+        })?;
+        let new_range = match placeholder {
+            Entry::File(File { entry: Some(range), .. }) => range,
+            _ => return ioerr!(Other, "Couldn't create the new directory entry"),
+        };
+
+        // Both entries are flagged, so deleting or truncating either one
+        // looks for the other first
+        let mut reg_entry = VFatRegularDirEntry::new(&meta, file.start);
+        reg_entry.set_linked(true);
+        let reg_entry_buf: &[u8] = unsafe { &core::mem::transmute::<VFatRegularDirEntry, [u8; 32]>(reg_entry) };
+        self.vfat.lock(|vfat: &mut VFat<HANDLE>| -> io::Result<()> {
+            if let Some(Range { end, .. }) = file.entry {
+                VFatRegularDirEntry::mark_linked(vfat, end)?;
+            }
+            vfat.write_cluster(new_range.end.cluster, new_range.end.offset, reg_entry_buf)?;
+            Ok(())
+        })?;
+
+        Ok(Entry::File(File {
+            vfat: self.vfat.clone(),
+            start: file.start,
+            meta,
+            entry: Some(new_range),
+            pos: Pos {
+                cluster: file.start,
+                offset: 0,
+            },
+            amt_read: 0,
+        }))
+    }
+
+    /// Returns how many file entries under `self`, including in its
+    /// subdirectories, have their contents starting at cluster `start`.
+    ///
+    /// This reads every directory below `self`, so it's only worth calling for
+    /// entries flagged by `Dir::link`.
+    pub fn count_links(&self, start: Cluster) -> io::Result<usize> {
+        use traits::Dir;
+
+        let mut count = 0;
+        for entry in self.entries()? {
+            match entry {
+                Entry::File(file) if file.start == start => count += 1,
+                Entry::File(_) => (),
+                Entry::Dir(dir) => {
+                    if dir.meta.name != "." && dir.meta.name != ".." {
+                        count += dir.count_links(start)?;
+                    }
+                },
+            }
+        }
+        Ok(count)
+    }
+
     fn get_start_pos(&mut self, prev_index: usize) -> io::Result<Pos> {
         /* Oh no, what if we're at the end of an EOC? Where do we write the new entry? write_chain_pos
            won't work because it's starting in an undefined region? Good question! A hack we can do is
@@ -191,7 +400,7 @@ impl<HANDLE: VFatHandle> Dir<HANDLE> {
             name: dot_name,
             ext: empty_ext,
             attrs: Attributes::default().dir(), // do we need to hide it?
-            __r0: 0,
+            flags: 0,
             created_millis: 0,
             created: Timestamp::default(),
             last_accessed: Date::default(),
@@ -211,7 +420,7 @@ impl<HANDLE: VFatHandle> Dir<HANDLE> {
             name: dotdot_name,
             ext: empty_ext,
             attrs: Attributes::default().dir(), // do we need to hide it?
-            __r0: 0,
+            flags: 0,
             created_millis: 0,
             created: Timestamp::default(),
             last_accessed: Date::default(),
@@ -252,7 +461,7 @@ impl<HANDLE: VFatHandle> Dir<HANDLE> {
             name,
             ext,
             attrs: meta.attributes,
-            __r0: 0,
+            flags: 0,
             created_millis: 0,
             created: Timestamp::default(),
             last_accessed: Date::default(),
@@ -19,17 +19,37 @@ impl<HANDLE: VFatHandle> File<HANDLE> {
     // updates the regular file entry for this file to match the current metadata
     // does not account for lfn entries
     pub fn update_entry(&self) -> io::Result<usize> {
-        let reg_entry = self.into();
-        let reg_entry_buf: &[u8] = unsafe { &mem::transmute::<VFatRegularDirEntry, [u8; 32]>(reg_entry) };
+        let mut reg_entry: VFatRegularDirEntry = self.into();
         match self.entry {
             Some(Range { end: e, .. }) => {
                 self.vfat.lock(|vfat: &mut VFat<HANDLE>| -> io::Result<usize> {
+                    // Keep the flag `Dir::link` may have set since we were opened
+                    reg_entry.set_linked(VFatRegularDirEntry::is_linked(vfat, e)?);
+                    let reg_entry_buf: &[u8] = unsafe { &mem::transmute::<VFatRegularDirEntry, [u8; 32]>(reg_entry) };
                     vfat.write_cluster(e.cluster, e.offset, reg_entry_buf)
                 })
             },
             _ => ioerr!(NotFound, "file entry not found")
         }
     }
+
+    /// Returns `true` if other entries may share this file's contents, i.e.
+    /// if its entry was flagged by `Dir::link`. Only then does
+    /// `Dir::count_links` need to scan the filesystem for them.
+    fn may_be_linked(&self) -> io::Result<bool> {
+        match self.entry {
+            Some(Range { end, .. }) if self.start.num() != 0 => {
+                self.vfat.lock(|vfat: &mut VFat<HANDLE>| VFatRegularDirEntry::is_linked(vfat, end))
+            },
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns `true` if another entry shares this file's contents.
+    fn is_shared(&self) -> io::Result<bool> {
+        Ok(self.may_be_linked()?
+            && traits::FileSystem::open_dir(&self.vfat, "/")?.count_links(self.start)? > 1)
+    }
 }
 
 impl<HANDLE: VFatHandle> io::Read for File<HANDLE> {
@@ -117,13 +137,69 @@ impl<HANDLE: VFatHandle> traits::File for File<HANDLE> {
             Some(Range {start, ..}) => start,
             None => return ioerr!(NotFound, "Cannot delete a file without a directory entry"),
         };
+        // The contents may be shared with links made by `Dir::link`, and are
+        // only freed along with the last entry pointing at them
+        let shared = self.is_shared()?;
         self.vfat.lock(|vfat: &mut VFat<HANDLE>| -> io::Result<()> {
             // Free all the allocated space for the file's contents
-            if self.size() > 0 { vfat.free_chain(self.start)?; }
+            if self.size() > 0 && !shared { vfat.free_chain(self.start)?; }
             // Then mark all the dir entries as invalid
             Dir::invalidate_entries(vfat, entries_start)
         })
     }
+
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        use shim::io::{Seek, Write};
+        use crate::vfat::Status;
+
+        let len = len as usize;
+        if len > self.meta.size {
+            // Extending is writing zeros past the end
+            let pos = self.amt_read as u64;
+            self.seek(SeekFrom::End(0))?;
+            let zeros = [0u8; 512];
+            while self.meta.size < len {
+                let amt = core::cmp::min(len - self.meta.size, zeros.len());
+                self.write_all(&zeros[..amt])?;
+            }
+            self.seek(SeekFrom::Start(pos))?;
+            return Ok(());
+        }
+
+        if self.start.num() != 0 {
+            // Other entries linked to the contents would be left with a size
+            // past the end of the chain
+            if self.is_shared()? {
+                return ioerr!(Other, "can't truncate a file with more than one link");
+            }
+
+            let cluster_size = self.vfat.lock(|vfat: &mut VFat<HANDLE>| vfat.bytes_per_cluster());
+            let keep = (len + cluster_size - 1) / cluster_size;
+            let start = self.start;
+            self.vfat.lock(|vfat: &mut VFat<HANDLE>| -> io::Result<()> {
+                if keep == 0 {
+                    return vfat.free_chain(start);
+                }
+                let last = vfat.seek(Pos { cluster: start, offset: 0 }, (keep - 1) * cluster_size)?.cluster;
+                if let Status::Data(next) = vfat.fat_entry(last)?.status() {
+                    vfat.set_fat_entry(last, Status::Eoc(0))
+                        .ok_or(io::Error::new(io::ErrorKind::Other, "Couldn't update FAT entry"))?;
+                    vfat.free_chain(next)?;
+                }
+                Ok(())
+            })?;
+            if keep == 0 {
+                self.start = Cluster::from(0);
+            }
+        }
+
+        self.meta.size = len;
+        self.update_entry()?;
+        if self.amt_read > len {
+            self.seek(SeekFrom::Start(len as u64))?;
+        }
+        Ok(())
+    }
 }
 
 impl<HANDLE: VFatHandle> io::Seek for File<HANDLE> {
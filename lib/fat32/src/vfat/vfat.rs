@@ -38,6 +38,8 @@ pub struct VFat<HANDLE: VFatHandle> {
     data_start_sector: u64,
     rootdir_cluster: Cluster,
     num_fats: u8,
+    /// Number of clusters in the data region
+    num_data_clusters: u32,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -111,6 +113,12 @@ impl<HANDLE: VFatHandle> VFat<HANDLE> {
 
         let sector_size = cached.sector_size() as u16;
 
+        // The FAT may have room for more clusters than the partition holds
+        let data_sectors = (ebpb.num_logical_sectors_ext as u64)
+            .saturating_sub(ebpb.num_reserved_sectors as u64 + num_sectors);
+        let fat_entries = ebpb.sectors_per_fat as u64 * sector_size as u64 / size_of::<FatEntry>() as u64;
+        let num_data_clusters = core::cmp::min(data_sectors / ebpb.sectors_per_cluster as u64, fat_entries.saturating_sub(2));
+
         let vfat = VFat {
             phantom: PhantomData,
             device: cached,
@@ -121,6 +129,7 @@ impl<HANDLE: VFatHandle> VFat<HANDLE> {
             data_start_sector: start_sector as u64 + ebpb.num_reserved_sectors as u64 + num_sectors,
             rootdir_cluster: Cluster::from(ebpb.root_cluster_number),
             num_fats,
+            num_data_clusters: num_data_clusters as u32,
         };
         Ok(HANDLE::new(vfat))
     }
@@ -479,6 +488,24 @@ impl<HANDLE: VFatHandle> VFat<HANDLE> {
         self.bytes_per_sector as usize * self.sectors_per_cluster as usize
     }
 
+    /// Returns the number of clusters that can hold data.
+    pub fn total_clusters(&self) -> u32 {
+        self.num_data_clusters
+    }
+
+    /// Counts the data clusters that aren't allocated. This reads every
+    /// sector of the FAT.
+    pub fn free_clusters(&mut self) -> io::Result<u32> {
+        let mut free = 0;
+        // Clusters 0 and 1 are reserved, so data clusters start at 2
+        for i in 2..self.num_data_clusters + 2 {
+            if let Status::Free = self.fat_entry(Cluster::from(i))?.status() {
+                free += 1;
+            }
+        }
+        Ok(free)
+    }
+
     // wrapper to give users of the filesystem ability to flush it
     pub fn flush(&mut self) {
         self.device.flush();
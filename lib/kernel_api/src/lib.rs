@@ -24,6 +24,7 @@ pub enum OsError {
     BadAddress = 50,
     FileExists = 60,
     InvalidArgument = 70,
+    CrossDevice = 80,
 
     IoError = 101,
     IoErrorEof = 102,
@@ -48,6 +49,7 @@ impl core::convert::From<u64> for OsError {
             50 => OsError::BadAddress,
             60 => OsError::FileExists,
             70 => OsError::InvalidArgument,
+            80 => OsError::CrossDevice,
 
             101 => OsError::IoError,
             102 => OsError::IoErrorEof,
@@ -113,6 +115,115 @@ impl core::convert::From<u64> for EntryKind {
   }
 }
 
+pub const SIGINT: u64 = 2;
+pub const SIGKILL: u64 = 9;
+pub const SIGSEGV: u64 = 11;
+
+/// Set in a process's dead handle once it has terminated.
+pub const STATUS_DEAD: u64 = 1 << 63;
+/// Set in a raw exit status when the process was killed by a signal.
+pub const STATUS_SIGNALED: u64 = 1 << 62;
+/// The bits of a raw exit status holding the exit code or signal number.
+pub const STATUS_CODE_MASK: u64 = STATUS_SIGNALED - 1;
+
+/// How a process terminated, as reported by `wait_pid`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+  /// The process called `exit` with the given code.
+  Exited(u64),
+  /// The process was killed by the kernel with the given signal.
+  Killed(u64),
+}
+
+impl ExitStatus {
+  pub fn as_u64(&self) -> u64 {
+    match self {
+      ExitStatus::Exited(code) => code & STATUS_CODE_MASK,
+      ExitStatus::Killed(signal) => STATUS_SIGNALED | (signal & STATUS_CODE_MASK),
+    }
+  }
+
+  /// Returns `true` if the process exited normally with code 0.
+  pub fn success(&self) -> bool {
+    *self == ExitStatus::Exited(0)
+  }
+}
+
+impl core::convert::From<u64> for ExitStatus {
+  fn from(raw: u64) -> Self {
+    if raw & STATUS_SIGNALED != 0 {
+      ExitStatus::Killed(raw & STATUS_CODE_MASK)
+    } else {
+      ExitStatus::Exited(raw & STATUS_CODE_MASK)
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProcState { Ready, Running, Waiting, Zombie }
+
+impl ProcState {
+  pub fn as_u64(&self) -> u64 {
+    match self {
+      ProcState::Ready => 0,
+      ProcState::Running => 1,
+      ProcState::Waiting => 2,
+      ProcState::Zombie => 3,
+    }
+  }
+}
+
+impl core::convert::From<u64> for ProcState {
+  fn from(s: u64) -> Self {
+    match s {
+      0 => ProcState::Ready,
+      1 => ProcState::Running,
+      2 => ProcState::Waiting,
+      _ => ProcState::Zombie,
+    }
+  }
+}
+
+/// Longest process name stored in a `ProcRecord`; longer names are truncated.
+pub const PROC_NAME_MAX: usize = 16;
+
+/// One entry of the process table, as filled in by `SYS_PROC_LIST`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ProcRecord {
+  pub pid: u64,
+  /// Parent process ID, or 0 if the process has no parent
+  pub parent: u64,
+  /// Raw `ProcState`
+  pub state: u64,
+  /// CPU time used, in microseconds
+  pub cpu_ticks: u64,
+  /// Program name, zero-padded
+  pub name: [u8; PROC_NAME_MAX],
+}
+
+impl ProcRecord {
+  pub fn state(&self) -> ProcState {
+    ProcState::from(self.state)
+  }
+
+  pub fn name(&self) -> &str {
+    let len = self.name.iter().position(|&b| b == 0).unwrap_or(PROC_NAME_MAX);
+    core::str::from_utf8(&self.name[0..len]).unwrap_or("?")
+  }
+
+  /// Stores `name`, truncating it to `PROC_NAME_MAX` bytes on a character
+  /// boundary.
+  pub fn set_name(&mut self, name: &str) {
+    let mut len = core::cmp::min(name.len(), PROC_NAME_MAX);
+    while !name.is_char_boundary(len) {
+      len -= 1;
+    }
+    self.name = [0; PROC_NAME_MAX];
+    self.name[0..len].copy_from_slice(&name.as_bytes()[0..len]);
+  }
+}
+
 pub const SEEK_FROM_START: u64 = 0;
 pub const SEEK_FROM_CURRENT: u64 = 1;
 pub const SEEK_FROM_END: u64 = 2;
@@ -145,6 +256,8 @@ pub const SYS_FORK: usize = 4;
 pub const SYS_EXEC: usize = 5;
 pub const SYS_WAIT_PID: usize = 6;
 pub const SYS_REQUEST_PAGE: usize = 7;
+pub const SYS_PROC_LIST: usize = 8;
+pub const SYS_SCHED_SETQUANTUM: usize = 9;
 
 // Miscellaneous I/O syscalls
 pub const SYS_TIME: usize = 10;
@@ -153,6 +266,10 @@ pub const SYS_OUTPUT: usize = 12;
 pub const SYS_ENV_GET: usize = 13;
 pub const SYS_ENV_SET: usize = 14;
 pub const SYS_ENV_VARS: usize = 15;
+pub const SYS_TIMES: usize = 16;
+pub const SYS_CHDIR: usize = 17;
+pub const SYS_GETCWD: usize = 18;
+pub const SYS_ENV_UNSET: usize = 19;
 
 // General filesystem syscalls
 pub const SYS_FS_CREATE: usize = 20;
@@ -164,6 +281,7 @@ pub const SYS_FS_FLUSH: usize = 25;
 pub const SYS_FS_LSBLK: usize = 26;
 pub const SYS_FS_MOUNT: usize = 27;
 pub const SYS_FS_UNMOUNT: usize = 28;
+pub const SYS_FS_RENAME: usize = 29;
 
 // File-specific syscalls
 pub const SYS_FILE_SEEK: usize = 30;
@@ -172,3 +290,204 @@ pub const SYS_FILE_WRITE: usize = 32;
 
 // Directory-specific syscalls
 pub const SYS_DIR_ENTRY: usize = 40;
+pub const SYS_FS_LINK: usize = 41;
+
+// Process management syscalls
+pub const SYS_NICE: usize = 50;
+pub const SYS_KILL: usize = 51;
+pub const SYS_HALT: usize = 52;
+pub const SYS_WAIT_ANY: usize = 53;
+pub const SYS_SET_FOREGROUND: usize = 54;
+pub const SYS_REBOOT: usize = 55;
+pub const SYS_SCHED_SETWATCHDOG: usize = 56;
+
+// Clock syscalls
+pub const SYS_TIME_WALL: usize = 60;
+pub const SYS_TIME_SET_WALL: usize = 61;
+
+// Console syscalls
+pub const SYS_TCSETATTR: usize = 70;
+
+/// Set in `StatBuf::attributes` for a read-only entry.
+pub const STAT_READ_ONLY: u64 = 1 << 0;
+/// Set in `StatBuf::attributes` for a hidden entry.
+pub const STAT_HIDDEN: u64 = 1 << 1;
+
+/// The metadata of a file or directory, as filled in by `SYS_FS_METADATA`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StatBuf {
+  /// Size in bytes, or 0 for a directory
+  pub size: u64,
+  /// Raw `EntryKind`
+  pub kind: u64,
+  /// `STAT_*` flags
+  pub attributes: u64,
+  pub created: DateTime,
+  pub modified: DateTime,
+}
+
+impl StatBuf {
+  pub fn kind(&self) -> EntryKind {
+    EntryKind::from(self.kind)
+  }
+}
+
+/// A calendar date and time in UTC, as returned for a count of seconds since
+/// the Unix epoch by `DateTime::from_epoch`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DateTime {
+  pub year: u64,
+  /// Starting at 1 for January
+  pub month: u8,
+  /// Starting at 1
+  pub day: u8,
+  pub hour: u8,
+  pub minute: u8,
+  pub second: u8,
+}
+
+impl DateTime {
+  /// Converts `secs` seconds since 1970-01-01 00:00:00 UTC to a date.
+  pub fn from_epoch(secs: u64) -> DateTime {
+    let days = secs / 86400;
+    let time = secs % 86400;
+
+    // Count from 0000-03-01 so that leap days fall at the end of a year
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    DateTime {
+      year,
+      month: month as u8,
+      day: day as u8,
+      hour: (time / 3600) as u8,
+      minute: (time / 60 % 60) as u8,
+      second: (time % 60) as u8,
+    }
+  }
+}
+
+/// Formatted like a FAT timestamp: `MM/DD/YYYY HH:MM:SS`.
+impl core::fmt::Display for DateTime {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    write!(f, "{:02}/{:02}/{:04} {:02}:{:02}:{:02}",
+           self.month, self.day, self.year,
+           self.hour, self.minute, self.second)
+  }
+}
+
+/// Writes `vars` into `buf` as `NAME=VALUE` entries that each end in a zero
+/// byte, as returned by `SYS_ENV_VARS`. Only whole entries are written.
+/// Returns the length of all of the entries, which is more than `buf.len()`
+/// if they didn't all fit.
+pub fn pack_env_vars<'a, I>(vars: I, buf: &mut [u8]) -> usize
+  where I: IntoIterator<Item = (&'a str, &'a str)>
+{
+  let mut len = 0;
+  for (name, value) in vars {
+    let entry_len = name.len() + 1 + value.len() + 1;
+    if len + entry_len <= buf.len() {
+      let entry = &mut buf[len..len + entry_len];
+      entry[..name.len()].copy_from_slice(name.as_bytes());
+      entry[name.len()] = b'=';
+      entry[name.len() + 1..entry_len - 1].copy_from_slice(value.as_bytes());
+      entry[entry_len - 1] = 0;
+    }
+    len += entry_len;
+  }
+  len
+}
+
+/// Iterates over the `(name, value)` pairs written by `pack_env_vars`.
+/// Entries that aren't valid UTF-8 are skipped.
+pub fn unpack_env_vars(buf: &[u8]) -> impl Iterator<Item = (&str, &str)> {
+  buf.split(|&byte| byte == 0)
+    .filter(|entry| !entry.is_empty())
+    .filter_map(|entry| core::str::from_utf8(entry).ok())
+    .map(|entry| match entry.find('=') {
+      Some(eq) => (&entry[..eq], &entry[eq + 1..]),
+      None => (entry, ""),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{pack_env_vars, unpack_env_vars, DateTime};
+
+  #[test]
+  fn env_vars_list_after_setting() {
+    let mut vars = [("HOME", "/"), ("PATH", "/bin")];
+    let mut buf = [0u8; 64];
+    let len = pack_env_vars(vars.iter().cloned(), &mut buf);
+    assert_eq!(&buf[..len], b"HOME=/\0PATH=/bin\0");
+
+    vars[0].1 = "/home";
+    let len = pack_env_vars(vars.iter().cloned(), &mut buf);
+    let mut listed = unpack_env_vars(&buf[..len]);
+    assert_eq!(listed.next(), Some(("HOME", "/home")));
+    assert_eq!(listed.next(), Some(("PATH", "/bin")));
+    assert_eq!(listed.next(), None);
+  }
+
+  #[test]
+  fn env_vars_report_full_length_when_buf_too_small() {
+    let vars = [("A", "1"), ("EMPTY", ""), ("LONGER", "value")];
+    let mut buf = [0u8; 12];
+    let len = pack_env_vars(vars.iter().cloned(), &mut buf);
+    assert_eq!(len, 4 + 7 + 13);
+    // The entries that fit are still listed
+    assert!(unpack_env_vars(&buf).eq([("A", "1"), ("EMPTY", "")].iter().cloned()));
+  }
+
+  /// Formats into a fixed buffer, since there's no allocator here.
+  struct FmtBuf {
+    buf: [u8; 32],
+    len: usize,
+  }
+
+  impl core::fmt::Write for FmtBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+      let end = self.len + s.len();
+      if end > self.buf.len() {
+        return Err(core::fmt::Error);
+      }
+      self.buf[self.len..end].copy_from_slice(s.as_bytes());
+      self.len = end;
+      Ok(())
+    }
+  }
+
+  fn format_date(secs: u64) -> FmtBuf {
+    use core::fmt::Write;
+    let mut out = FmtBuf { buf: [0; 32], len: 0 };
+    write!(out, "{}", DateTime::from_epoch(secs)).unwrap();
+    out
+  }
+
+  #[test]
+  fn date_from_known_epochs() {
+    assert_eq!(&format_date(0).buf[..19], b"01/01/1970 00:00:00");
+    assert_eq!(&format_date(1_000_000_000).buf[..19], b"09/09/2001 01:46:40");
+    // A leap day, and the last second before the next day
+    assert_eq!(&format_date(951_868_799).buf[..19], b"02/29/2000 23:59:59");
+    assert_eq!(&format_date(951_868_800).buf[..19], b"03/01/2000 00:00:00");
+  }
+
+  #[test]
+  fn date_fields_from_epoch() {
+    assert_eq!(DateTime::from_epoch(1_577_836_800 + 86399), DateTime {
+      year: 2020, month: 1, day: 1, hour: 23, minute: 59, second: 59,
+    });
+    assert_eq!(DateTime::from_epoch(4_107_542_400).year, 2100);
+    assert_eq!(DateTime::from_epoch(4_107_542_400).month, 3);
+  }
+}
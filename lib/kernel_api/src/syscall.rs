@@ -8,7 +8,11 @@ use core::time::Duration;
 use crate::*;
 
 pub fn exit() -> ! {
-    unsafe { do_syscall0!(SYS_EXIT) }
+    exit_with(0)
+}
+
+pub fn exit_with(code: u64) -> ! {
+    unsafe { do_syscall0!(SYS_EXIT, code) }
     loop {}
 }
 
@@ -39,19 +43,102 @@ pub fn exec(path: &str, args: &[&str]) -> OsResult<()> {
     unsafe { do_syscall0r!(SYS_EXEC, path_ptr, path_len, args_ptr, args_len) }
 }
 
-pub fn wait_pid(pid: u64) -> OsResult<()> {
-    unsafe { do_syscall0r!(SYS_WAIT_PID, pid) }
+pub fn wait_pid(pid: u64) -> OsResult<ExitStatus> {
+    unsafe { do_syscall1r!(SYS_WAIT_PID, pid).map(ExitStatus::from) }
+}
+
+// Waits for whichever child exits first and returns its ID and exit status
+pub fn wait_any() -> OsResult<(u64, ExitStatus)> {
+    unsafe { do_syscall2r!(SYS_WAIT_ANY).map(|(pid, code)| (pid, ExitStatus::from(code))) }
 }
 
 pub fn request_page(pages: u64) -> OsResult<usize> {
     unsafe { do_syscall1r!(SYS_REQUEST_PAGE, pages).map(|x| x as usize) }
 }
 
+// Fills `buf` with as many process records as fit and returns the total
+// number of processes, which may be larger than `buf.len()`
+pub fn proc_list(buf: &mut [ProcRecord]) -> OsResult<usize> {
+    unsafe {
+        do_syscall1r!(SYS_PROC_LIST, buf.as_mut_ptr() as u64, buf.len() as u64)
+            .map(|x| x as usize)
+    }
+}
+
+// Sets the scheduling quantum (or only queries it if `quantum` is zero).
+// Returns the previous quantum and the average scheduling latency under it.
+pub fn sched_setquantum(quantum: Duration) -> OsResult<(Duration, Duration)> {
+    let micros = quantum.as_micros() as u64;
+    unsafe {
+        do_syscall2r!(SYS_SCHED_SETQUANTUM, micros)
+            .map(|(old, latency)| (Duration::from_micros(old), Duration::from_micros(latency)))
+    }
+}
+
+// Makes the scheduler kill processes that go `quanta` quanta in a row without
+// a syscall, or turns that off if `quanta` is zero. Returns the previous
+// limit, or zero if it was off
+pub fn sched_setwatchdog(quanta: u64) -> OsResult<u64> {
+    unsafe { do_syscall1r!(SYS_SCHED_SETWATCHDOG, quanta) }
+}
+
+// Adds `delta` to the calling process's niceness and returns the new value
+pub fn nice(delta: i64) -> OsResult<i64> {
+    unsafe { do_syscall1r!(SYS_NICE, delta as u64).map(|x| x as i64) }
+}
+
+// Kills the process `pid`, which may be the caller itself
+pub fn kill(pid: u64) -> OsResult<()> {
+    unsafe { do_syscall0r!(SYS_KILL, pid) }
+}
+
+// Makes `pid` the process that Ctrl-C on the console kills with `SIGINT`, or
+// clears it if `pid` is 0
+pub fn set_foreground(pid: u64) -> OsResult<()> {
+    unsafe { do_syscall0r!(SYS_SET_FOREGROUND, pid) }
+}
+
+// Kills every process, flushes all filesystems and stops the machine. Only
+// the init process may halt; for anyone else this returns `NoAccess`
+pub fn halt() -> OsResult<()> {
+    unsafe { do_syscall0r!(SYS_HALT) }
+}
+
+// Like `halt`, but resets the board afterwards. Only the init process may
+// reboot; for anyone else this returns `NoAccess`
+pub fn reboot() -> OsResult<()> {
+    unsafe { do_syscall0r!(SYS_REBOOT) }
+}
+
 pub fn time() -> Duration {
     let (secs, nanos) = unsafe { do_syscall2!(SYS_TIME) };
     Duration::new(secs, nanos as u32)
 }
 
+// Returns the wall-clock time as a duration since the Unix epoch
+pub fn time_wall() -> Duration {
+    let (secs, nanos) = unsafe { do_syscall2!(SYS_TIME_WALL) };
+    Duration::new(secs, nanos as u32)
+}
+
+// Sets the wall-clock time to `secs` seconds since the Unix epoch
+pub fn time_set_wall(secs: u64) -> OsResult<()> {
+    unsafe { do_syscall0r!(SYS_TIME_SET_WALL, secs) }
+}
+
+// Returns the CPU time used by the calling process
+pub fn times() -> Duration {
+    let (secs, nanos) = unsafe { do_syscall2!(SYS_TIMES) };
+    Duration::new(secs, nanos as u32)
+}
+
+// Switches the console to raw mode, where `input` neither echoes nor edits,
+// or back to cooked mode. Returns whether it was in raw mode before. The
+// kernel restores the previous mode when the calling process exits
+pub fn tcsetattr(raw: bool) -> OsResult<bool> {
+    unsafe { do_syscall1r!(SYS_TCSETATTR, raw as u64).map(|was_raw: u64| was_raw != 0) }
+}
+
 pub fn input() -> u8 {
     unsafe { do_syscall1!(SYS_INPUT) as u8 }
 }
@@ -75,11 +162,45 @@ pub fn env_set(var: &str, val: &str) -> OsResult<()> {
     let var_ptr = &var.as_bytes()[0] as *const u8 as u64;
 
     let val_len = val.len() as u64;
-    let val_ptr = &val.as_bytes()[0] as *const u8 as u64;
+    // The value may be empty, so don't index into it
+    let val_ptr = val.as_ptr() as u64;
 
     unsafe { do_syscall0r!(SYS_ENV_SET, var_ptr, var_len, val_ptr, val_len) }
 }
 
+pub fn env_unset(var: &str) -> OsResult<()> {
+    let var_len = var.len() as u64;
+    let var_ptr = &var.as_bytes()[0] as *const u8 as u64;
+
+    unsafe { do_syscall0r!(SYS_ENV_UNSET, var_ptr, var_len) }
+}
+
+// Lists the environment variables into `buf` for `unpack_env_vars`. Returns
+// the length of all of them, which is more than `buf.len()` if they didn't fit.
+pub fn env_vars(buf: &mut [u8]) -> OsResult<usize> {
+    unsafe { do_syscall1r!(SYS_ENV_VARS, buf.as_mut_ptr() as u64, buf.len() as u64).map(|x| x as usize) }
+}
+
+pub fn chdir(path: &str) -> OsResult<()> {
+    let path_ptr = &path.as_bytes()[0] as *const u8 as u64;
+    let path_len = path.len() as u64;
+
+    unsafe { do_syscall0r!(SYS_CHDIR, path_ptr, path_len) }
+}
+
+// Returns the length of the path
+pub fn getcwd(buf: &mut [u8]) -> OsResult<usize> {
+    unsafe { do_syscall1r!(SYS_GETCWD, buf.as_mut_ptr() as u64, buf.len() as u64).map(|x| x as usize) }
+}
+
+// Fills in `buf` with the metadata of the file or directory at `path`
+pub fn fs_metadata(path: &str, buf: &mut StatBuf) -> OsResult<()> {
+    let path_ptr = &path.as_bytes()[0] as *const u8 as u64;
+    let path_len = path.len() as u64;
+
+    unsafe { do_syscall0r!(SYS_FS_METADATA, path_ptr, path_len, buf as *mut StatBuf as u64) }
+}
+
 pub fn fs_create(path: &str, kind: EntryKind) -> OsResult<()> {
     let path_ptr = &path.as_bytes()[0] as *const u8 as u64;
     let path_len = path.len() as u64;
@@ -107,6 +228,31 @@ pub fn fs_delete(path: &str) -> OsResult<()> {
     unsafe { do_syscall0r!(SYS_FS_DELETE, path_ptr, path_len) }
 }
 
+pub fn fs_rename(from: &str, to: &str) -> OsResult<()> {
+    let from_ptr = &from.as_bytes()[0] as *const u8 as u64;
+    let from_len = from.len() as u64;
+    let to_ptr = &to.as_bytes()[0] as *const u8 as u64;
+    let to_len = to.len() as u64;
+
+    unsafe { do_syscall0r!(SYS_FS_RENAME, from_ptr, from_len, to_ptr, to_len) }
+}
+
+pub fn fs_link(from: &str, to: &str) -> OsResult<()> {
+    let from_ptr = &from.as_bytes()[0] as *const u8 as u64;
+    let from_len = from.len() as u64;
+    let to_ptr = &to.as_bytes()[0] as *const u8 as u64;
+    let to_len = to.len() as u64;
+
+    unsafe { do_syscall0r!(SYS_FS_LINK, from_ptr, from_len, to_ptr, to_len) }
+}
+
+pub fn fs_flush(path: &str) -> OsResult<()> {
+    let path_ptr = &path.as_bytes()[0] as *const u8 as u64;
+    let path_len = path.len() as u64;
+
+    unsafe { do_syscall0r!(SYS_FS_FLUSH, path_ptr, path_len) }
+}
+
 pub fn file_seek(fd: &Fd, sf: shim::io::SeekFrom) -> OsResult<u64> {
     let (mode, offset) = seek_mode_to_raw(sf);
     unsafe { do_syscall1r!(SYS_FILE_SEEK, fd.as_u64(), mode, offset as u64) }
@@ -119,6 +265,13 @@ pub fn file_read(fd: &Fd, buf: &mut [u8]) -> OsResult<usize> {
     }
 }
 
+pub fn file_write(fd: &Fd, buf: &[u8]) -> OsResult<usize> {
+    unsafe {
+        do_syscall1r!(SYS_FILE_WRITE, fd.as_u64(), buf.as_ptr() as u64, buf.len() as u64)
+            .map(|x| x as usize)
+    }
+}
+
 pub fn mount(part_num: u64, path: &str, encrypted: bool) -> OsResult<()> {
     let path_ptr = &path.as_bytes()[0] as *const u8 as u64;
     let path_len = path.len() as u64;
@@ -0,0 +1,145 @@
+use shim::io;
+
+use crate::mailbox::{self, Message, Tag};
+
+/// The bits of a VideoCore bus address that make up the ARM physical address.
+const BUS_ADDRESS_MASK: u32 = 0x3FFF_FFFF;
+
+/// Bits per pixel the firmware is asked for.
+const DEPTH: u32 = 32;
+/// `Tag::SetPixelOrder` value that puts blue in the low byte of a pixel.
+const PIXEL_ORDER_RGB: u32 = 1;
+/// Alignment, in bytes, the firmware is asked to allocate the buffer with.
+const BUFFER_ALIGN: u32 = 16;
+
+/// A linear framebuffer with 32 bits per pixel, stored as `0x00RRGGBB`.
+pub struct Framebuffer<'a> {
+    pixels: &'a mut [u32],
+    width: usize,
+    height: usize,
+    /// Number of pixels between the starts of two rows
+    stride: usize,
+}
+
+impl Framebuffer<'static> {
+    /// Asks the firmware for a `width` by `height` framebuffer and returns
+    /// it. The firmware may pick a different size if it can't do that one.
+    /// Returns an error of kind `InvalidData` if it doesn't hand out a buffer.
+    ///
+    /// The buffer is outside of the ARM's share of memory, so the caller has
+    /// to make sure it is mapped (non-cacheable) before drawing once the MMU
+    /// is on.
+    pub fn new(width: u32, height: u32) -> io::Result<Framebuffer<'static>> {
+        use shim::ioerr;
+
+        let mut message = Message::new();
+        let size = message.push(Tag::SetPhysicalSize, &[width, height], 2).unwrap();
+        message.push(Tag::SetVirtualSize, &[width, height], 2).unwrap();
+        message.push(Tag::SetDepth, &[DEPTH], 1).unwrap();
+        message.push(Tag::SetPixelOrder, &[PIXEL_ORDER_RGB], 1).unwrap();
+        let buffer = message.push(Tag::AllocateBuffer, &[BUFFER_ALIGN], 2).unwrap();
+        let pitch = message.push(Tag::GetPitch, &[], 1).unwrap();
+        mailbox::call(&mut message)?;
+
+        match (message.response(size), message.response(buffer), message.response(pitch)) {
+            (Some(&[width, height]), Some(&[addr, len]), Some(&[pitch])) if addr != 0 => {
+                let (width, height, stride) = (width as usize, height as usize, pitch as usize / 4);
+                if width == 0 || stride < width || stride * height > len as usize / 4 {
+                    return ioerr!(InvalidData, "firmware handed out a bad framebuffer");
+                }
+
+                let addr = (addr & BUS_ADDRESS_MASK) as usize;
+                let pixels = unsafe {
+                    core::slice::from_raw_parts_mut(addr as *mut u32, stride * height)
+                };
+                Ok(Framebuffer::from_pixels(pixels, width, height, stride))
+            },
+            _ => ioerr!(InvalidData, "firmware didn't hand out a framebuffer"),
+        }
+    }
+}
+
+impl<'a> Framebuffer<'a> {
+    /// Returns a `width` by `height` framebuffer drawing into `pixels`, with
+    /// rows starting `stride` pixels apart.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is too small or `stride` is less than `width`.
+    pub fn from_pixels(pixels: &'a mut [u32], width: usize, height: usize, stride: usize) -> Framebuffer<'a> {
+        assert!(stride >= width && pixels.len() >= stride * height, "framebuffer doesn't fit");
+        Framebuffer { pixels, width, height, stride }
+    }
+
+    /// Returns the width in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel at (`x`, `y`).
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.stride + x]
+    }
+
+    /// Sets the pixel at (`x`, `y`) to `color`.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        self.pixels[y * self.stride + x] = color;
+    }
+
+    /// Fills the `width` by `height` rectangle whose top left corner is at
+    /// (`x`, `y`) with `color`, clipped to the framebuffer.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let x_end = core::cmp::min(x + width, self.width);
+        let y_end = core::cmp::min(y + height, self.height);
+        for y in y..y_end {
+            let row = y * self.stride;
+            for pixel in &mut self.pixels[row + x..row + x_end] {
+                *pixel = color;
+            }
+        }
+    }
+
+    /// Moves everything up by `rows` rows and fills the rows uncovered at the
+    /// bottom with `color`.
+    pub fn scroll_up(&mut self, rows: usize, color: u32) {
+        let rows = core::cmp::min(rows, self.height);
+        let kept = (self.height - rows) * self.stride;
+        let src = rows * self.stride;
+        unsafe {
+            let base = self.pixels.as_mut_ptr();
+            core::ptr::copy(base.add(src), base, kept);
+        }
+        let (width, height) = (self.width, self.height);
+        self.fill_rect(0, height - rows, width, rows, color);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Framebuffer;
+
+    #[test]
+    fn test_fill_and_scroll() {
+        // 4x3 pixels with one pixel of padding at the end of each row
+        let mut pixels = [0u32; 5 * 3];
+        let mut fb = Framebuffer::from_pixels(&mut pixels, 4, 3, 5);
+        fb.fill_rect(1, 1, 10, 10, 7);
+        assert_eq!(fb.pixel(0, 1), 0);
+        assert_eq!(fb.pixel(3, 2), 7);
+        fb.set_pixel(0, 2, 9);
+
+        fb.scroll_up(1, 1);
+        assert_eq!(fb.pixel(0, 0), 0);
+        assert_eq!(fb.pixel(1, 0), 7);
+        assert_eq!(fb.pixel(0, 1), 9);
+        assert_eq!(fb.pixel(3, 1), 7);
+        assert_eq!(fb.pixel(2, 2), 1);
+        // The padding is never drawn on
+        assert_eq!(pixels[4], 0);
+    }
+}
@@ -46,6 +46,23 @@ struct Registers {
     PUDCLK: [Volatile<u32>; 2],
 }
 
+/// The number of GPIO pins.
+pub const PIN_COUNT: u8 = 54;
+
+/// Returns the index of the `FSEL` register that selects the function of
+/// `pin`, and the shift of its 3-bit field in that register. Each register
+/// covers 10 pins.
+fn fsel_position(pin: u8) -> (usize, u32) {
+    ((pin / 10) as usize, (pin % 10) as u32 * 3)
+}
+
+/// Returns the index of the register covering `pin` in a bank of one-bit
+/// registers like `SET`, `CLR` and `LEV`, and the shift of its bit. Each
+/// register covers 32 pins.
+fn bank_position(pin: u8) -> (usize, u32) {
+    ((pin / 32) as usize, (pin % 32) as u32)
+}
+
 /// Possible states for a GPIO pin.
 #[allow(unused_doc_comments)]
 states! {
@@ -86,8 +103,8 @@ impl Gpio<Uninitialized> {
     ///
     /// Panics if `pin` > `53`.
     pub fn new(pin: u8) -> Gpio<Uninitialized> {
-        if pin > 53 {
-            panic!("Gpio::new(): pin {} exceeds maximum of 53", pin);
+        if pin >= PIN_COUNT {
+            panic!("Gpio::new(): pin {} exceeds maximum of {}", pin, PIN_COUNT - 1);
         }
 
         Gpio {
@@ -100,9 +117,9 @@ impl Gpio<Uninitialized> {
     /// Enables the alternative function `function` for `self`. Consumes self
     /// and returns a `Gpio` structure in the `Alt` state.
     pub fn into_alt(self, function: Function) -> Gpio<Alt> {
-        let fsel_register = (self.pin * 3 / 30) as usize;
-        let fsel_offset = self.pin * 3 % 30;
-        let old_value = self.registers.FSEL[fsel_register].read();
+        let (fsel_register, fsel_offset) = fsel_position(self.pin);
+        // Clear the old function first, or its bits would mix with the new one
+        let old_value = self.registers.FSEL[fsel_register].read() & !(0b111 << fsel_offset);
         let new_value = old_value | ((function as u32) << fsel_offset);
         self.registers.FSEL[fsel_register].write(new_value);
         self.transition()
@@ -124,16 +141,14 @@ impl Gpio<Uninitialized> {
 impl Gpio<Output> {
     /// Sets (turns on) the pin.
     pub fn set(&mut self) {
-        let set_register = (self.pin / 32) as usize;
-        let set_offset = self.pin % 32;
+        let (set_register, set_offset) = bank_position(self.pin);
         let new_value = 0b1 << set_offset;
         self.registers.SET[set_register].write(new_value);
     }
 
     /// Clears (turns off) the pin.
     pub fn clear(&mut self) {
-        let clr_register = (self.pin / 32) as usize;
-        let clr_offset = self.pin % 32;
+        let (clr_register, clr_offset) = bank_position(self.pin);
         let new_value = 0b1 << clr_offset;
         self.registers.CLR[clr_register].write(new_value);
     }
@@ -143,8 +158,7 @@ impl Gpio<Input> {
     /// Reads the pin's value. Returns `true` if the level is high and `false`
     /// if the level is low.
     pub fn level(&mut self) -> bool {
-        let lev_register = (self.pin / 32) as usize;
-        let lev_offset = self.pin % 32;
+        let (lev_register, lev_offset) = bank_position(self.pin);
         let reg_val = self.registers.LEV[lev_register].read();
         match (reg_val >> lev_offset) & 0b1 {
             0  => false,
@@ -152,3 +166,30 @@ impl Gpio<Input> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{bank_position, fsel_position, PIN_COUNT};
+
+    #[test]
+    fn test_fsel_position() {
+        assert_eq!(fsel_position(0), (0, 0));
+        assert_eq!(fsel_position(9), (0, 27));
+        assert_eq!(fsel_position(10), (1, 0));
+        // The blinky LED, and the mini UART's TXD1/RXD1
+        assert_eq!(fsel_position(16), (1, 18));
+        assert_eq!(fsel_position(14), (1, 12));
+        assert_eq!(fsel_position(15), (1, 15));
+        assert_eq!(fsel_position(47), (4, 21));
+        assert_eq!(fsel_position(PIN_COUNT - 1), (5, 9));
+    }
+
+    #[test]
+    fn test_bank_position() {
+        assert_eq!(bank_position(0), (0, 0));
+        assert_eq!(bank_position(16), (0, 16));
+        assert_eq!(bank_position(31), (0, 31));
+        assert_eq!(bank_position(32), (1, 0));
+        assert_eq!(bank_position(PIN_COUNT - 1), (1, 21));
+    }
+}
@@ -7,7 +7,11 @@
 
 pub mod atags;
 pub mod common;
+pub mod framebuffer;
 pub mod gpio;
 pub mod interrupt;
+pub mod mailbox;
+pub mod rng;
 pub mod timer;
 pub mod uart;
+pub mod watchdog;
@@ -0,0 +1,284 @@
+use shim::const_assert_size;
+use shim::io;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile};
+
+use crate::common::IO_BASE;
+
+/// The base address for the VideoCore mailbox registers.
+const MBOX_REG_BASE: usize = IO_BASE + 0xB880;
+
+/// The mailbox channel for property tags from the ARM to the VideoCore.
+const PROPERTY_CHANNEL: u32 = 8;
+
+/// `STATUS` bit set while the mailbox can't take another write.
+const STATUS_FULL: u32 = 1 << 31;
+/// `STATUS` bit set while there is nothing to read.
+const STATUS_EMPTY: u32 = 1 << 30;
+
+/// The code in a message's header when it is sent.
+const REQUEST: u32 = 0;
+/// The code the firmware puts in the header if it parsed the whole message.
+const RESPONSE_SUCCESS: u32 = 0x8000_0000;
+/// Set in a tag's code once the firmware has answered it. The other bits are
+/// the length of the answer in bytes.
+const TAG_RESPONSE: u32 = 1 << 31;
+/// Marks the end of the tags in a message.
+const END_TAG: u32 = 0;
+
+/// The number of 32-bit words a `Message` holds, header and end tag included.
+const MESSAGE_WORDS: usize = 64;
+
+/// The size of a data cache line, in bytes.
+const CACHE_LINE: usize = 64;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    READ: ReadVolatile<u32>,
+    __r0: [Reserved<u32>; 3],
+    PEEK: ReadVolatile<u32>,
+    SENDER: ReadVolatile<u32>,
+    STATUS: ReadVolatile<u32>,
+    CONFIG: Volatile<u32>,
+    WRITE: Volatile<u32>,
+}
+
+const_assert_size!(Registers, 0x7E00B8A4 - 0x7E00B880);
+
+/// Property tags that can be requested with a `Message`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tag {
+    /// Answers with the board's serial number as two words, low word first.
+    GetBoardSerial = 0x0001_0004,
+    /// Takes a `Clock` and answers with it followed by its rate in Hz.
+    GetClockRate = 0x0003_0002,
+    /// Takes an alignment and answers with the framebuffer's bus address and
+    /// size in bytes.
+    AllocateBuffer = 0x0004_0001,
+    /// Answers with the number of bytes between the starts of two rows.
+    GetPitch = 0x0004_0008,
+    /// Takes and answers with the display's width and height in pixels.
+    SetPhysicalSize = 0x0004_8003,
+    /// Takes and answers with the framebuffer's width and height in pixels.
+    SetVirtualSize = 0x0004_8004,
+    /// Takes and answers with the number of bits per pixel.
+    SetDepth = 0x0004_8005,
+    /// Takes and answers with 0 for BGR or 1 for RGB.
+    SetPixelOrder = 0x0004_8006,
+}
+
+/// Clocks whose rate can be read with `clock_rate()`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Clock {
+    Emmc = 1,
+    Uart = 2,
+    Arm = 3,
+    /// The VideoCore clock, which the mini UART's baud rate is derived from.
+    Core = 4,
+}
+
+/// A handle to a tag in a `Message`, for reading back its response.
+#[derive(Debug, Copy, Clone)]
+pub struct TagHandle(usize);
+
+/// A property channel message: a list of tags that `call()` hands to the
+/// firmware, which answers each of them in place.
+#[repr(C, align(16))]
+pub struct Message {
+    words: [u32; MESSAGE_WORDS],
+    /// Index of the first unused word
+    len: usize,
+}
+
+impl Message {
+    /// Returns a new message with no tags.
+    pub fn new() -> Message {
+        Message {
+            words: [0; MESSAGE_WORDS],
+            // Room for the size and code
+            len: 2,
+        }
+    }
+
+    /// Adds a request for `tag` with the request values `values`, and room
+    /// for a response of `response_words` words. Returns a handle to read the
+    /// response with, or `None` if the message is full.
+    pub fn push(&mut self, tag: Tag, values: &[u32], response_words: usize) -> Option<TagHandle> {
+        let buffer_words = core::cmp::max(values.len(), response_words);
+        let start = self.len;
+        // The tag's id, buffer size and code, then the buffer, then the end tag
+        if start + 3 + buffer_words + 1 > MESSAGE_WORDS {
+            return None;
+        }
+
+        self.words[start] = tag as u32;
+        self.words[start + 1] = (buffer_words * 4) as u32;
+        self.words[start + 2] = REQUEST;
+        let buffer = &mut self.words[start + 3..start + 3 + buffer_words];
+        buffer[..values.len()].copy_from_slice(values);
+        for word in &mut buffer[values.len()..] {
+            *word = 0;
+        }
+        self.len = start + 3 + buffer_words;
+        Some(TagHandle(start))
+    }
+
+    /// Returns the response values of the tag at `handle`, or `None` if the
+    /// firmware didn't answer it.
+    pub fn response(&self, handle: TagHandle) -> Option<&[u32]> {
+        let TagHandle(start) = handle;
+        let code = self.words[start + 2];
+        if code & TAG_RESPONSE == 0 {
+            return None;
+        }
+
+        // A response longer than the buffer is cut short
+        let buffer_words = self.words[start + 1] as usize / 4;
+        let response_words = ((code & !TAG_RESPONSE) as usize + 3) / 4;
+        let values = start + 3;
+        Some(&self.words[values..values + core::cmp::min(buffer_words, response_words)])
+    }
+
+    /// Fills in the header and end tag so the message can be sent.
+    fn finish(&mut self) {
+        self.words[self.len] = END_TAG;
+        self.words[0] = ((self.len + 1) * 4) as u32;
+        self.words[1] = REQUEST;
+    }
+
+    /// Returns `true` if the firmware parsed the whole message.
+    fn succeeded(&self) -> bool {
+        self.words[1] == RESPONSE_SUCCESS
+    }
+}
+
+/// Writes the data cache lines covering `len` bytes at `addr` back to memory
+/// and drops them, so the VideoCore sees what the CPU wrote and the CPU then
+/// sees what the VideoCore wrote.
+#[cfg(target_arch = "aarch64")]
+fn clean_and_invalidate(addr: usize, len: usize) {
+    let start = addr & !(CACHE_LINE - 1);
+    for line in (start..addr + len).step_by(CACHE_LINE) {
+        unsafe { asm!("dc civac, $0" :: "r"(line) : "memory" : "volatile"); }
+    }
+    unsafe { asm!("dsb sy" ::: "memory" : "volatile"); }
+}
+
+/// Off the board, as in unit tests, there is no VideoCore to keep in sync
+/// with, and the instructions above don't assemble.
+#[cfg(not(target_arch = "aarch64"))]
+fn clean_and_invalidate(_addr: usize, _len: usize) {}
+
+/// Sends `message` to the firmware on the property channel and waits for it
+/// to answer, which it does by writing into `message`. Returns an error of
+/// kind `InvalidData` if the firmware couldn't parse the message.
+///
+/// The message has to be in memory the VideoCore can address, i.e. the first
+/// gigabyte of physical memory, which it is when it lives on a kernel stack.
+pub fn call(message: &mut Message) -> io::Result<()> {
+    use shim::ioerr;
+
+    message.finish();
+    let addr = message.words.as_ptr() as usize;
+    let size = message.words[0] as usize;
+    let registers = unsafe { &mut *(MBOX_REG_BASE as *mut Registers) };
+    let mail = addr as u32 | PROPERTY_CHANNEL;
+
+    clean_and_invalidate(addr, size);
+    while registers.STATUS.has_mask(STATUS_FULL) {
+        unsafe { asm!("nop" :::: "volatile"); }
+    }
+    registers.WRITE.write(mail);
+
+    // Skip any answers to other channels
+    loop {
+        while registers.STATUS.has_mask(STATUS_EMPTY) {
+            unsafe { asm!("nop" :::: "volatile"); }
+        }
+        if registers.READ.read() == mail {
+            break;
+        }
+    }
+    clean_and_invalidate(addr, size);
+
+    if message.succeeded() {
+        Ok(())
+    } else {
+        ioerr!(InvalidData, "firmware rejected the property message")
+    }
+}
+
+/// Returns the board's serial number.
+pub fn board_serial() -> io::Result<u64> {
+    use shim::ioerr;
+
+    let mut message = Message::new();
+    let serial = message.push(Tag::GetBoardSerial, &[], 2).unwrap();
+    call(&mut message)?;
+    match message.response(serial) {
+        Some(&[low, high]) => Ok((high as u64) << 32 | low as u64),
+        _ => ioerr!(InvalidData, "firmware didn't answer with a serial number"),
+    }
+}
+
+/// Returns the rate of `clock` in Hz.
+pub fn clock_rate(clock: Clock) -> io::Result<u32> {
+    use shim::ioerr;
+
+    let mut message = Message::new();
+    let rate = message.push(Tag::GetClockRate, &[clock as u32], 2).unwrap();
+    call(&mut message)?;
+    match message.response(rate) {
+        // A rate of 0 means the clock doesn't exist
+        Some(&[_, rate]) if rate != 0 => Ok(rate),
+        _ => ioerr!(InvalidData, "firmware didn't answer with a clock rate"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Clock, Message, Tag};
+    use super::{END_TAG, MESSAGE_WORDS, RESPONSE_SUCCESS, TAG_RESPONSE};
+
+    #[test]
+    fn test_message_layout() {
+        let mut message = Message::new();
+        let serial = message.push(Tag::GetBoardSerial, &[], 2).unwrap();
+        let rate = message.push(Tag::GetClockRate, &[Clock::Core as u32], 2).unwrap();
+        message.finish();
+
+        assert_eq!(&message.words[..13], &[
+            13 * 4, 0,
+            0x0001_0004, 8, 0, 0, 0,
+            0x0003_0002, 8, 0, 4, 0,
+            END_TAG,
+        ]);
+        assert!(!message.succeeded());
+        assert_eq!(message.response(serial), None);
+
+        // What the firmware writes back when the core clock runs at 250 MHz
+        message.words[1] = RESPONSE_SUCCESS;
+        message.words[4] = TAG_RESPONSE | 8;
+        message.words[5..7].copy_from_slice(&[0x1234_5678, 0x9abc]);
+        message.words[9] = TAG_RESPONSE | 8;
+        message.words[11] = 250_000_000;
+
+        assert!(message.succeeded());
+        assert_eq!(message.response(serial), Some(&[0x1234_5678, 0x9abc][..]));
+        assert_eq!(message.response(rate), Some(&[4, 250_000_000][..]));
+    }
+
+    #[test]
+    fn test_message_full() {
+        let mut message = Message::new();
+        // Header, tag header, buffer, end tag
+        let fits = MESSAGE_WORDS - 2 - 3 - 1;
+        assert!(message.push(Tag::GetBoardSerial, &[], fits + 1).is_none());
+        assert!(message.push(Tag::GetBoardSerial, &[], fits).is_some());
+        assert!(message.push(Tag::GetBoardSerial, &[], 0).is_none());
+    }
+}
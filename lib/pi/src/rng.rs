@@ -0,0 +1,112 @@
+use rand_core::{impls, Error, RngCore};
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Reserved, Volatile};
+
+use crate::common::IO_BASE;
+
+/// The base address for the hardware random number generator registers.
+const RNG_REG_BASE: usize = IO_BASE + 0x104000;
+
+/// Written to `STATUS` before starting the generator: the number of
+/// initial values it throws away while it warms up.
+const RNG_WARMUP_COUNT: u32 = 0x40000;
+/// `CTRL` bit that turns the generator on.
+const CTRL_RBGEN: u32 = 1;
+/// `INT_MASK` bit that keeps the generator from raising interrupts.
+const INT_MASK_OFF: u32 = 1;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    CTRL: Volatile<u32>,
+    STATUS: Volatile<u32>,
+    DATA: ReadVolatile<u32>,
+    __r0: Reserved<u32>,
+    INT_MASK: Volatile<u32>,
+}
+
+/// Returns the number of words of entropy ready to read, from the top byte
+/// of `STATUS`.
+fn words_ready(status: u32) -> u32 {
+    status >> 24
+}
+
+/// The BCM2837's hardware random number generator.
+pub struct HwRng {
+    registers: &'static mut Registers,
+}
+
+impl HwRng {
+    /// Returns a handle to the hardware random number generator, starting it
+    /// if it isn't running yet. Reads wait until the generator has entropy to
+    /// give, so a handle is usable as soon as it is returned.
+    pub fn new() -> HwRng {
+        let registers = unsafe { &mut *(RNG_REG_BASE as *mut Registers) };
+        // The enable bit doubles as the guard, since atomics aren't usable
+        // before the MMU is on. Restarting would redo the warm-up.
+        if !registers.CTRL.has_mask(CTRL_RBGEN) {
+            registers.STATUS.write(RNG_WARMUP_COUNT);
+            registers.INT_MASK.or_mask(INT_MASK_OFF);
+            registers.CTRL.or_mask(CTRL_RBGEN);
+        }
+        HwRng { registers }
+    }
+
+    /// Waits until a word of entropy is ready and returns it.
+    fn next_word(&mut self) -> u32 {
+        while words_ready(self.registers.STATUS.read()) == 0 {
+            unsafe { asm!("nop" :::: "volatile"); }
+        }
+        self.registers.DATA.read()
+    }
+}
+
+impl RngCore for HwRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_word()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let upper = self.next_word() as u64;
+        let lower = self.next_word() as u64;
+        (upper << 32) | lower
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        Ok(self.fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::words_ready;
+    #[cfg(target_arch = "aarch64")]
+    use super::{HwRng, RngCore};
+
+    #[test]
+    fn test_words_ready() {
+        // Right after warm-up is written, nothing is ready yet
+        assert_eq!(words_ready(0x0004_0000), 0);
+        assert_eq!(words_ready(0x0100_0000), 1);
+        assert_eq!(words_ready(0x0504_0000), 5);
+        assert_eq!(words_ready(0xff00_0000), 255);
+    }
+
+    /// Needs the generator itself, so this only builds for the board, and
+    /// has to be asked for there with `--ignored`.
+    #[test]
+    #[ignore]
+    #[cfg(target_arch = "aarch64")]
+    fn test_draws_differ() {
+        let mut rng = HwRng::new();
+        let mut draws = [0u32; 16];
+        for draw in draws.iter_mut() {
+            *draw = rng.next_u32();
+        }
+        assert!(draws.iter().any(|&draw| draw != draws[0]));
+    }
+}
@@ -7,6 +7,14 @@ use volatile::{ReadVolatile, Volatile};
 /// The base address for the ARM system timer registers.
 const TIMER_REG_BASE: usize = IO_BASE + 0x3000;
 
+/// The comparator `tick_in` uses for the scheduler tick. Matches raise
+/// `Interrupt::Timer1`.
+const TICK_CHANNEL: usize = 1;
+
+/// The comparator `Alarm` uses. Matches raise `Interrupt::Timer3`.
+/// Comparators 0 and 2 belong to the GPU.
+const ALARM_CHANNEL: usize = 3;
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -16,6 +24,26 @@ struct Registers {
     COMPARE: [Volatile<u32>; 4],
 }
 
+impl Registers {
+    /// Sets comparator `channel` to match when `CLO` reaches `compare` and
+    /// acknowledges any earlier match on it, leaving the other channels be.
+    fn arm(&mut self, channel: usize, compare: u32) {
+        self.COMPARE[channel].write(compare);
+        self.CS.write(match_bit(channel));
+    }
+}
+
+/// Returns the `CS` bit for a match on comparator `channel`. Writing it
+/// acknowledges that match; writing 0 to the other bits doesn't touch them.
+fn match_bit(channel: usize) -> u32 {
+    1 << channel
+}
+
+/// Returns the comparator value for a tick `t` microseconds after `now`.
+fn tick_compare(now: u64, t: u64) -> u32 {
+    (now + t) as u32
+}
+
 /// The Raspberry Pi ARM system timer.
 pub struct Timer {
     registers: &'static mut Registers,
@@ -42,11 +70,59 @@ impl Timer {
     /// interrupts for timer 1 are enabled and IRQs are unmasked, then a timer
     /// interrupt will be issued in `t` duration.
     pub fn tick_in(&mut self, t: Duration) {
-        let now = self.read().as_micros();
-        let finish = now + t.as_micros();
-        self.registers.COMPARE[1].write(finish as u32);
-        // Clear timer 1 to begin
-        self.registers.CS.or_mask(0b10);
+        let now = self.read().as_micros() as u64;
+        let compare = tick_compare(now, t.as_micros() as u64);
+        self.registers.arm(TICK_CHANNEL, compare);
+    }
+}
+
+/// The shortest time ahead of the counter an alarm is armed for. The
+/// comparator only matches when `CLO` equals it exactly, so a deadline that
+/// has already passed (or passes while it is being written) would otherwise
+/// not go off until the counter wraps around, over an hour later.
+pub const ALARM_MIN_LEAD: Duration = Duration::from_micros(20);
+
+/// Returns the value to write to a comparator so that it matches at
+/// `deadline`, or `ALARM_MIN_LEAD` after `now` if that is later. Both are
+/// microseconds since boot.
+fn alarm_compare(now: u64, deadline: u64) -> u32 {
+    let earliest = now + ALARM_MIN_LEAD.as_micros() as u64;
+    core::cmp::max(deadline, earliest) as u32
+}
+
+/// A one-shot alarm on its own system timer comparator, separate from the
+/// one `tick_in` uses for the scheduler tick. If interrupts for timer 3
+/// are enabled and IRQs are unmasked, a timer 3 interrupt is issued when it
+/// goes off.
+pub struct Alarm {
+    registers: &'static mut Registers,
+}
+
+impl Alarm {
+    /// Returns a new instance of `Alarm`.
+    pub fn new() -> Alarm {
+        Alarm {
+            registers: unsafe { &mut *(TIMER_REG_BASE as *mut Registers) },
+        }
+    }
+
+    /// Arms the alarm to go off at `deadline`, measured like `current_time()`.
+    /// Deadlines in the past go off as soon as possible. Any earlier setting
+    /// is replaced and any pending match is acknowledged.
+    pub fn set(&mut self, deadline: Duration) {
+        let now = Timer::new().read().as_micros() as u64;
+        let compare = alarm_compare(now, deadline.as_micros() as u64);
+        self.registers.arm(ALARM_CHANNEL, compare);
+    }
+
+    /// Returns whether the alarm has gone off since it was last set or cleared.
+    pub fn fired(&self) -> bool {
+        self.registers.CS.has_mask(match_bit(ALARM_CHANNEL))
+    }
+
+    /// Acknowledges the alarm going off, which lowers its interrupt line.
+    pub fn clear(&mut self) {
+        self.registers.CS.write(match_bit(ALARM_CHANNEL));
     }
 }
 
@@ -72,3 +148,97 @@ pub fn tick_in(t: Duration) {
     let mut timer = Timer::new();
     timer.tick_in(t);
 }
+
+#[cfg(test)]
+mod test {
+    use super::{alarm_compare, match_bit, tick_compare};
+    use super::{ALARM_CHANNEL, ALARM_MIN_LEAD, TICK_CHANNEL};
+
+    /// Runs a counter from `now` and returns when it first matches `compare`,
+    /// the way the hardware comparator would.
+    fn fires_at(now: u64, compare: u32) -> u64 {
+        (now..).find(|&t| t as u32 == compare).unwrap()
+    }
+
+    #[test]
+    fn test_alarm_fires_within_tolerance() {
+        let lead = ALARM_MIN_LEAD.as_micros() as u64;
+
+        // A deadline in the future goes off exactly on time, even when the
+        // low 32 bits of the counter wrap around in between
+        for &(now, deadline) in &[(1_000, 5_000), (0xffff_fff0, 0x1_0000_0010), (7, 7 + lead)] {
+            assert_eq!(fires_at(now, alarm_compare(now, deadline)), deadline);
+        }
+
+        // One that is too close or already passed goes off shortly after
+        for &(now, deadline) in &[(5_000, 5_001), (5_000, 5_000), (5_000, 1_000), (5_000, 0)] {
+            let fired = fires_at(now, alarm_compare(now, deadline));
+            assert!(fired >= deadline && fired <= now + lead);
+        }
+    }
+
+    /// The comparators and `CS` the way the hardware updates them.
+    struct Comparators {
+        compare: [u32; 4],
+        cs: u32,
+    }
+
+    impl Comparators {
+        fn arm(&mut self, channel: usize, compare: u32) {
+            self.compare[channel] = compare;
+            self.acknowledge(match_bit(channel));
+        }
+
+        fn acknowledge(&mut self, written: u32) {
+            self.cs &= !written;
+        }
+
+        fn count(&mut self, clo: u32) {
+            for (channel, &compare) in self.compare.iter().enumerate() {
+                if compare == clo {
+                    self.cs |= match_bit(channel);
+                }
+            }
+        }
+
+        fn pending(&self, channel: usize) -> bool {
+            self.cs & match_bit(channel) != 0
+        }
+    }
+
+    #[test]
+    fn test_tick_and_alarm_fire_independently() {
+        let mut timer = Comparators { compare: [0; 4], cs: 0 };
+        let now = 1_000_000;
+        timer.arm(TICK_CHANNEL, tick_compare(now, 10_000));
+        timer.arm(ALARM_CHANNEL, alarm_compare(now, now + 3_000));
+
+        let mut tick_at = None;
+        let mut alarm_at = None;
+        for clo in now..now + 20_000 {
+            timer.count(clo as u32);
+            if timer.pending(ALARM_CHANNEL) {
+                assert!(alarm_at.is_none());
+                alarm_at = Some(clo);
+                timer.acknowledge(match_bit(ALARM_CHANNEL));
+            }
+            if timer.pending(TICK_CHANNEL) {
+                assert!(tick_at.is_none());
+                tick_at = Some(clo);
+                // The scheduler rearms the tick from its interrupt handler
+                timer.arm(TICK_CHANNEL, tick_compare(clo, 1_000_000));
+            }
+        }
+        assert_eq!(alarm_at, Some(now + 3_000));
+        assert_eq!(tick_at, Some(now + 10_000));
+
+        // Rearming the tick while an alarm is pending doesn't lose the alarm,
+        // and the other way around
+        timer.cs = match_bit(TICK_CHANNEL) | match_bit(ALARM_CHANNEL);
+        timer.arm(TICK_CHANNEL, 0);
+        assert!(timer.pending(ALARM_CHANNEL) && !timer.pending(TICK_CHANNEL));
+        timer.cs = match_bit(TICK_CHANNEL) | match_bit(ALARM_CHANNEL);
+        timer.arm(ALARM_CHANNEL, 0);
+        assert!(timer.pending(TICK_CHANNEL) && !timer.pending(ALARM_CHANNEL));
+    }
+}
@@ -9,6 +9,7 @@ use volatile::{ReadVolatile, Reserved, Volatile};
 
 use crate::common::IO_BASE;
 use crate::gpio::{Function, Gpio};
+use crate::mailbox::{self, Clock};
 use crate::timer;
 
 /// The base address for the `MU` registers.
@@ -17,6 +18,47 @@ const MU_REG_BASE: usize = IO_BASE + 0x215040;
 /// The `AUXENB` register from page 9 of the BCM2837 documentation.
 const AUX_ENABLES: *mut Volatile<u8> = (IO_BASE + 0x215004) as *mut Volatile<u8>;
 
+/// The VideoCore clock that the mini UART's baud rate is derived from, in Hz,
+/// with `core_freq=250` in `config.txt`. Used if the firmware can't be asked.
+pub const CORE_CLOCK: u32 = 250_000_000;
+
+/// Returns the rate of the VideoCore clock that the mini UART's baud rate is
+/// derived from, as reported by the firmware, or `CORE_CLOCK` if it can't be
+/// read.
+pub fn core_clock() -> u32 {
+    mailbox::clock_rate(Clock::Core).unwrap_or(CORE_CLOCK)
+}
+
+/// The baud rate the mini UART starts out with.
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+/// How far, in percent, the achievable baud rate may be from the requested
+/// one before the other end can't keep in sync.
+const MAX_BAUD_ERROR: u64 = 2;
+
+/// Returns the `AUX_MU_BAUD_REG` divider that comes closest to `rate` with a
+/// core clock of `clock` Hz, using `rate = clock / (8 * (divider + 1))`.
+/// Returns `None` if the divider doesn't fit in the register or the closest
+/// rate is off by more than `MAX_BAUD_ERROR` percent.
+pub fn baud_divider(clock: u32, rate: u32) -> Option<u16> {
+    let (clock, rate) = (clock as u64, rate as u64);
+    if rate == 0 {
+        return None;
+    }
+    // Round to the nearest divisor rather than always down
+    let divisor = (clock + 4 * rate) / (8 * rate);
+    if divisor == 0 || divisor - 1 > core::u16::MAX as u64 {
+        return None;
+    }
+
+    let actual = clock / (8 * divisor);
+    let error = if actual > rate { actual - rate } else { rate - actual };
+    if error * 100 > rate * MAX_BAUD_ERROR {
+        return None;
+    }
+    Some((divisor - 1) as u16)
+}
+
 /// Enum representing bit fields of the `AUX_MU_LSR_REG` register.
 #[repr(u8)]
 enum LsrStatus {
@@ -24,6 +66,51 @@ enum LsrStatus {
     TxAvailable = 1 << 5,
 }
 
+/// Bits of `AUX_MU_CNTL_REG` that turn on automatic flow control: RTS is
+/// de-asserted when the receive FIFO is almost full (bit 2), and transmitting
+/// stops while CTS is de-asserted (bit 3).
+const CNTL_AUTO_FLOW: u8 = 0b1100;
+
+/// Returns the value of `AUX_MU_CNTL_REG` `cntl` with automatic RTS/CTS flow
+/// control turned on or off. The RTS level and assert polarity bits are left
+/// at their defaults.
+fn flow_control_bits(cntl: u8, enabled: bool) -> u8 {
+    if enabled { cntl | CNTL_AUTO_FLOW } else { cntl & !CNTL_AUTO_FLOW }
+}
+
+/// The number of bytes the mini UART's transmit FIFO holds.
+const TX_FIFO_DEPTH: usize = 8;
+
+/// Expands every `\n` in `bytes` to `\r\n`, as terminals expect.
+fn crlf<'a>(bytes: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+    bytes.iter().flat_map(|&b| {
+        let cr = if b == b'\n' { Some(b'\r') } else { None };
+        cr.into_iter().chain(Some(b))
+    })
+}
+
+/// Writes every byte of `bytes` with `write`, asking `room` how many bytes
+/// the transmit FIFO can take only once those it last reported have been
+/// written. `room` is called again until it reports space. Returns the
+/// number of times `room` was called.
+fn write_batched<I, R, W>(bytes: I, mut room: R, mut write: W) -> usize
+where
+    I: Iterator<Item = u8>,
+    R: FnMut() -> usize,
+    W: FnMut(u8),
+{
+    let (mut left, mut polls) = (0, 0);
+    for byte in bytes {
+        while left == 0 {
+            left = room();
+            polls += 1;
+        }
+        write(byte);
+        left -= 1;
+    }
+    polls
+}
+
 #[repr(C)]
 #[allow(non_snake_case)]
 struct Registers {
@@ -89,11 +176,58 @@ impl MiniUart {
         }
     }
 
+    /// Initializes the mini UART like `new()`, but at the baud rate `rate`
+    /// if given. Returns an error of kind `InvalidInput` if the rate can't be
+    /// reached from the core clock.
+    pub fn with_baud_rate(rate: Option<u32>) -> io::Result<MiniUart> {
+        let mut uart = MiniUart::new();
+        if let Some(rate) = rate {
+            uart.set_baud_rate(rate)?;
+        }
+        Ok(uart)
+    }
+
+    /// Switches to the baud rate `rate`. Returns an error of kind
+    /// `InvalidInput`, leaving the rate unchanged, if it can't be reached
+    /// from the core clock. See `baud_divider()` and `core_clock()`.
+    pub fn set_baud_rate(&mut self, rate: u32) -> io::Result<()> {
+        use shim::ioerr;
+
+        match baud_divider(core_clock(), rate) {
+            Some(divider) => {
+                self.registers.AUX_MU_BAUD_REG.write(divider);
+                Ok(())
+            },
+            None => ioerr!(InvalidInput, "baud rate can't be reached from the core clock"),
+        }
+    }
+
+    /// Turns automatic RTS/CTS hardware flow control on or off. Turning it on
+    /// also sets GPIO pins 16 and 17 to alternative function 5 (CTS1/RTS1),
+    /// which have to be wired to the other end's RTS and CTS.
+    pub fn set_flow_control(&mut self, enabled: bool) {
+        if enabled {
+            let _cts1 = Gpio::new(16).into_alt(Function::Alt5);
+            let _rts1 = Gpio::new(17).into_alt(Function::Alt5);
+        }
+        let cntl = self.registers.AUX_MU_CNTL_REG.read();
+        self.registers.AUX_MU_CNTL_REG.write(flow_control_bits(cntl, enabled));
+    }
+
     /// Set the read timeout to `t` duration.
     pub fn set_read_timeout(&mut self, t: Duration) {
         self.timeout = Some(t);
     }
     
+    /// Raises the mini UART's interrupt (`Interrupt::Aux`) whenever there is
+    /// a byte ready to read. The interrupt stays pending until every received
+    /// byte has been read.
+    pub fn enable_rx_interrupt(&mut self) {
+        // set DLAB = 0 so that IER is addressable
+        self.registers.AUX_MU_LCR_REG.and_mask(!(0b1 << 7));
+        self.registers.AUX_MU_IER_REG.or_mask(0b1);
+    }
+
     /// Write the byte `byte`. This method blocks until there is space available
     /// in the output FIFO.
     pub fn write_byte(&mut self, byte: u8) {
@@ -108,6 +242,25 @@ impl MiniUart {
         self.registers.AUX_MU_IO_REG.write(byte);
     }
 
+    /// Writes all of `bytes`, filling the transmit FIFO as far as it goes
+    /// before checking it for room again. This is much faster than calling
+    /// `write_byte` for each byte.
+    pub fn write_bytes<I: Iterator<Item = u8>>(&mut self, bytes: I) {
+        // set DLAB = 0
+        self.registers.AUX_MU_LCR_REG.and_mask(!(0b1 << 7));
+        let registers = &mut *self.registers;
+        let (stat, io) = (&registers.AUX_MU_STAT_REG, &mut registers.AUX_MU_IO_REG);
+        write_batched(bytes, || {
+            // bits 24-27 are the transmit FIFO fill level
+            let level = ((stat.read() >> 24) & 0b1111) as usize;
+            let room = TX_FIFO_DEPTH.saturating_sub(level);
+            if room == 0 {
+                unsafe { asm!("nop" :::: "volatile"); }
+            }
+            room
+        }, |byte| io.write(byte));
+    }
+
     /// Returns `true` if there is at least one byte ready to be read. If this
     /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
     /// return immediately. This method does not block.
@@ -164,16 +317,7 @@ impl MiniUart {
 // before writing any b'\n' byte.
 impl fmt::Write for MiniUart {
     fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
-        let bytes = s.as_bytes();
-        for b in bytes {
-            match b {
-                b'\n' => {
-                    self.write_byte(b'\r');
-                    self.write_byte(b'\n');
-                },
-                _ => self.write_byte(*b)
-            }
-        }
+        self.write_bytes(crlf(s.as_bytes()));
         Ok(())
     }
 }
@@ -216,14 +360,8 @@ mod uart_io {
 
     impl io::Write for MiniUart {
         fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-            let mut count = 0;
-
-            for i in 0..buf.len() {
-                count += 1;
-                self.write_byte(buf[i]);
-            }
-
-            Ok(count)
+            self.write_bytes(buf.iter().cloned());
+            Ok(buf.len())
         }
 
         fn flush(&mut self) -> io::Result<()> {
@@ -231,3 +369,58 @@ mod uart_io {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{baud_divider, crlf, flow_control_bits, write_batched};
+    use super::{CORE_CLOCK, DEFAULT_BAUD_RATE, TX_FIFO_DEPTH};
+
+    #[test]
+    fn test_baud_divider() {
+        // The divider `new()` has always used
+        assert_eq!(baud_divider(CORE_CLOCK, DEFAULT_BAUD_RATE), Some(270));
+        assert_eq!(baud_divider(CORE_CLOCK, 9600), Some(3254));
+        assert_eq!(baud_divider(CORE_CLOCK, 57600), Some(542));
+        assert_eq!(baud_divider(CORE_CLOCK, 230400), Some(135));
+        assert_eq!(baud_divider(CORE_CLOCK, 921600), Some(33));
+
+        // Too fast to get close to, too slow for 16 bits, and nonsense
+        assert_eq!(baud_divider(CORE_CLOCK, 3_000_000), None);
+        assert_eq!(baud_divider(CORE_CLOCK, 300), None);
+        assert_eq!(baud_divider(CORE_CLOCK, 0), None);
+    }
+
+    #[test]
+    fn test_flow_control_bits() {
+        // Transmitter and receiver enabled, as `new()` leaves it
+        assert_eq!(flow_control_bits(0b11, true), 0b1111);
+        assert_eq!(flow_control_bits(0b1111, false), 0b11);
+        // The RTS level and polarity bits are left alone
+        assert_eq!(flow_control_bits(0b1111_0011, true), 0b1111_1111);
+        assert_eq!(flow_control_bits(0b1111_1111, false), 0b1111_0011);
+    }
+    #[test]
+    fn test_crlf() {
+        assert!(crlf(b"a\nb\n").eq(b"a\r\nb\r\n".iter().cloned()));
+        assert!(crlf(b"no newline").eq(b"no newline".iter().cloned()));
+        assert_eq!(crlf(b"").count(), 0);
+    }
+
+    #[test]
+    fn test_write_batched_polls_once_per_fifo() {
+        let data = [b'x'; 1000];
+
+        // A FIFO that has drained completely every time it's checked
+        let mut written = 0;
+        let polls = write_batched(data.iter().cloned(), || TX_FIFO_DEPTH, |_| written += 1);
+        assert_eq!(written, data.len());
+        assert_eq!(polls, data.len() / TX_FIFO_DEPTH);
+
+        // A FIFO that is sometimes still full keeps being checked
+        let mut room = [0, 0, 3, 0, TX_FIFO_DEPTH].iter().cycle().cloned();
+        let mut written = 0;
+        let polls = write_batched(data.iter().cloned(), || room.next().unwrap(), |_| written += 1);
+        assert_eq!(written, data.len());
+        assert!(polls < data.len());
+    }
+}
@@ -0,0 +1,127 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use crate::common::IO_BASE;
+
+use volatile::prelude::*;
+use volatile::Volatile;
+
+/// The base address for the power management (PM) registers that the
+/// watchdog lives in.
+const PM_REG_BASE: usize = IO_BASE + 0x100000 + 0x1c;
+
+/// Every write to a PM register has to carry this in its top byte.
+const PM_PASSWORD: u32 = 0x5a00_0000;
+/// Clears the reset configuration bits of `RSTC`.
+const PM_RSTC_WRCFG_CLR: u32 = 0xffff_ffcf;
+/// Sets the reset configuration in `RSTC` to a full reset.
+const PM_RSTC_WRCFG_FULL_RESET: u32 = 0x0000_0020;
+/// Written to `RSTC` to stop the watchdog.
+const PM_RSTC_RESET: u32 = 0x0000_0102;
+/// The bits of `WDOG` holding the time left, in ticks.
+const PM_WDOG_TIME_SET: u32 = 0x000f_ffff;
+
+/// The watchdog counts down 65536 ticks a second.
+const TICKS_PER_SEC: u64 = 1 << 16;
+/// The ticks `reset_now()` gives the watchdog: just enough for the writes to
+/// land.
+const RESET_TICKS: u32 = 10;
+
+/// The longest timeout the watchdog supports, just under 16 seconds.
+pub const MAX_TIMEOUT: Duration = Duration::from_micros(PM_WDOG_TIME_SET as u64 * 1_000_000 / TICKS_PER_SEC);
+
+/// Returns the number of watchdog ticks in `timeout`, capped at what `WDOG`
+/// holds. A zero timeout still gets one tick.
+fn timeout_ticks(timeout: Duration) -> u32 {
+    let ticks = timeout.as_micros() as u64 * TICKS_PER_SEC / 1_000_000;
+    core::cmp::min(core::cmp::max(ticks, 1), PM_WDOG_TIME_SET as u64) as u32
+}
+
+/// Returns the values to write to `WDOG` and then `RSTC` to have the board
+/// fully reset in `ticks` ticks, given the current value of `RSTC`.
+fn start_values(ticks: u32, rstc: u32) -> (u32, u32) {
+    let wdog = PM_PASSWORD | (ticks & PM_WDOG_TIME_SET);
+    let rstc = PM_PASSWORD | (rstc & PM_RSTC_WRCFG_CLR) | PM_RSTC_WRCFG_FULL_RESET;
+    (wdog, rstc)
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct Registers {
+    RSTC: Volatile<u32>,
+    RSTS: Volatile<u32>,
+    WDOG: Volatile<u32>,
+}
+
+/// The timeout `feed()` restarts the countdown from, in ticks, or 0 if the
+/// watchdog isn't running.
+static TIMEOUT_TICKS: AtomicU32 = AtomicU32::new(0);
+
+fn registers() -> &'static mut Registers {
+    unsafe { &mut *(PM_REG_BASE as *mut Registers) }
+}
+
+fn start_ticks(ticks: u32) {
+    let registers = registers();
+    let (wdog, rstc) = start_values(ticks, registers.RSTC.read());
+    registers.WDOG.write(wdog);
+    registers.RSTC.write(rstc);
+}
+
+/// Starts the watchdog, which resets the board unless `feed()` is called
+/// within `timeout`. Timeouts longer than `MAX_TIMEOUT` are cut to it.
+pub fn start(timeout: Duration) {
+    let ticks = timeout_ticks(timeout);
+    TIMEOUT_TICKS.store(ticks, Ordering::Relaxed);
+    start_ticks(ticks);
+}
+
+/// Restarts the countdown of a running watchdog from the timeout it was
+/// started with, holding off the reset. Does nothing if it isn't running.
+pub fn feed() {
+    let ticks = TIMEOUT_TICKS.load(Ordering::Relaxed);
+    if ticks != 0 {
+        start_ticks(ticks);
+    }
+}
+
+/// Stops the watchdog, so it no longer needs to be fed.
+pub fn stop() {
+    TIMEOUT_TICKS.store(0, Ordering::Relaxed);
+    registers().RSTC.write(PM_PASSWORD | PM_RSTC_RESET);
+}
+
+/// Resets the board as soon as possible by arming the watchdog with a tiny
+/// timeout. Anything that has to survive the reset, like dirty filesystem
+/// sectors, must be written out before calling this.
+pub fn reset_now() -> ! {
+    start_ticks(RESET_TICKS);
+
+    loop {}
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+    use super::{start_values, timeout_ticks, MAX_TIMEOUT, RESET_TICKS};
+
+    #[test]
+    fn test_timeout_ticks() {
+        assert_eq!(timeout_ticks(Duration::from_secs(1)), 0x1_0000);
+        assert_eq!(timeout_ticks(Duration::from_millis(500)), 0x8000);
+        assert_eq!(timeout_ticks(Duration::from_secs(15)), 0xf_0000);
+        assert_eq!(timeout_ticks(MAX_TIMEOUT), 0xf_fffe);
+        assert_eq!(timeout_ticks(Duration::from_secs(60)), 0xf_ffff);
+        assert_eq!(timeout_ticks(Duration::from_secs(0)), 1);
+    }
+
+    #[test]
+    fn test_reset_sequence() {
+        // From a freshly booted board, with a reset configuration left over
+        assert_eq!(start_values(RESET_TICKS, 0x0000_0030), (0x5a00_000a, 0x5a00_0020));
+        // Bits outside the reset configuration are kept
+        assert_eq!(start_values(0x1_0000, 0x0000_1201), (0x5a01_0000, 0x5a00_1221));
+        // A tick count too big for the register doesn't spill into the password
+        assert_eq!(start_values(0x10_0001, 0), (0x5a00_0001, 0x5a00_0020));
+    }
+}
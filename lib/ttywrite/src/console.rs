@@ -0,0 +1,121 @@
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+/// Ctrl-], which ends the session, as in telnet.
+pub const ESCAPE: u8 = 0x1d;
+
+/// Splits the bytes typed in `buf` at the escape character. Returns the bytes
+/// to pass on to the port, and whether the escape character was typed.
+pub fn split_escape(buf: &[u8]) -> (&[u8], bool) {
+    match buf.iter().position(|&byte| byte == ESCAPE) {
+        Some(i) => (&buf[..i], true),
+        None => (buf, false),
+    }
+}
+
+/// Keeps the terminal on stdin in raw mode, so every key press is read as it
+/// happens and nothing is echoed, until dropped.
+#[cfg(unix)]
+struct RawMode {
+    original: termios::Termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        use termios::{cfmakeraw, tcsetattr, Termios, TCSANOW};
+
+        let original = Termios::from_fd(0)?;
+        let mut raw = original;
+        cfmakeraw(&mut raw);
+        tcsetattr(0, TCSANOW, &raw)?;
+        Ok(RawMode { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(0, termios::TCSANOW, &self.original);
+    }
+}
+
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        Ok(RawMode)
+    }
+}
+
+/// Passes bytes between the terminal and `port` both ways until Ctrl-] is
+/// typed or stdin is closed.
+///
+/// Typed bytes are only written out between reads from `port`, so reads
+/// should time out quickly.
+pub fn run<P: Read + Write>(port: &mut P) -> io::Result<()> {
+    let _raw = RawMode::enter()?;
+
+    // Reading stdin blocks, so it happens on a thread of its own, which
+    // hangs up once the session is over
+    let (typed_tx, typed_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buf = [0u8; 64];
+        loop {
+            let n = match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => n,
+            };
+
+            let (typed, escaped) = split_escape(&buf[..n]);
+            if !typed.is_empty() && typed_tx.send(typed.to_vec()).is_err() {
+                return;
+            }
+            if escaped {
+                return;
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+    let mut buf = [0u8; 256];
+    loop {
+        match port.read(&mut buf) {
+            Ok(n) => {
+                stdout.write_all(&buf[..n])?;
+                stdout.flush()?;
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => (),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+
+        loop {
+            match typed_rx.try_recv() {
+                Ok(typed) => port.write_all(&typed)?,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_escape, ESCAPE};
+
+    #[test]
+    fn escape_ends_input() {
+        assert_eq!(split_escape(b"ls\r"), (&b"ls\r"[..], false));
+        assert_eq!(split_escape(b""), (&b""[..], false));
+        assert_eq!(split_escape(&[b'q', ESCAPE, b'x']), (&b"q"[..], true));
+        assert_eq!(split_escape(&[ESCAPE]), (&b""[..], true));
+
+        // Other control characters are passed through
+        assert_eq!(split_escape(&[0x03, 0x1b, 0x1c, 0x1e]), (&[0x03, 0x1b, 0x1c, 0x1e][..], false));
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+/// Opens the files in `paths` as one stream that reads them one after the
+/// other, in order, or opens stdin if `paths` is empty.
+///
+/// Every file is opened up front, so a missing one is reported before
+/// anything is sent.
+pub fn open(paths: &[PathBuf]) -> io::Result<Box<dyn BufRead>> {
+    if paths.is_empty() {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+
+    let mut stream: Box<dyn Read> = Box::new(io::empty());
+    for path in paths {
+        let file = File::open(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+        })?;
+        stream = Box::new(stream.chain(file));
+    }
+    Ok(Box::new(BufReader::new(stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+    use std::path::PathBuf;
+    use super::open;
+
+    #[test]
+    fn files_are_read_in_order() {
+        let dir = std::env::temp_dir().join(format!("ttywrite-input-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("create dir");
+        let (header, payload) = (dir.join("header"), dir.join("payload"));
+        fs::write(&header, b"HEADER").expect("write header");
+        fs::write(&payload, b"payload bytes").expect("write payload");
+
+        let read = |paths: &[PathBuf]| {
+            let mut data = Vec::new();
+            open(paths).expect("open inputs").read_to_end(&mut data).expect("read inputs");
+            data
+        };
+        assert_eq!(read(&[header.clone(), payload.clone()]), b"HEADERpayload bytes");
+        assert_eq!(read(&[payload.clone(), header.clone()]), b"payload bytesHEADER");
+        assert_eq!(read(&[header.clone(), header.clone()]), b"HEADERHEADER");
+
+        let missing = open(&[header.clone(), dir.join("missing")]).err().expect("missing file");
+        assert!(missing.to_string().contains("missing"));
+
+        fs::remove_dir_all(&dir).expect("remove dir");
+    }
+}
@@ -1,11 +1,17 @@
+mod console;
+mod input;
 mod parsers;
+mod progress;
 
 use serial;
 use structopt;
 use structopt_derive::StructOpt;
-use xmodem::{Xmodem, Progress};
+use xmodem::Xmodem;
 
+use std::fs;
+use std::io;
 use std::path::PathBuf;
+use std::process;
 use std::time::Duration;
 
 use structopt::StructOpt;
@@ -16,8 +22,9 @@ use parsers::{parse_width, parse_stop_bits, parse_flow_control, parse_baud_rate}
 #[derive(StructOpt, Debug)]
 #[structopt(about = "Write to TTY using the XMODEM protocol by default.")]
 struct Opt {
-    #[structopt(short = "i", help = "Input file (defaults to stdin if not set)", parse(from_os_str))]
-    input: Option<PathBuf>,
+    #[structopt(short = "i", number_of_values = 1, parse(from_os_str),
+                help = "Input file, sent before those after the TTY path (defaults to stdin if none are set)")]
+    input: Vec<PathBuf>,
 
     #[structopt(short = "b", long = "baud", parse(try_from_str = "parse_baud_rate"),
                 help = "Set baud rate", default_value = "115200")]
@@ -44,13 +51,72 @@ struct Opt {
 
     #[structopt(short = "r", long = "raw", help = "Disable XMODEM")]
     raw: bool,
+
+    #[structopt(short = "y", long = "ymodem", help = "Send the input files as a YMODEM batch")]
+    ymodem: bool,
+
+    #[structopt(short = "c", long = "console",
+                help = "Pass input between the terminal and the TTY after sending, until Ctrl-]")]
+    console: bool,
+
+    #[structopt(long = "retries", parse(try_from_str),
+                help = "Set how many times a packet is sent before giving up", default_value = "10")]
+    retries: usize,
+
+    #[structopt(help = "More input files, sent one after the other (or as separate files with YMODEM)",
+                parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+}
+
+/// Adds how far the transfer of `total` bytes got to `error`, which ended it
+/// after the receiver acknowledged `acked` bytes.
+fn transfer_error(error: io::Error, acked: u64, total: usize) -> io::Error {
+    let message = format!("transfer failed after {} of {} bytes: {}", acked, total, error);
+    io::Error::new(error.kind(), message)
+}
+
+/// Sends each file in `paths` to `port` as part of one YMODEM batch, named
+/// after the last component of its path, sending each packet up to `retries`
+/// times. Returns the number of bytes sent.
+fn send_batch<P: io::Read + io::Write>(paths: &[PathBuf], mut port: P, retries: usize) -> io::Result<usize> {
+    let mut sent = 0;
+    for path in paths {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "input has no file name")),
+        };
+        let data = fs::read(path)?;
+
+        println!("sending {}", name);
+        progress::start(data.len() as u64);
+        let result = Xmodem::transmit_file_with_retries(name, data.len() as u64, &data[..], &mut port,
+                                                        retries, progress::report);
+        let acked = progress::finish();
+        sent += result.map_err(|e| transfer_error(e, acked, data.len()))?;
+    }
+
+    Xmodem::finish_batch(&mut port)?;
+    Ok(sent)
+}
+
+/// Hands `port` over to the user until they press Ctrl-].
+fn attach_console<P: SerialDevice>(port: &mut P) {
+    // Typed input goes out between reads, so don't wait long for the device
+    port.set_timeout(Duration::from_millis(10)).expect("unable to set port timeout");
+    println!("console attached, press Ctrl-] to exit");
+    console::run(port).expect("console failed");
+    println!();
 }
 
 fn main() {
-    use std::fs::File;
-    use std::io::{self, BufRead, BufReader};
+    use std::io::Read;
 
     let opt = Opt::from_args();
+    let inputs: Vec<PathBuf> = opt.input.iter().chain(opt.inputs.iter()).cloned().collect();
+    if opt.ymodem && (opt.raw || inputs.is_empty()) {
+        eprintln!("--ymodem needs at least one input file and can't be used with --raw");
+        process::exit(1);
+    }
 
     let mut port = serial::open(&opt.tty_path).expect("path points to invalid TTY");
     let mut serial_settings = port.read_settings().expect("serial settings not available");
@@ -62,25 +128,40 @@ fn main() {
     port.set_timeout(Duration::new(opt.timeout, 0)).expect("unable to set port timeout");
 
     let target = opt.tty_path.to_str().unwrap();
-    let mut input_buffer: Box<dyn BufRead> = match opt.input {
-        None => {
-            let stdin = io::stdin();
-            Box::new(BufReader::new(stdin))
-        },
-        Some(name) => {
-            let fd = File::open(name).expect("Unable to open file");
-            Box::new(BufReader::new(fd))
+    if opt.ymodem {
+        let num_written = send_batch(&inputs, &mut port, opt.retries).unwrap_or_else(|e| {
+            eprintln!("couldn't transmit batch: {}", e);
+            process::exit(1);
+        });
+        println!("wrote {} bytes in {} file(s) to {}", num_written, inputs.len(), target);
+        if opt.console {
+            attach_console(&mut port);
         }
-    };
+        return;
+    }
+
+    let mut input_buffer = input::open(&inputs).unwrap_or_else(|e| {
+        eprintln!("couldn't open input: {}", e);
+        process::exit(1);
+    });
 
     let num_written =
         if opt.raw {
             io::copy(&mut input_buffer, &mut port).expect("couldn't copy to port") as usize
         } else {
-            fn print_progress(p: Progress) {
-                println!("Progress: {:?}", p);
-            }
-            Xmodem::transmit_with_progress(input_buffer, &mut port, print_progress).expect("couldn't transmit")
+            // Read it all first, so the progress bar knows the total
+            let mut data = Vec::new();
+            input_buffer.read_to_end(&mut data).expect("couldn't read input");
+            progress::start(data.len() as u64);
+            let sent = Xmodem::transmit_with_retries(&data[..], &mut port, opt.retries, progress::report);
+            let acked = progress::finish();
+            sent.unwrap_or_else(|e| {
+                eprintln!("couldn't transmit: {}", transfer_error(e, acked, data.len()));
+                process::exit(1);
+            })
         };
     println!("wrote {} bytes to {}", num_written, target);
+    if opt.console {
+        attach_console(&mut port);
+    }
 }
@@ -0,0 +1,186 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use xmodem::Progress;
+
+/// Width of the bar itself, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// Percentage between the lines printed when stdout isn't a TTY.
+const PLAIN_STEP: u64 = 10;
+
+/// Keeps count of a transfer of a known number of bytes from its `Progress`
+/// events, and renders it as a status line.
+pub struct Tracker {
+    total: u64,
+    sent: u64,
+    started: Option<Instant>,
+}
+
+impl Tracker {
+    pub fn new(total: u64) -> Tracker {
+        Tracker { total, sent: 0, started: None }
+    }
+
+    /// Updates the count for `progress`, which happened at `now`. Returns
+    /// `true` if the status line changed.
+    pub fn update(&mut self, progress: Progress, now: Instant) -> bool {
+        match progress {
+            Progress::Started => {
+                self.started = Some(now);
+                true
+            },
+            Progress::Packet(bytes) => {
+                self.sent = std::cmp::min(self.sent + bytes as u64, self.total);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns how much of the transfer is done, in percent.
+    pub fn percent(&self) -> u64 {
+        match self.total {
+            0 => 100,
+            total => self.sent * 100 / total,
+        }
+    }
+
+    /// Returns the status line as of `now`: the bar, percentage, bytes sent
+    /// and throughput.
+    pub fn line(&self, now: Instant) -> String {
+        let filled = (self.sent * BAR_WIDTH as u64 / std::cmp::max(self.total, 1)) as usize;
+        let filled = std::cmp::min(filled, BAR_WIDTH);
+        let elapsed = self.started.map(|start| now.duration_since(start));
+        let rate = match elapsed {
+            Some(elapsed) if elapsed.as_millis() > 0 => self.sent * 1000 / elapsed.as_millis() as u64,
+            _ => 0,
+        };
+
+        format!("[{}{}] {:>3}% {}/{} {}/s",
+                "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), self.percent(),
+                Size(self.sent), Size(self.total), Size(rate))
+    }
+}
+
+/// A number of bytes, shown in B, KiB or MiB with one decimal.
+struct Size(u64);
+
+impl std::fmt::Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.0 {
+            bytes if bytes < 1 << 10 => write!(f, "{} B", bytes),
+            bytes if bytes < 1 << 20 => write!(f, "{:.1} KiB", bytes as f64 / (1 << 10) as f64),
+            bytes => write!(f, "{:.1} MiB", bytes as f64 / (1 << 20) as f64),
+        }
+    }
+}
+
+/// The transfer being shown, and whether stdout is a TTY.
+struct Display {
+    tracker: Tracker,
+    tty: bool,
+    /// The last percentage printed as a plain line
+    printed: Option<u64>,
+}
+
+thread_local! {
+    // `Xmodem` takes a plain `fn` as its progress callback, so the state
+    // can't live in a closure
+    static DISPLAY: RefCell<Option<Display>> = RefCell::new(None);
+}
+
+/// Starts showing the progress of sending `total` bytes. On a TTY the status
+/// line is redrawn in place; otherwise a line is printed every `PLAIN_STEP`
+/// percent.
+pub fn start(total: u64) {
+    let display = Display { tracker: Tracker::new(total), tty: stdout_is_tty(), printed: None };
+    DISPLAY.with(|d| *d.borrow_mut() = Some(display));
+}
+
+/// Progress callback for `Xmodem::transmit_with_progress()`.
+pub fn report(progress: Progress) {
+    DISPLAY.with(|d| {
+        if let Some(display) = d.borrow_mut().as_mut() {
+            let now = Instant::now();
+            if !display.tracker.update(progress, now) {
+                return;
+            }
+
+            let percent = display.tracker.percent();
+            if display.tty {
+                print!("\r{}", display.tracker.line(now));
+                let _ = io::stdout().flush();
+            } else if display.printed.map_or(true, |printed| percent >= printed + PLAIN_STEP) {
+                println!("{}", display.tracker.line(now));
+                display.printed = Some(percent - percent % PLAIN_STEP);
+            }
+        }
+    });
+}
+
+/// Stops showing progress, ending the status line. Returns the number of
+/// bytes the receiver acknowledged.
+pub fn finish() -> u64 {
+    DISPLAY.with(|d| match d.borrow_mut().take() {
+        Some(display) => {
+            if display.tty {
+                println!();
+            }
+            display.tracker.sent
+        },
+        None => 0,
+    })
+}
+
+#[cfg(unix)]
+fn stdout_is_tty() -> bool {
+    extern "C" {
+        fn isatty(fd: i32) -> i32;
+    }
+    unsafe { isatty(1) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stdout_is_tty() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use xmodem::Progress;
+    use super::Tracker;
+
+    #[test]
+    fn bar_follows_progress() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut tracker = Tracker::new(1024);
+
+        assert!(!tracker.update(Progress::Waiting, at(0)));
+        assert_eq!(tracker.line(at(0)), "[------------------------------]   0% 0 B/1.0 KiB 0 B/s");
+
+        assert!(tracker.update(Progress::Started, at(0)));
+        for _ in 0..2 {
+            assert!(!tracker.update(Progress::Waiting, at(100)));
+            assert!(tracker.update(Progress::Packet(128), at(100)));
+        }
+        assert_eq!(tracker.line(at(500)), "[#######-----------------------]  25% 256 B/1.0 KiB 512 B/s");
+
+        for _ in 0..6 {
+            tracker.update(Progress::Packet(128), at(900));
+        }
+        assert_eq!(tracker.percent(), 100);
+        assert_eq!(tracker.line(at(1000)), "[##############################] 100% 1.0 KiB/1.0 KiB 1.0 KiB/s");
+
+        // The last packet is padded, which doesn't count as more data
+        let mut tracker = Tracker::new(200);
+        tracker.update(Progress::Started, at(0));
+        tracker.update(Progress::Packet(128), at(10));
+        tracker.update(Progress::Packet(128), at(20));
+        assert_eq!(tracker.percent(), 100);
+        assert!(tracker.line(at(2000)).starts_with("[##############################] 100% 200 B/200 B 100 B/s"));
+    }
+}
@@ -8,6 +8,7 @@ use shim::ioerr;
 #[cfg(test)] mod tests;
 mod read_ext;
 mod progress;
+pub mod ymodem;
 
 pub use progress::{Progress, ProgressFn};
 
@@ -19,6 +20,10 @@ const ACK: u8 = 0x06;
 const NAK: u8 = 0x15;
 const CAN: u8 = 0x18;
 
+/// How many times a packet is sent before the transmitter gives up, unless
+/// given otherwise.
+pub const DEFAULT_RETRIES: usize = 10;
+
 /// Implementation of the XMODEM protocol.
 pub struct Xmodem<R> {
     packet: u8,
@@ -48,33 +53,48 @@ impl Xmodem<()> {
     /// the transmission. See the [`Progress`] enum for more information.
     ///
     /// Returns the number of bytes written to `to`, excluding padding zeroes.
-    pub fn transmit_with_progress<R, W>(mut data: R, to: W, f: ProgressFn) -> io::Result<usize>
+    #[inline]
+    pub fn transmit_with_progress<R, W>(data: R, to: W, f: ProgressFn) -> io::Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        Xmodem::transmit_with_retries(data, to, DEFAULT_RETRIES, f)
+    }
+
+    /// Transmits `data` to the receiver `to` using the XMODEM protocol. If the
+    /// length of the total data yielded by `data` is not a multiple of 128
+    /// bytes, the data is padded with zeroes and sent to the receiver.
+    ///
+    /// Each packet is sent up to `retries` times. A packet is sent again when
+    /// the receiver answers it with a `NAK` or reading its answer from `to`
+    /// times out, so the transfer picks up from the last packet the receiver
+    /// acknowledged rather than from the beginning.
+    ///
+    /// The function `f` is used as a callback to indicate progress throughout
+    /// the transmission. See the [`Progress`] enum for more information.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `BrokenPipe` is returned if a packet still isn't
+    /// acknowledged after `retries` attempts.
+    pub fn transmit_with_retries<R, W>(mut data: R, to: W, retries: usize, f: ProgressFn) -> io::Result<usize>
         where W: io::Read + io::Write, R: io::Read
     {
         let mut transmitter = Xmodem::new_with_progress(to, f);
         let mut packet = [0u8; 128];
         let mut written = 0;
-        'next_packet: loop {
+        loop {
             let n = data.read_max(&mut packet)?;
             packet[n..].iter_mut().for_each(|b| *b = 0);
 
             if n == 0 {
-                transmitter.write_packet(&[])?;
+                transmitter.write_packet_retrying(&[], retries)?;
                 return Ok(written);
             }
 
-            for _ in 0..10 {
-                match transmitter.write_packet(&packet) {
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e),
-                    Ok(_) => {
-                        written += n;
-                        continue 'next_packet;
-                    }
-                }
-            }
-
-            return ioerr!(BrokenPipe, "bad transmit");
+            transmitter.write_packet_retrying(&packet, retries)?;
+            written += n;
         }
     }
 
@@ -119,6 +139,15 @@ impl Xmodem<()> {
     }
 }
 
+/// Returns `true` if sending a packet failed in a way that sending it again
+/// could fix: the receiver asked for it again, or didn't answer in time.
+fn is_retryable(e: &io::Error) -> bool {
+    match e.kind() {
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => true,
+        _ => false,
+    }
+}
+
 fn get_checksum(buf: &[u8]) -> u8 {
     return buf.iter().fold(0, |a, b| a.wrapping_add(*b));
 }
@@ -331,9 +360,46 @@ impl<T: io::Read + io::Write> Xmodem<T> {
             return Ok(0)
         }
 
+        let packet = self.packet;
+        self.write_block(packet, buf)?;
+        self.packet = self.packet.wrapping_add(1);
+        (self.progress)(Progress::Packet(128));
+        Ok(128)
+    }
+
+    /// Calls `write_packet()` until the receiver acknowledges the packet, up to
+    /// `retries` times. A packet is sent again if the receiver answers it with
+    /// a `NAK` or the inner stream times out waiting for its answer, which is
+    /// reported as `Progress::NAK`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any other error from `write_packet()` right away. An error of
+    /// kind `BrokenPipe` is returned once `retries` attempts have failed.
+    fn write_packet_retrying(&mut self, buf: &[u8], retries: usize) -> io::Result<usize> {
+        for _ in 0..retries {
+            match self.write_packet(buf) {
+                Err(ref e) if is_retryable(e) => (self.progress)(Progress::NAK),
+                result => return result,
+            }
+        }
+
+        ioerr!(BrokenPipe, "packet not acknowledged after all retries")
+    }
+
+    /// Sends the 128 bytes in `buf` as a block numbered `number` and waits for
+    /// the receiver's reply. Returns `Ok` if the receiver sent an `ACK`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or writing to the inner stream fails. An
+    /// error of kind `Interrupted` is returned if the receiver replies with a
+    /// `NAK`, and one of kind `InvalidData` if it replies with anything else
+    /// besides an `ACK`.
+    fn write_block(&mut self, number: u8, buf: &[u8]) -> io::Result<()> {
         let _ = self.write_byte(SOH)?;
-        let _ = self.write_byte(self.packet)?;
-        let _ = self.write_byte(255 - self.packet)?;
+        let _ = self.write_byte(number)?;
+        let _ = self.write_byte(255 - number)?;
 
         for i in 0..128 {
             let _ = self.write_byte(buf[i])?;
@@ -346,9 +412,7 @@ impl<T: io::Read + io::Write> Xmodem<T> {
 
         let reply = self.read_byte(true)?;
         if reply == ACK {
-            self.packet = self.packet.wrapping_add(1);
-            (self.progress)(Progress::Packet(128));
-            Ok(128)
+            Ok(())
         } else if reply == NAK {
             ioerr!(Interrupted, "receiver indicated checksum mismatch")
         } else {
@@ -12,6 +12,7 @@ pub enum Progress {
     Started,
     /// Packet `.0` was transmitted/received.
     Packet(u8),
+    /// The packet being sent will be sent again.
     NAK,
     Unknown,
 }
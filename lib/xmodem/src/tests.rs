@@ -227,3 +227,123 @@ fn test_eot() {
 
     assert_eq!(&buffer[..], &[NAK, EOT, NAK, EOT, ACK]);
 }
+
+#[test]
+fn test_ymodem_header() {
+    let block = ymodem::header("kernel.bin", 1234567).expect("header okay");
+    assert_eq!(&block[..19], b"kernel.bin\x001234567\x00");
+    assert!(block[19..].iter().all(|b| *b == 0));
+
+    let block = ymodem::header("a", 0).expect("empty file okay");
+    assert_eq!(&block[..4], b"a\x000\x00");
+
+    // The name, NUL, size and a final NUL must all fit
+    let name = "n".repeat(128 - 4);
+    assert!(ymodem::header(&name, 123).is_err());
+    assert!(ymodem::header(&name[1..], 123).is_ok());
+
+    for name in &["", "bad\0name"] {
+        let e = ymodem::header(name, 1).expect_err("bad name");
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_ymodem_batch() {
+    /// Reads a header block as a receiver would and returns its contents.
+    fn read_header(pipe: &mut Pipe) -> [u8; 128] {
+        use std::io::{Read, Write};
+
+        pipe.write_all(&[NAK]).expect("write NAK");
+        let mut packet = [0u8; 132];
+        pipe.read_exact(&mut packet).expect("read header");
+        assert_eq!(&packet[..3], &[SOH, 0, 255]);
+        assert_eq!(packet[131], get_checksum(&packet[3..131]));
+        pipe.write_all(&[ACK]).expect("write ACK");
+
+        let mut block = [0u8; 128];
+        block.copy_from_slice(&packet[3..131]);
+        block
+    }
+
+    let (mut tx, mut rx) = pipe();
+    let tx_thread = std::thread::spawn(move || {
+        Xmodem::transmit_file("one", 3, &b"abc"[..], &mut rx).expect("transmit one");
+        Xmodem::transmit_file("two", 200, &[7u8; 200][..], &mut rx).expect("transmit two");
+        Xmodem::finish_batch(&mut rx).expect("finish batch");
+    });
+
+    assert_eq!(&read_header(&mut tx)[..6], b"one\x003\x00");
+    let mut one = [0u8; 128];
+    assert_eq!(Xmodem::receive(&mut tx, &mut one[..]).expect("receive one"), 128);
+    assert_eq!(&one[..4], b"abc\0");
+
+    assert_eq!(&read_header(&mut tx)[..8], b"two\x00200\x00");
+    let mut two = [0u8; 256];
+    assert_eq!(Xmodem::receive(&mut tx, &mut two[..]).expect("receive two"), 256);
+    assert_eq!(&two[..200], &[7u8; 200][..]);
+
+    assert_eq!(&read_header(&mut tx)[..], &[0u8; 128][..]);
+    tx_thread.join().expect("tx join okay");
+}
+
+/// A receiver that answers with the bytes in `.0` in order, where `None`
+/// times out instead. Everything written to it is kept in `.1`.
+struct Scripted(std::vec::IntoIter<Option<u8>>, Vec<u8>);
+
+impl io::Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.next() {
+            Some(Some(byte)) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            Some(None) => Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+            None => Ok(0),
+        }
+    }
+}
+
+impl io::Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.1.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_retry_after_nak() {
+    let input: Vec<u8> = (0..200).map(|i| i as u8).collect();
+    let packet = |number: u8, data: &[u8]| {
+        let mut block = [0u8; 128];
+        block[..data.len()].copy_from_slice(data);
+        let mut bytes = vec![SOH, number, 255 - number];
+        bytes.extend_from_slice(&block);
+        bytes.push(get_checksum(&block));
+        bytes
+    };
+
+    // Packet 2 is NAKed once and its answer times out once, so it's sent
+    // three times, while packet 1 isn't sent again
+    let replies = vec![Some(NAK), Some(ACK), Some(NAK), None, Some(ACK), Some(NAK), Some(ACK)];
+    let mut to = Scripted(replies.into_iter(), vec![]);
+    assert_eq!(Xmodem::transmit_with_retries(&input[..], &mut to, 3, progress::noop).expect("retried"), 200);
+
+    let mut expected = packet(1, &input[..128]);
+    for _ in 0..3 {
+        expected.extend(packet(2, &input[128..]));
+    }
+    expected.extend_from_slice(&[EOT, EOT]);
+    assert_eq!(to.1, expected);
+
+    // Giving up is a clear error
+    let replies = vec![Some(NAK), Some(NAK), None, Some(NAK)];
+    let mut to = Scripted(replies.into_iter(), vec![]);
+    let e = Xmodem::transmit_with_retries(&input[..], &mut to, 3, progress::noop).expect_err("gave up");
+    assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(to.1.len(), 3 * 132);
+}
@@ -0,0 +1,140 @@
+//! YMODEM batch transfers, which send several files in one session.
+//!
+//! Each file is sent as an XMODEM transfer preceded by a header block
+//! numbered 0 that holds the file's name and size, and the batch ends with an
+//! empty header block. Only the 128-byte, checksummed blocks of XMODEM are
+//! used, so the receiver starts every block 0 and every file with a `NAK`.
+
+use shim::io;
+use shim::ioerr;
+
+use crate::{is_retryable, progress, Progress, ProgressFn, Xmodem, DEFAULT_RETRIES, NAK};
+
+/// Returns the header block announcing a file named `name` that is `size`
+/// bytes long: the name, a `NUL`, the size in decimal and zeroes up to 128
+/// bytes.
+///
+/// # Errors
+///
+/// Returns an error of kind `InvalidInput` if `name` is empty or contains a
+/// `NUL`, since either would end the name early, or if the name and size
+/// don't fit in one block.
+pub fn header(name: &str, size: u64) -> io::Result<[u8; 128]> {
+    if name.is_empty() || name.bytes().any(|b| b == 0) {
+        return ioerr!(InvalidInput, "file name must be non-empty and have no NUL");
+    }
+
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    let mut left = size;
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (left % 10) as u8;
+        left /= 10;
+        if left == 0 {
+            break;
+        }
+    }
+    let digits = &digits[start..];
+
+    // The size is followed by at least one zero as well
+    let mut block = [0u8; 128];
+    if name.len() + 1 + digits.len() >= block.len() {
+        return ioerr!(InvalidInput, "file name is too long for the header block");
+    }
+    block[..name.len()].copy_from_slice(name.as_bytes());
+    block[name.len() + 1..name.len() + 1 + digits.len()].copy_from_slice(digits);
+    Ok(block)
+}
+
+impl Xmodem<()> {
+    /// Transmits the `size` bytes yielded by `data` to the receiver `to` as
+    /// the file `name` in a YMODEM batch. Call [`Xmodem::finish_batch()`]
+    /// after the last file.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    #[inline]
+    pub fn transmit_file<R, W>(name: &str, size: u64, data: R, to: W) -> io::Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        Xmodem::transmit_file_with_progress(name, size, data, to, progress::noop)
+    }
+
+    /// Transmits the `size` bytes yielded by `data` to the receiver `to` as
+    /// the file `name` in a YMODEM batch. Call [`Xmodem::finish_batch()`]
+    /// after the last file.
+    ///
+    /// The function `f` is used as a callback to indicate progress throughout
+    /// the transmission of the file's data. See the [`Progress`] enum for more
+    /// information.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    #[inline]
+    pub fn transmit_file_with_progress<R, W>(name: &str, size: u64, data: R, to: W, f: ProgressFn) -> io::Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        Xmodem::transmit_file_with_retries(name, size, data, to, DEFAULT_RETRIES, f)
+    }
+
+    /// Transmits the `size` bytes yielded by `data` to the receiver `to` as
+    /// the file `name` in a YMODEM batch, sending the header and each packet
+    /// up to `retries` times as in [`Xmodem::transmit_with_retries()`]. Call
+    /// [`Xmodem::finish_batch()`] after the last file.
+    ///
+    /// The function `f` is used as a callback to indicate progress throughout
+    /// the transmission of the file's data. See the [`Progress`] enum for more
+    /// information.
+    ///
+    /// Returns the number of bytes written to `to`, excluding padding zeroes.
+    pub fn transmit_file_with_retries<R, W>(name: &str, size: u64, data: R, mut to: W, retries: usize,
+                                            f: ProgressFn) -> io::Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        let block = header(name, size)?;
+        Xmodem::new_with_progress(&mut to, f).write_header_retrying(&block, retries)?;
+        Xmodem::transmit_with_retries(data, to, retries, f)
+    }
+
+    /// Ends a YMODEM batch sent to `to` by sending an empty header block.
+    pub fn finish_batch<W>(to: W) -> io::Result<()>
+        where W: io::Read + io::Write
+    {
+        Xmodem::new(to).write_header_retrying(&[0; 128], DEFAULT_RETRIES)
+    }
+}
+
+impl<T: io::Read + io::Write> Xmodem<T> {
+    /// Sends `block` as a YMODEM header block, numbered 0, once the receiver
+    /// asks for it with a `NAK`. Afterwards the transfer starts over, so the
+    /// file's data can be sent with `write_packet()` as usual.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or writing to the inner stream fails, or if
+    /// the receiver's first byte isn't a `NAK`. An error of kind `Interrupted`
+    /// is returned if the receiver indicates a checksum mismatch.
+    pub fn write_header(&mut self, block: &[u8; 128]) -> io::Result<()> {
+        if !self.started {
+            let _ = self.expect_byte(NAK, "expected NAK to start transmission")?;
+            self.started = true;
+        }
+
+        self.write_block(0, block)?;
+        self.packet = 1;
+        self.started = false;
+        Ok(())
+    }
+
+    /// Calls `write_header()` until the receiver takes the block, up to
+    /// `retries` times, as `write_packet_retrying()` does for packets.
+    fn write_header_retrying(&mut self, block: &[u8; 128], retries: usize) -> io::Result<()> {
+        for _ in 0..retries {
+            match self.write_header(block) {
+                Err(ref e) if is_retryable(e) => (self.progress)(Progress::NAK),
+                result => return result,
+            }
+        }
+
+        ioerr!(BrokenPipe, "header not acknowledged after all retries")
+    }
+}
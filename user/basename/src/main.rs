@@ -0,0 +1,32 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+
+use coreutils::path::basename;
+use kernel_api::println;
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// Prints the last component of a path: `basename path [suffix]`, with
+/// `suffix` removed from the end if it's there. Trailing slashes are ignored,
+/// so `/a/b/` prints `b`, and `/` prints itself.
+fn main(args: &[&str]) {
+    let (path, suffix) = match args {
+        [path] => (*path, ""),
+        [path, suffix] => (*path, *suffix),
+        _ => {
+            println!("USAGE: basename [path] [suffix]");
+            return
+        },
+    };
+
+    println!("{}", basename(path, suffix));
+}
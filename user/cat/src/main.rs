@@ -7,51 +7,73 @@
 mod cr0;
 mod allocator;
 
-#[macro_use]
 extern crate alloc;
-use alloc::string::String;
+use alloc::vec::Vec;
 
 use shim::io::SeekFrom;
 use kernel_api::{print, println, OsResult, OsError};
-use kernel_api::syscall::{env_get, fs_open, fs_close, file_seek, file_read};
+use kernel_api::syscall::{fs_open, fs_close, file_seek, file_read};
 
 #[global_allocator]
 pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
 
-fn cat_file(path: String) -> OsResult<()> {
-    let fd = fs_open(&path)?;
+/// Prints the file at `path`, prefixing each line with its number if
+/// `number_lines`. A multibyte character split across two reads is held back
+/// until the rest of it arrives.
+fn cat_file(path: &str, number_lines: bool) -> OsResult<()> {
+    let fd = fs_open(path)?;
     file_seek(&fd, SeekFrom::Start(0))?;
     let mut buf = [0u8; 128];
-    loop {
-        let amt_read = file_read(&fd, &mut buf)?;
-        let buf_str = core::str::from_utf8(&buf[0..amt_read]).map_err(|_| OsError::Unknown)?;
-        print!("{}", buf_str);
-        if amt_read < 128 { break }
-        //file_seek(&fd, SeekFrom::Current(amt_read as i64))?;
+    let mut pending = Vec::new();
+    let mut line = 1;
+    let mut at_line_start = true;
+    let result = loop {
+        let amt_read = match file_read(&fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(amt_read) => amt_read,
+            Err(e) => break Err(e),
+        };
+        pending.extend_from_slice(&buf[0..amt_read]);
+
+        let valid = match core::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            // Only the end of a character is missing, so wait for more
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => break Err(OsError::IoErrorInvalidData),
+        };
+        let mut rest = core::str::from_utf8(&pending[0..valid]).unwrap_or_default();
+        while !rest.is_empty() {
+            if number_lines && at_line_start {
+                print!("{:>6}\t", line);
+                line += 1;
+            }
+            let end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+            print!("{}", &rest[..end]);
+            at_line_start = rest[..end].ends_with('\n');
+            rest = &rest[end..];
+        }
+        pending.drain(0..valid);
+    };
+    fs_close(&fd)?;
+    match result {
+        Ok(()) if !pending.is_empty() => Err(OsError::IoErrorInvalidData),
+        result => result,
     }
-    fs_close(&fd)
 }
 
 fn main(args: &[&str]) {
-    let mut cwd_buf = [0u8; 128];
-    let cwd = match env_get("CWD", &mut cwd_buf) {
-        Ok(len) => core::str::from_utf8(&cwd_buf[0..len]).expect("Couldn't parse as UTF-8"),
-        Err(e) => {
-            println!("Couldn't read $CWD: {:?}", e);
-            return
-        },
+    let (number_lines, paths) = match args.first() {
+        Some(&"-n") => (true, &args[1..]),
+        _ => (false, args),
     };
 
-    for arg in args {
-        let full_path = if arg.chars().nth(0) == Some('/') {
-            String::from(*arg)
-        } else {
-            format!("{}{}", cwd, arg)
-        };
-
-        match cat_file(full_path) {
+    for (i, path) in paths.iter().enumerate() {
+        if paths.len() > 1 {
+            println!("{}==> {} <==", if i > 0 { "\r\n" } else { "" }, path);
+        }
+        match cat_file(path, number_lines) {
             Ok(_) => (),
-            Err(e) => println!("Error while reading from {}: {:?}", arg, e),
+            Err(e) => println!("Error while reading from {}: {:?}", path, e),
         }
     }
 }
@@ -0,0 +1,15 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::print;
+
+/// ANSI escape sequence that clears the screen and moves the cursor to the
+/// top-left corner.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+fn main(_args: &[&str]) {
+    print!("{}", CLEAR_SCREEN);
+}
@@ -0,0 +1,95 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use coreutils::cmp::first_mismatch;
+use kernel_api::{println, Fd, OsResult};
+use kernel_api::syscall::{exit_with, fs_close, fs_open, file_read};
+
+/// The size of the chunks that both files are compared in.
+const CHUNK: usize = 512;
+
+/// Reads until `buf` is full or the file ends, so that both files are always
+/// compared at the same offsets. Returns the amount read.
+fn read_chunk(fd: &Fd, buf: &mut [u8]) -> OsResult<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file_read(fd, &mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+/// Compares the open files `a` and `b`, printing where they first differ.
+/// Returns `true` if they're identical.
+fn compare(a: (&str, &Fd), b: (&str, &Fd)) -> OsResult<bool> {
+    let (mut a_buf, mut b_buf) = ([0u8; CHUNK], [0u8; CHUNK]);
+    let mut offset = 0;
+    let mut line = 1;
+    loop {
+        let a_len = read_chunk(a.1, &mut a_buf)?;
+        let b_len = read_chunk(b.1, &mut b_buf)?;
+        let (a_chunk, b_chunk) = (&a_buf[..a_len], &b_buf[..b_len]);
+
+        match first_mismatch(a_chunk, b_chunk) {
+            None if a_len < CHUNK => return Ok(true),
+            None => (),
+            Some(i) => {
+                line += a_chunk[..i].iter().filter(|&&b| b == b'\n').count();
+                // Offsets are counted from 1, like Unix cmp
+                if i < a_len && i < b_len {
+                    println!("{} {} differ: byte {}, line {}", a.0, b.0, offset + i + 1, line);
+                } else {
+                    let shorter = if a_len < b_len { a.0 } else { b.0 };
+                    println!("cmp: EOF on {} after byte {}", shorter, offset + i);
+                }
+                return Ok(false)
+            },
+        }
+
+        line += a_chunk.iter().filter(|&&b| b == b'\n').count();
+        offset += CHUNK;
+    }
+}
+
+/// Compares two files byte by byte: `cmp a b`. Prints nothing and exits with
+/// 0 if they're identical, reports the first difference and exits with 1 if
+/// not, and exits with 2 if either file can't be read.
+fn main(args: &[&str]) {
+    if args.len() != 2 {
+        println!("USAGE: cmp [file] [file]");
+        exit_with(2)
+    }
+
+    let a = match fs_open(args[0]) {
+        Ok(fd) => fd,
+        Err(e) => {
+            println!("cmp: {}: {:?}", args[0], e);
+            exit_with(2)
+        },
+    };
+    let b = match fs_open(args[1]) {
+        Ok(fd) => fd,
+        Err(e) => {
+            println!("cmp: {}: {:?}", args[1], e);
+            let _ = fs_close(&a);
+            exit_with(2)
+        },
+    };
+
+    let result = compare((args[0], &a), (args[1], &b));
+    let _ = fs_close(&a);
+    let _ = fs_close(&b);
+    match result {
+        Ok(true) => (),
+        Ok(false) => exit_with(1),
+        Err(e) => {
+            println!("Error while comparing {} and {}: {:?}", args[0], args[1], e);
+            exit_with(2)
+        },
+    }
+}
@@ -0,0 +1,197 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::cp::{copy, is_within, join, Fs};
+use kernel_api::{println, EntryKind, Fd, OsResult, OsError};
+use kernel_api::syscall::{dir_entry, getcwd, fs_close, fs_create, fs_delete, fs_flush, fs_open, file_read, file_write};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// The size of the chunks that files are copied in.
+const COPY_CHUNK: usize = 512;
+
+/// Returns `true` if there is a file or directory at `path`.
+fn exists(path: &str) -> bool {
+    match fs_open(path) {
+        Ok(fd) => {
+            let _ = fs_close(&fd);
+            true
+        },
+        Err(_) => false,
+    }
+}
+
+fn is_dir(path: &str) -> bool {
+    let mut name_buf = [0u8; 512];
+    match dir_entry(path, &mut name_buf, 0) {
+        // Only a path that isn't a directory fails to open as one
+        Err(OsError::IoErrorInvalidInput) | Err(OsError::InvalidArgument) => false,
+        _ => true,
+    }
+}
+
+/// Returns `path` as an absolute path without `.` or `..` components, so that
+/// paths naming the same entry compare equal.
+fn absolute(path: &str) -> String {
+    let mut cwd_buf = [0u8; 512];
+    let cwd = match getcwd(&mut cwd_buf) {
+        Ok(len) if !path.starts_with('/') => core::str::from_utf8(&cwd_buf[..len]).unwrap_or("/"),
+        _ => "/",
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in cwd.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => (),
+            ".." => { components.pop(); },
+            name => components.push(name),
+        }
+    }
+    if components.is_empty() {
+        return String::from("/")
+    }
+    let mut absolute = String::new();
+    for component in components {
+        absolute.push('/');
+        absolute.push_str(component);
+    }
+    absolute
+}
+
+fn copy_fds(src: &Fd, dst: &Fd) -> OsResult<()> {
+    let mut buf = [0u8; COPY_CHUNK];
+    loop {
+        let amt_read = file_read(src, &mut buf)?;
+        if amt_read == 0 { return Ok(()) }
+
+        let mut written = 0;
+        while written < amt_read {
+            match file_write(dst, &buf[written..amt_read])? {
+                0 => return Err(OsError::IoError),
+                n => written += n,
+            }
+        }
+    }
+}
+
+fn copy_file(src: &str, dst: &str) -> OsResult<()> {
+    let src_fd = fs_open(src)?;
+
+    // There's no truncate, so start over with a new file
+    let _ = fs_delete(dst);
+    let dst_fd = match fs_create(dst, EntryKind::File).and_then(|_| fs_open(dst)) {
+        Ok(fd) => fd,
+        Err(e) => {
+            let _ = fs_close(&src_fd);
+            return Err(e)
+        },
+    };
+
+    let result = copy_fds(&src_fd, &dst_fd);
+    let _ = fs_close(&src_fd);
+    let _ = fs_close(&dst_fd);
+    result?;
+    fs_flush(dst)
+}
+
+/// The filesystem, through syscalls.
+struct Kernel;
+
+impl Fs for Kernel {
+    fn is_dir(&mut self, path: &str) -> bool {
+        is_dir(path)
+    }
+
+    fn create_dir(&mut self, path: &str) -> OsResult<()> {
+        fs_create(path, EntryKind::Dir)?;
+        fs_flush(path)
+    }
+
+    fn entries(&mut self, path: &str) -> OsResult<Vec<String>> {
+        let mut names = Vec::new();
+        let mut offset = 0;
+        loop {
+            let mut name_buf = [0u8; 512];
+            let has_next = match dir_entry(path, &mut name_buf, offset) {
+                Ok(has_next) => has_next,
+                Err(OsError::IoErrorEof) => break,
+                Err(e) => return Err(e),
+            };
+            offset += 1;
+
+            let len = name_buf.iter().position(|&b| b == 0).unwrap_or(name_buf.len());
+            let name = core::str::from_utf8(&name_buf[..len]).map_err(|_| OsError::IoErrorInvalidData)?;
+            names.push(String::from(name));
+
+            if !has_next { break }
+        }
+        Ok(names)
+    }
+
+    fn copy_file(&mut self, src: &str, dst: &str) -> OsResult<()> {
+        copy_file(src, dst)
+    }
+}
+
+/// Copies a file, or a directory with `-r`: `cp [-r] source destination`.
+/// Relative paths are resolved against the working directory by the kernel.
+/// If the destination is a directory, the source is copied into it.
+fn main(args: &[&str]) {
+    let (recursive, args) = match args.first() {
+        Some(&"-r") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() != 2 {
+        println!("USAGE: cp [-r] [source] [destination]");
+        return
+    }
+    if !exists(args[0]) {
+        println!("cp: {}: No such file or directory", args[0]);
+        return
+    }
+
+    // Compare absolute paths, so that copying `.` into `./sub` is caught
+    let src = absolute(args[0]);
+    let mut dst = absolute(args[1]);
+
+    // Copying into a directory keeps the source's name
+    if is_dir(&dst) {
+        let name = src.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        if name.is_empty() {
+            println!("Can't copy /");
+            return
+        }
+        dst = join(&dst, name);
+    }
+    if dst == src {
+        println!("cp: {} and {} are the same file", args[0], args[1]);
+        return
+    }
+    if is_dir(&src) && !recursive {
+        println!("cp: {}: is a directory (use cp -r)", args[0]);
+        return
+    }
+    if is_within(&dst, &src) {
+        println!("Can't copy {} into itself", args[0]);
+        return
+    }
+    if is_dir(&dst) && !is_dir(&src) {
+        println!("cp: {}: Can't overwrite a directory with a file", dst);
+        return
+    }
+
+    if let Err(e) = copy(&mut Kernel, &src, &dst, recursive) {
+        println!("Error while copying {} to {}: {:?}", args[0], args[1], e);
+    }
+}
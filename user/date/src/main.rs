@@ -0,0 +1,26 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::{println, DateTime};
+use kernel_api::syscall::{time_wall, time_set_wall};
+
+/// Prints the wall-clock time: `date [-u]`, where `-u` prints raw seconds
+/// since the Unix epoch. `date -s seconds` sets the clock instead, since
+/// there's no real-time clock to read it from at boot.
+fn main(args: &[&str]) {
+    match args {
+        [] => println!("{} UTC", DateTime::from_epoch(time_wall().as_secs())),
+        ["-u"] => println!("{}", time_wall().as_secs()),
+        ["-s", secs] => match secs.parse::<u64>() {
+            Ok(secs) => match time_set_wall(secs) {
+                Ok(()) => println!("{} UTC", DateTime::from_epoch(secs)),
+                Err(e) => println!("Couldn't set the time: {:?}", e),
+            },
+            Err(_) => println!("date: invalid number of seconds: {}", secs),
+        },
+        _ => println!("USAGE: date [-u] [-s seconds]"),
+    }
+}
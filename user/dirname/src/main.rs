@@ -0,0 +1,28 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+
+use coreutils::path::dirname;
+use kernel_api::println;
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// Prints a path with its last component removed: `dirname path`. Trailing
+/// slashes are ignored, so `/a/b/` prints `/a`. The parent of `/` is `/`, and
+/// a bare name's parent is the working directory, `.`.
+fn main(args: &[&str]) {
+    if args.len() != 1 {
+        println!("USAGE: dirname [path]");
+        return
+    }
+
+    println!("{}", dirname(args[0]));
+}
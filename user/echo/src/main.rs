@@ -5,7 +5,7 @@
 mod cr0;
 
 use kernel_api::{print, println};
-use kernel_api::syscall::{input, output, fork, exec, wait_pid, getpid, sleep, exit};
+use kernel_api::syscall::{input, fork, exec, wait_pid, getpid, sleep, exit};
 
 fn main(args: &[&str]) {
     for arg in args {
@@ -14,15 +14,14 @@ fn main(args: &[&str]) {
 
     print!("Echo {}> ", getpid());
     loop {
+        // The console echoes input itself in cooked mode
         let ch = input();
-        output(ch);
         if ch == '\n' as u8 || ch == '\r' as u8 {
             break;
         } else if ch == '!' as u8 {
             exit();
         }
     }
-    println!("");
 
     println!("Forking {}...", getpid());
 
@@ -0,0 +1,112 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+#[macro_use]
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::path::search_path;
+use kernel_api::{println, unpack_env_vars, OsResult, OsError};
+use kernel_api::syscall::{env_get, env_set, env_unset, env_vars, exec, fs_open, fs_close, exit_with};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// Fetches every environment variable, growing the buffer until they fit.
+fn fetch_vars() -> OsResult<Vec<u8>> {
+    let mut buf = vec![0u8; 256];
+    loop {
+        let len = env_vars(&mut buf)?;
+        if len <= buf.len() {
+            buf.truncate(len);
+            return Ok(buf)
+        }
+        buf = vec![0u8; len];
+    }
+}
+
+/// Finds the program `name` runs, looking it up in `$PATH` unless it starts
+/// with `/`.
+fn resolve_program(name: &str) -> Option<String> {
+    if name.starts_with('/') {
+        return Some(String::from(name))
+    }
+
+    let mut path_buf = [0u8; 256];
+    let path = match env_get("PATH", &mut path_buf) {
+        Ok(len) => core::str::from_utf8(&path_buf[0..len]).unwrap_or_default(),
+        Err(_) => return None,
+    };
+    let mut program_buf = [0u8; 512];
+    let exists = |path: &str| match fs_open(path) {
+        Ok(fd) => {
+            let _ = fs_close(&fd);
+            true
+        },
+        Err(_) => false,
+    };
+    search_path(path, name, &mut program_buf, exists).map(String::from)
+}
+
+/// Changes the environment and runs a command in it:
+/// `env [-u name]... [name=value]... [command [args...]]`. Without a command,
+/// prints the resulting environment instead.
+fn main(args: &[&str]) {
+    let mut args = args;
+    while let Some(&arg) = args.first() {
+        if arg == "-u" {
+            let name = match args.get(1) {
+                Some(name) => name,
+                None => {
+                    println!("USAGE: env [-u name]... [name=value]... [command [args...]]");
+                    return
+                },
+            };
+            match env_unset(name) {
+                // Unsetting a variable that isn't set is fine
+                Ok(()) | Err(OsError::NoEntry) => (),
+                Err(e) => println!("Couldn't unset {}: {:?}", name, e),
+            }
+            args = &args[2..];
+        } else if let Some(eq) = arg.find('=').filter(|&eq| eq > 0) {
+            if let Err(e) = env_set(&arg[..eq], &arg[eq + 1..]) {
+                println!("Couldn't set {}: {:?}", &arg[..eq], e);
+            }
+            args = &args[1..];
+        } else {
+            break
+        }
+    }
+
+    if let Some(&command) = args.first() {
+        let program = match resolve_program(command) {
+            Some(program) => program,
+            None => {
+                println!("{}: command not found", command);
+                exit_with(127)
+            },
+        };
+        // The environment is kept across exec, so the command sees it
+        if let Err(e) = exec(&program, &args[1..]) {
+            println!("Couldn't run {}: {:?}", command, e);
+            exit_with(126)
+        }
+        return
+    }
+
+    match fetch_vars() {
+        Ok(buf) => {
+            for (name, value) in unpack_env_vars(&buf) {
+                println!("{}={}", name, value);
+            }
+        },
+        Err(e) => println!("Couldn't list the environment: {:?}", e),
+    }
+}
@@ -0,0 +1,81 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::find::{walk, Filter, Found};
+use kernel_api::{println, EntryKind, OsResult, OsError, StatBuf};
+use kernel_api::syscall::{dir_entry, fs_metadata};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+fn children(dir: &str) -> OsResult<Vec<String>> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut name_buf = [0u8; 512];
+        let has_next = match dir_entry(dir, &mut name_buf, offset) {
+            Ok(has_next) => has_next,
+            Err(OsError::IoErrorEof) => break,
+            Err(e) => return Err(e),
+        };
+        offset += 1;
+
+        let len = name_buf.iter().position(|&b| b == 0).unwrap_or(name_buf.len());
+        let name = core::str::from_utf8(&name_buf[..len]).map_err(|_| OsError::IoErrorInvalidData)?;
+        if name != "." && name != ".." {
+            names.push(String::from(name));
+        }
+
+        if !has_next { break }
+    }
+    Ok(names)
+}
+
+/// Prints `path` if it matches `filter`, then everything under it that does.
+fn print_matches(path: &str, filter: &Filter) {
+    let mut kind_of = |path: &str| {
+        let mut buf = StatBuf::default();
+        fs_metadata(path, &mut buf).map(|_| buf.kind())
+    };
+    walk(path, filter, &mut kind_of, &mut children, &mut |found| match found {
+        Found::Match(path) => println!("{}", path),
+        Found::Error(path, e) => println!("find: {}: {:?}", path, e),
+    });
+}
+
+/// Prints every path under a directory that matches all of the tests:
+/// `find [path] [-name pattern] [-type f|d]`. The path defaults to `.`.
+fn main(args: &[&str]) {
+    const USAGE: &str = "USAGE: find [path] [-name pattern] [-type f|d]";
+
+    let (root, mut args) = match args.first() {
+        Some(path) if !path.starts_with('-') => (*path, &args[1..]),
+        _ => (".", args),
+    };
+
+    let mut filter = Filter { name: None, kind: None };
+    while !args.is_empty() {
+        match (args[0], args.get(1)) {
+            ("-name", Some(pattern)) => filter.name = Some(*pattern),
+            ("-type", Some(&"f")) => filter.kind = Some(EntryKind::File),
+            ("-type", Some(&"d")) => filter.kind = Some(EntryKind::Dir),
+            _ => {
+                println!("{}", USAGE);
+                return
+            },
+        }
+        args = &args[2..];
+    }
+
+    print_matches(root, &filter);
+}
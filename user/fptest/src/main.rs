@@ -0,0 +1,58 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::println;
+use kernel_api::syscall::{exit_with, fork, getpid, wait_pid};
+
+const ITERATIONS: u64 = 10000;
+const ROUNDS: u64 = 200;
+
+fn series(seed: f64) -> f64 {
+    let mut x = 0.0;
+    for i in 0..ITERATIONS {
+        x = x * 0.999 + seed / (i as f64 + 1.0);
+    }
+    x
+}
+
+// Recomputes the series many times, long enough to be preempted while the
+// other process runs its own series, and checks the result never changes.
+// Returns the number of corrupted rounds.
+fn check(seed: f64) -> u64 {
+    let expected = series(seed).to_bits();
+    let mut corrupted = 0;
+    for _ in 0..ROUNDS {
+        // Stop the compiler from hoisting the computation out of the loop
+        let seed = unsafe { core::ptr::read_volatile(&seed) };
+        if series(seed).to_bits() != expected {
+            corrupted += 1;
+        }
+    }
+    corrupted
+}
+
+fn main(_args: &[&str]) {
+    let child = match fork() {
+        Ok(0) => {
+            let corrupted = check(2.5);
+            exit_with(corrupted);
+        },
+        Ok(child) => child,
+        Err(e) => {
+            println!("Couldn't fork: {:?}", e);
+            return
+        },
+    };
+
+    let corrupted = check(1.5);
+    println!("[{}] {} corrupted rounds", getpid(), corrupted);
+
+    match wait_pid(child) {
+        Ok(status) if status.success() && corrupted == 0 => println!("PASS"),
+        Ok(status) => println!("FAIL: child exited with {:?}", status),
+        Err(e) => println!("Couldn't wait on child: {:?}", e),
+    }
+}
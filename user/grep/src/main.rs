@@ -0,0 +1,117 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use kernel_api::{print, println, OsResult};
+use kernel_api::syscall::{fs_open, fs_close, file_read};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+struct Options {
+    line_numbers: bool,
+    ignore_case: bool,
+    /// Print the file name before each match, when searching several files.
+    with_filename: bool,
+}
+
+/// Prints `line` if it contains `pattern`. When ignoring case, `pattern` is
+/// already lowercase.
+fn match_line(line: &[u8], number: usize, path: &str, pattern: &str, options: &Options) {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim_end_matches('\r');
+    let found = if options.ignore_case {
+        line.to_lowercase().contains(pattern)
+    } else {
+        line.contains(pattern)
+    };
+    if !found { return }
+
+    if options.with_filename {
+        print!("{}:", path);
+    }
+    if options.line_numbers {
+        print!("{}:", number);
+    }
+    println!("{}", line);
+}
+
+/// Searches the file at `path` line by line. A line split across reads is
+/// held back until the rest of it arrives, so lines of any length match.
+fn grep_file(path: &str, pattern: &str, options: &Options) -> OsResult<()> {
+    let fd = fs_open(path)?;
+    let mut buf = [0u8; 512];
+    let mut pending = Vec::new();
+    let mut number = 0;
+    let result = loop {
+        let amt_read = match file_read(&fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(amt_read) => amt_read,
+            Err(e) => break Err(e),
+        };
+        pending.extend_from_slice(&buf[0..amt_read]);
+
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+            number += 1;
+            match_line(&pending[start..start + end], number, path, pattern, options);
+            start += end + 1;
+        }
+        pending.drain(0..start);
+    };
+    fs_close(&fd)?;
+
+    // The last line doesn't have to end in a newline
+    if result.is_ok() && !pending.is_empty() {
+        match_line(&pending, number + 1, path, pattern, options);
+    }
+    result
+}
+
+/// Prints the lines of each file that contain a literal pattern:
+/// `grep [-ni] pattern file...`.
+fn main(args: &[&str]) {
+    let mut options = Options { line_numbers: false, ignore_case: false, with_filename: false };
+    let mut args = args;
+    while let Some(flags) = args.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+        for flag in flags[1..].chars() {
+            match flag {
+                'n' => options.line_numbers = true,
+                'i' => options.ignore_case = true,
+                _ => {
+                    println!("grep: unknown flag -{}", flag);
+                    println!("USAGE: grep [-ni] [pattern] [file...]");
+                    return
+                },
+            }
+        }
+        args = &args[1..];
+    }
+    if args.len() < 2 {
+        println!("USAGE: grep [-ni] [pattern] [file...]");
+        return
+    }
+
+    let pattern = if options.ignore_case {
+        args[0].to_lowercase()
+    } else {
+        String::from(args[0])
+    };
+    let paths = &args[1..];
+    options.with_filename = paths.len() > 1;
+
+    for path in paths {
+        if let Err(e) = grep_file(path, &pattern, &options) {
+            println!("Error while reading from {}: {:?}", path, e);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::lines::first_lines_len;
+use kernel_api::{print, println, OsResult};
+use kernel_api::syscall::{fs_open, fs_close, file_read};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// The number of lines printed without `-n`.
+const DEFAULT_LINES: usize = 10;
+
+/// Reads the first `n` lines of the file at `path`, stopping as soon as they
+/// have all been read.
+fn head_file(path: &str, n: usize) -> OsResult<Vec<u8>> {
+    let fd = fs_open(path)?;
+    let mut buf = [0u8; 512];
+    let mut lines = Vec::new();
+    let mut remaining = n;
+    let result = loop {
+        let amt_read = match file_read(&fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(amt_read) => amt_read,
+            Err(e) => break Err(e),
+        };
+
+        let chunk = &buf[0..amt_read];
+        match first_lines_len(chunk, remaining) {
+            Some(len) => {
+                lines.extend_from_slice(&chunk[..len]);
+                break Ok(())
+            },
+            None => {
+                lines.extend_from_slice(chunk);
+                remaining -= chunk.iter().filter(|&&b| b == b'\n').count();
+            },
+        }
+    };
+    fs_close(&fd)?;
+    result.map(|_| lines)
+}
+
+/// Prints the first lines of each file: `head [-n lines] file...`.
+fn main(args: &[&str]) {
+    let (n, paths) = match args.first() {
+        Some(&"-n") if args.len() > 1 => match args[1].parse::<usize>() {
+            Ok(n) => (n, &args[2..]),
+            Err(_) => {
+                println!("head: invalid number of lines: {}", args[1]);
+                return
+            },
+        },
+        _ => (DEFAULT_LINES, args),
+    };
+    if paths.is_empty() {
+        println!("USAGE: head [-n lines] [file...]");
+        return
+    }
+
+    for (i, path) in paths.iter().enumerate() {
+        if paths.len() > 1 {
+            println!("{}==> {} <==", if i > 0 { "\r\n" } else { "" }, path);
+        }
+        match head_file(path, n) {
+            Ok(lines) => print!("{}", String::from_utf8_lossy(&lines)),
+            Err(e) => println!("Error while reading from {}: {:?}", path, e),
+        }
+    }
+}
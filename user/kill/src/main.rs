@@ -0,0 +1,36 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::{println, OsError};
+use kernel_api::syscall::kill;
+
+/// Kills each process given: `kill [-signal] pid...`. Every process is killed
+/// outright, so a signal like `-9` or `-KILL` is accepted and ignored.
+fn main(args: &[&str]) {
+    let pids = match args.first() {
+        Some(flag) if flag.starts_with('-') && flag.len() > 1 => &args[1..],
+        _ => args,
+    };
+    if pids.is_empty() {
+        println!("USAGE: kill [-signal] [pid...]");
+        return
+    }
+
+    for arg in pids {
+        let pid = match arg.parse::<u64>() {
+            Ok(pid) if pid > 0 => pid,
+            _ => {
+                println!("kill: {}: Not a process ID", arg);
+                continue
+            },
+        };
+        match kill(pid) {
+            Ok(()) => println!("Killed {}", pid),
+            Err(OsError::NoEntry) => println!("kill: {}: No such process", pid),
+            Err(e) => println!("Error while killing {}: {:?}", pid, e),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::{println, OsError};
+use kernel_api::syscall::fs_link;
+
+/// Adds a second name for a file: `ln source link`. Both names share the
+/// file's clusters, so writes through one show up through the other, but
+/// each keeps its own size and timestamps since FAT has nowhere to share them.
+fn main(args: &[&str]) {
+    if args.len() != 2 {
+        println!("USAGE: ln [source] [link]");
+        return
+    }
+
+    match fs_link(args[0], args[1]) {
+        Ok(()) => (),
+        Err(OsError::NoEntry) => println!("ln: {}: No such file or directory", args[0]),
+        Err(OsError::FileExists) => println!("ln: {}: File exists", args[1]),
+        Err(OsError::CrossDevice) => println!("ln: Can't link across mounts"),
+        Err(OsError::InvalidArgument) => println!("ln: {}: Can only link non-empty files", args[0]),
+        Err(e) => println!("Error while linking {} to {}: {:?}", args[1], args[0], e),
+    }
+}
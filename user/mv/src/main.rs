@@ -0,0 +1,170 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+#[macro_use]
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use kernel_api::{println, EntryKind, Fd, OsResult, OsError};
+use kernel_api::syscall::{dir_entry, fs_close, fs_create, fs_delete, fs_flush, fs_open, fs_rename, file_read, file_write};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// The size of the chunks that files are copied in across mounts.
+const COPY_CHUNK: usize = 512;
+
+/// Returns `true` if there is a file or directory at `path`.
+fn exists(path: &str) -> bool {
+    match fs_open(path) {
+        Ok(fd) => {
+            let _ = fs_close(&fd);
+            true
+        },
+        Err(_) => false,
+    }
+}
+
+fn is_dir(path: &str) -> bool {
+    let mut name_buf = [0u8; 512];
+    match dir_entry(path, &mut name_buf, 0) {
+        // Only a path that isn't a directory fails to open as one
+        Err(OsError::IoErrorInvalidInput) | Err(OsError::InvalidArgument) => false,
+        _ => true,
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn children(dir: &str) -> OsResult<Vec<String>> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    loop {
+        let mut name_buf = [0u8; 512];
+        let has_next = match dir_entry(dir, &mut name_buf, offset) {
+            Ok(has_next) => has_next,
+            Err(OsError::IoErrorEof) => break,
+            Err(e) => return Err(e),
+        };
+        offset += 1;
+
+        let len = name_buf.iter().position(|&b| b == 0).unwrap_or(name_buf.len());
+        let name = core::str::from_utf8(&name_buf[..len]).map_err(|_| OsError::IoErrorInvalidData)?;
+        if name != "." && name != ".." {
+            names.push(String::from(name));
+        }
+
+        if !has_next { break }
+    }
+    Ok(names)
+}
+
+fn copy_fds(src: &Fd, dst: &Fd) -> OsResult<()> {
+    let mut buf = [0u8; COPY_CHUNK];
+    loop {
+        let amt_read = file_read(src, &mut buf)?;
+        if amt_read == 0 { return Ok(()) }
+
+        let mut written = 0;
+        while written < amt_read {
+            match file_write(dst, &buf[written..amt_read])? {
+                0 => return Err(OsError::IoError),
+                n => written += n,
+            }
+        }
+    }
+}
+
+fn copy(src: &str, dst: &str) -> OsResult<()> {
+    if is_dir(src) {
+        fs_create(dst, EntryKind::Dir)?;
+        for name in children(src)? {
+            copy(&join(src, &name), &join(dst, &name))?;
+        }
+        return fs_flush(dst)
+    }
+
+    let src_fd = fs_open(src)?;
+    // There's no truncate, so start over with a new file
+    let _ = fs_delete(dst);
+    let dst_fd = match fs_create(dst, EntryKind::File).and_then(|_| fs_open(dst)) {
+        Ok(fd) => fd,
+        Err(e) => {
+            let _ = fs_close(&src_fd);
+            return Err(e)
+        },
+    };
+
+    let result = copy_fds(&src_fd, &dst_fd);
+    let _ = fs_close(&src_fd);
+    let _ = fs_close(&dst_fd);
+    result?;
+    fs_flush(dst)
+}
+
+fn remove(path: &str) -> OsResult<()> {
+    if is_dir(path) {
+        for name in children(path)? {
+            remove(&join(path, &name))?;
+        }
+    }
+    fs_delete(path)?;
+    fs_flush(path)
+}
+
+/// Moves a file or directory: `mv source destination`. Relative paths are
+/// resolved against the working directory by the kernel. If the destination
+/// is a directory, the source is moved into it.
+fn main(args: &[&str]) {
+    if args.len() != 2 {
+        println!("USAGE: mv [source] [destination]");
+        return
+    }
+    if !exists(args[0]) {
+        println!("mv: {}: No such file or directory", args[0]);
+        return
+    }
+
+    let src = String::from(args[0]);
+    let mut dst = String::from(args[1]);
+
+    // Moving into a directory keeps the source's name
+    if is_dir(&dst) {
+        let name = src.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        if name.is_empty() {
+            println!("Can't move /");
+            return
+        }
+        dst = join(&dst, name);
+    }
+    if dst == src || dst.starts_with(&join(&src, "")) {
+        println!("Can't move {} into itself", args[0]);
+        return
+    }
+    if is_dir(&dst) && !is_dir(&src) {
+        println!("mv: {}: Can't overwrite a directory with a file", dst);
+        return
+    }
+
+    // Renaming only works within a mount, otherwise copy and then delete
+    let result = match fs_rename(&src, &dst) {
+        Err(OsError::CrossDevice) => copy(&src, &dst).and_then(|_| remove(&src)),
+        result => result,
+    };
+    if let Err(e) = result {
+        println!("Error while moving {} to {}: {:?}", args[0], args[1], e);
+    }
+}
@@ -7,7 +7,7 @@ fi
 
 MNT=$1
 
-PROGS=(sleep fib echo shell mkdir touch rm lsblk mount umount su ls)
+PROGS=(sleep fib echo shell mkdir touch rm lsblk mount umount su ls ps fptest cp mv clear wc grep head tail kill env date stat write uptime yes true false seq xxd sort uniq find ln basename dirname reboot tee cmp)
 
 for d in ${PROGS[@]}; do
     (cd $d; make build)
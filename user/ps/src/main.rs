@@ -0,0 +1,58 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+#[macro_use]
+extern crate alloc;
+use alloc::vec::Vec;
+
+use kernel_api::{println, OsResult, ProcRecord, ProcState};
+use kernel_api::syscall::proc_list;
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// The number of records room is made for on the first try.
+const INITIAL_PROCS: usize = 16;
+
+/// Fetches the whole process table. The kernel reports how many processes
+/// there are even if they don't all fit, so retry with a buffer that large
+/// until the table stops growing under us.
+fn fetch_records() -> OsResult<Vec<ProcRecord>> {
+    let mut records = vec![ProcRecord::default(); INITIAL_PROCS];
+    loop {
+        let count = proc_list(&mut records)?;
+        if count <= records.len() {
+            records.truncate(count);
+            return Ok(records)
+        }
+        records = vec![ProcRecord::default(); count];
+    }
+}
+
+fn main(_args: &[&str]) {
+    let mut records = match fetch_records() {
+        Ok(records) => records,
+        Err(e) => {
+            println!("Couldn't list processes: {:?}", e);
+            return
+        },
+    };
+    records.sort_by_key(|record| record.pid);
+
+    println!("PID   PPID  STATE         TIME NAME");
+    for record in &records {
+        let state = match record.state() {
+            ProcState::Ready => "ready",
+            ProcState::Running => "running",
+            ProcState::Waiting => "waiting",
+            ProcState::Zombie => "zombie",
+        };
+        println!("{:<5} {:<5} {:<8} {:>7}ms {}", record.pid, record.parent, state, record.cpu_ticks / 1000, record.name());
+    }
+}
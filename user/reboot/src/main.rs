@@ -0,0 +1,24 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::{println, OsError};
+use kernel_api::syscall::reboot;
+
+/// Restarts the board: `reboot`. The kernel kills every process and flushes
+/// every mount before resetting, so this only returns if it wasn't allowed:
+/// only init may reboot, so from the shell use its `reboot` builtin.
+fn main(args: &[&str]) {
+    if !args.is_empty() {
+        println!("USAGE: reboot");
+        return
+    }
+
+    match reboot() {
+        Err(OsError::NoAccess) => println!("reboot: Only init can reboot; use the shell's reboot builtin"),
+        Err(e) => println!("reboot: {:?}", e),
+        Ok(()) => (),
+    }
+}
@@ -0,0 +1,40 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use coreutils::seq::seq;
+use kernel_api::println;
+
+/// Prints a sequence of integers, one per line: `seq [first [incr]] last`.
+/// `first` and `incr` default to 1.
+fn main(args: &[&str]) {
+    let mut numbers = [0i64; 3];
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        match arg.parse::<i64>() {
+            Ok(n) => *number = n,
+            Err(_) => {
+                println!("seq: invalid number: {}", arg);
+                return
+            },
+        }
+    }
+
+    let (first, incr, last) = match args.len() {
+        1 => (1, 1, numbers[0]),
+        2 => (numbers[0], 1, numbers[1]),
+        3 => (numbers[0], numbers[1], numbers[2]),
+        _ => {
+            println!("USAGE: seq [first [incr]] [last]");
+            return
+        },
+    };
+
+    match seq(first, incr, last) {
+        Some(values) => for value in values {
+            println!("{}", value);
+        },
+        None => println!("seq: increment {} doesn't go from {} to {}", incr, first, last),
+    }
+}
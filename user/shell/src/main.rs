@@ -11,69 +11,233 @@ mod allocator;
 extern crate alloc;
 use alloc::vec::Vec;
 use alloc::string::String;
+use core::fmt;
 
-use kernel_api::{print, println, EntryKind};
-use kernel_api::syscall::{input, output, env_get, env_set, fork, fs_create, fs_open, fs_close, fs_delete, exec, wait_pid, exit};
+use coreutils::path::search_path;
+use coreutils::shell::{assignment, exit_code, expand_vars, reap_jobs, run_niced, Job, System};
+use kernel_api::{print, println, EntryKind, ExitStatus, OsError, OsResult, ProcRecord, ProcState};
+use kernel_api::syscall::{input, env_get, env_set, chdir, getcwd, fork, fs_create, fs_open, fs_close, fs_delete, dir_entry, exec, wait_pid, exit, exit_with, halt, nice, output, proc_list, reboot, set_foreground};
 
 #[global_allocator]
 pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
 
 fn parse_command<'a>(buffer: &'a [u8]) -> Option<Vec<String>> {
-    let mut start = 0;
+    let line = core::str::from_utf8(buffer).ok()?;
+    Some(line.split(' ').filter(|part| !part.is_empty()).map(String::from).collect())
+}
 
-    let mut args = Vec::new();
+/// Returns the value of the environment variable `name`, if it's set.
+fn env_var(name: &str) -> Option<String> {
+    let mut value_buf = [0u8; 256];
+    let len = env_get(name, &mut value_buf).ok()?;
+    Some(String::from(core::str::from_utf8(&value_buf[0..len]).unwrap_or_default()))
+}
 
-    for i in 0..buffer.len() {
-        if buffer[i] == ' ' as u8 || i == buffer.len() - 1 {
-            let part = core::str::from_utf8(&buffer[start..i]).ok()?;
-            args.push(String::from(part));
-            start = i + 1;
-        }
+/// Returns `true` if `path` names a file that can be opened, rather than a
+/// directory or nothing at all.
+fn is_executable(path: &str) -> bool {
+    match fs_open(path) {
+        Ok(fd) => { let _ = fs_close(&fd); },
+        Err(_) => return false,
+    }
+
+    let mut name_buf = [0u8; 512];
+    match dir_entry(path, &mut name_buf, 0) {
+        Err(OsError::IoErrorInvalidInput) | Err(OsError::InvalidArgument) => true,
+        _ => false,
     }
-    Some(args)
 }
 
-fn run_program(program: &str, args: &[&str]) {
-    if program == "exit" { exit() }
+/// Finds the program `name` would run. Names starting with `/` are used as
+/// is; anything else is looked up in each directory of the colon-separated
+/// `$PATH`, in order, and the first match wins.
+fn resolve_program(name: &str) -> Option<String> {
+    if name.starts_with('/') {
+        return Some(String::from(name))
+    }
 
-    let program = if program.chars().nth(0) != Some('/') {
-        let mut path_buf = [0u8; 128];
-        let path = match env_get("PATH", &mut path_buf) {
-            Ok(len) => core::str::from_utf8(&path_buf[0..len]).expect("Couldn't parse as UTF-8"),
-            Err(_)  => {
-                println!("Path is relative, but $PATH doesn't exist");
-                return
+    let mut path_buf = [0u8; 256];
+    let path = match env_get("PATH", &mut path_buf) {
+        Ok(len) => core::str::from_utf8(&path_buf[0..len]).unwrap_or_default(),
+        Err(_) => return None,
+    };
+    let mut program_buf = [0u8; 512];
+    search_path(path, name, &mut program_buf, is_executable).map(String::from)
+}
+
+/// Runs a builtin or program and returns its exit status: 0 on success.
+fn run_program(program: &str, args: &[&str], jobs: &mut Vec<Job>) -> u64 {
+    if program == "exit" { exit() }
+    // NAME=value sets an environment variable
+    if let Some((name, value)) = assignment(program, args) {
+        if let Err(e) = env_set(name, value) {
+            println!("Couldn't set {}: {:?}", name, e);
+            return 1
+        }
+        return 0
+    }
+    if program == "jobs" {
+        for job in jobs.iter() {
+            println!("[{}] {} Running    {}", job.id, job.pid, job.command);
+        }
+        return 0
+    }
+    // wait [pid]: wait for one background job, or all of them
+    if program == "wait" {
+        let target = match args.get(0).map(|pid| pid.parse::<u64>()) {
+            Some(Ok(pid)) => Some(pid),
+            Some(Err(_)) => {
+                println!("wait: invalid pid: {}", args[0]);
+                return 1
+            },
+            None => None,
+        };
+        let (waiting, rest): (Vec<Job>, Vec<Job>) = jobs.drain(..)
+            .partition(|job| target.map(|pid| pid == job.pid).unwrap_or(true));
+        *jobs = rest;
+        // Like the shell's `wait`, the status is that of the last job
+        let mut code = 0;
+        for job in waiting {
+            match wait_pid(job.pid) {
+                Ok(status) => {
+                    println!("[{}] Done ({:?})    {}", job.id, status, job.command);
+                    code = exit_code(status);
+                },
+                Err(e) => {
+                    println!("Failed to wait for process: {:?}", e);
+                    code = 1;
+                },
+            }
+        }
+        return code
+    }
+    // cd [dir]: the kernel tracks the working directory, and children
+    // inherit it
+    if program == "cd" {
+        let dir = args.get(0).cloned().unwrap_or("/");
+        if let Err(e) = chdir(dir) {
+            println!("cd: {}: {:?}", dir, e);
+            return 1
+        }
+        return 0
+    }
+    if program == "pwd" {
+        let mut cwd_buf = [0u8; 256];
+        match getcwd(&mut cwd_buf) {
+            Ok(len) => println!("{}", core::str::from_utf8(&cwd_buf[0..len]).unwrap_or_default()),
+            Err(e) => {
+                println!("pwd: {:?}", e);
+                return 1
             },
+        }
+        return 0
+    }
+    // which name...: print the program each name would run. Fails if any
+    // name isn't found
+    if program == "which" {
+        let mut code = 0;
+        for name in args {
+            match resolve_program(name).filter(|path| is_executable(path)) {
+                Some(path) => println!("{}", path),
+                None => code = 1,
+            }
+        }
+        return code
+    }
+    if program == "halt" {
+        if let Err(e) = halt() {
+            println!("halt: {:?}", e);
+        }
+        return 1
+    }
+    // The shell is init, which is the only process allowed to reboot
+    if program == "reboot" {
+        if let Err(e) = reboot() {
+            println!("reboot: {:?}", e);
+        }
+        return 1
+    }
+
+    // nice [-n delta] program args...
+    if program == "nice" {
+        let (delta, rest) = if args.len() >= 2 && args[0] == "-n" {
+            match args[1].parse::<i64>() {
+                Ok(delta) => (delta, &args[2..]),
+                Err(_) => {
+                    println!("nice: invalid adjustment: {}", args[1]);
+                    return 1
+                },
+            }
+        } else {
+            (10, args)
         };
+        if rest.len() == 0 {
+            println!("USAGE: nice [-n delta] program [args]");
+            return 1
+        }
+        return run_niced(&mut Kernel, delta, rest[0], &rest[1..], jobs);
+    }
+
+    run_niced(&mut Kernel, 0, program, args, jobs)
+}
+
+/// The shell's `System`, which makes the syscalls.
+struct Kernel;
+
+impl fmt::Write for Kernel {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Lines end in "\r\n" on the console, as with `println!`
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                output(b'\r');
+            }
+            output(byte);
+        }
+        Ok(())
+    }
+}
+
+impl System for Kernel {
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        resolve_program(name)
+    }
+
+    fn spawn(&mut self, niceness: i64, program: &str, args: &[&str]) -> OsResult<u64> {
+        match fork()? {
+            0 => match nice(niceness).and_then(|_| exec(program, args)) {
+                Ok(()) => exit(),
+                Err(e) => {
+                    println!("Encountered error: {:?}", e);
+                    exit_with(127)
+                },
+            },
+            pid => Ok(pid),
+        }
+    }
 
-        format!("{}{}", path, program)
-    } else { String::from(program) };
+    fn wait(&mut self, pid: u64) -> OsResult<ExitStatus> {
+        wait_pid(pid)
+    }
+
+    fn set_foreground(&mut self, pid: u64) {
+        let _ = set_foreground(pid);
+    }
 
-    match fork() {
-        Ok(0) => match exec(program.as_str(), args) {
-            Ok(()) => (),
-            Err(e) => println!("Encountered error: {:?}", e),
-        },
-        Ok(pid) => match wait_pid(pid) {
-            Ok(()) => (),
-            Err(e) => println!("Failed to wait for process: {:?}", e),
-        },
-        Err(e) => println!("Error running while {}: {:?}", program, e),
+    fn zombies(&mut self) -> Vec<u64> {
+        let mut records = [ProcRecord::default(); 64];
+        let count = match proc_list(&mut records) {
+            Ok(count) => core::cmp::min(count, records.len()),
+            Err(_) => return Vec::new(),
+        };
+        records[0..count].iter()
+            .filter(|record| record.state() == ProcState::Zombie)
+            .map(|record| record.pid)
+            .collect()
     }
 }
 
 fn main(_args: &[&str]) {
     let _ = env_set("PATH", "/bin/").expect("Couldn't set $PATH");
-    let _ = env_set("CWD", "/").expect("Couldn't set $CWD");
-
-    let mut cwd_buf = [0u8; 128];
-    match env_get("CWD", &mut cwd_buf) {
-        Ok(len) => {
-            let cwd_string = core::str::from_utf8(&cwd_buf[0..len]).expect("Couldn't parse as UTF-8");
-            println!("$CWD: {:?}", cwd_string)
-        },
-        Err(e)  => println!("Couldn't read $CWD: {:?}", e),
-    };
 
     match fs_open("/bin/fib") {
         Ok(fd) => println!("/bin/fib: {:?}", fd),
@@ -115,39 +279,38 @@ fn main(_args: &[&str]) {
         Err(e) => println!("Couldn't open /foo: {:?}", e),
     }
 
+    let mut jobs = Vec::new();
+    let mut status = 0;
     loop {
+        reap_jobs(&mut Kernel, &mut jobs);
         print!("sh> ");
 
+        // The console is in cooked mode, so the kernel echoes and edits the
+        // line and hands it over once it's entered
         let mut text_idx = 0;
         let mut text_buf = [0u8; 512];
-
         loop {
-            if text_idx >= 512 { continue; }
             let ch = input();
-            if ch == '\n' as u8 || ch == '\r' as u8 {
+            // Ctrl-D on an empty line comes through by itself
+            if ch == b'\n' || ch == 4 {
                 break;
-            } else if ch == 8 || ch == 127 { // backspace
-                if text_idx > 0 {
-                    text_idx -= 1;
-                    text_buf[text_idx] = b' ' as u8;
-                    print!("\x08 \x08");
-                }
-            } else {
+            }
+            // Anything past the end of the buffer is dropped
+            if text_idx < text_buf.len() {
                 text_buf[text_idx] = ch;
                 text_idx += 1;
-                output(ch);
             }
         }
-        println!("");
 
-        let command_text = &text_buf[0..=text_idx];
+        let command_text = &text_buf[0..text_idx];
         match parse_command(command_text) {
             Some(args) => {
                 if args.len() == 0 { continue }
+                let args = args.iter().map(|x| expand_vars(x, status, env_var)).collect::<Vec<_>>();
                 let args = args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
                 let program = &args[0];
                 let args = &args[1..];
-                run_program(program, args);
+                status = run_program(program, args, &mut jobs);
             },
             None => println!("Parse error!"),
         }
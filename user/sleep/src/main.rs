@@ -1,31 +1,36 @@
 #![feature(asm)]
-
 #![no_std]
 #![no_main]
 
-use core::panic::PanicInfo;
+mod cr0;
 
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    loop {}
-}
+use core::time::Duration;
+
+use coreutils::sleep::parse_seconds;
+use kernel_api::println;
+use kernel_api::syscall::sleep;
 
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
-    loop {
-        let ms = 10000;
-        let error: u64;
-        let elapsed_ms: u64;
+/// Sleeps for a number of seconds: `sleep seconds`, where the seconds can be
+/// fractional, like `0.5`. Prints how long the kernel actually slept for,
+/// which is never less than what was asked for.
+fn main(args: &[&str]) {
+    let span = match args {
+        [secs] => match parse_seconds(secs) {
+            Some(span) => span,
+            None => {
+                println!("sleep: invalid number of seconds: {}", secs);
+                return
+            },
+        },
+        _ => {
+            println!("USAGE: sleep [seconds]");
+            return
+        },
+    };
 
-        unsafe {
-            asm!("mov x0, $2
-                  svc 1
-                  mov $0, x0
-                  mov $1, x7"
-                 : "=r"(elapsed_ms), "=r"(error)
-                 : "r"(ms)
-                 : "x0", "x7"
-                 : "volatile");
-        }
+    // The kernel counts in whole milliseconds, so round up rather than short
+    match sleep(span + Duration::from_nanos(999_999)) {
+        Ok(slept) => println!("Slept for {}.{:03}s", slept.as_secs(), slept.subsec_millis()),
+        Err(e) => println!("Couldn't sleep: {:?}", e),
     }
 }
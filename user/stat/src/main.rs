@@ -0,0 +1,40 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::{println, EntryKind, OsError, StatBuf, STAT_HIDDEN, STAT_READ_ONLY};
+use kernel_api::syscall::fs_metadata;
+
+fn print_stat(path: &str, buf: &StatBuf) {
+    let kind = match buf.kind() {
+        EntryKind::File => "file",
+        EntryKind::Dir => "directory",
+    };
+    let flag = |set: u64, c: char| if buf.attributes & set != 0 { c } else { '-' };
+
+    println!("  File: {}", path);
+    println!("  Size: {:<12} Type: {}", buf.size, kind);
+    println!(" Attrs: {}{}", flag(STAT_READ_ONLY, 'r'), flag(STAT_HIDDEN, 'h'));
+    println!("Create: {}", buf.created);
+    println!("Modify: {}", buf.modified);
+}
+
+/// Prints the metadata of each path: `stat path...`. Relative paths are
+/// resolved against the working directory by the kernel.
+fn main(args: &[&str]) {
+    if args.is_empty() {
+        println!("USAGE: stat [path...]");
+        return
+    }
+
+    for path in args {
+        let mut buf = StatBuf::default();
+        match fs_metadata(path, &mut buf) {
+            Ok(()) => print_stat(path, &buf),
+            Err(OsError::NoEntry) => println!("stat: {}: No such file or directory", path),
+            Err(e) => println!("Error while reading metadata of {}: {:?}", path, e),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+#[macro_use]
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use shim::io::SeekFrom;
+use coreutils::lines::last_lines_start;
+use kernel_api::{print, println, Fd, OsResult, OsError};
+use kernel_api::syscall::{fs_open, fs_close, file_seek, file_read};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// The number of lines printed without `-n`.
+const DEFAULT_LINES: usize = 10;
+
+/// The size of the chunks read backwards from the end of the file.
+const CHUNK: u64 = 512;
+
+/// Reads `len` bytes starting at `offset` into the front of `text`.
+fn prepend(fd: &Fd, offset: u64, len: usize, text: &mut Vec<u8>) -> OsResult<()> {
+    file_seek(fd, SeekFrom::Start(offset))?;
+    let mut chunk = vec![0u8; len];
+    let mut amt_read = 0;
+    while amt_read < len {
+        match file_read(fd, &mut chunk[amt_read..])? {
+            0 => return Err(OsError::IoErrorEof),
+            n => amt_read += n,
+        }
+    }
+    text.splice(0..0, chunk);
+    Ok(())
+}
+
+/// Reads the last `n` lines of the file at `path`. The file is read backwards
+/// from the end a chunk at a time until it holds `n` lines, so only the end of
+/// a long file is read.
+fn tail_file(path: &str, n: usize) -> OsResult<Vec<u8>> {
+    let fd = fs_open(path)?;
+    let result = (|| -> OsResult<Vec<u8>> {
+        let mut offset = file_seek(&fd, SeekFrom::End(0))?;
+        let mut text = Vec::new();
+        while offset > 0 {
+            let start = offset.saturating_sub(CHUNK);
+            prepend(&fd, start, (offset - start) as usize, &mut text)?;
+            offset = start;
+
+            if let Some(lines_start) = last_lines_start(&text, n) {
+                text.drain(0..lines_start);
+                break
+            }
+        }
+        Ok(text)
+    })();
+    fs_close(&fd)?;
+    result
+}
+
+/// Prints the last lines of each file: `tail [-n lines] file...`.
+fn main(args: &[&str]) {
+    let (n, paths) = match args.first() {
+        Some(&"-n") if args.len() > 1 => match args[1].parse::<usize>() {
+            Ok(n) => (n, &args[2..]),
+            Err(_) => {
+                println!("tail: invalid number of lines: {}", args[1]);
+                return
+            },
+        },
+        _ => (DEFAULT_LINES, args),
+    };
+    if paths.is_empty() {
+        println!("USAGE: tail [-n lines] [file...]");
+        return
+    }
+
+    for (i, path) in paths.iter().enumerate() {
+        if paths.len() > 1 {
+            println!("{}==> {} <==", if i > 0 { "\r\n" } else { "" }, path);
+        }
+        match tail_file(path, n) {
+            Ok(lines) => print!("{}", String::from_utf8_lossy(&lines)),
+            Err(e) => println!("Error while reading from {}: {:?}", path, e),
+        }
+    }
+}
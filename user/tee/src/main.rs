@@ -0,0 +1,103 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use shim::io::SeekFrom;
+use coreutils::tee::{tee, Sink};
+use kernel_api::{println, EntryKind, Fd, OsResult, OsError};
+use kernel_api::syscall::{fs_close, fs_create, fs_delete, fs_flush, fs_open, file_seek, file_write, input, output};
+
+/// The console, which needs a carriage return before each newline.
+struct Stdout;
+
+impl Sink for Stdout {
+    fn write_all(&mut self, buf: &[u8]) -> OsResult<()> {
+        for &byte in buf {
+            if byte == b'\n' {
+                output(b'\r');
+            }
+            output(byte);
+        }
+        Ok(())
+    }
+}
+
+/// The file being written to.
+struct Output<'a>(&'a Fd);
+
+impl Sink for Output<'_> {
+    fn write_all(&mut self, mut buf: &[u8]) -> OsResult<()> {
+        while !buf.is_empty() {
+            match file_write(self.0, buf)? {
+                0 => return Err(OsError::IoError),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Opens the file at `path` for writing at its end, replacing its contents
+/// unless `append`.
+fn open_output(path: &str, append: bool) -> OsResult<Fd> {
+    if !append {
+        // There's no truncate, so start over with a new file
+        let _ = fs_delete(path);
+    }
+    // Creating a file that already exists leaves it alone
+    fs_create(path, EntryKind::File)?;
+
+    let fd = fs_open(path)?;
+    if let Err(e) = file_seek(&fd, SeekFrom::End(0)) {
+        let _ = fs_close(&fd);
+        return Err(e)
+    }
+    Ok(fd)
+}
+
+/// Returns the next byte from the console, or `None` at Ctrl-D. Input is
+/// cooked, so each line arrives once it's entered, after the console has
+/// echoed it.
+fn read_byte() -> Option<u8> {
+    match input() {
+        4 => None, // Ctrl-D
+        byte => Some(byte),
+    }
+}
+
+/// Copies the console to both stdout and a file: `tee [-a] file`, where `-a`
+/// appends instead of overwriting. Input ends with Ctrl-D. If the file can't
+/// be opened or written, the input still goes to stdout.
+fn main(args: &[&str]) {
+    let (append, args) = match args.first() {
+        Some(&"-a") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() != 1 {
+        println!("USAGE: tee [-a] [file]");
+        return
+    }
+    let path = args[0];
+
+    let fd = match open_output(path, append) {
+        Ok(fd) => Some(fd),
+        Err(e) => {
+            println!("tee: {}: {:?}", path, e);
+            None
+        },
+    };
+
+    let mut file = fd.as_ref().map(Output);
+    if let Err(e) = tee(core::iter::from_fn(read_byte), &mut Stdout, file.as_mut()) {
+        println!("tee: {}: {:?}", path, e);
+    }
+
+    if let Some(out) = fd {
+        let result = fs_close(&out).and_then(|_| fs_flush(path));
+        if let Err(e) = result {
+            println!("tee: {}: {:?}", path, e);
+        }
+    }
+}
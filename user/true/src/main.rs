@@ -0,0 +1,12 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use kernel_api::syscall::exit_with;
+
+/// Does nothing, successfully.
+fn main(_args: &[&str]) {
+    exit_with(0)
+}
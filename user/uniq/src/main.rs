@@ -0,0 +1,98 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::uniq::adjacent_runs;
+use kernel_api::{println, OsResult};
+use kernel_api::syscall::{fs_open, fs_close, file_read};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+fn push_line(lines: &mut Vec<String>, line: &[u8]) {
+    let line = String::from_utf8_lossy(line);
+    lines.push(String::from(line.trim_end_matches('\r')));
+}
+
+/// Adds each line of the file at `path` to `lines`. A line split across reads
+/// is held back until the rest of it arrives.
+fn read_lines(path: &str, lines: &mut Vec<String>) -> OsResult<()> {
+    let fd = fs_open(path)?;
+    let mut buf = [0u8; 512];
+    let mut pending = Vec::new();
+    let result = loop {
+        let amt_read = match file_read(&fd, &mut buf) {
+            Ok(0) => break Ok(()),
+            Ok(amt_read) => amt_read,
+            Err(e) => break Err(e),
+        };
+        pending.extend_from_slice(&buf[0..amt_read]);
+
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+            push_line(lines, &pending[start..start + end]);
+            start += end + 1;
+        }
+        pending.drain(0..start);
+    };
+    fs_close(&fd)?;
+
+    // The last line doesn't have to end in a newline
+    if !pending.is_empty() {
+        push_line(lines, &pending);
+    }
+    result
+}
+
+/// Prints the lines of a file with adjacent duplicates collapsed:
+/// `uniq [-cd] file`, where `-c` prefixes each line with its count and `-d`
+/// only prints lines that were repeated.
+fn main(args: &[&str]) {
+    let mut counts = false;
+    let mut repeated_only = false;
+    let mut paths = args;
+    while let Some(flags) = paths.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+        for flag in flags[1..].chars() {
+            match flag {
+                'c' => counts = true,
+                'd' => repeated_only = true,
+                _ => {
+                    println!("uniq: unknown flag -{}", flag);
+                    println!("USAGE: uniq [-cd] [file]");
+                    return
+                },
+            }
+        }
+        paths = &paths[1..];
+    }
+    if paths.len() != 1 {
+        println!("USAGE: uniq [-cd] [file]");
+        return
+    }
+
+    let mut lines = Vec::new();
+    if let Err(e) = read_lines(paths[0], &mut lines) {
+        println!("Error while reading from {}: {:?}", paths[0], e);
+        return
+    }
+
+    for (line, count) in adjacent_runs(lines.iter()) {
+        if repeated_only && count < 2 {
+            continue
+        }
+        if counts {
+            println!("{:>7} {}", count, line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use coreutils::uptime::HumanDuration;
+use kernel_api::println;
+use kernel_api::syscall::time;
+
+/// Prints how long it has been since boot.
+fn main(_args: &[&str]) {
+    println!("up {}", HumanDuration(time()));
+}
@@ -0,0 +1,95 @@
+#![feature(asm)]
+#![feature(alloc_error_handler)]
+#![feature(optin_builtin_traits)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+mod allocator;
+
+use coreutils::wc::WordCount;
+use kernel_api::{print, println, OsResult};
+use kernel_api::syscall::{fs_open, fs_close, file_read};
+
+#[global_allocator]
+pub static ALLOCATOR: allocator::Allocator = allocator::Allocator::uninitialized();
+
+/// Which of the tallies to print.
+#[derive(Copy, Clone)]
+struct Columns {
+    lines: bool,
+    words: bool,
+    bytes: bool,
+}
+
+fn count_file(path: &str) -> OsResult<WordCount> {
+    let fd = fs_open(path)?;
+    let mut buf = [0u8; 512];
+    let mut count = WordCount::new();
+    let result = loop {
+        match file_read(&fd, &mut buf) {
+            Ok(0) => break Ok(count),
+            Ok(amt_read) => count.feed(&buf[0..amt_read]),
+            Err(e) => break Err(e),
+        }
+    };
+    fs_close(&fd)?;
+    result
+}
+
+fn print_count(count: &WordCount, columns: Columns, name: &str) {
+    if columns.lines {
+        print!("{:>8}", count.lines);
+    }
+    if columns.words {
+        print!("{:>8}", count.words);
+    }
+    if columns.bytes {
+        print!("{:>8}", count.bytes);
+    }
+    println!(" {}", name);
+}
+
+/// Counts the lines, words and bytes in each file: `wc [-lwc] file...`.
+/// Without flags all three are printed, followed by a total for more than
+/// one file.
+fn main(args: &[&str]) {
+    let mut columns = Columns { lines: false, words: false, bytes: false };
+    let mut paths = args;
+    while let Some(flags) = paths.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+        for flag in flags[1..].chars() {
+            match flag {
+                'l' => columns.lines = true,
+                'w' => columns.words = true,
+                'c' => columns.bytes = true,
+                _ => {
+                    println!("wc: unknown flag -{}", flag);
+                    println!("USAGE: wc [-lwc] [file...]");
+                    return
+                },
+            }
+        }
+        paths = &paths[1..];
+    }
+    if !(columns.lines || columns.words || columns.bytes) {
+        columns = Columns { lines: true, words: true, bytes: true };
+    }
+    if paths.is_empty() {
+        println!("USAGE: wc [-lwc] [file...]");
+        return
+    }
+
+    let mut total = WordCount::new();
+    for path in paths {
+        match count_file(path) {
+            Ok(count) => {
+                print_count(&count, columns, path);
+                total.add(&count);
+            },
+            Err(e) => println!("Error while reading from {}: {:?}", path, e),
+        }
+    }
+    if paths.len() > 1 {
+        print_count(&total, columns, "total");
+    }
+}
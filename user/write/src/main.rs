@@ -0,0 +1,61 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use shim::io::SeekFrom;
+use kernel_api::{println, EntryKind, Fd, OsResult, OsError};
+use kernel_api::syscall::{fs_close, fs_create, fs_delete, fs_flush, fs_open, file_seek, file_write};
+
+fn write_all(fd: &Fd, mut buf: &[u8]) -> OsResult<()> {
+    while !buf.is_empty() {
+        match file_write(fd, buf)? {
+            0 => return Err(OsError::IoError),
+            n => buf = &buf[n..],
+        }
+    }
+    Ok(())
+}
+
+/// Writes `words` to the file at `path` separated by spaces and followed by a
+/// newline, either replacing its contents or appending to them.
+fn write_line(path: &str, words: &[&str], append: bool) -> OsResult<()> {
+    if !append {
+        // There's no truncate, so start over with a new file
+        let _ = fs_delete(path);
+    }
+    // Creating a file that already exists leaves it alone
+    fs_create(path, EntryKind::File)?;
+
+    let fd = fs_open(path)?;
+    let result = file_seek(&fd, SeekFrom::End(0)).and_then(|_| {
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                write_all(&fd, b" ")?;
+            }
+            write_all(&fd, word.as_bytes())?;
+        }
+        write_all(&fd, b"\n")
+    });
+    fs_close(&fd)?;
+    result?;
+    fs_flush(path)
+}
+
+/// Writes a line to a file: `write [-a] file text...`. The file is created if
+/// needed and overwritten, or appended to with `-a`.
+fn main(args: &[&str]) {
+    let (append, args) = match args.first() {
+        Some(&"-a") => (true, &args[1..]),
+        _ => (false, args),
+    };
+    if args.len() < 2 {
+        println!("USAGE: write [-a] [file] [text...]");
+        return
+    }
+
+    if let Err(e) = write_line(args[0], &args[1..], append) {
+        println!("Error while writing to {}: {:?}", args[0], e);
+    }
+}
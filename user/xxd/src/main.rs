@@ -0,0 +1,59 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use coreutils::xxd::{hex_row, HEX_ROW_LEN, HEX_ROW_MAX};
+use kernel_api::{println, OsResult};
+use kernel_api::syscall::{fs_open, fs_close, file_read};
+
+/// Prints the file at `path` as rows of `HEX_ROW_LEN` bytes.
+fn dump_file(path: &str) -> OsResult<()> {
+    let fd = fs_open(path)?;
+    let mut row = [0u8; HEX_ROW_LEN];
+    let mut row_buf = [0u8; HEX_ROW_MAX];
+    let mut offset = 0;
+    let result = loop {
+        // Fill the whole row even if a read comes back short
+        let mut filled = 0;
+        let mut error = None;
+        while filled < row.len() {
+            match file_read(&fd, &mut row[filled..]) {
+                Ok(0) => break,
+                Ok(amt_read) => filled += amt_read,
+                Err(e) => {
+                    error = Some(e);
+                    break
+                },
+            }
+        }
+
+        if filled > 0 {
+            println!("{}", hex_row(offset, &row[..filled], &mut row_buf));
+            offset += filled as u64;
+        }
+        if let Some(e) = error {
+            break Err(e)
+        }
+        if filled < row.len() {
+            break Ok(())
+        }
+    };
+    fs_close(&fd)?;
+    result
+}
+
+/// Prints a hex dump of each file: `xxd file...`.
+fn main(args: &[&str]) {
+    if args.is_empty() {
+        println!("USAGE: xxd [file...]");
+        return
+    }
+
+    for path in args {
+        if let Err(e) = dump_file(path) {
+            println!("Error while reading from {}: {:?}", path, e);
+        }
+    }
+}
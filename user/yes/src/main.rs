@@ -0,0 +1,17 @@
+#![feature(asm)]
+#![no_std]
+#![no_main]
+
+mod cr0;
+
+use coreutils::yes::Line;
+use kernel_api::println;
+
+/// Prints a line over and over until killed: `yes [text...]`, where the
+/// words of `text` are joined with spaces and it defaults to `y`.
+fn main(args: &[&str]) {
+    let line = Line(args);
+    loop {
+        println!("{}", line);
+    }
+}